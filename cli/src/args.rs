@@ -14,7 +14,7 @@ pub enum Commands {
     Parse(SqlQuery),
 
     /// Prints the query plan for the given sql query
-    Plan(SqlQuery),
+    Plan(PlanCommand),
 
     /// Creates a new database with the given name
     Create(CreateDatabaseCommand),
@@ -34,6 +34,16 @@ pub struct SqlQuery {
     pub query: String,
 }
 
+#[derive(Debug, Args)]
+pub struct PlanCommand {
+    /// A database name
+    pub database: String,
+
+    /// An sql string to plan
+    #[arg(short, long)]
+    pub query: String,
+}
+
 #[derive(Debug, Args)]
 pub struct CreateDatabaseCommand {
     /// A database name
@@ -2,16 +2,35 @@
 mod args;
 
 use crate::args::{Cli, Commands};
-use args::{CreateDatabaseCommand, DatabaseName};
+use args::{CreateDatabaseCommand, DatabaseName, PlanCommand};
 use clap::Parser;
-use sql::{Lexer, Token};
+use sql::{Lexer, Parser as SqlParser, Token};
 use std::io::{self, Result, Write};
 
 use engine::sqlite::Database;
 
 fn parse_sql_query(query: &str) {
-    let vec: Vec<Token> = Lexer::new(query).map(|option| option.unwrap()).collect();
-    println!("{:?}", vec);
+    match Lexer::new(query).collect::<std::result::Result<Vec<Token>, _>>() {
+        Ok(tokens) => println!("{:?}", tokens),
+        Err(err) => println!("Unable to parse query: {err}"),
+    }
+}
+
+fn print_query_plan(command: PlanCommand) {
+    let database = match Database::open(command.database) {
+        Ok(database) => database,
+        Err(err) => return println!("Unable to open database: {err:?}"),
+    };
+    let statements = match SqlParser::new(&command.query).parse_all() {
+        Ok(statements) => statements,
+        Err(err) => return println!("Unable to parse query: {err}"),
+    };
+    for statement in statements {
+        match database.query_plan(&statement) {
+            Ok(plan) => print!("{plan}"),
+            Err(err) => println!("Error: {err}"),
+        }
+    }
 }
 
 fn start_repl(database: &mut Database) {
@@ -28,8 +47,10 @@ fn start_repl(database: &mut Database) {
             println!("Goodbye!");
             break;
         }
-        let result = database.execute_sql(command);
-        println!("{:?}", result);
+        match database.execute_sql(&command) {
+            Ok(results) => results.iter().for_each(|result| print!("{result}")),
+            Err(err) => println!("Error: {err}"),
+        }
     }
 }
 
@@ -57,7 +78,7 @@ fn main() {
     let args = Cli::parse();
     match args.command {
         Commands::Parse(query) => parse_sql_query(&query.query),
-        Commands::Plan(query) => parse_sql_query(&query.query),
+        Commands::Plan(command) => print_query_plan(command),
         Commands::Create(command) => create_database(command),
         Commands::Connect(db) => connect_database(db),
         Commands::DbInfo(db) => database_info(db),
@@ -1,11 +1,27 @@
 use std::fmt::Display;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParsingError {
     UnexpectedToken(String),
     UnexpectedKeyword(String),
     UnexpectedEOF,
     InvalidDataType(String),
+    /// A valid, but not yet supported statement or clause
+    Unsupported(String),
+    /// The expression or subquery is nested deeper than the given limit
+    TooDeep(usize),
+    /// The same alias is used twice in one FROM clause, or a WITH clause names two CTEs alike
+    DuplicateTable(String),
+    /// A table definition names the same column twice
+    DuplicateColumn(String),
+    /// The SELECTs around the given compound operator return different numbers of columns
+    CompoundColumnCount(String),
+    /// The rows of a VALUES clause have different lengths
+    ValuesTermCount,
+    /// A row of columns is assigned another number of values: `(a, b) = (1, 2, 3)`
+    AssignmentCount(usize, usize),
+    /// An error in the n-th (1-based) statement of a script
+    InStatement(usize, Box<ParsingError>),
 }
 
 impl Display for ParsingError {
@@ -17,6 +33,25 @@ impl Display for ParsingError {
             }
             ParsingError::UnexpectedEOF => write!(f, "Unexpected EOF"),
             ParsingError::InvalidDataType(message) => write!(f, "Invalid Data Type: {message}"),
+            ParsingError::Unsupported(feature) => write!(f, "Unsupported: {feature}"),
+            ParsingError::TooDeep(limit) => {
+                write!(f, "Statement is nested deeper than {limit} levels")
+            }
+            ParsingError::DuplicateTable(name) => {
+                write!(f, "Table name specified more than once: {name}")
+            }
+            ParsingError::DuplicateColumn(name) => write!(f, "Duplicate column name: {name}"),
+            ParsingError::ValuesTermCount => {
+                write!(f, "All VALUES must have the same number of terms")
+            }
+            ParsingError::AssignmentCount(columns, values) => {
+                write!(f, "{columns} columns assigned {values} values")
+            }
+            ParsingError::CompoundColumnCount(operator) => write!(
+                f,
+                "SELECTs to the left and right of {operator} do not have the same number of result columns"
+            ),
+            ParsingError::InStatement(index, error) => write!(f, "Statement #{index}: {error}"),
         }
     }
 }
@@ -1,4 +1,6 @@
+use std::cmp::Ordering;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 
 #[derive(Debug, PartialEq)]
 pub enum DataType {
@@ -21,14 +23,138 @@ impl Display for DataType {
     }
 }
 
-#[derive(Debug)]
+/// A value of one of the SQLite storage classes
+#[derive(Debug, Clone)]
 pub enum Value {
-    Boolean(bool),
-    Integer(i32),
+    Null,
+    Integer(i64),
     Double(f64),
-    Char(char),
     Text(String),
-    Null,
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    /// The order of the storage classes when sorting, numbers compare with each other
+    fn class(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Integer(_) | Value::Double(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        }
+    }
+}
+
+/// Compares exactly, without rounding the integer to a double first
+fn compare_integer_double(integer: i64, double: f64) -> Ordering {
+    if double.is_nan() || double >= i64::MAX as f64 {
+        return Ordering::Less;
+    }
+    if double < i64::MIN as f64 {
+        return Ordering::Greater;
+    }
+    integer
+        .cmp(&(double.trunc() as i64))
+        .then_with(|| 0.0.partial_cmp(&double.fract()).unwrap_or(Ordering::Equal))
+}
+
+/// SQLite's sort order: NULL first, then numbers by value, text and blobs by their bytes
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Double(a), Value::Double(b)) => a.partial_cmp(b).unwrap_or(a.total_cmp(b)),
+            (Value::Integer(a), Value::Double(b)) => compare_integer_double(*a, *b),
+            (Value::Double(a), Value::Integer(b)) => compare_integer_double(*b, *a).reverse(),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+            _ => self.class().cmp(&other.class()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Equal numbers are equal whatever their storage class, `1 = 1.0`
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.class().hash(state);
+        match self {
+            Value::Null => {}
+            Value::Integer(integer) => integer.hash(state),
+            // Integral doubles hash like the integer they are equal to
+            Value::Double(double) if double.fract() == 0.0 && double.abs() < 9.2e18 => {
+                (*double as i64).hash(state)
+            }
+            Value::Double(double) => double.to_bits().hash(state),
+            Value::Text(text) => text.hash(state),
+            Value::Blob(blob) => blob.hash(state),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Integer(integer) => write!(f, "{integer}"),
+            Value::Double(double) => format_double(*double, f),
+            Value::Text(text) => f.write_str(text),
+            Value::Blob(blob) => f.write_str(&String::from_utf8_lossy(blob)),
+        }
+    }
+}
+
+/// Formats a double the way SQLite's `%!.15g` does: 15 significant digits, in exponent
+/// notation below 1e-4 and from 1e15 on, and always with a fraction, `2.0` rather than `2`
+fn format_double(double: f64, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if double.is_nan() {
+        return Ok(());
+    }
+    if double.is_infinite() {
+        return f.write_str(if double > 0.0 { "Inf" } else { "-Inf" });
+    }
+    if double == 0.0 {
+        return f.write_str("0.0");
+    }
+    // The exponent after rounding to 15 digits decides the notation, as in C's `%g`
+    let scientific = format!("{double:.14e}");
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let with_fraction = |digits: &str| {
+        if !digits.contains('.') {
+            return format!("{digits}.0");
+        }
+        let digits = digits.trim_end_matches('0');
+        match digits.strip_suffix('.') {
+            Some(integral) => format!("{integral}.0"),
+            None => digits.to_string(),
+        }
+    };
+    if (-4..15).contains(&exponent) {
+        let fixed = format!("{double:.*}", (14 - exponent) as usize);
+        f.write_str(&with_fraction(&fixed))
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        write!(
+            f,
+            "{}e{sign}{:02}",
+            with_fraction(mantissa),
+            exponent.unsigned_abs()
+        )
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -1,4 +1,5 @@
-/// TODO: Rework the Errors num, make it more real (not too abstract)
+use common::errors::ParsingError;
+use std::fmt::Display;
 
 #[derive(Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -6,4 +7,46 @@ pub enum DatabaseError {
     FileSystemError(String),
     StateError(String),
     InternalError(String),
+    ParsingError(ParsingError),
+    /// The statement refers to a table, view or CTE that doesn't exist
+    NoSuchTable(String),
+    /// The statement refers to an index or trigger (the kind) that doesn't exist
+    NoSuchObject(&'static str, String),
+    NoSuchColumn(String),
+    /// An unqualified column name matches columns of several tables
+    AmbiguousColumn(String),
+    /// Rows have the same values in the columns of a UNIQUE index, or constraint
+    UniqueViolation(String),
+    /// A row breaks a NOT NULL or CHECK constraint (the kind) of the column or constraint named
+    ConstraintViolation(&'static str, String),
+    /// The statement is valid SQL, but can't be executed, e.g. a misused aggregate
+    ExecutionError(String),
+}
+
+impl Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseError::FileSystemError(message)
+            | DatabaseError::StateError(message)
+            | DatabaseError::InternalError(message)
+            | DatabaseError::ExecutionError(message) => f.write_str(message),
+            DatabaseError::ParsingError(error) => error.fmt(f),
+            DatabaseError::NoSuchTable(name) => write!(f, "no such table: {name}"),
+            DatabaseError::NoSuchObject(kind, name) => write!(f, "no such {kind}: {name}"),
+            DatabaseError::NoSuchColumn(name) => write!(f, "no such column: {name}"),
+            DatabaseError::AmbiguousColumn(name) => write!(f, "ambiguous column name: {name}"),
+            DatabaseError::UniqueViolation(columns) => {
+                write!(f, "UNIQUE constraint failed: {columns}")
+            }
+            DatabaseError::ConstraintViolation(kind, target) => {
+                write!(f, "{kind} constraint failed: {target}")
+            }
+        }
+    }
+}
+
+impl From<ParsingError> for DatabaseError {
+    fn from(error: ParsingError) -> Self {
+        DatabaseError::ParsingError(error)
+    }
 }
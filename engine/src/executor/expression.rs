@@ -0,0 +1,964 @@
+use super::{Executor, Scope};
+use crate::errors::DatabaseError;
+use common::types::Value;
+use sql::ast::{BinaryOperator, Expression, Literal, PatternOperator, Select, UnaryOperator};
+use std::cmp::Ordering;
+
+/// The type a declared column type or CAST converts values to, see `Datatypes In SQLite`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    /// Derives the affinity from the words of a type name, in the order SQLite checks them
+    pub(super) fn of(type_name: &str) -> Affinity {
+        let type_name = type_name.to_ascii_uppercase();
+        if type_name.contains("INT") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|word| type_name.contains(word))
+        {
+            Affinity::Text
+        } else if type_name.contains("BLOB") || type_name.is_empty() {
+            Affinity::Blob
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|word| type_name.contains(word))
+        {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+}
+
+impl Executor<'_> {
+    pub(super) fn evaluate(
+        &mut self,
+        expression: &Expression,
+        scope: &Scope,
+    ) -> Result<Value, DatabaseError> {
+        match expression {
+            Expression::Literal(literal) => Ok(match literal {
+                Literal::Null => Value::Null,
+                Literal::String(text) => Value::Text(text.clone()),
+                Literal::Integer(integer) => Value::Integer(*integer),
+                Literal::Float(double) => Value::Double(*double),
+                Literal::Boolean(boolean) => Value::Integer(*boolean as i64),
+                Literal::Blob(blob) => Value::Blob(blob.clone()),
+            }),
+            Expression::Column(column) => scope.lookup(column),
+            Expression::Wildcard(_) => Err(DatabaseError::ExecutionError(format!(
+                "{expression} is only allowed as a result column"
+            ))),
+            Expression::Unary { operator, operand } => {
+                let value = self.evaluate(operand, scope)?;
+                Ok(unary(*operator, value))
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => self.binary(left, *operator, right, scope),
+            Expression::Collate {
+                expression,
+                collation,
+            } => {
+                if !["BINARY", "NOCASE", "RTRIM"]
+                    .iter()
+                    .any(|known| collation.eq_ignore_ascii_case(known))
+                {
+                    return Err(DatabaseError::ExecutionError(format!(
+                        "no such collation sequence: {collation}"
+                    )));
+                }
+                self.evaluate(expression, scope)
+            }
+            Expression::Function {
+                name,
+                arguments,
+                distinct,
+                filter,
+            } => self.function(name, arguments, *distinct, filter.as_deref(), scope),
+            Expression::Like {
+                expression: value,
+                negated,
+                operator,
+                pattern,
+                escape,
+            } => {
+                let value = self.evaluate(value, scope)?;
+                let pattern = self.evaluate(pattern, scope)?;
+                let escape = match escape {
+                    Some(escape) => Some(self.evaluate(escape, scope)?),
+                    None => None,
+                };
+                let matched = pattern_match(*operator, &value, &pattern, escape.as_ref())?;
+                Ok(boolean(matched.map(|matched| matched != *negated)))
+            }
+            Expression::Between {
+                expression: value,
+                negated,
+                low,
+                high,
+            } => {
+                // Compared like `value >= low AND value <= high`
+                let (low_affinity, high_affinity) = (
+                    comparison_affinity(value, low, scope),
+                    comparison_affinity(value, high, scope),
+                );
+                let low_collation = comparison_collation(value, low, scope);
+                let high_collation = comparison_collation(value, high, scope);
+                let value = self.evaluate(value, scope)?;
+                let low = self.evaluate(low, scope)?;
+                let high = self.evaluate(high, scope)?;
+                let above = compare(
+                    &convert(value.clone(), low_affinity),
+                    &convert(low, low_affinity),
+                    low_collation,
+                )
+                .map(|ordering| ordering.is_ge());
+                let below = compare(
+                    &convert(value, high_affinity),
+                    &convert(high, high_affinity),
+                    high_collation,
+                )
+                .map(|ordering| ordering.is_le());
+                Ok(boolean(and(above, below).map(|inside| inside != *negated)))
+            }
+            Expression::InList {
+                expression: value,
+                negated,
+                list,
+            } => {
+                // Only the left operand decides the affinity and collation of an IN list
+                let (affinity, collation) = (affinity(value, scope), left_collation(value, scope));
+                let value = convert(self.evaluate(value, scope)?, affinity);
+                let mut candidates = vec![];
+                for item in list {
+                    candidates.push(convert(self.evaluate(item, scope)?, affinity));
+                }
+                Ok(boolean(
+                    contains(&value, &candidates, collation).map(|found| found != *negated),
+                ))
+            }
+            Expression::InSubquery {
+                expression: value,
+                negated,
+                subquery,
+            } => {
+                let (affinity, collation) = (affinity(value, scope), left_collation(value, scope));
+                let value = convert(self.evaluate(value, scope)?, affinity);
+                let candidates = self
+                    .subquery_column(subquery, scope)?
+                    .into_iter()
+                    .map(|candidate| convert(candidate, affinity))
+                    .collect::<Vec<_>>();
+                Ok(boolean(
+                    contains(&value, &candidates, collation).map(|found| found != *negated),
+                ))
+            }
+            Expression::Subquery(subquery) => Ok(self
+                .subquery_column(subquery, scope)?
+                .into_iter()
+                .next()
+                .unwrap_or(Value::Null)),
+            Expression::Exists(subquery) => {
+                let result = self.query(subquery, Some(scope))?;
+                Ok(Value::Integer(!result.rows.is_empty() as i64))
+            }
+            Expression::IsNull {
+                expression: value,
+                negated,
+            } => {
+                let value = self.evaluate(value, scope)?;
+                Ok(Value::Integer(((value == Value::Null) != *negated) as i64))
+            }
+            Expression::IsDistinctFrom {
+                left,
+                negated,
+                right,
+            } => {
+                let affinity = comparison_affinity(left, right, scope);
+                let collation = comparison_collation(left, right, scope);
+                let left = convert(self.evaluate(left, scope)?, affinity);
+                let right = convert(self.evaluate(right, scope)?, affinity);
+                let distinct = !same(&left, &right, collation);
+                Ok(Value::Integer((distinct != *negated) as i64))
+            }
+            Expression::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                let operand = match operand {
+                    Some(operand) => Some((operand, self.evaluate(operand, scope)?)),
+                    None => None,
+                };
+                for (condition, result) in branches {
+                    let value = self.evaluate(condition, scope)?;
+                    // `CASE operand WHEN value` compares like `operand = value`
+                    let taken = match &operand {
+                        Some((expression, operand)) => {
+                            let affinity = comparison_affinity(expression, condition, scope);
+                            let collation = comparison_collation(expression, condition, scope);
+                            compare(
+                                &convert(operand.clone(), affinity),
+                                &convert(value, affinity),
+                                collation,
+                            ) == Some(Ordering::Equal)
+                        }
+                        None => truth(&value) == Some(true),
+                    };
+                    if taken {
+                        return self.evaluate(result, scope);
+                    }
+                }
+                match else_result {
+                    Some(result) => self.evaluate(result, scope),
+                    None => Ok(Value::Null),
+                }
+            }
+            Expression::Cast {
+                expression,
+                data_type,
+            } => {
+                let value = self.evaluate(expression, scope)?;
+                Ok(cast(value, Affinity::of(&data_type.name)))
+            }
+            Expression::Nested(expression) => self.evaluate(expression, scope),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        left: &Expression,
+        operator: BinaryOperator,
+        right: &Expression,
+        scope: &Scope,
+    ) -> Result<Value, DatabaseError> {
+        // AND and OR skip the right side once the left one decides the result
+        if let BinaryOperator::And | BinaryOperator::Or = operator {
+            let decisive = operator == BinaryOperator::Or;
+            let left = truth(&self.evaluate(left, scope)?);
+            if left == Some(decisive) {
+                return Ok(boolean(left));
+            }
+            let right = truth(&self.evaluate(right, scope)?);
+            return Ok(boolean(match operator {
+                BinaryOperator::And => and(left, right),
+                _ => and(left.map(|left| !left), right.map(|right| !right)).map(|both| !both),
+            }));
+        }
+
+        let affinity = comparison_affinity(left, right, scope);
+        let collation = comparison_collation(left, right, scope);
+        let left = self.evaluate(left, scope)?;
+        let right = self.evaluate(right, scope)?;
+        // Only comparisons convert their operands
+        let (left, right) = match operator {
+            BinaryOperator::LessThan
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterOrEqual
+            | BinaryOperator::Equals
+            | BinaryOperator::NotEquals
+            | BinaryOperator::Is
+            | BinaryOperator::IsNot => (convert(left, affinity), convert(right, affinity)),
+            _ => (left, right),
+        };
+        Ok(match operator {
+            BinaryOperator::Concat => match (&left, &right) {
+                (Value::Null, _) | (_, Value::Null) => Value::Null,
+                _ => Value::Text(format!("{left}{right}")),
+            },
+            BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulo
+            | BinaryOperator::Add
+            | BinaryOperator::Subtract => arithmetic(operator, &left, &right),
+            BinaryOperator::BitAnd
+            | BinaryOperator::BitOr
+            | BinaryOperator::ShiftLeft
+            | BinaryOperator::ShiftRight => match (to_integer(&left), to_integer(&right)) {
+                (Some(left), Some(right)) => Value::Integer(bitwise(operator, left, right)),
+                _ => Value::Null,
+            },
+            BinaryOperator::LessThan => {
+                boolean(compare(&left, &right, collation).map(Ordering::is_lt))
+            }
+            BinaryOperator::LessOrEqual => {
+                boolean(compare(&left, &right, collation).map(Ordering::is_le))
+            }
+            BinaryOperator::GreaterThan => {
+                boolean(compare(&left, &right, collation).map(Ordering::is_gt))
+            }
+            BinaryOperator::GreaterOrEqual => {
+                boolean(compare(&left, &right, collation).map(Ordering::is_ge))
+            }
+            BinaryOperator::Equals => {
+                boolean(compare(&left, &right, collation).map(Ordering::is_eq))
+            }
+            BinaryOperator::NotEquals => {
+                boolean(compare(&left, &right, collation).map(Ordering::is_ne))
+            }
+            BinaryOperator::Is => Value::Integer(same(&left, &right, collation) as i64),
+            BinaryOperator::IsNot => Value::Integer(!same(&left, &right, collation) as i64),
+            BinaryOperator::And | BinaryOperator::Or => unreachable!(),
+        })
+    }
+
+    /// Runs a subquery used as a value or IN list, which must return a single column
+    fn subquery_column(
+        &mut self,
+        subquery: &Select,
+        scope: &Scope,
+    ) -> Result<Vec<Value>, DatabaseError> {
+        let result = self.query(subquery, Some(scope))?;
+        if result.columns.len() != 1 {
+            return Err(DatabaseError::ExecutionError(format!(
+                "sub-select returns {} columns - expected 1",
+                result.columns.len()
+            )));
+        }
+        Ok(result
+            .rows
+            .into_iter()
+            .filter_map(|row| row.into_iter().next())
+            .collect())
+    }
+}
+
+/// The truth value of a condition, unknown for NULL
+pub(super) fn truth(value: &Value) -> Option<bool> {
+    match to_number(value) {
+        Value::Integer(integer) => Some(integer != 0),
+        Value::Double(double) => Some(double != 0.0),
+        _ => None,
+    }
+}
+
+fn boolean(value: Option<bool>) -> Value {
+    match value {
+        Some(value) => Value::Integer(value as i64),
+        None => Value::Null,
+    }
+}
+
+/// Three-valued AND: false wins over unknown
+fn and(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+/// The collation a COLLATE clause gives the expression
+pub(super) fn collation(expression: &Expression) -> Option<&str> {
+    match expression {
+        Expression::Collate { collation, .. } => Some(collation),
+        Expression::Nested(expression) => collation(expression),
+        _ => None,
+    }
+}
+
+/// The collation of the left operand of a comparison: its COLLATE clause, or the declared
+/// one of the column it is
+pub(super) fn left_collation<'e>(expression: &'e Expression, scope: &'e Scope) -> Option<&'e str> {
+    collation(expression).or_else(|| column_collation(expression, scope))
+}
+
+/// The collation a comparison uses: a COLLATE clause on the left, then on the right, then the
+/// declared collation of a column on the left, then on the right
+pub(super) fn comparison_collation<'e>(
+    left: &'e Expression,
+    right: &'e Expression,
+    scope: &'e Scope,
+) -> Option<&'e str> {
+    collation(left)
+        .or(collation(right))
+        .or_else(|| column_collation(left, scope))
+        .or_else(|| column_collation(right, scope))
+}
+
+/// The declared collation of the table column an expression is
+pub(super) fn column_collation<'e>(expression: &Expression, scope: &'e Scope) -> Option<&'e str> {
+    match expression {
+        Expression::Column(column) => {
+            let (scope, index) = scope.resolve(column).ok()?;
+            scope.columns[index].collation.as_deref()
+        }
+        Expression::Nested(expression) => column_collation(expression, scope),
+        _ => None,
+    }
+}
+
+/// The affinity of an expression: a table column has the one of its declared type and a CAST
+/// the one of its type, the others have none
+fn affinity(expression: &Expression, scope: &Scope) -> Option<Affinity> {
+    match expression {
+        Expression::Column(column) => {
+            let (scope, index) = scope.resolve(column).ok()?;
+            scope.columns[index].affinity
+        }
+        Expression::Cast { data_type, .. } => Some(Affinity::of(&data_type.name)),
+        Expression::Collate { expression, .. } | Expression::Nested(expression) => {
+            affinity(expression, scope)
+        }
+        _ => None,
+    }
+}
+
+/// The affinity a comparison converts both operands to, see `Type Conversions Prior To
+/// Comparison`: a numeric one if an operand has one, else the one of the only operand with an
+/// affinity
+fn comparison_affinity(left: &Expression, right: &Expression, scope: &Scope) -> Option<Affinity> {
+    let numeric = |affinity| {
+        matches!(
+            affinity,
+            Affinity::Integer | Affinity::Real | Affinity::Numeric
+        )
+    };
+    match (affinity(left, scope), affinity(right, scope)) {
+        (Some(left), Some(right)) if numeric(left) || numeric(right) => Some(Affinity::Numeric),
+        (Some(_), Some(_)) => None,
+        (affinity, None) | (None, affinity) => affinity,
+    }
+}
+
+/// Converts a value to the affinity of a comparison, if it has one
+pub(super) fn convert(value: Value, affinity: Option<Affinity>) -> Value {
+    match affinity {
+        Some(affinity) => apply_affinity(value, affinity),
+        None => value,
+    }
+}
+
+/// Compares two values, texts by the given collation
+pub(super) fn compare_collated(left: &Value, right: &Value, collation: Option<&str>) -> Ordering {
+    match (left, right, collation) {
+        (Value::Text(left), Value::Text(right), Some(collation)) => {
+            if collation.eq_ignore_ascii_case("NOCASE") {
+                left.to_ascii_lowercase().cmp(&right.to_ascii_lowercase())
+            } else if collation.eq_ignore_ascii_case("RTRIM") {
+                left.trim_end_matches(' ').cmp(right.trim_end_matches(' '))
+            } else {
+                left.cmp(right)
+            }
+        }
+        _ => left.cmp(right),
+    }
+}
+
+/// Compares two values, unknown if one of them is NULL
+fn compare(left: &Value, right: &Value, collation: Option<&str>) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        _ => Some(compare_collated(left, right, collation)),
+    }
+}
+
+/// The NULL-aware equality of IS
+fn same(left: &Value, right: &Value, collation: Option<&str>) -> bool {
+    match (left, right) {
+        (Value::Null, Value::Null) => true,
+        _ => compare(left, right, collation) == Some(Ordering::Equal),
+    }
+}
+
+/// Whether the value is in the list, unknown if it isn't but the list holds a NULL
+fn contains(value: &Value, candidates: &[Value], collation: Option<&str>) -> Option<bool> {
+    if candidates.is_empty() {
+        return Some(false);
+    }
+    if candidates
+        .iter()
+        .any(|candidate| compare(value, candidate, collation) == Some(Ordering::Equal))
+    {
+        return Some(true);
+    }
+    if *value == Value::Null || candidates.contains(&Value::Null) {
+        return None;
+    }
+    Some(false)
+}
+
+/// The numeric value of a text: its longest prefix that reads as a number, 0 if there is none
+fn numeric_prefix(text: &str) -> Value {
+    let text = text.trim_start();
+    let bytes = text.as_bytes();
+    let digits = |mut end: usize| {
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+        end
+    };
+
+    let start = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    let mut end = digits(start);
+    let mut has_digits = end > start;
+    let mut integral = true;
+    if bytes.get(end) == Some(&b'.') {
+        let fraction = digits(end + 1);
+        if fraction > end + 1 || has_digits {
+            has_digits = true;
+            integral = false;
+            end = fraction;
+        }
+    }
+    if !has_digits {
+        return Value::Integer(0);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        let exponent = digits(end + 1 + sign);
+        if exponent > end + 1 + sign {
+            integral = false;
+            end = exponent;
+        }
+    }
+
+    let number = &text[..end];
+    if integral {
+        if let Ok(integer) = number.parse() {
+            return Value::Integer(integer);
+        }
+    }
+    Value::Double(number.parse().unwrap_or(0.0))
+}
+
+/// Converts text and blobs to numbers for arithmetic, NULL stays NULL
+pub(super) fn to_number(value: &Value) -> Value {
+    match value {
+        Value::Text(text) => numeric_prefix(text),
+        Value::Blob(blob) => numeric_prefix(&String::from_utf8_lossy(blob)),
+        value => value.clone(),
+    }
+}
+
+pub(super) fn to_integer(value: &Value) -> Option<i64> {
+    match to_number(value) {
+        Value::Integer(integer) => Some(integer),
+        Value::Double(double) => Some(double as i64),
+        _ => None,
+    }
+}
+
+pub(super) fn to_double(value: &Value) -> Option<f64> {
+    match to_number(value) {
+        Value::Integer(integer) => Some(integer as f64),
+        Value::Double(double) => Some(double),
+        _ => None,
+    }
+}
+
+fn unary(operator: UnaryOperator, value: Value) -> Value {
+    match (operator, to_number(&value)) {
+        (UnaryOperator::Plus, _) => value,
+        (_, Value::Null) => Value::Null,
+        (UnaryOperator::Minus, Value::Integer(integer)) => match integer.checked_neg() {
+            Some(negated) => Value::Integer(negated),
+            None => Value::Double(-(integer as f64)),
+        },
+        (UnaryOperator::Minus, Value::Double(double)) => Value::Double(-double),
+        (UnaryOperator::BitNot, number) => Value::Integer(!to_integer(&number).unwrap_or_default()),
+        (UnaryOperator::Not, number) => boolean(truth(&number).map(|truth| !truth)),
+        (_, number) => number,
+    }
+}
+
+/// Integer arithmetic falls back to doubles on overflow, a division by zero is NULL
+fn arithmetic(operator: BinaryOperator, left: &Value, right: &Value) -> Value {
+    match (to_number(left), to_number(right)) {
+        (Value::Null, _) | (_, Value::Null) => Value::Null,
+        (Value::Integer(left), Value::Integer(right)) => {
+            let result = match operator {
+                BinaryOperator::Add => left.checked_add(right),
+                BinaryOperator::Subtract => left.checked_sub(right),
+                BinaryOperator::Multiply => left.checked_mul(right),
+                BinaryOperator::Divide | BinaryOperator::Modulo if right == 0 => {
+                    return Value::Null
+                }
+                BinaryOperator::Divide => left.checked_div(right),
+                _ => Some(left.wrapping_rem(right)),
+            };
+            match result {
+                Some(result) => Value::Integer(result),
+                None => arithmetic(
+                    operator,
+                    &Value::Double(left as f64),
+                    &Value::Double(right as f64),
+                ),
+            }
+        }
+        (left, right) => {
+            let (left, right) = (
+                to_double(&left).unwrap_or_default(),
+                to_double(&right).unwrap_or_default(),
+            );
+            match operator {
+                BinaryOperator::Add => Value::Double(left + right),
+                BinaryOperator::Subtract => Value::Double(left - right),
+                BinaryOperator::Multiply => Value::Double(left * right),
+                BinaryOperator::Divide if right == 0.0 => Value::Null,
+                BinaryOperator::Divide => Value::Double(left / right),
+                // The remainder of doubles is taken of their integer parts
+                _ => match (left as i64, right as i64) {
+                    (_, 0) => Value::Null,
+                    (left, right) => Value::Double(left.wrapping_rem(right) as f64),
+                },
+            }
+        }
+    }
+}
+
+fn bitwise(operator: BinaryOperator, left: i64, right: i64) -> i64 {
+    // A negative shift goes the other way
+    let (operator, right) = match operator {
+        BinaryOperator::ShiftLeft if right < 0 => (
+            BinaryOperator::ShiftRight,
+            right.checked_neg().unwrap_or(i64::MAX),
+        ),
+        BinaryOperator::ShiftRight if right < 0 => (
+            BinaryOperator::ShiftLeft,
+            right.checked_neg().unwrap_or(i64::MAX),
+        ),
+        _ => (operator, right),
+    };
+    match operator {
+        BinaryOperator::BitAnd => left & right,
+        BinaryOperator::BitOr => left | right,
+        BinaryOperator::ShiftLeft if right >= 64 => 0,
+        BinaryOperator::ShiftLeft => left << right,
+        BinaryOperator::ShiftRight if right >= 64 => left >> 63,
+        _ => left >> right,
+    }
+}
+
+pub(super) fn cast(value: Value, affinity: Affinity) -> Value {
+    if value == Value::Null {
+        return Value::Null;
+    }
+    match affinity {
+        Affinity::Integer => Value::Integer(to_integer(&value).unwrap_or_default()),
+        Affinity::Real => Value::Double(to_double(&value).unwrap_or_default()),
+        Affinity::Text => match value {
+            Value::Blob(blob) => Value::Text(String::from_utf8_lossy(&blob).into_owned()),
+            Value::Text(text) => Value::Text(text),
+            value => Value::Text(value.to_string()),
+        },
+        Affinity::Blob => match value {
+            Value::Blob(blob) => Value::Blob(blob),
+            Value::Text(text) => Value::Blob(text.into_bytes()),
+            value => Value::Blob(value.to_string().into_bytes()),
+        },
+        // Integral values become integers
+        Affinity::Numeric => match to_number(&value) {
+            Value::Double(double) if double.fract() == 0.0 && double.abs() < i64::MAX as f64 => {
+                Value::Integer(double as i64)
+            }
+            number => number,
+        },
+    }
+}
+
+/// Converts a value stored in a column to the column's affinity. Unlike CAST nothing is lost:
+/// text only becomes a number if it reads as one as a whole, and only integral reals become
+/// integers
+pub(super) fn apply_affinity(value: Value, affinity: Affinity) -> Value {
+    match (affinity, value) {
+        (Affinity::Text, value @ (Value::Integer(_) | Value::Double(_))) => {
+            Value::Text(value.to_string())
+        }
+        (Affinity::Text | Affinity::Blob, value) => value,
+        (_, Value::Text(text)) if is_number(&text) => {
+            apply_affinity(numeric_prefix(&text), affinity)
+        }
+        (Affinity::Real, Value::Integer(integer)) => Value::Double(integer as f64),
+        (Affinity::Integer | Affinity::Numeric, Value::Double(double))
+            if double.fract() == 0.0 && double.abs() < i64::MAX as f64 =>
+        {
+            Value::Integer(double as i64)
+        }
+        (_, value) => value,
+    }
+}
+
+/// Returns true if the whole text, but the spaces around it, is a decimal number
+fn is_number(text: &str) -> bool {
+    let text = text.trim();
+    !text.is_empty()
+        && text
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || b"+-.eE".contains(&byte))
+        && text.parse::<f64>().is_ok()
+}
+
+/// A piece of a LIKE or GLOB pattern
+enum PatternToken {
+    /// `%` or `*`
+    Any,
+    /// `_` or `?`
+    One,
+    Char(char),
+    /// A GLOB `[...]` class, with its inclusive ranges
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+fn pattern_match(
+    operator: PatternOperator,
+    value: &Value,
+    pattern: &Value,
+    escape: Option<&Value>,
+) -> Result<Option<bool>, DatabaseError> {
+    let escape = match escape {
+        Some(Value::Null) => return Ok(None),
+        Some(escape) => {
+            let escape = escape.to_string();
+            let mut chars = escape.chars();
+            match (chars.next(), chars.next()) {
+                (Some(escape), None) => Some(escape),
+                _ => {
+                    return Err(DatabaseError::ExecutionError(
+                        "ESCAPE expression must be a single character".into(),
+                    ))
+                }
+            }
+        }
+        None => None,
+    };
+    if *value == Value::Null || *pattern == Value::Null {
+        return Ok(None);
+    }
+    let text: Vec<char> = value.to_string().chars().collect();
+    let pattern = pattern.to_string();
+    Ok(Some(match operator {
+        PatternOperator::Like => matches(&like_pattern(&pattern, escape), &text, true),
+        PatternOperator::Glob => matches(&glob_pattern(&pattern), &text, false),
+        PatternOperator::Regexp | PatternOperator::Match => {
+            return Err(DatabaseError::ExecutionError(format!(
+                "no such function: {}",
+                format!("{operator:?}").to_ascii_lowercase()
+            )))
+        }
+    }))
+}
+
+fn like_pattern(pattern: &str, escape: Option<char>) -> Vec<PatternToken> {
+    let mut tokens = vec![];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            c if Some(c) == escape => PatternToken::Char(chars.next().unwrap_or(c)),
+            '%' => PatternToken::Any,
+            '_' => PatternToken::One,
+            c => PatternToken::Char(c),
+        });
+    }
+    tokens
+}
+
+fn glob_pattern(pattern: &str) -> Vec<PatternToken> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = vec![];
+    let mut index = 0;
+    while index < chars.len() {
+        let token = match chars[index] {
+            '*' => PatternToken::Any,
+            '?' => PatternToken::One,
+            '[' => match glob_class(&chars[index + 1..]) {
+                Some((class, length)) => {
+                    index += length;
+                    class
+                }
+                None => PatternToken::Char('['),
+            },
+            c => PatternToken::Char(c),
+        };
+        tokens.push(token);
+        index += 1;
+    }
+    tokens
+}
+
+/// Parses the inside of a `[...]` class, returning it with the length up to the `]`
+fn glob_class(chars: &[char]) -> Option<(PatternToken, usize)> {
+    let negated = chars.first() == Some(&'^');
+    let mut index = negated as usize;
+    let mut ranges = vec![];
+    // A `]` right at the start is part of the class
+    if chars.get(index) == Some(&']') {
+        ranges.push((']', ']'));
+        index += 1;
+    }
+    while let Some(&c) = chars.get(index) {
+        if c == ']' {
+            return Some((PatternToken::Class { negated, ranges }, index + 1));
+        }
+        match (chars.get(index + 1), chars.get(index + 2)) {
+            (Some('-'), Some(&end)) if end != ']' => {
+                ranges.push((c, end));
+                index += 3;
+            }
+            _ => {
+                ranges.push((c, c));
+                index += 1;
+            }
+        }
+    }
+    None
+}
+
+/// Matches the text against the pattern, backtracking to the last `%` on a mismatch
+fn matches(pattern: &[PatternToken], text: &[char], ignore_case: bool) -> bool {
+    let matches_char = |token: &PatternToken, c: char| match token {
+        PatternToken::Any | PatternToken::One => true,
+        PatternToken::Char(expected) if ignore_case => expected.eq_ignore_ascii_case(&c),
+        PatternToken::Char(expected) => *expected == c,
+        PatternToken::Class { negated, ranges } => {
+            ranges.iter().any(|(low, high)| (*low..=*high).contains(&c)) != *negated
+        }
+    };
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(PatternToken::Any) => {
+                backtrack = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some(token) if matches_char(token, text[t]) => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            // Lets the last `%` swallow one more character
+            Some((any, start)) => {
+                p = any + 1;
+                t = start + 1;
+                backtrack = Some((any, start + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..]
+        .iter()
+        .all(|token| matches!(token, PatternToken::Any))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_prefix() {
+        assert_eq!(numeric_prefix(" 12abc"), Value::Integer(12));
+        assert_eq!(numeric_prefix("-1.5e2x"), Value::Double(-150.0));
+        assert_eq!(numeric_prefix(".5"), Value::Double(0.5));
+        assert_eq!(numeric_prefix("1e"), Value::Integer(1));
+        assert_eq!(numeric_prefix("abc"), Value::Integer(0));
+        assert_eq!(numeric_prefix("99999999999999999999"), Value::Double(1e20));
+    }
+
+    #[test]
+    fn test_apply_affinity() {
+        let text = |text: &str| Value::Text(text.into());
+        for (value, affinity, expected) in [
+            (text("5"), Affinity::Real, Value::Double(5.0)),
+            (text(" 7 "), Affinity::Integer, Value::Integer(7)),
+            (Value::Double(2.0), Affinity::Integer, Value::Integer(2)),
+            (Value::Double(2.5), Affinity::Integer, Value::Double(2.5)),
+            (text("10.0"), Affinity::Numeric, Value::Integer(10)),
+            (text("1e3"), Affinity::Numeric, Value::Integer(1000)),
+            (text("12abc"), Affinity::Numeric, text("12abc")),
+            (text("inf"), Affinity::Real, text("inf")),
+            (Value::Integer(8), Affinity::Text, text("8")),
+            (Value::Double(8.0), Affinity::Text, text("8.0")),
+            (text("8"), Affinity::Blob, text("8")),
+            (Value::Null, Affinity::Integer, Value::Null),
+        ] {
+            assert_eq!(
+                apply_affinity(value.clone(), affinity),
+                expected,
+                "{value:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_patterns() {
+        let like = |text: &str, pattern: &str| {
+            let text: Vec<char> = text.chars().collect();
+            matches(&like_pattern(pattern, Some('\\')), &text, true)
+        };
+        assert!(like("Hello", "h%O"));
+        assert!(like("Hello", "_ell_"));
+        assert!(like("a%b", "a\\%b"));
+        assert!(!like("axb", "a\\%b"));
+        assert!(like("aaa", "%a%a%a%"));
+        assert!(!like("aa", "%a%a%a%"));
+
+        let glob = |text: &str, pattern: &str| {
+            let text: Vec<char> = text.chars().collect();
+            matches(&glob_pattern(pattern), &text, false)
+        };
+        assert!(glob("Hello", "H*o"));
+        assert!(!glob("Hello", "h*"));
+        assert!(glob("b2", "[a-c][0-9]"));
+        assert!(glob("x", "[^a-c]"));
+        assert!(glob("]", "[]]"));
+        assert!(glob("[", "["));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let integer = Value::Integer;
+        assert_eq!(
+            arithmetic(BinaryOperator::Divide, &integer(7), &integer(0)),
+            Value::Null
+        );
+        assert_eq!(
+            arithmetic(BinaryOperator::Add, &integer(i64::MAX), &integer(1)),
+            Value::Double(i64::MAX as f64 + 1.0)
+        );
+        assert_eq!(
+            arithmetic(BinaryOperator::Modulo, &Value::Double(5.5), &integer(2)),
+            Value::Double(1.0)
+        );
+        assert_eq!(
+            arithmetic(
+                BinaryOperator::Multiply,
+                &Value::Text("3x".into()),
+                &integer(2)
+            ),
+            integer(6)
+        );
+        assert_eq!(bitwise(BinaryOperator::ShiftLeft, 1, -1), 0);
+        assert_eq!(bitwise(BinaryOperator::ShiftRight, -8, 100), -1);
+        assert_eq!(
+            unary(UnaryOperator::Minus, integer(i64::MIN)),
+            Value::Double(9.223372036854776e18)
+        );
+        assert_eq!(
+            cast(Value::Text("3.0".into()), Affinity::Numeric),
+            integer(3)
+        );
+        assert_eq!(Affinity::of("VARCHAR(10)"), Affinity::Text);
+        assert_eq!(Affinity::of("FLOATING POINT"), Affinity::Integer);
+    }
+}
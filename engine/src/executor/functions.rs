@@ -0,0 +1,531 @@
+use super::expression::{to_double, to_integer, to_number, truth};
+use super::{Executor, Scope};
+use crate::errors::DatabaseError;
+use common::types::Value;
+use sql::ast::Expression;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+/// Aggregates compute a value over the rows of a group. MIN and MAX with several arguments
+/// are scalar functions instead
+pub(super) fn is_aggregate(name: &str, arguments: usize) -> bool {
+    match name.to_ascii_lowercase().as_str() {
+        "count" | "sum" | "total" | "avg" | "group_concat" | "string_agg" => true,
+        "min" | "max" => arguments == 1,
+        _ => false,
+    }
+}
+
+/// Collects the aggregate calls of an expression, leaving out the ones of its subqueries
+pub(super) fn aggregates<'e>(expression: &'e Expression, found: &mut Vec<&'e Expression>) {
+    let mut visit = |expression: &'e Expression| aggregates(expression, found);
+    match expression {
+        Expression::Function {
+            name, arguments, ..
+        } if is_aggregate(name, arguments.len()) => found.push(expression),
+        Expression::Literal(_)
+        | Expression::Column(_)
+        | Expression::Wildcard(_)
+        | Expression::Subquery(_)
+        | Expression::Exists(_) => {}
+        Expression::Unary { operand, .. } => visit(operand),
+        Expression::Binary { left, right, .. } | Expression::IsDistinctFrom { left, right, .. } => {
+            visit(left);
+            visit(right);
+        }
+        Expression::Collate { expression, .. }
+        | Expression::Cast { expression, .. }
+        | Expression::Nested(expression)
+        | Expression::IsNull { expression, .. }
+        | Expression::InSubquery { expression, .. } => visit(expression),
+        Expression::Function {
+            arguments, filter, ..
+        } => {
+            arguments.iter().for_each(&mut visit);
+            filter.iter().for_each(|filter| visit(filter));
+        }
+        Expression::Like {
+            expression,
+            pattern,
+            escape,
+            ..
+        } => {
+            visit(expression);
+            visit(pattern);
+            escape.iter().for_each(|escape| visit(escape));
+        }
+        Expression::Between {
+            expression,
+            low,
+            high,
+            ..
+        } => {
+            visit(expression);
+            visit(low);
+            visit(high);
+        }
+        Expression::InList {
+            expression, list, ..
+        } => {
+            visit(expression);
+            list.iter().for_each(visit);
+        }
+        Expression::Case {
+            operand,
+            branches,
+            else_result,
+        } => {
+            operand.iter().for_each(|operand| visit(operand));
+            for (condition, result) in branches {
+                visit(condition);
+                visit(result);
+            }
+            else_result.iter().for_each(|result| visit(result));
+        }
+    }
+}
+
+fn check_arguments(
+    name: &str,
+    arguments: usize,
+    expected: RangeInclusive<usize>,
+) -> Result<(), DatabaseError> {
+    if expected.contains(&arguments) {
+        Ok(())
+    } else {
+        Err(DatabaseError::ExecutionError(format!(
+            "wrong number of arguments to function {name}()"
+        )))
+    }
+}
+
+impl Executor<'_> {
+    pub(super) fn function(
+        &mut self,
+        name: &str,
+        arguments: &[Expression],
+        distinct: bool,
+        filter: Option<&Expression>,
+        scope: &Scope,
+    ) -> Result<Value, DatabaseError> {
+        if is_aggregate(name, arguments.len()) {
+            return self.aggregate(name, arguments, distinct, filter, scope);
+        }
+        if distinct || filter.is_some() {
+            return Err(DatabaseError::ExecutionError(format!(
+                "{} may not be used with non-aggregate {name}()",
+                if distinct { "DISTINCT" } else { "FILTER" }
+            )));
+        }
+
+        // These evaluate their arguments only as far as needed
+        let lowercase = name.to_ascii_lowercase();
+        match lowercase.as_str() {
+            "coalesce" | "ifnull" => {
+                let expected = if lowercase == "ifnull" {
+                    2..=2
+                } else {
+                    2..=usize::MAX
+                };
+                check_arguments(name, arguments.len(), expected)?;
+                for argument in arguments {
+                    let value = self.evaluate(argument, scope)?;
+                    if value != Value::Null {
+                        return Ok(value);
+                    }
+                }
+                return Ok(Value::Null);
+            }
+            "iif" => {
+                check_arguments(name, arguments.len(), 3..=3)?;
+                let condition = self.evaluate(&arguments[0], scope)?;
+                let branch = if truth(&condition) == Some(true) {
+                    1
+                } else {
+                    2
+                };
+                return self.evaluate(&arguments[branch], scope);
+            }
+            _ => {}
+        }
+
+        let values = arguments
+            .iter()
+            .map(|argument| self.evaluate(argument, scope))
+            .collect::<Result<Vec<_>, _>>()?;
+        scalar(name, &lowercase, values)
+    }
+
+    fn aggregate(
+        &mut self,
+        name: &str,
+        arguments: &[Expression],
+        distinct: bool,
+        filter: Option<&Expression>,
+        scope: &Scope,
+    ) -> Result<Value, DatabaseError> {
+        let Some(group) = scope.group else {
+            return Err(DatabaseError::ExecutionError(format!(
+                "misuse of aggregate function {name}()"
+            )));
+        };
+        let lowercase = name.to_ascii_lowercase();
+        let expected = match lowercase.as_str() {
+            "count" => 0..=1,
+            "group_concat" => 1..=2,
+            "string_agg" => 2..=2,
+            _ => 1..=1,
+        };
+        check_arguments(name, arguments.len(), expected)?;
+        // `COUNT(*)` and `COUNT()` count the rows themselves
+        let rows_only = match arguments.first() {
+            None => true,
+            Some(Expression::Wildcard(None)) if lowercase == "count" => true,
+            _ => false,
+        };
+        if distinct && arguments.len() != 1 {
+            return Err(DatabaseError::ExecutionError(
+                "DISTINCT aggregates must have exactly one argument".into(),
+            ));
+        }
+
+        let mut count = 0;
+        let mut seen = HashSet::new();
+        // The non-NULL values of the first argument, with the separator to put before them
+        let mut values: Vec<(Value, Option<Value>)> = vec![];
+        for row in group {
+            let inner = Scope {
+                columns: scope.columns,
+                row,
+                group: None,
+                outer: scope.outer,
+            };
+            if let Some(filter) = filter {
+                if truth(&self.evaluate(filter, &inner)?) != Some(true) {
+                    continue;
+                }
+            }
+            if rows_only {
+                count += 1;
+                continue;
+            }
+            let value = self.evaluate(&arguments[0], &inner)?;
+            if value == Value::Null || (distinct && !seen.insert(value.clone())) {
+                continue;
+            }
+            let separator = match arguments.get(1) {
+                Some(separator) => Some(self.evaluate(separator, &inner)?),
+                None => None,
+            };
+            values.push((value, separator));
+        }
+
+        let numbers = || values.iter().map(|(value, _)| to_number(value));
+        Ok(match lowercase.as_str() {
+            "count" if rows_only => Value::Integer(count),
+            "count" => Value::Integer(values.len() as i64),
+            "sum" if values.is_empty() => Value::Null,
+            "sum" if numbers().all(|number| matches!(number, Value::Integer(_))) => {
+                let mut sum: i64 = 0;
+                for number in numbers() {
+                    sum = sum
+                        .checked_add(to_integer(&number).unwrap_or_default())
+                        .ok_or_else(|| DatabaseError::ExecutionError("integer overflow".into()))?;
+                }
+                Value::Integer(sum)
+            }
+            "sum" | "total" => {
+                Value::Double(numbers().filter_map(|number| to_double(&number)).sum())
+            }
+            "avg" if values.is_empty() => Value::Null,
+            "avg" => {
+                let sum: f64 = numbers().filter_map(|number| to_double(&number)).sum();
+                Value::Double(sum / values.len() as f64)
+            }
+            "min" => values
+                .into_iter()
+                .map(|(value, _)| value)
+                .min()
+                .unwrap_or(Value::Null),
+            "max" => values
+                .into_iter()
+                .map(|(value, _)| value)
+                .max()
+                .unwrap_or(Value::Null),
+            _ => {
+                let mut values = values.into_iter();
+                let Some((first, _)) = values.next() else {
+                    return Ok(Value::Null);
+                };
+                let mut text = first.to_string();
+                for (value, separator) in values {
+                    match separator {
+                        Some(separator) => text.push_str(&separator.to_string()),
+                        None => text.push(','),
+                    }
+                    text.push_str(&value.to_string());
+                }
+                Value::Text(text)
+            }
+        })
+    }
+}
+
+fn scalar(name: &str, lowercase: &str, values: Vec<Value>) -> Result<Value, DatabaseError> {
+    let arguments = |expected: RangeInclusive<usize>| check_arguments(name, values.len(), expected);
+    let text = |value: &Value| match value {
+        Value::Null => None,
+        value => Some(value.to_string()),
+    };
+
+    Ok(match lowercase {
+        "abs" => {
+            arguments(1..=1)?;
+            match to_number(&values[0]) {
+                Value::Integer(integer) => Value::Integer(
+                    integer
+                        .checked_abs()
+                        .ok_or_else(|| DatabaseError::ExecutionError("integer overflow".into()))?,
+                ),
+                Value::Double(double) => Value::Double(double.abs()),
+                number => number,
+            }
+        }
+        "length" => {
+            arguments(1..=1)?;
+            match &values[0] {
+                Value::Null => Value::Null,
+                Value::Blob(blob) => Value::Integer(blob.len() as i64),
+                value => Value::Integer(value.to_string().chars().count() as i64),
+            }
+        }
+        "lower" | "upper" => {
+            arguments(1..=1)?;
+            match text(&values[0]) {
+                Some(text) if lowercase == "lower" => Value::Text(text.to_ascii_lowercase()),
+                Some(text) => Value::Text(text.to_ascii_uppercase()),
+                None => Value::Null,
+            }
+        }
+        "typeof" => {
+            arguments(1..=1)?;
+            Value::Text(
+                match values[0] {
+                    Value::Null => "null",
+                    Value::Integer(_) => "integer",
+                    Value::Double(_) => "real",
+                    Value::Text(_) => "text",
+                    Value::Blob(_) => "blob",
+                }
+                .into(),
+            )
+        }
+        "nullif" => {
+            arguments(2..=2)?;
+            if values[1] != Value::Null && values[0] == values[1] {
+                Value::Null
+            } else {
+                values[0].clone()
+            }
+        }
+        "substr" | "substring" => {
+            arguments(2..=3)?;
+            if values.contains(&Value::Null) {
+                return Ok(Value::Null);
+            }
+            let chars: Vec<char> = values[0].to_string().chars().collect();
+            let start = to_integer(&values[1]).unwrap_or_default();
+            let length = values.get(2).and_then(to_integer);
+            let (start, length) = substring_range(chars.len() as i64, start, length);
+            Value::Text(chars[start..start + length].iter().collect())
+        }
+        "trim" | "ltrim" | "rtrim" => {
+            arguments(1..=2)?;
+            let (Some(value), characters) = (text(&values[0]), values.get(1).map(text)) else {
+                return Ok(Value::Null);
+            };
+            let characters: Vec<char> = match characters {
+                Some(Some(characters)) => characters.chars().collect(),
+                Some(None) => return Ok(Value::Null),
+                None => vec![' '],
+            };
+            let trimmed = match lowercase {
+                "ltrim" => value.trim_start_matches(characters.as_slice()),
+                "rtrim" => value.trim_end_matches(characters.as_slice()),
+                _ => value.trim_matches(characters.as_slice()),
+            };
+            Value::Text(trimmed.into())
+        }
+        "replace" => {
+            arguments(3..=3)?;
+            match (text(&values[0]), text(&values[1]), text(&values[2])) {
+                (Some(value), Some(pattern), _) if pattern.is_empty() => Value::Text(value),
+                (Some(value), Some(pattern), Some(replacement)) => {
+                    Value::Text(value.replace(&pattern, &replacement))
+                }
+                _ => Value::Null,
+            }
+        }
+        "instr" => {
+            arguments(2..=2)?;
+            match (text(&values[0]), text(&values[1])) {
+                (Some(value), Some(needle)) => Value::Integer(
+                    value
+                        .find(&needle)
+                        .map_or(0, |index| value[..index].chars().count() as i64 + 1),
+                ),
+                _ => Value::Null,
+            }
+        }
+        "round" => {
+            arguments(1..=2)?;
+            let digits = match values.get(1) {
+                Some(Value::Null) => return Ok(Value::Null),
+                Some(digits) => to_integer(digits).unwrap_or_default().clamp(0, 30),
+                None => 0,
+            };
+            match to_double(&values[0]) {
+                // Rounds half away from zero, unlike formatting
+                Some(number) if digits <= 15 => {
+                    let scale = 10f64.powi(digits as i32);
+                    Value::Double((number * scale).round() / scale)
+                }
+                Some(number) => Value::Double(number),
+                None => Value::Null,
+            }
+        }
+        "min" | "max" => {
+            arguments(2..=usize::MAX)?;
+            if values.contains(&Value::Null) {
+                return Ok(Value::Null);
+            }
+            let values = values.into_iter();
+            if lowercase == "min" {
+                values.min()
+            } else {
+                values.max()
+            }
+            .unwrap_or(Value::Null)
+        }
+        "sign" => {
+            arguments(1..=1)?;
+            match &values[0] {
+                Value::Integer(integer) => Value::Integer(integer.signum()),
+                Value::Double(double) if *double == 0.0 => Value::Integer(0),
+                Value::Double(double) => Value::Integer(double.signum() as i64),
+                _ => Value::Null,
+            }
+        }
+        "hex" => {
+            arguments(1..=1)?;
+            let bytes = match &values[0] {
+                Value::Blob(blob) => blob.clone(),
+                value => value.to_string().into_bytes(),
+            };
+            Value::Text(bytes.iter().map(|byte| format!("{byte:02X}")).collect())
+        }
+        "quote" => {
+            arguments(1..=1)?;
+            Value::Text(match &values[0] {
+                Value::Null => "NULL".into(),
+                Value::Text(text) => format!("'{}'", text.replace('\'', "''")),
+                Value::Blob(blob) => {
+                    let hex: String = blob.iter().map(|byte| format!("{byte:02X}")).collect();
+                    format!("X'{hex}'")
+                }
+                value => value.to_string(),
+            })
+        }
+        "char" => Value::Text(
+            values
+                .iter()
+                .filter_map(to_integer)
+                .filter_map(|code| u32::try_from(code).ok().and_then(char::from_u32))
+                .collect(),
+        ),
+        "unicode" => {
+            arguments(1..=1)?;
+            match text(&values[0]).and_then(|text| text.chars().next()) {
+                Some(c) => Value::Integer(c as i64),
+                None => Value::Null,
+            }
+        }
+        _ => {
+            return Err(DatabaseError::ExecutionError(format!(
+                "no such function: {name}"
+            )))
+        }
+    })
+}
+
+/// The start and length of a `substr()` in a text of the given length, following SQLite:
+/// the start is 1-based, counts from the end if negative, and a negative length takes the
+/// characters before the start
+fn substring_range(size: i64, start: i64, length: Option<i64>) -> (usize, usize) {
+    let (mut start, mut length, negative) = match length {
+        Some(length) if length < 0 => (start, length.saturating_neg(), true),
+        Some(length) => (start, length, false),
+        None => (start, i64::MAX, false),
+    };
+    if start < 0 {
+        start = start.saturating_add(size);
+        if start < 0 {
+            length = (length.saturating_add(start)).max(0);
+            start = 0;
+        }
+    } else if start > 0 {
+        start -= 1;
+    } else if length > 0 {
+        length -= 1;
+    }
+    if negative {
+        start -= length;
+        if start < 0 {
+            length += start;
+            start = 0;
+        }
+    }
+    let start = start.min(size);
+    let length = length.clamp(0, size - start);
+    (start as usize, length as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substring_range() {
+        // substr('hello', ...)
+        assert_eq!(substring_range(5, 2, Some(3)), (1, 3));
+        assert_eq!(substring_range(5, 0, Some(2)), (0, 1));
+        assert_eq!(substring_range(5, -3, None), (2, 3));
+        assert_eq!(substring_range(5, 4, Some(-2)), (1, 2));
+        assert_eq!(substring_range(5, 9, Some(2)), (5, 0));
+        assert_eq!(substring_range(5, -9, Some(5)), (0, 1));
+    }
+
+    #[test]
+    fn test_scalar_functions() {
+        let call = |name: &str, values: Vec<Value>| scalar(name, name, values).unwrap();
+        let text = |text: &str| Value::Text(text.into());
+        assert_eq!(call("trim", vec![text("xxhixx"), text("x")]), text("hi"));
+        assert_eq!(
+            call("instr", vec![text("héllo"), text("l")]),
+            Value::Integer(3)
+        );
+        assert_eq!(call("round", vec![Value::Double(2.5)]), Value::Double(3.0));
+        assert_eq!(
+            call("round", vec![Value::Double(-1.255), Value::Integer(1)]),
+            Value::Double(-1.3)
+        );
+        assert_eq!(call("quote", vec![text("it's")]), text("'it''s'"));
+        assert_eq!(call("max", vec![Value::Integer(2), text("a")]), text("a"));
+        assert_eq!(
+            call("nullif", vec![Value::Integer(1), Value::Double(1.0)]),
+            Value::Null
+        );
+        assert!(scalar("nope", "nope", vec![]).is_err());
+        assert!(scalar("abs", "abs", vec![]).is_err());
+    }
+}
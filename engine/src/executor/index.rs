@@ -0,0 +1,356 @@
+use super::expression::{collation, compare_collated, convert, truth, Affinity};
+use super::plan::Access;
+use super::select::table_columns;
+use super::{Executor, RelationColumn, Scope};
+use crate::errors::DatabaseError;
+use crate::sqlite::{scan_table, search_index, search_table, Database, SchemaObject, TableRow};
+use common::types::Value;
+use sql::ast::{ColumnRef, CreateIndex, Expression, IndexedColumn, Ordering};
+use std::ops::RangeInclusive;
+
+impl Executor<'_> {
+    /// Computes the records of a new index over the rows of a table: the values of the indexed
+    /// columns followed by the rowid, sorted in index order
+    pub fn index_records(
+        &mut self,
+        table: &SchemaObject,
+        index: &CreateIndex,
+    ) -> Result<Vec<Vec<Value>>, DatabaseError> {
+        let relation = self.read_table(self.database, table, &table.name, &Access::Scan)?;
+        // Unknown columns and collations are errors even if the table is empty
+        let nulls = vec![Value::Null; relation.columns.len()];
+        self.record_of(&relation.columns, &nulls, index)?;
+        let mut records = vec![];
+        for row in &relation.rows {
+            records.extend(self.record_of(&relation.columns, row, index)?);
+        }
+
+        let compare = key_order(table, index);
+        let keys = index.columns.len();
+        records.sort_by(|a, b| compare(a, b).then_with(|| a[keys].cmp(&b[keys])));
+
+        // NULLs are distinct from each other, keys with one never collide
+        if index.unique {
+            let duplicate = records.windows(2).any(|pair| {
+                !pair[0][..keys].contains(&Value::Null) && compare(&pair[0], &pair[1]).is_eq()
+            });
+            if duplicate {
+                return Err(DatabaseError::UniqueViolation(unique_target(table, index)));
+            }
+        }
+        Ok(records)
+    }
+
+    /// Computes the record a new row of a table has in an index, `None` if it is left out of
+    /// it. The values are the ones of the columns, the rowid alias holding the rowid. A row
+    /// whose key is already in a UNIQUE index is an error
+    pub(crate) fn new_index_record(
+        &mut self,
+        file: &Database,
+        table: &SchemaObject,
+        index: &CreateIndex,
+        root: u32,
+        values: &[Value],
+        rowid: i64,
+    ) -> Result<Option<Vec<Value>>, DatabaseError> {
+        let columns = table_columns(table, &table.name);
+        let mut row = values.to_vec();
+        row.truncate(columns.len());
+        row.resize(columns.len(), Value::Integer(rowid));
+        let Some(record) = self.record_of(&columns, &row, index)? else {
+            return Ok(None);
+        };
+
+        let keys = index.columns.len();
+        if index.unique && !record[..keys].contains(&Value::Null) {
+            let compare = key_order(table, index);
+            let encoding = file.header.text_encoding;
+            let found = search_index(&file.pager, root, encoding, &mut |existing| {
+                compare(existing, &record)
+            })?;
+            if !found.is_empty() {
+                return Err(DatabaseError::UniqueViolation(unique_target(table, index)));
+            }
+        }
+        Ok(Some(record))
+    }
+
+    /// The record of a row of a table in an index: the values of the indexed columns followed
+    /// by the rowid, `None` for a row a partial index leaves out
+    fn record_of(
+        &mut self,
+        columns: &[RelationColumn],
+        row: &[Value],
+        index: &CreateIndex,
+    ) -> Result<Option<Vec<Value>>, DatabaseError> {
+        let scope = Scope {
+            columns,
+            row,
+            group: None,
+            outer: None,
+        };
+        let mut record = vec![];
+        for column in &index.columns {
+            record.push(self.evaluate(&column.expression, &scope)?);
+        }
+        if let Some(condition) = &index.where_clause {
+            if truth(&self.evaluate(condition, &scope)?) != Some(true) {
+                return Ok(None);
+            }
+        }
+        record.push(scope.lookup(&ColumnRef::new("rowid"))?);
+        Ok(Some(record))
+    }
+
+    /// Reads the rows of a table the access path finds. The rows an index finds come in index
+    /// order, the others in rowid order
+    pub(super) fn find_rows(
+        &mut self,
+        file: &Database,
+        table: &SchemaObject,
+        access: &Access,
+    ) -> Result<Vec<TableRow>, DatabaseError> {
+        // The values are compared with the columns the way the WHERE clause does, converted
+        // to the affinity of the column
+        let (equal, range, affinities) = match access {
+            Access::Scan => return scan_table(&file.pager, table.root_page),
+            Access::Rowid { equal, range } => {
+                (equal.as_slice(), range, vec![Some(Affinity::Integer)])
+            }
+            Access::Index {
+                equal,
+                range,
+                definition,
+                ..
+            } => (
+                equal.as_slice(),
+                range,
+                definition
+                    .columns
+                    .iter()
+                    .map(|column| key_affinity(table, column))
+                    .collect(),
+            ),
+        };
+        let affinity = |position: usize| affinities[position.min(affinities.len() - 1)];
+        let mut values = vec![];
+        for value in equal {
+            values.push(convert(self.constant(value)?, affinity(values.len())));
+        }
+        let bound = affinity(values.len());
+        let low = match range.low {
+            Some((value, inclusive)) => Some((convert(self.constant(value)?, bound), inclusive)),
+            None => None,
+        };
+        let high = match range.high {
+            Some((value, inclusive)) => Some((convert(self.constant(value)?, bound), inclusive)),
+            None => None,
+        };
+        // Nothing is equal to NULL, or within a range ending at it
+        let bounds = values
+            .iter()
+            .chain(low.iter().chain(&high).map(|(value, _)| value));
+        if bounds.clone().any(|value| *value == Value::Null) {
+            return Ok(vec![]);
+        }
+
+        let Access::Index {
+            name, definition, ..
+        } = access
+        else {
+            let rowids = match values.first() {
+                Some(value) => rowid_range(Some((value, true)), Some((value, true))),
+                None => rowid_range(
+                    low.as_ref().map(|(value, inclusive)| (value, *inclusive)),
+                    high.as_ref().map(|(value, inclusive)| (value, *inclusive)),
+                ),
+            };
+            return search_table(&file.pager, table.root_page, rowids);
+        };
+
+        let root = file
+            .catalog
+            .object(name)
+            .map(|index| index.root_page)
+            .ok_or_else(|| DatabaseError::NoSuchObject("index", name.clone()))?;
+        let keys: Vec<(Option<&str>, bool)> = definition
+            .columns
+            .iter()
+            .map(|column| {
+                (
+                    key_collation(table, column),
+                    column.ordering == Some(Ordering::Descending),
+                )
+            })
+            .collect();
+        let directed = |ordering: std::cmp::Ordering, descending: bool| match descending {
+            true => ordering.reverse(),
+            false => ordering,
+        };
+        let mut position = |record: &[Value]| {
+            for ((key, value), (collation, descending)) in record.iter().zip(&values).zip(&keys) {
+                let ordering = compare_collated(key, value, *collation);
+                if ordering.is_ne() {
+                    return directed(ordering, *descending);
+                }
+            }
+            if low.is_none() && high.is_none() {
+                return std::cmp::Ordering::Equal;
+            }
+            let (key, descending) = (&record[values.len()], keys[values.len()].1);
+            if let Some((low, inclusive)) = &low {
+                let ordering = key.cmp(low);
+                if ordering.is_lt() || (ordering.is_eq() && !inclusive) {
+                    return directed(std::cmp::Ordering::Less, descending);
+                }
+            }
+            if let Some((high, inclusive)) = &high {
+                let ordering = key.cmp(high);
+                if ordering.is_gt() || (ordering.is_eq() && !inclusive) {
+                    return directed(std::cmp::Ordering::Greater, descending);
+                }
+            }
+            std::cmp::Ordering::Equal
+        };
+        let encoding = file.header.text_encoding;
+        let records = search_index(&file.pager, root, encoding, &mut position)?;
+
+        let mut rows = vec![];
+        for record in records {
+            let Some(Value::Integer(rowid)) = record.last() else {
+                return Err(DatabaseError::StateError(format!(
+                    "Malformed record of index {name}"
+                )));
+            };
+            rows.extend(search_table(&file.pager, table.root_page, *rowid..=*rowid)?);
+        }
+        Ok(rows)
+    }
+
+    /// Evaluates an expression of literals
+    pub(crate) fn constant(&mut self, expression: &Expression) -> Result<Value, DatabaseError> {
+        let scope = Scope {
+            columns: &[],
+            row: &[],
+            group: None,
+            outer: None,
+        };
+        self.evaluate(expression, &scope)
+    }
+}
+
+/// The rowids within the bounds, each given with whether it is included. Rowids are integers,
+/// the bounds of other types are rounded outwards, the WHERE clause decides about the ends
+fn rowid_range(low: Option<(&Value, bool)>, high: Option<(&Value, bool)>) -> RangeInclusive<i64> {
+    let limit = |bound: Option<(&Value, bool)>, lower: bool| -> Option<i64> {
+        let Some((value, inclusive)) = bound else {
+            return Some(if lower { i64::MIN } else { i64::MAX });
+        };
+        match value {
+            Value::Integer(integer) if inclusive => Some(*integer),
+            Value::Integer(integer) if lower => integer.checked_add(1),
+            Value::Integer(integer) => integer.checked_sub(1),
+            Value::Double(double) if double.is_nan() => None,
+            Value::Double(double) if lower => Some(double.floor() as i64),
+            Value::Double(double) => Some(double.ceil() as i64),
+            // Numbers sort before texts and blobs
+            _ if lower => None,
+            _ => Some(i64::MAX),
+        }
+    };
+    match (limit(low, true), limit(high, false)) {
+        (Some(low), Some(high)) => low..=high,
+        #[allow(clippy::reversed_empty_ranges)]
+        _ => 1..=0,
+    }
+}
+
+/// What a failed UNIQUE constraint names: the indexed columns, or the index if it has
+/// expressions
+fn unique_target(table: &SchemaObject, index: &CreateIndex) -> String {
+    let mut columns = vec![];
+    for column in &index.columns {
+        // Named as declared in the table, whatever the spelling in the index
+        let declared = table_column(&column.expression).and_then(|reference| {
+            table
+                .columns
+                .iter()
+                .find(|column| column.name.eq_ignore_ascii_case(&reference.name))
+        });
+        match declared {
+            Some(column) => columns.push(format!("{}.{}", table.name, column.name)),
+            None => return format!("index '{}'", index.name),
+        }
+    }
+    columns.join(", ")
+}
+
+/// Compares the keys of two records of an index, by the collation and direction of each column.
+/// The rowids after the keys are left out
+pub(crate) fn key_order<'a>(
+    table: &'a SchemaObject,
+    index: &'a CreateIndex,
+) -> impl Fn(&[Value], &[Value]) -> std::cmp::Ordering + 'a {
+    let collations: Vec<Option<&str>> = index
+        .columns
+        .iter()
+        .map(|column| key_collation(table, column))
+        .collect();
+    let descending: Vec<bool> = index
+        .columns
+        .iter()
+        .map(|column| column.ordering == Some(Ordering::Descending))
+        .collect();
+    move |a, b| {
+        a.iter()
+            .zip(b)
+            .zip(collations.iter().zip(&descending))
+            .map(|((a, b), (collation, descending))| {
+                let ordering = compare_collated(a, b, *collation);
+                if *descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
+/// The collation an index orders a column by: its own one, unless the index gives another
+pub(super) fn key_collation<'a>(
+    table: &'a SchemaObject,
+    column: &'a IndexedColumn,
+) -> Option<&'a str> {
+    collation(&column.expression).or_else(|| {
+        let reference = table_column(&column.expression)?;
+        table
+            .columns
+            .iter()
+            .find(|column| column.name.eq_ignore_ascii_case(&reference.name))?
+            .collation
+            .as_deref()
+    })
+}
+
+/// The affinity of an indexed column, the one of the table column it is
+fn key_affinity(table: &SchemaObject, column: &IndexedColumn) -> Option<Affinity> {
+    let reference = table_column(&column.expression)?;
+    table
+        .columns
+        .iter()
+        .find(|column| column.name.eq_ignore_ascii_case(&reference.name))
+        .map(|column| Affinity::of(&column.declared_type))
+}
+
+/// The column an indexed expression is, if it is a plain one
+pub(super) fn table_column(expression: &Expression) -> Option<&ColumnRef> {
+    match expression {
+        Expression::Column(column) => Some(column),
+        Expression::Collate { expression, .. } | Expression::Nested(expression) => {
+            table_column(expression)
+        }
+        _ => None,
+    }
+}
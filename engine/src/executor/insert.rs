@@ -0,0 +1,171 @@
+use super::expression::{apply_affinity, truth, Affinity};
+use super::{Executor, RelationColumn, Scope};
+use crate::errors::DatabaseError;
+use crate::sqlite::SchemaObject;
+use common::types::Value;
+use sql::ast::{
+    ColumnConstraintKind, ColumnDefinition, Expression, Insert, InsertSource, TableConstraint,
+    TableConstraintKind,
+};
+
+/// A row an INSERT adds: its rowid, unless one is to be assigned, and a value per column
+pub(crate) type NewRow = (Option<i64>, Vec<Value>);
+
+impl Executor<'_> {
+    /// Computes the rows an INSERT adds to a table. The columns left out get their default,
+    /// every value is converted to the affinity of its column, and the rowid comes from the
+    /// rowid alias, or the `rowid` column if it is given
+    pub(crate) fn insert_rows(
+        &mut self,
+        table: &SchemaObject,
+        insert: &Insert,
+    ) -> Result<Vec<NewRow>, DatabaseError> {
+        // The column each value goes into, `None` for the rowid
+        let mut targets = vec![];
+        if insert.columns.is_empty() {
+            targets.extend((0..table.columns.len()).map(Some));
+        }
+        for name in &insert.columns {
+            match table
+                .columns
+                .iter()
+                .position(|column| column.name.eq_ignore_ascii_case(name))
+            {
+                Some(index) => targets.push(Some(index)),
+                None if is_rowid(name) => targets.push(None),
+                None => {
+                    return Err(DatabaseError::ExecutionError(format!(
+                        "table {} has no column named {name}",
+                        table.name
+                    )))
+                }
+            }
+        }
+
+        let (supplied, sources) = match &insert.source {
+            InsertSource::Values(rows) => {
+                let mut sources = vec![];
+                for row in rows {
+                    let values = row
+                        .iter()
+                        .map(|expression| self.constant(expression))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    sources.push(values);
+                }
+                (rows.first().map_or(0, Vec::len), sources)
+            }
+            InsertSource::Query(select) => {
+                let result = self.query(select, None)?;
+                (result.columns.len(), result.rows)
+            }
+            InsertSource::DefaultValues => {
+                targets.clear();
+                (0, vec![vec![]])
+            }
+        };
+        if supplied != targets.len() {
+            return Err(DatabaseError::ExecutionError(
+                match insert.columns.is_empty() {
+                    true => format!(
+                        "table {} has {} columns but {supplied} values were supplied",
+                        table.name,
+                        targets.len()
+                    ),
+                    false => format!("{supplied} values for {} columns", targets.len()),
+                },
+            ));
+        }
+
+        let defaults = self.column_defaults(table)?;
+        let mut rows = vec![];
+        for source in sources {
+            let mut values = defaults.clone();
+            let mut rowid = Value::Null;
+            for (target, value) in targets.iter().zip(source) {
+                match target {
+                    Some(index) => values[*index] = value,
+                    None => rowid = value,
+                }
+            }
+            for (index, (value, column)) in values.iter_mut().zip(&table.columns).enumerate() {
+                *value = apply_affinity(value.clone(), Affinity::of(&column.declared_type));
+                // The rowid alias isn't stored in the record, it is the rowid
+                if column.rowid_alias {
+                    let alias = std::mem::replace(value, Value::Null);
+                    if targets.contains(&Some(index)) {
+                        rowid = alias;
+                    }
+                }
+            }
+            let rowid = match apply_affinity(rowid, Affinity::Integer) {
+                Value::Null => None,
+                Value::Integer(rowid) => Some(rowid),
+                _ => return Err(DatabaseError::ExecutionError("datatype mismatch".into())),
+            };
+            rows.push((rowid, values));
+        }
+        Ok(rows)
+    }
+
+    /// Checks the NOT NULL and CHECK constraints of a table against a row, whose rowid alias
+    /// holds the rowid. A CHECK expression only fails if it is false, not NULL
+    pub(crate) fn check_row(
+        &mut self,
+        table: &SchemaObject,
+        columns: &[ColumnDefinition],
+        constraints: &[TableConstraint],
+        row: &[Value],
+    ) -> Result<(), DatabaseError> {
+        let mut checks: Vec<(&Option<String>, &Expression)> = vec![];
+        for (definition, value) in columns.iter().zip(row) {
+            for constraint in &definition.constraints {
+                match &constraint.kind {
+                    ColumnConstraintKind::NotNull(_) if *value == Value::Null => {
+                        return Err(DatabaseError::ConstraintViolation(
+                            "NOT NULL",
+                            format!("{}.{}", table.name, definition.name),
+                        ));
+                    }
+                    ColumnConstraintKind::Check(expression) => {
+                        checks.push((&constraint.name, expression))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for constraint in constraints {
+            if let TableConstraintKind::Check(expression) = &constraint.kind {
+                checks.push((&constraint.name, expression));
+            }
+        }
+
+        let relation_columns: Vec<RelationColumn> = table
+            .columns
+            .iter()
+            .map(|column| RelationColumn::declared(&table.name, column))
+            .collect();
+        let scope = Scope {
+            columns: &relation_columns,
+            row,
+            group: None,
+            outer: None,
+        };
+        for (name, expression) in checks {
+            if truth(&self.evaluate(expression, &scope)?) == Some(false) {
+                let target = match name {
+                    Some(name) => name.clone(),
+                    None => expression.to_string(),
+                };
+                return Err(DatabaseError::ConstraintViolation("CHECK", target));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns true for the names the rowid goes by, when no column has them
+fn is_rowid(name: &str) -> bool {
+    ["rowid", "oid", "_rowid_"]
+        .iter()
+        .any(|rowid| rowid.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,819 @@
+mod expression;
+mod functions;
+mod index;
+mod insert;
+mod plan;
+mod select;
+
+pub(crate) use index::key_order;
+pub(crate) use insert::NewRow;
+pub use plan::{PlanNode, Planner, QueryPlan};
+
+use crate::errors::DatabaseError;
+use crate::sqlite::{Database, TableColumn};
+use common::types::Value;
+use expression::Affinity;
+use sql::ast::{ColumnRef, Select};
+use std::fmt::{Display, Formatter};
+
+/// The rows a statement returns, under the names of its result columns
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Prints the column names, then a line per row, with the values separated by `|`
+impl Display for ResultSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.columns.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "{}", self.columns.join("|"))?;
+        for row in &self.rows {
+            let values: Vec<String> = row.iter().map(Value::to_string).collect();
+            writeln!(f, "{}", values.join("|"))?;
+        }
+        Ok(())
+    }
+}
+
+/// A column of an intermediate table, e.g. the rows a FROM clause produces
+#[derive(Debug, Clone)]
+struct RelationColumn {
+    /// The schema of the table the column comes from, if it has no alias
+    schema: Option<String>,
+    /// The name or alias of the table the column comes from
+    table: Option<String>,
+    name: String,
+    /// Left out of `*`, like the rowid
+    hidden: bool,
+    /// The right-hand copy of a USING or NATURAL join column, only reachable qualified
+    merged: bool,
+    /// The affinity of a table column, which its comparisons convert the other operand to
+    affinity: Option<Affinity>,
+    /// The `COLLATE` of a table column, which its comparisons and ORDER BY use by default
+    collation: Option<String>,
+}
+
+impl RelationColumn {
+    fn new(table: &str, name: &str) -> Self {
+        RelationColumn {
+            schema: None,
+            table: Some(table.into()),
+            name: name.into(),
+            hidden: false,
+            merged: false,
+            affinity: None,
+            collation: None,
+        }
+    }
+
+    /// A column of a table, compared by its declared type and collation
+    fn declared(table: &str, column: &TableColumn) -> Self {
+        RelationColumn {
+            affinity: Some(Affinity::of(&column.declared_type)),
+            collation: column.collation.clone(),
+            ..RelationColumn::new(table, &column.name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Relation {
+    columns: Vec<RelationColumn>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl Relation {
+    /// Names the columns after a single table
+    fn named(table: &str, names: &[String], rows: Vec<Vec<Value>>) -> Self {
+        Relation {
+            columns: names
+                .iter()
+                .map(|name| RelationColumn::new(table, name))
+                .collect(),
+            rows,
+        }
+    }
+}
+
+/// The row an expression is evaluated against, chained to the rows of the enclosing queries
+/// for correlated subqueries
+#[derive(Clone, Copy)]
+struct Scope<'s> {
+    columns: &'s [RelationColumn],
+    row: &'s [Value],
+    /// The rows of the current group, where aggregates can be computed
+    group: Option<&'s [Vec<Value>]>,
+    outer: Option<&'s Scope<'s>>,
+}
+
+impl<'s> Scope<'s> {
+    fn lookup(&self, column: &ColumnRef) -> Result<Value, DatabaseError> {
+        let (scope, index) = self.resolve(column)?;
+        Ok(scope.row[index].clone())
+    }
+
+    /// Finds the scope a column reference is in, and the index of the column in it
+    fn resolve(&self, column: &ColumnRef) -> Result<(&Scope<'s>, usize), DatabaseError> {
+        let mut scope = Some(self);
+        while let Some(current) = scope {
+            let mut matches = current.columns.iter().enumerate().filter(|(_, candidate)| {
+                candidate.name.eq_ignore_ascii_case(&column.name)
+                    && match &column.table {
+                        Some(table) => candidate
+                            .table
+                            .as_ref()
+                            .is_some_and(|name| name.eq_ignore_ascii_case(table)),
+                        None => !candidate.merged,
+                    }
+                    && column.schema.as_ref().is_none_or(|schema| {
+                        candidate
+                            .schema
+                            .as_ref()
+                            .is_some_and(|name| name.eq_ignore_ascii_case(schema))
+                    })
+            });
+            if let Some((index, _)) = matches.next() {
+                if matches.next().is_some() {
+                    return Err(DatabaseError::AmbiguousColumn(column.to_string()));
+                }
+                return Ok((current, index));
+            }
+            scope = current.outer;
+        }
+        Err(DatabaseError::NoSuchColumn(column.to_string()))
+    }
+}
+
+/// Evaluates statements against a database
+pub struct Executor<'d> {
+    database: &'d Database,
+    /// The common table expressions in scope, the innermost last
+    ctes: Vec<(String, Relation)>,
+    /// The views being expanded, a view referring to one of them is circularly defined
+    views: Vec<String>,
+}
+
+impl<'d> Executor<'d> {
+    pub fn new(database: &'d Database) -> Self {
+        Executor {
+            database,
+            ctes: vec![],
+            views: vec![],
+        }
+    }
+
+    pub fn select(&mut self, select: &Select) -> Result<ResultSet, DatabaseError> {
+        self.query(select, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{error, fixture, library, query};
+
+    #[test]
+    fn test_select_table() {
+        let mut database = fixture("employees.db");
+        let result = database
+            .execute_sql("SELECT * FROM employees WHERE id = 11")
+            .unwrap();
+        assert_eq!(
+            result[0].columns,
+            vec!["id", "name", "city", "department", "salary"]
+        );
+        assert_eq!(
+            result[0].to_string(),
+            "id|name|city|department|salary\n11|Diane|London|hr|70\n"
+        );
+
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM employees"),
+            vec!["10"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT rowid, name FROM employees WHERE salary > 100 ORDER BY salary DESC, name"
+            ),
+            vec!["25|Frank", "23|Henry", "24|Irene"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT name FROM sqlite_schema WHERE type = 'table' ORDER BY 1"
+            ),
+            vec!["employees", "expenses"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT 1 + 2 * 3, 7 / 2, 7 / 2.0, 'a' || 1"),
+            vec!["7|3|3.5|a1"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT DISTINCT city FROM employees ORDER BY city LIMIT 1 OFFSET 1"
+            ),
+            vec!["London"]
+        );
+    }
+
+    #[test]
+    fn test_select_doubles() {
+        let mut database = fixture("employees.db");
+        // Formatted like SQLite's `%!.15g`
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT 292 / 3.0, 9223372036854775807.0, 2.0, 1e20, 1e15, 123456789012345.0"
+            ),
+            vec!["97.3333333333333|9.22337203685478e+18|2.0|1.0e+20|1.0e+15|123456789012345.0"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT 0.1, 1e-5, 0.0001, -1.5e-7, 99999999999999.99, 0.1 + 0.2, -0.0, 1e999"
+            ),
+            vec!["0.1|1.0e-05|0.0001|-1.5e-07|100000000000000.0|0.3|0.0|Inf"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT 'x' || 1e100, CAST(5e-324 AS TEXT)"),
+            vec!["x1.0e+100|4.94065645841247e-324"]
+        );
+    }
+
+    #[test]
+    fn test_select_expressions() {
+        let mut database = fixture("employees.db");
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT name FROM employees WHERE name LIKE '%e' AND city GLOB 'L*' ORDER BY name"
+            ),
+            vec!["Dave", "Diane"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT name, CASE WHEN salary >= 100 THEN 'high' ELSE 'low' END FROM employees \
+                 WHERE salary BETWEEN 96 AND 104 AND department IN ('it', 'sales') ORDER BY id"
+            ),
+            vec![
+                "Henry|high",
+                "Irene|high",
+                "Cindy|low",
+                "Dave|low",
+                "Alice|high"
+            ]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT NULL IS NULL, NULL = NULL, NULL OR 1, NULL AND 0, 5 NOT IN (1, NULL)"
+            ),
+            vec!["1||1|0|"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT CAST('12abc' AS INTEGER), CAST(3 AS TEXT) || 'x', typeof(1.5), abs(-2)"
+            ),
+            vec!["12|3x|real|2"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT typeof(x'4142'), length(x'4142'), 0x10 + .5 -- done"
+            ),
+            vec!["blob|2|16.5"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT -9223372036854775808, typeof(-9223372036854775808)"
+            ),
+            vec!["-9223372036854775808|integer"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT upper(substr(name, 2, 3)), length(city), coalesce(NULL, 'x') FROM employees WHERE id = 11"),
+            vec!["IAN|6|x"]
+        );
+    }
+
+    #[test]
+    fn test_select_aggregates() {
+        let mut database = fixture("employees.db");
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT department, count(*), sum(salary), round(avg(salary), 1) \
+                 FROM employees GROUP BY department HAVING count(*) > 2 ORDER BY 3 DESC"
+            ),
+            vec!["it|5|502|100.4", "sales|3|292|97.3"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT name, max(salary) FROM employees"),
+            vec!["Frank|120"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT count(DISTINCT city), count(*) FILTER (WHERE city = 'Berlin') FROM employees"),
+            vec!["2|5"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT sum(salary), count(*) FROM employees WHERE id < 0"
+            ),
+            vec!["|0"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT group_concat(name, ';') FROM employees WHERE department = 'hr'"
+            ),
+            vec!["Diane;Bob"]
+        );
+        assert_eq!(
+            error(
+                &mut database,
+                "SELECT name FROM employees WHERE max(salary) > 1"
+            ),
+            "misuse of aggregate function max()"
+        );
+    }
+
+    #[test]
+    fn test_select_joins_and_subqueries() {
+        let mut database = fixture("employees.db");
+        // A table can be joined with itself under its own name
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM employees NATURAL JOIN employees WHERE id = 11"
+            ),
+            vec!["1"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM employees, employees"),
+            vec!["100"]
+        );
+        assert_eq!(
+            error(
+                &mut database,
+                "SELECT employees.id FROM employees, employees"
+            ),
+            "ambiguous column name: employees.id"
+        );
+        assert_eq!(
+            error(
+                &mut database,
+                "SELECT count(*) FROM employees, employees WHERE employees.id = 11"
+            ),
+            "ambiguous column name: employees.id"
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT e.name, m.name FROM employees e JOIN employees m \
+                 ON m.department = e.department AND m.salary > e.salary + 20 ORDER BY 1, 2"
+            ),
+            vec!["Emma|Frank", "Grace|Frank"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT name FROM employees e WHERE salary = \
+                 (SELECT max(salary) FROM employees WHERE department = e.department) ORDER BY id"
+            ),
+            vec!["Bob", "Frank", "Alice"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT d.department, t.total FROM (SELECT DISTINCT department FROM employees) d \
+                 LEFT JOIN (SELECT department, sum(salary) AS total FROM employees \
+                 WHERE city = 'London' GROUP BY 1) t USING (department) ORDER BY 1"
+            ),
+            vec!["hr|148", "it|188", "sales|96"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM employees a LEFT JOIN employees b \
+                 ON a.salary < b.salary AND b.department = 'hr' WHERE b.id IS NULL"
+            ),
+            vec!["9"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT name FROM employees WHERE EXISTS (SELECT 1 FROM expenses WHERE income = salary) ORDER BY 1"),
+            vec!["Alice", "Henry", "Irene"]
+        );
+        assert_eq!(
+            error(
+                &mut database,
+                "SELECT id FROM employees, expenses, employees e"
+            ),
+            "ambiguous column name: id"
+        );
+        assert_eq!(
+            error(&mut database, "SELECT x FROM employees"),
+            "no such column: x"
+        );
+        assert_eq!(
+            error(&mut database, "SELECT * FROM managers"),
+            "no such table: managers"
+        );
+    }
+
+    #[test]
+    fn test_select_compound() {
+        let mut database = fixture("employees.db");
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT city FROM employees UNION SELECT department FROM employees"
+            ),
+            vec!["Berlin", "London", "hr", "it", "sales"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT salary FROM employees WHERE city = 'Berlin' INTERSECT \
+                 SELECT salary FROM employees WHERE city = 'London' ORDER BY 1 DESC"
+            ),
+            vec!["104", "96"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT 1 AS n UNION ALL SELECT 1 EXCEPT SELECT 2 ORDER BY n"
+            ),
+            vec!["1"]
+        );
+        assert_eq!(
+            error(&mut database, "SELECT * FROM employees UNION SELECT 1"),
+            "SELECTs to the left and right of UNION do not have the same number of result columns"
+        );
+    }
+
+    #[test]
+    fn test_select_with() {
+        let mut database = fixture("employees.db");
+        assert_eq!(
+            query(&mut database, "WITH totals(department, total) AS (SELECT department, sum(salary) FROM employees GROUP BY 1), \
+                 best AS (SELECT max(total) AS total FROM totals) \
+                 SELECT department FROM totals JOIN best USING (total)"
+            ),
+            vec!["it"]
+        );
+        assert_eq!(
+            query(&mut database, "WITH months AS MATERIALIZED (SELECT month, income - expense AS profit FROM expenses) \
+                 SELECT count(*) FROM months WHERE profit > 0"
+            ),
+            vec!["7"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT (WITH t AS (SELECT salary FROM employees WHERE department = e.department) \
+                 SELECT min(salary) FROM t) FROM employees e WHERE id = 33"
+            ),
+            vec!["96"]
+        );
+        assert_eq!(
+            error(&mut database, "WITH t(a, b) AS (SELECT 1) SELECT * FROM t"),
+            "table t has 1 values for 2 columns"
+        );
+        assert_eq!(
+            error(
+                &mut database,
+                "SELECT * FROM (WITH t AS (SELECT 1) SELECT * FROM t), t"
+            ),
+            "no such table: t"
+        );
+    }
+
+    #[test]
+    fn test_select_with_recursive() {
+        let mut database = fixture("employees.db");
+        assert_eq!(
+            query(&mut database, "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 5) SELECT group_concat(x) FROM n"),
+            vec!["1,2,3,4,5"]
+        );
+        assert_eq!(
+            query(&mut database, "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x * 2 FROM n LIMIT 4) SELECT x FROM n"),
+            vec!["1", "2", "4", "8"]
+        );
+        // The outer LIMIT stops a CTE that reads itself without end
+        assert_eq!(
+            query(&mut database, "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT x FROM c LIMIT 3"),
+            vec!["1", "2", "3"]
+        );
+        assert_eq!(
+            query(&mut database, "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT x * 10 FROM c AS d LIMIT 2 OFFSET 3"),
+            vec!["40", "50"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 5) \
+                  SELECT x, (SELECT count(*) FROM c) FROM c LIMIT 2"
+            ),
+            vec!["1|5", "2|5"]
+        );
+        // UNION drops the rows seen before, which ends the cycle
+        assert_eq!(
+            query(&mut database, "WITH RECURSIVE n(x) AS (SELECT 0 UNION SELECT (x + 1) % 3 FROM n) SELECT x FROM n ORDER BY x"),
+            vec!["0", "1", "2"]
+        );
+        // The management chain, from a manager assigned by the first digit of the id
+        assert_eq!(
+            query(&mut database, "WITH RECURSIVE managers(id, manager) AS ( \
+                     SELECT id, CASE WHEN id % 10 = 1 THEN NULL ELSE id / 10 * 10 + 1 END FROM employees), \
+                 chain(id, name, depth) AS ( \
+                     SELECT e.id, e.name, 0 FROM employees e WHERE e.name = 'Frank' \
+                     UNION ALL \
+                     SELECT m.manager, e.name, chain.depth + 1 FROM chain \
+                     JOIN managers m ON m.id = chain.id JOIN employees e ON e.id = m.manager) \
+                 SELECT name, depth FROM chain ORDER BY depth"
+            ),
+            vec!["Frank|0", "Emma|1"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "WITH RECURSIVE running(month, balance) AS ( \
+                     SELECT 0, 0 UNION ALL \
+                     SELECT e.month, balance + income - expense FROM running \
+                     JOIN expenses e ON e.month = running.month + 1) \
+                 SELECT balance FROM running WHERE month = 12"
+            ),
+            vec!["26"]
+        );
+    }
+
+    #[test]
+    fn test_select_views() {
+        let mut database = fixture("employees.db");
+        assert_eq!(
+            query(
+                &mut database,
+                "CREATE VIEW london (who, pay) AS \
+                     SELECT name, salary FROM employees WHERE city = 'London'; \
+                 SELECT * FROM london ORDER BY pay DESC"
+            ),
+            vec!["Henry|104", "Dave|96", "Emma|84", "Bob|78", "Diane|70"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "CREATE VIEW by_department AS \
+                     SELECT department, count(*), max(salary) FROM employees GROUP BY department; \
+                 SELECT d.department, d.\"count(*)\", e.name FROM by_department d \
+                 JOIN employees e ON e.salary = d.\"max(salary)\" AND e.department = d.department \
+                 ORDER BY 1"
+            ),
+            vec!["hr|2|Bob", "it|5|Frank", "sales|3|Alice"]
+        );
+        // Repeated column names get a suffix
+        assert_eq!(
+            query(&mut database, "CREATE VIEW v AS SELECT id, id FROM employees; SELECT * FROM v WHERE \"id:1\" > 31"),
+            vec!["32|32", "33|33"]
+        );
+        // The query of a view doesn't see the CTEs of the statement using it
+        assert_eq!(
+            query(
+                &mut database,
+                "CREATE VIEW rich AS SELECT name FROM employees WHERE salary > 100; \
+                 WITH rich AS (SELECT 'cte') SELECT (SELECT count(*) FROM main.rich), * FROM rich"
+            ),
+            vec!["3|cte"]
+        );
+        assert_eq!(
+            error(
+                &mut fixture("employees.db"),
+                "CREATE VIEW v (a) AS SELECT 1, 2; SELECT * FROM v"
+            ),
+            "expected 1 columns for 'v' but got 2"
+        );
+        assert_eq!(
+            error(
+                &mut fixture("employees.db"),
+                "CREATE VIEW v AS SELECT * FROM nope; SELECT * FROM v"
+            ),
+            "no such table: nope"
+        );
+        assert_eq!(
+            error(
+                &mut fixture("employees.db"),
+                "CREATE VIEW a AS SELECT 1; CREATE VIEW b AS SELECT * FROM a; DROP VIEW a; \
+                 CREATE VIEW a AS SELECT * FROM b; SELECT * FROM a"
+            ),
+            "view a is circularly defined"
+        );
+    }
+
+    #[test]
+    fn test_select_stored_view() {
+        let mut database = library();
+        let results = database
+            .execute_sql(
+                "SELECT count(*), min(year) FROM recent_books; \
+                 SELECT title FROM main.recent_books ORDER BY year DESC, title LIMIT 2",
+            )
+            .unwrap();
+        assert_eq!(results[0].to_string(), "count(*)|min(year)\n80|2000\n");
+        assert_eq!(results[1].to_string(), "title\nBook 139\nBook 209\n");
+    }
+
+    #[test]
+    fn test_select_affinity() {
+        let mut database = library();
+        // A text is converted to the INTEGER affinity of the column, on either side
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM books WHERE year = '2000'"
+            ),
+            ["4"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM books WHERE '2000' = year"
+            ),
+            ["4"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM books WHERE year = '2000.0'"
+            ),
+            ["4"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM books WHERE year = '2000abc'"
+            ),
+            ["0"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM books WHERE id = '5'"),
+            ["1"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM books WHERE rowid = '5'"
+            ),
+            ["1"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM books WHERE year IN ('2000', '2001')"
+            ),
+            ["8"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM books WHERE year BETWEEN '2000' AND '2001'"
+            ),
+            ["8"]
+        );
+        // Without a column or CAST there is no affinity, an IN list only takes the one of
+        // its left operand
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT CAST(5 AS TEXT) = 5, 5 = CAST(5 AS TEXT), CAST('5' AS INTEGER) = '5', \
+                 '5' = 5, '5' IN (5), CAST(5 AS TEXT) IN (5), 5 IN (CAST(5 AS TEXT))"
+            ),
+            ["1|1|1|0|0|1|0"]
+        );
+    }
+
+    #[test]
+    fn test_select_real_affinity() {
+        let mut database = fixture("inventory.db");
+        // The price of Crate is stored as the integer 12
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT price, price / 8, typeof(price) FROM items WHERE id = 3"
+            ),
+            ["12.0|1.5|real"]
+        );
+    }
+
+    #[test]
+    fn test_select_generated_columns() {
+        let mut database = fixture("inventory.db");
+        // b, e and f are VIRTUAL, not in the records, d is STORED
+        assert_eq!(
+            query(&mut database, "SELECT * FROM shapes"),
+            [
+                "3|6|three|9.0|THREE!|!",
+                "4.5|9.0|four|13.5|FOUR!|!",
+                "|||||!"
+            ]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT a FROM shapes WHERE b > 7"),
+            ["4.5"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT * FROM keyed"),
+            ["a|1|2", "bb|2|1"]
+        );
+    }
+
+    #[test]
+    fn test_select_declared_collation() {
+        let mut database = fixture("inventory.db");
+        // items.name is declared COLLATE NOCASE
+        database
+            .execute_sql("INSERT INTO items (sku, name, price) VALUES ('D-4', 'apple', 1)")
+            .unwrap();
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM items WHERE name = 'anvil'"
+            ),
+            ["1"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM items WHERE 'ANVIL' = name"
+            ),
+            ["1"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM items WHERE name = 'anvil' COLLATE BINARY"
+            ),
+            ["0"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM items WHERE name > 'B'"),
+            ["2"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM items WHERE name IN ('ANVIL', 'bolt')"
+            ),
+            ["2"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT group_concat(name) FROM (SELECT name FROM items ORDER BY name)"
+            ),
+            ["Anvil,apple,Bolt,Crate"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT group_concat(n) FROM (SELECT name n FROM items ORDER BY 1 DESC)"
+            ),
+            ["Crate,Bolt,apple,Anvil"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT group_concat(name) FROM (SELECT * FROM items ORDER BY name COLLATE BINARY)"
+            ),
+            ["Anvil,Bolt,Crate,apple"]
+        );
+    }
+
+    #[test]
+    fn test_select_without_rowid() {
+        let mut database = library();
+        // The rows of loans are the records of its primary key index
+        assert_eq!(query(&mut database, "SELECT count(*) FROM loans"), ["3"]);
+        assert_eq!(
+            query(&mut database, "SELECT * FROM loans WHERE book_id = 2"),
+            ["2|bob|2024-02-01", "2|cid|"]
+        );
+        assert_eq!(
+            error(&mut database, "SELECT rowid FROM loans"),
+            "no such column: rowid"
+        );
+        assert_eq!(
+            error(&mut database, "CREATE INDEX loans_by_due ON loans (due)"),
+            "cannot index loans: WITHOUT ROWID tables are not supported"
+        );
+        assert_eq!(
+            error(&mut database, "ALTER TABLE loans ADD COLUMN x NOT NULL"),
+            "Cannot add a NOT NULL column with default value NULL"
+        );
+    }
+}
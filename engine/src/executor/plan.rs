@@ -0,0 +1,1015 @@
+use super::index::{key_collation, table_column};
+use super::select::{qualified, references};
+use super::ResultSet;
+use crate::errors::DatabaseError;
+use crate::sqlite::{Database, SchemaObject};
+use common::types::Value;
+use sql::ast::{
+    Ast, BinaryOperator, CommonTableExpression, CompoundOperator, CreateIndex, Expression,
+    InsertSource, JoinConstraint, Select, TableExpression,
+};
+use sql::Parser;
+use std::fmt::{Display, Formatter};
+
+/// A step of a query plan, under the step it is part of
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanNode {
+    pub id: usize,
+    /// The id of the parent step, 0 for the steps of the statement itself
+    pub parent: usize,
+    pub detail: String,
+}
+
+/// How a statement is run: the tables it reads and how, and the temporary b-trees it builds,
+/// as returned by `EXPLAIN QUERY PLAN`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryPlan {
+    pub nodes: Vec<PlanNode>,
+}
+
+impl QueryPlan {
+    fn add(&mut self, parent: usize, detail: String) -> usize {
+        let id = self.nodes.len() + 1;
+        self.nodes.push(PlanNode { id, parent, detail });
+        id
+    }
+
+    fn render(&self, f: &mut Formatter<'_>, parent: usize, indent: &str) -> std::fmt::Result {
+        let children: Vec<&PlanNode> = self
+            .nodes
+            .iter()
+            .filter(|node| node.parent == parent)
+            .collect();
+        for (index, node) in children.iter().enumerate() {
+            let last = index + 1 == children.len();
+            writeln!(
+                f,
+                "{indent}{}{}",
+                if last { "`--" } else { "|--" },
+                node.detail
+            )?;
+            let indent = format!("{indent}{}", if last { "   " } else { "|  " });
+            self.render(f, node.id, &indent)?;
+        }
+        Ok(())
+    }
+}
+
+/// Draws the steps as a tree, the way the sqlite3 shell does
+impl Display for QueryPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "QUERY PLAN")?;
+        self.render(f, 0, "")
+    }
+}
+
+impl From<QueryPlan> for ResultSet {
+    fn from(plan: QueryPlan) -> Self {
+        ResultSet {
+            columns: ["id", "parent", "notused", "detail"]
+                .map(String::from)
+                .to_vec(),
+            rows: plan
+                .nodes
+                .into_iter()
+                .map(|node| {
+                    vec![
+                        Value::Integer(node.id as i64),
+                        Value::Integer(node.parent as i64),
+                        Value::Integer(0),
+                        Value::Text(node.detail),
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The bounds a WHERE clause puts on a column, each with whether it is included
+#[derive(Debug, Default)]
+pub(super) struct Bounds<'q> {
+    pub low: Option<(&'q Expression, bool)>,
+    pub high: Option<(&'q Expression, bool)>,
+}
+
+impl Bounds<'_> {
+    fn is_empty(&self) -> bool {
+        self.low.is_none() && self.high.is_none()
+    }
+
+    /// Describes the bounds the way SQLite does, e.g. `year>? AND year<?`
+    fn describe(&self, column: &str) -> Vec<String> {
+        let low = self.low.map(|_| format!("{column}>?"));
+        let high = self.high.map(|_| format!("{column}<?"));
+        low.into_iter().chain(high).collect()
+    }
+}
+
+/// How the rows of a table in a FROM clause are found. The searches only narrow down the rows
+/// the WHERE clause is evaluated on, they never replace it
+#[derive(Debug)]
+pub(super) enum Access<'q> {
+    /// Every row, in rowid order
+    Scan,
+    /// The row of the given rowid, or the rows with a rowid in the bounds
+    Rowid {
+        equal: Option<&'q Expression>,
+        range: Bounds<'q>,
+    },
+    /// The rows whose index keys start with the `equal` values, followed by a value in the
+    /// range, in index order
+    Index {
+        name: String,
+        definition: Box<CreateIndex>,
+        equal: Vec<&'q Expression>,
+        range: Bounds<'q>,
+    },
+}
+
+impl Access<'_> {
+    /// The line of the query plan for the table read this way
+    pub(super) fn detail(&self, table: &SchemaObject, label: &str) -> String {
+        match self {
+            Access::Scan => format!("SCAN {label}"),
+            Access::Rowid { equal, range } => {
+                let terms = match equal {
+                    Some(_) => vec!["rowid=?".to_string()],
+                    None => range.describe("rowid"),
+                };
+                format!(
+                    "SEARCH {label} USING INTEGER PRIMARY KEY ({})",
+                    terms.join(" AND ")
+                )
+            }
+            Access::Index {
+                name,
+                definition,
+                equal,
+                range,
+            } => {
+                let names = index_columns(table, definition);
+                let mut terms: Vec<String> = names[..equal.len()]
+                    .iter()
+                    .map(|name| format!("{name}=?"))
+                    .collect();
+                if !range.is_empty() {
+                    terms.extend(range.describe(names[equal.len()]));
+                }
+                format!(
+                    "SEARCH {label} USING INDEX {name} ({})",
+                    terms.join(" AND ")
+                )
+            }
+        }
+    }
+}
+
+/// A column of a table a WHERE clause compares with a constant
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Rowid,
+    Column(usize),
+}
+
+/// A `column op constant` term of a WHERE clause
+struct Term<'q> {
+    target: Target,
+    operator: BinaryOperator,
+    value: &'q Expression,
+}
+
+/// Chooses how to read a table of a FROM clause, given the WHERE clause of the query. A
+/// lookup by rowid is preferred, then the index matching the most columns with `=`. The
+/// columns are qualified with the label, and the schema for a table without an alias
+pub(super) fn access<'q>(
+    database: &Database,
+    table: &SchemaObject,
+    qualifier: (Option<&str>, &str),
+    filter: Option<&'q Expression>,
+) -> Access<'q> {
+    let mut terms = vec![];
+    if let Some(filter) = filter {
+        collect_terms(filter, table, qualifier, &mut terms);
+    }
+    // A WITHOUT ROWID table has no rowid to search, and its indexes don't point to one
+    if terms.is_empty() || table.without_rowid.is_some() {
+        return Access::Scan;
+    }
+
+    let equal = |target: Target| {
+        terms
+            .iter()
+            .find(|term| term.target == target && term.operator == BinaryOperator::Equals)
+            .map(|term| term.value)
+    };
+    let bounds = |target: Target| {
+        let mut bounds = Bounds::default();
+        for term in terms.iter().filter(|term| term.target == target) {
+            match term.operator {
+                BinaryOperator::GreaterThan => bounds.low = Some((term.value, false)),
+                BinaryOperator::GreaterOrEqual => bounds.low = Some((term.value, true)),
+                BinaryOperator::LessThan => bounds.high = Some((term.value, false)),
+                BinaryOperator::LessOrEqual => bounds.high = Some((term.value, true)),
+                _ => {}
+            }
+        }
+        bounds
+    };
+
+    if let Some(value) = equal(Target::Rowid) {
+        return Access::Rowid {
+            equal: Some(value),
+            range: Bounds::default(),
+        };
+    }
+
+    // How many columns of each index the terms match with `=`, and whether the next one has
+    // a range
+    let mut candidates = vec![];
+    for index in database.catalog.objects.iter().filter(|object| {
+        object.object_type == "index" && object.table_name.eq_ignore_ascii_case(&table.name)
+    }) {
+        let Some(definition) = index_definition(index) else {
+            continue;
+        };
+        if definition.where_clause.is_some() {
+            continue;
+        }
+        let mut matched = vec![];
+        let mut range = Bounds::default();
+        for column in &definition.columns {
+            let Some(position) = index_target(table, &column.expression) else {
+                break;
+            };
+            let target = Target::Column(position);
+            // The comparisons use the collation of the column. Values equal in BINARY are
+            // found in an index of another collation, they are equal in it too. It orders the
+            // other values differently
+            let binary =
+                |name: Option<&str>| name.is_none_or(|name| name.eq_ignore_ascii_case("BINARY"));
+            let (declared, key) = (
+                table.columns[position].collation.as_deref(),
+                key_collation(table, column),
+            );
+            let same = match (declared, key) {
+                (Some(declared), Some(key)) => declared.eq_ignore_ascii_case(key),
+                (declared, key) => binary(declared) && binary(key),
+            };
+            if let (Some(value), true) = (equal(target), same || binary(declared)) {
+                matched.push(value);
+                continue;
+            }
+            if same {
+                range = bounds(target);
+            }
+            break;
+        }
+        if matched.is_empty() && range.is_empty() {
+            continue;
+        }
+        let rank = (matched.len(), !range.is_empty());
+        let access = Access::Index {
+            name: index.name.clone(),
+            definition: Box::new(definition),
+            equal: matched,
+            range,
+        };
+        candidates.push((rank, access));
+    }
+    // The index defined first wins a tie
+    let best = candidates.into_iter().rev().max_by_key(|(rank, _)| *rank);
+
+    match best {
+        Some(((equal, _), access)) if equal > 0 => access,
+        best => {
+            let range = bounds(Target::Rowid);
+            if !range.is_empty() {
+                return Access::Rowid { equal: None, range };
+            }
+            best.map_or(Access::Scan, |(_, access)| access)
+        }
+    }
+}
+
+/// Parses the definition of an index, the ones SQLite creates for constraints have none
+fn index_definition(index: &SchemaObject) -> Option<CreateIndex> {
+    match Parser::new(index.sql.as_deref()?).parse() {
+        Ok(Ast::CreateIndex(definition)) => Some(definition),
+        _ => None,
+    }
+}
+
+/// The table column an indexed column is, if it is a plain one
+fn index_target(table: &SchemaObject, expression: &Expression) -> Option<usize> {
+    let column = table_column(expression)?;
+    table
+        .columns
+        .iter()
+        .position(|candidate| candidate.name.eq_ignore_ascii_case(&column.name))
+}
+
+/// The names of the leading plain columns of an index, as declared in the table
+fn index_columns<'t>(table: &'t SchemaObject, definition: &CreateIndex) -> Vec<&'t str> {
+    definition
+        .columns
+        .iter()
+        .map_while(|column| index_target(table, &column.expression))
+        .map(|index| table.columns[index].name.as_str())
+        .collect()
+}
+
+/// Collects the terms of an AND-ed condition that compare a column of the table with a
+/// constant, `BETWEEN` gives two of them
+fn collect_terms<'q>(
+    condition: &'q Expression,
+    table: &SchemaObject,
+    qualifier: (Option<&str>, &str),
+    terms: &mut Vec<Term<'q>>,
+) {
+    match condition {
+        Expression::Nested(condition) => collect_terms(condition, table, qualifier, terms),
+        Expression::Binary {
+            left,
+            operator: BinaryOperator::And,
+            right,
+        } => {
+            collect_terms(left, table, qualifier, terms);
+            collect_terms(right, table, qualifier, terms);
+        }
+        Expression::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let operator = *operator;
+            let flipped = match operator {
+                BinaryOperator::Equals => BinaryOperator::Equals,
+                BinaryOperator::LessThan => BinaryOperator::GreaterThan,
+                BinaryOperator::LessOrEqual => BinaryOperator::GreaterOrEqual,
+                BinaryOperator::GreaterThan => BinaryOperator::LessThan,
+                BinaryOperator::GreaterOrEqual => BinaryOperator::LessOrEqual,
+                _ => return,
+            };
+            if let (Some(target), true) = (target(left, table, qualifier), is_constant(right)) {
+                terms.push(Term {
+                    target,
+                    operator,
+                    value: right,
+                });
+            } else if let (Some(target), true) =
+                (target(right, table, qualifier), is_constant(left))
+            {
+                terms.push(Term {
+                    target,
+                    operator: flipped,
+                    value: left,
+                });
+            }
+        }
+        Expression::Between {
+            expression,
+            negated: false,
+            low,
+            high,
+        } => {
+            if let (Some(target), true, true) = (
+                target(expression, table, qualifier),
+                is_constant(low),
+                is_constant(high),
+            ) {
+                terms.push(Term {
+                    target,
+                    operator: BinaryOperator::GreaterOrEqual,
+                    value: low,
+                });
+                terms.push(Term {
+                    target,
+                    operator: BinaryOperator::LessOrEqual,
+                    value: high,
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The column of the table an expression refers to. A COLLATE makes the comparison another
+/// one than the index order, and is never a target
+fn target(
+    expression: &Expression,
+    table: &SchemaObject,
+    (schema, label): (Option<&str>, &str),
+) -> Option<Target> {
+    let column = match expression {
+        Expression::Column(column) => column,
+        Expression::Nested(expression) => return target(expression, table, (schema, label)),
+        _ => return None,
+    };
+    // A qualifier naming another table or schema, a table with an alias has no schema
+    let other = |name: &Option<String>, expected: &str| {
+        name.as_ref()
+            .is_some_and(|name| !name.eq_ignore_ascii_case(expected))
+    };
+    if other(&column.table, label) || other(&column.schema, schema.unwrap_or_default()) {
+        return None;
+    }
+    match table
+        .columns
+        .iter()
+        .position(|candidate| candidate.name.eq_ignore_ascii_case(&column.name))
+    {
+        Some(index) if table.columns[index].rowid_alias => Some(Target::Rowid),
+        Some(index) => Some(Target::Column(index)),
+        None if column.name.eq_ignore_ascii_case("rowid") => Some(Target::Rowid),
+        None => None,
+    }
+}
+
+/// Returns true for an expression of literals, which has the same value for every row
+fn is_constant(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(_) => true,
+        Expression::Unary { operand, .. } => is_constant(operand),
+        Expression::Binary { left, right, .. } => is_constant(left) && is_constant(right),
+        Expression::Cast { expression, .. } | Expression::Nested(expression) => {
+            is_constant(expression)
+        }
+        _ => false,
+    }
+}
+
+/// Describes how the executor runs a statement, following the same choices
+pub struct Planner<'d> {
+    database: &'d Database,
+    plan: QueryPlan,
+    /// The names of the common table expressions in scope
+    ctes: Vec<String>,
+    /// The views being expanded, see [`super::Executor`]
+    views: Vec<String>,
+    /// The number of subqueries met so far, they are numbered in the plan
+    subqueries: usize,
+}
+
+impl<'d> Planner<'d> {
+    pub fn new(database: &'d Database) -> Self {
+        Planner {
+            database,
+            plan: QueryPlan::default(),
+            ctes: vec![],
+            views: vec![],
+            subqueries: 0,
+        }
+    }
+
+    /// Plans a statement. The ones that read no table have an empty plan
+    pub fn plan(mut self, statement: &Ast) -> Result<QueryPlan, DatabaseError> {
+        match statement {
+            Ast::Explain(statement) | Ast::ExplainQueryPlan(statement) => {
+                return self.plan(statement)
+            }
+            Ast::Select(select) => self.query(select, 0)?,
+            Ast::CreateIndex(create) => {
+                let table = self
+                    .database
+                    .relation(create.schema.as_deref(), &create.table_name)
+                    .filter(|table| table.object.object_type == "table")
+                    .ok_or_else(|| DatabaseError::NoSuchTable(create.table_name.clone()))?;
+                table.object.readable()?;
+                self.plan.add(0, format!("SCAN {}", table.object.name));
+            }
+            Ast::Insert(insert) => {
+                self.database
+                    .relation(insert.schema.as_deref(), &insert.table_name)
+                    .ok_or_else(|| {
+                        DatabaseError::NoSuchTable(qualified(&insert.schema, &insert.table_name))
+                    })?;
+                match &insert.source {
+                    InsertSource::Query(select) => self.query(select, 0)?,
+                    InsertSource::Values(rows) => {
+                        for expression in rows.iter().flatten() {
+                            self.expression(expression, 0)?;
+                        }
+                    }
+                    InsertSource::DefaultValues => {}
+                }
+            }
+            Ast::CreateView(_)
+            | Ast::AlterTable(_)
+            | Ast::Drop(_)
+            | Ast::Begin(_)
+            | Ast::Commit
+            | Ast::Rollback(_)
+            | Ast::Savepoint(_)
+            | Ast::Release(_)
+            | Ast::Attach(_)
+            | Ast::Detach(_) => {}
+            _ => {
+                return Err(DatabaseError::ExecutionError(format!(
+                    "Unsupported statement: {statement}"
+                )))
+            }
+        }
+        Ok(self.plan)
+    }
+
+    fn query(&mut self, select: &Select, parent: usize) -> Result<(), DatabaseError> {
+        let ctes = self.ctes.len();
+        let result = self.query_with(select, parent);
+        self.ctes.truncate(ctes);
+        result
+    }
+
+    fn query_with(&mut self, select: &Select, parent: usize) -> Result<(), DatabaseError> {
+        if let Some(with) = &select.with {
+            for cte in &with.tables {
+                self.common_table(cte, parent)?;
+                self.ctes.push(cte.name.clone());
+            }
+        }
+
+        if select.compound.is_empty() {
+            self.core(select, parent)?;
+        } else {
+            let compound = self.plan.add(parent, "COMPOUND QUERY".into());
+            let first = self.plan.add(compound, "LEFT-MOST SUBQUERY".into());
+            self.core(select, first)?;
+            for (operator, core) in &select.compound {
+                let detail = match operator {
+                    CompoundOperator::UnionAll => "UNION ALL".to_string(),
+                    operator => format!("{operator} USING TEMP B-TREE"),
+                };
+                let node = self.plan.add(compound, detail);
+                self.core(core, node)?;
+            }
+        }
+
+        if let Some(order_by) = &select.order_by {
+            for term in order_by {
+                self.expression(&term.expression, parent)?;
+            }
+            self.plan.add(parent, "USE TEMP B-TREE FOR ORDER BY".into());
+        }
+        Ok(())
+    }
+
+    fn core(&mut self, select: &Select, parent: usize) -> Result<(), DatabaseError> {
+        match &select.from {
+            Some(from) => self.table_expression(from, select.where_clause.as_ref(), parent)?,
+            None => {
+                self.plan.add(parent, "SCAN CONSTANT ROW".into());
+            }
+        }
+        let expressions = select
+            .where_clause
+            .iter()
+            .chain(select.columns.iter().map(|column| &column.expression))
+            .chain(select.group_by.iter().flatten())
+            .chain(select.having.iter());
+        for expression in expressions {
+            self.expression(expression, parent)?;
+        }
+        if select.group_by.is_some() {
+            self.plan.add(parent, "USE TEMP B-TREE FOR GROUP BY".into());
+        }
+        if select.distinct {
+            self.plan.add(parent, "USE TEMP B-TREE FOR DISTINCT".into());
+        }
+        Ok(())
+    }
+
+    fn table_expression(
+        &mut self,
+        table: &TableExpression,
+        filter: Option<&Expression>,
+        parent: usize,
+    ) -> Result<(), DatabaseError> {
+        match table {
+            TableExpression::Table {
+                schema,
+                name,
+                alias,
+            } => {
+                let label = alias.as_ref().unwrap_or(name);
+                let cte =
+                    schema.is_none() && self.ctes.iter().any(|cte| cte.eq_ignore_ascii_case(name));
+                if cte {
+                    self.plan.add(parent, format!("SCAN {label}"));
+                    return Ok(());
+                }
+                let located = self
+                    .database
+                    .relation(schema.as_deref(), name)
+                    .ok_or_else(|| DatabaseError::NoSuchTable(qualified(schema, name)))?;
+                // The plan names a table without an alias as it is written
+                let shown = match alias {
+                    Some(alias) => alias.clone(),
+                    None => qualified(schema, name),
+                };
+                let object = &located.object;
+                if object.object_type == "view" {
+                    let database = std::mem::replace(&mut self.database, located.file);
+                    let result = self.view(object, &shown, parent);
+                    self.database = database;
+                    result?;
+                } else {
+                    object.readable()?;
+                    let qualifier = (alias.is_none().then_some(located.schema), label.as_str());
+                    let access = access(located.file, object, qualifier, filter);
+                    self.plan.add(parent, access.detail(object, &shown));
+                }
+            }
+            TableExpression::Subquery { query, alias } => {
+                self.subqueries += 1;
+                let name = match alias {
+                    Some(alias) => alias.clone(),
+                    None => format!("(subquery-{})", self.subqueries),
+                };
+                let node = self.plan.add(parent, format!("MATERIALIZE {name}"));
+                self.query(query, node)?;
+                self.plan.add(parent, format!("SCAN {name}"));
+            }
+            TableExpression::Join {
+                left,
+                right,
+                constraint,
+                ..
+            } => {
+                self.table_expression(left, filter, parent)?;
+                self.table_expression(right, filter, parent)?;
+                if let Some(JoinConstraint::On(condition)) = constraint {
+                    self.expression(condition, parent)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A view is materialized by running its query, which can't see the CTEs of the statement
+    fn view(
+        &mut self,
+        view: &SchemaObject,
+        label: &str,
+        parent: usize,
+    ) -> Result<(), DatabaseError> {
+        let malformed = || DatabaseError::StateError(format!("Malformed view {}", view.name));
+        let definition = match Parser::new(view.sql.as_deref().ok_or_else(malformed)?).parse()? {
+            Ast::CreateView(definition) => definition,
+            _ => return Err(malformed()),
+        };
+        if self
+            .views
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(&view.name))
+        {
+            return Err(DatabaseError::ExecutionError(format!(
+                "view {} is circularly defined",
+                view.name
+            )));
+        }
+
+        let node = self.plan.add(parent, format!("MATERIALIZE {label}"));
+        self.views.push(view.name.clone());
+        let ctes = std::mem::take(&mut self.ctes);
+        let result = self.query(&definition.query, node);
+        self.ctes = ctes;
+        self.views.pop();
+        result?;
+        self.plan.add(parent, format!("SCAN {label}"));
+        Ok(())
+    }
+
+    fn common_table(
+        &mut self,
+        cte: &CommonTableExpression,
+        parent: usize,
+    ) -> Result<(), DatabaseError> {
+        let query = &cte.query;
+        let cores: Vec<&Select> = std::iter::once(query.as_ref())
+            .chain(query.compound.iter().map(|(_, core)| core))
+            .collect();
+        let recursive = cores.iter().position(|core| {
+            core.from
+                .as_ref()
+                .is_some_and(|from| references(from, &cte.name))
+        });
+
+        let node = self.plan.add(parent, format!("MATERIALIZE {}", cte.name));
+        match recursive {
+            None => self.query(query, node),
+            Some(0) => Err(DatabaseError::ExecutionError(format!(
+                "circular reference: {}",
+                cte.name
+            ))),
+            Some(start) => {
+                let setup = self.plan.add(node, "SETUP".into());
+                for core in &cores[..start] {
+                    self.core(core, setup)?;
+                }
+                let step = self.plan.add(node, "RECURSIVE STEP".into());
+                self.ctes.push(cte.name.clone());
+                let result = cores[start..]
+                    .iter()
+                    .try_for_each(|core| self.core(core, step));
+                self.ctes.pop();
+                result
+            }
+        }
+    }
+
+    /// Adds the subqueries of an expression, each run separately
+    fn expression(&mut self, expression: &Expression, parent: usize) -> Result<(), DatabaseError> {
+        let (kind, select) = match expression {
+            Expression::Subquery(select) | Expression::Exists(select) => ("SCALAR", select),
+            Expression::InSubquery {
+                expression,
+                subquery,
+                ..
+            } => {
+                self.expression(expression, parent)?;
+                ("LIST", subquery)
+            }
+            expression => {
+                return operands(expression)
+                    .into_iter()
+                    .try_for_each(|operand| self.expression(operand, parent))
+            }
+        };
+        self.subqueries += 1;
+        let node = self
+            .plan
+            .add(parent, format!("{kind} SUBQUERY {}", self.subqueries));
+        self.query(select, node)
+    }
+}
+
+/// The expressions an expression is made of, the ones of its subqueries aside
+pub(super) fn operands(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::Literal(_)
+        | Expression::Column(_)
+        | Expression::Wildcard(_)
+        | Expression::Subquery(_)
+        | Expression::Exists(_) => vec![],
+        Expression::Unary { operand, .. } => vec![operand],
+        Expression::Binary { left, right, .. } | Expression::IsDistinctFrom { left, right, .. } => {
+            vec![left, right]
+        }
+        Expression::Collate { expression, .. }
+        | Expression::Cast { expression, .. }
+        | Expression::Nested(expression)
+        | Expression::IsNull { expression, .. }
+        | Expression::InSubquery { expression, .. } => vec![expression],
+        Expression::Function {
+            arguments, filter, ..
+        } => arguments.iter().chain(filter.as_deref()).collect(),
+        Expression::Like {
+            expression,
+            pattern,
+            escape,
+            ..
+        } => [expression, pattern]
+            .into_iter()
+            .map(Box::as_ref)
+            .chain(escape.as_deref())
+            .collect(),
+        Expression::Between {
+            expression,
+            low,
+            high,
+            ..
+        } => vec![expression, low, high],
+        Expression::InList {
+            expression, list, ..
+        } => std::iter::once(expression.as_ref()).chain(list).collect(),
+        Expression::Case {
+            operand,
+            branches,
+            else_result,
+        } => operand
+            .as_deref()
+            .into_iter()
+            .chain(
+                branches
+                    .iter()
+                    .flat_map(|(condition, result)| [condition, result]),
+            )
+            .chain(else_result.as_deref())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::library;
+    use sql::Parser;
+
+    fn plan(database: &Database, query: &str) -> String {
+        let statement = Parser::new(query).parse().unwrap();
+        match database.query_plan(&statement) {
+            Ok(plan) => plan.to_string(),
+            Err(err) => panic!("{query}: {err}"),
+        }
+    }
+
+    fn rows(database: &mut Database, query: &str) -> Vec<Vec<Value>> {
+        let mut results = database
+            .execute_sql(query)
+            .unwrap_or_else(|err| panic!("{query}: {err}"));
+        results.pop().unwrap().rows
+    }
+
+    #[test]
+    fn test_plan_access() {
+        let database = library();
+        for (query, detail) in [
+            ("SELECT * FROM books", "SCAN books"),
+            ("SELECT * FROM books AS b WHERE b.title = 'x'", "SCAN b"),
+            (
+                "SELECT * FROM books WHERE id = 7",
+                "SEARCH books USING INTEGER PRIMARY KEY (rowid=?)",
+            ),
+            (
+                "SELECT * FROM books WHERE 250 < rowid",
+                "SEARCH books USING INTEGER PRIMARY KEY (rowid>?)",
+            ),
+            (
+                "SELECT * FROM books WHERE id BETWEEN 10 AND 20",
+                "SEARCH books USING INTEGER PRIMARY KEY (rowid>? AND rowid<?)",
+            ),
+            (
+                "SELECT * FROM books WHERE year = 1999 AND id > 3",
+                "SEARCH books USING INDEX books_by_year (year=?)",
+            ),
+            (
+                "SELECT * FROM books WHERE year = 1999 AND id = 3",
+                "SEARCH books USING INTEGER PRIMARY KEY (rowid=?)",
+            ),
+            (
+                "SELECT * FROM books WHERE year = 1999 AND title > 'M'",
+                "SEARCH books USING INDEX books_by_year (year=? AND title>?)",
+            ),
+            (
+                "SELECT * FROM books WHERE year < 1950",
+                "SEARCH books USING INDEX books_by_year (year<?)",
+            ),
+            (
+                "SELECT * FROM authors WHERE name = 'x'",
+                "SEARCH authors USING INDEX authors_by_name (name=?)",
+            ),
+            // Neither side of these terms is a plain column
+            ("SELECT * FROM books WHERE +year = 1999", "SCAN books"),
+            ("SELECT * FROM books WHERE year = author_id", "SCAN books"),
+            ("SELECT * FROM books WHERE year = 1 OR id = 2", "SCAN books"),
+            ("SELECT * FROM books WHERE title = 'x'", "SCAN books"),
+        ] {
+            assert_eq!(
+                plan(&database, query),
+                format!("QUERY PLAN\n`--{detail}\n"),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_plan_tree() {
+        let database = library();
+        for (query, expected) in [
+            ("SELECT 1", "`--SCAN CONSTANT ROW\n"),
+            (
+                "SELECT DISTINCT author_id FROM books ORDER BY 1",
+                "|--SCAN books\n\
+                 |--USE TEMP B-TREE FOR DISTINCT\n\
+                 `--USE TEMP B-TREE FOR ORDER BY\n",
+            ),
+            (
+                "SELECT year, count(*) FROM books GROUP BY year",
+                "|--SCAN books\n`--USE TEMP B-TREE FOR GROUP BY\n",
+            ),
+            (
+                "SELECT * FROM books b JOIN authors a ON a.id = b.author_id WHERE a.id = 3",
+                "|--SCAN b\n`--SEARCH a USING INTEGER PRIMARY KEY (rowid=?)\n",
+            ),
+            (
+                "SELECT id FROM books UNION SELECT id FROM authors",
+                "`--COMPOUND QUERY\n   \
+                    |--LEFT-MOST SUBQUERY\n   \
+                    |  `--SCAN books\n   \
+                    `--UNION USING TEMP B-TREE\n      \
+                       `--SCAN authors\n",
+            ),
+            (
+                "SELECT * FROM recent_books",
+                "|--MATERIALIZE recent_books\n\
+                 |  `--SEARCH books USING INDEX books_by_year (year>?)\n\
+                 `--SCAN recent_books\n",
+            ),
+            (
+                "SELECT title FROM books WHERE author_id IN (SELECT id FROM authors) \
+                 AND (SELECT count(*) FROM loans) > 0",
+                "|--SCAN books\n\
+                 |--LIST SUBQUERY 1\n\
+                 |  `--SCAN authors\n\
+                 `--SCALAR SUBQUERY 2\n   \
+                    `--SCAN loans\n",
+            ),
+            (
+                "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 5) \
+                 SELECT x FROM n",
+                "|--MATERIALIZE n\n\
+                 |  |--SETUP\n\
+                 |  |  `--SCAN CONSTANT ROW\n\
+                 |  `--RECURSIVE STEP\n\
+                 |     `--SCAN n\n\
+                 `--SCAN n\n",
+            ),
+            (
+                "SELECT * FROM (SELECT title FROM books LIMIT 3)",
+                "|--MATERIALIZE (subquery-1)\n\
+                 |  `--SCAN books\n\
+                 `--SCAN (subquery-1)\n",
+            ),
+            ("CREATE INDEX i ON books (summary)", "`--SCAN books\n"),
+        ] {
+            assert_eq!(
+                plan(&database, query),
+                format!("QUERY PLAN\n{expected}"),
+                "{query}"
+            );
+        }
+        assert_eq!(plan(&database, "DROP VIEW recent_books"), "QUERY PLAN\n");
+    }
+
+    #[test]
+    fn test_explain_rows() {
+        let mut database = library();
+        for query in [
+            "EXPLAIN QUERY PLAN SELECT DISTINCT year FROM books WHERE id < 10",
+            "EXPLAIN SELECT DISTINCT year FROM books WHERE id < 10",
+        ] {
+            let result = database.execute_sql(query).unwrap().pop().unwrap();
+            assert_eq!(result.columns, ["id", "parent", "notused", "detail"]);
+            let rows: Vec<_> = result
+                .rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(Value::to_string)
+                        .collect::<Vec<_>>()
+                        .join("|")
+                })
+                .collect();
+            assert_eq!(
+                rows,
+                [
+                    "1|0|0|SEARCH books USING INTEGER PRIMARY KEY (rowid<?)",
+                    "2|0|0|USE TEMP B-TREE FOR DISTINCT",
+                ]
+            );
+        }
+        // Statements are planned, not run
+        database
+            .execute_sql("EXPLAIN QUERY PLAN DROP TABLE books")
+            .unwrap();
+        assert!(database.catalog.table("books").is_some());
+    }
+
+    #[test]
+    fn test_search_matches_scan() {
+        let mut database = library();
+        for filter in [
+            "id = 7",
+            "id = 7.0",
+            "id = 7.5",
+            "id > 250",
+            "id >= 250.5 AND id < 260",
+            "id BETWEEN 290 AND 1000",
+            "id < -3",
+            "id = NULL",
+            "year = 1999",
+            "year > 2010",
+            "year >= 1990 AND year <= 1995",
+            "year BETWEEN 1990 AND 1995 AND title < 'M'",
+            "year = 1999 AND title >= 'Book 2'",
+            "year = NULL",
+        ] {
+            let searched = format!("SELECT * FROM books WHERE {filter} ORDER BY id");
+            let scanned = searched.replace("id", "+id").replace("year", "+year");
+            assert_ne!(plan(&database, &searched), plan(&database, &scanned));
+            assert_eq!(
+                rows(&mut database, &searched),
+                rows(&mut database, &scanned),
+                "{filter}"
+            );
+        }
+        for name in ["Author 10", "Nobody"] {
+            let searched = format!("SELECT * FROM authors WHERE name = '{name}'");
+            let scanned = searched.replace("name", "+name");
+            assert_eq!(
+                rows(&mut database, &searched),
+                rows(&mut database, &scanned),
+                "{name}"
+            );
+        }
+    }
+}
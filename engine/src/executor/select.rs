@@ -0,0 +1,1267 @@
+use super::expression::{
+    apply_affinity, collation, compare_collated, left_collation, to_integer, truth, Affinity,
+};
+use super::functions::aggregates;
+use super::plan::{self, Access};
+use super::{Executor, Relation, RelationColumn, ResultSet, Scope};
+use crate::errors::DatabaseError;
+use crate::sqlite::{decode_record, search_index, Database, SchemaObject};
+use common::types::Value;
+use sql::ast::{
+    Ast, ColumnLiteral, CommonTableExpression, CompoundOperator, Expression, JoinConstraint,
+    JoinKind, JoinOperator, Limit, Literal, NullsOrdering, Ordering, OrderingTerm, Select,
+    TableExpression,
+};
+use sql::Parser;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// A result column of a SELECT core
+enum Projection<'q> {
+    /// A column of the FROM clause, expanded from a `*`
+    Column(usize),
+    Expression(&'q Expression),
+}
+
+/// Where an ORDER BY term takes its value from
+enum OrderKey<'q> {
+    /// A result column, referred to by its number or alias
+    Output(usize),
+    Expression(&'q Expression),
+}
+
+/// The rows of a SELECT core, each with its ORDER BY keys
+struct Core {
+    columns: Vec<String>,
+    rows: Vec<(Vec<Value>, Vec<Value>)>,
+    /// The collation each ORDER BY key sorts by
+    collations: Vec<Option<String>>,
+}
+
+impl Executor<'_> {
+    pub(super) fn query(
+        &mut self,
+        select: &Select,
+        outer: Option<&Scope>,
+    ) -> Result<ResultSet, DatabaseError> {
+        // The CTEs of the statement go out of scope with it
+        let ctes = self.ctes.len();
+        let result = self.query_with(select, outer);
+        self.ctes.truncate(ctes);
+        result
+    }
+
+    fn query_with(
+        &mut self,
+        select: &Select,
+        outer: Option<&Scope>,
+    ) -> Result<ResultSet, DatabaseError> {
+        if let Some(with) = &select.with {
+            let wanted = self.rows_wanted(select, outer)?;
+            for cte in &with.tables {
+                let wanted = wanted
+                    .filter(|(name, _)| name.eq_ignore_ascii_case(&cte.name))
+                    .map(|(_, count)| count);
+                let relation = self.common_table(cte, wanted, outer)?;
+                self.ctes.push((cte.name.clone(), relation));
+            }
+        }
+
+        let order_by = select.order_by.as_deref().unwrap_or_default();
+        let (columns, mut rows, collations) = if select.compound.is_empty() {
+            let core = self.core(select, order_by, outer)?;
+            (core.columns, core.rows, core.collations)
+        } else {
+            let first = self.core(select, &[], outer)?;
+            let columns = first.columns;
+            let mut rows: Vec<Vec<Value>> = first.rows.into_iter().map(|(row, _)| row).collect();
+            for (operator, core) in &select.compound {
+                let right = self.core(core, &[], outer)?;
+                if right.columns.len() != columns.len() {
+                    return Err(DatabaseError::ExecutionError(format!(
+                        "SELECTs to the left and right of {operator} do not have the same number of result columns"
+                    )));
+                }
+                rows = compound(*operator, rows, right.rows.into_iter().map(|(row, _)| row));
+            }
+            // The ORDER BY of a compound can only refer to its result columns
+            let keys = order_by
+                .iter()
+                .enumerate()
+                .map(|(index, term)| compound_order_key(index, term, &columns))
+                .collect::<Result<Vec<usize>, _>>()?;
+            let rows = rows
+                .into_iter()
+                .map(|row| {
+                    let key = keys.iter().map(|key| row[*key].clone()).collect();
+                    (row, key)
+                })
+                .collect();
+            let collations = order_by
+                .iter()
+                .map(|term| collation(&term.expression).map(String::from))
+                .collect();
+            (columns, rows, collations)
+        };
+
+        if !order_by.is_empty() {
+            rows.sort_by(|(_, a), (_, b)| compare_keys(a, b, order_by, &collations));
+        }
+        let mut rows: Vec<Vec<Value>> = rows.into_iter().map(|(row, _)| row).collect();
+        if let Some(limit) = &select.limit {
+            let (offset, count) = self.limit(limit, outer)?;
+            rows = rows.into_iter().skip(offset).take(count).collect();
+        }
+        Ok(ResultSet { columns, rows })
+    }
+
+    /// Evaluates a single SELECT core, the compounded ones and ORDER BY and LIMIT aside
+    fn core(
+        &mut self,
+        select: &Select,
+        order_by: &[OrderingTerm],
+        outer: Option<&Scope>,
+    ) -> Result<Core, DatabaseError> {
+        let Relation { columns, rows } = match &select.from {
+            Some(from) => self.table_expression(from, select.where_clause.as_ref(), outer)?,
+            None => Relation {
+                columns: vec![],
+                rows: vec![vec![]],
+            },
+        };
+        let (names, aliases, projection) = expand(&select.columns, &columns)?;
+        let order_keys = order_by
+            .iter()
+            .enumerate()
+            .map(|(index, term)| order_key(index, term, &aliases))
+            .collect::<Result<Vec<_>, _>>()?;
+        // A term sorts by its COLLATE clause, or the declared collation of the column it is
+        let names_only = Scope {
+            columns: &columns,
+            row: &[],
+            group: None,
+            outer,
+        };
+        let collations = order_by
+            .iter()
+            .zip(&order_keys)
+            .map(|(term, key)| {
+                let declared = match key {
+                    OrderKey::Output(index) => match &projection[*index] {
+                        Projection::Column(column) => columns[*column].collation.as_deref(),
+                        Projection::Expression(expression) => {
+                            left_collation(expression, &names_only)
+                        }
+                    },
+                    OrderKey::Expression(expression) => left_collation(expression, &names_only),
+                };
+                collation(&term.expression).or(declared).map(String::from)
+            })
+            .collect();
+
+        let mut filtered = vec![];
+        for row in rows {
+            if let Some(condition) = &select.where_clause {
+                let scope = Scope {
+                    columns: &columns,
+                    row: &row,
+                    group: None,
+                    outer,
+                };
+                if truth(&self.evaluate(condition, &scope)?) != Some(true) {
+                    continue;
+                }
+            }
+            filtered.push(row);
+        }
+
+        let mut found = vec![];
+        for projection in &projection {
+            if let Projection::Expression(expression) = projection {
+                aggregates(expression, &mut found);
+            }
+        }
+        let having: Vec<&Expression> = select.having.iter().collect();
+        for expression in
+            having
+                .iter()
+                .copied()
+                .chain(order_keys.iter().filter_map(|key| match key {
+                    OrderKey::Expression(expression) => Some(*expression),
+                    OrderKey::Output(_) => None,
+                }))
+        {
+            aggregates(expression, &mut found);
+        }
+
+        let mut output = vec![];
+        if select.group_by.is_none() && select.having.is_none() && found.is_empty() {
+            for row in &filtered {
+                let scope = Scope {
+                    columns: &columns,
+                    row,
+                    group: None,
+                    outer,
+                };
+                let values = self.project(&projection, &scope)?;
+                let keys = self.order_values(&order_keys, &values, &scope)?;
+                output.push((values, keys));
+            }
+        } else {
+            let groups = match &select.group_by {
+                Some(terms) => {
+                    let terms = terms
+                        .iter()
+                        .map(|term| group_term(term, &select.columns, &columns))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let mut groups: BTreeMap<Vec<Value>, Vec<Vec<Value>>> = BTreeMap::new();
+                    for row in filtered {
+                        let scope = Scope {
+                            columns: &columns,
+                            row: &row,
+                            group: None,
+                            outer,
+                        };
+                        let key = terms
+                            .iter()
+                            .map(|term| self.evaluate(term, &scope))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        groups.entry(key).or_default().push(row);
+                    }
+                    groups.into_values().collect()
+                }
+                // Without GROUP BY the aggregates see every row, even if there are none
+                None => vec![filtered],
+            };
+
+            let empty = vec![Value::Null; columns.len()];
+            for group in &groups {
+                let row = match self.representative(group, &found, &columns, outer)? {
+                    Some(index) => &group[index],
+                    None => &empty,
+                };
+                let scope = Scope {
+                    columns: &columns,
+                    row,
+                    group: Some(group),
+                    outer,
+                };
+                if let Some(having) = &select.having {
+                    if truth(&self.evaluate(having, &scope)?) != Some(true) {
+                        continue;
+                    }
+                }
+                let values = self.project(&projection, &scope)?;
+                let keys = self.order_values(&order_keys, &values, &scope)?;
+                output.push((values, keys));
+            }
+        }
+
+        if select.distinct {
+            let mut seen = HashSet::new();
+            output.retain(|(row, _)| seen.insert(row.clone()));
+        }
+        Ok(Core {
+            columns: names,
+            rows: output,
+            collations,
+        })
+    }
+
+    /// The row the bare columns of an aggregate query are taken from: the one holding the
+    /// value of a lone `min()` or `max()`, the last one otherwise
+    fn representative(
+        &mut self,
+        group: &[Vec<Value>],
+        found: &[&Expression],
+        columns: &[RelationColumn],
+        outer: Option<&Scope>,
+    ) -> Result<Option<usize>, DatabaseError> {
+        let extreme = match found {
+            [Expression::Function {
+                name, arguments, ..
+            }] if arguments.len() == 1 => match name.to_ascii_lowercase().as_str() {
+                "min" => Some((&arguments[0], std::cmp::Ordering::Less)),
+                "max" => Some((&arguments[0], std::cmp::Ordering::Greater)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some((argument, wanted)) = extreme else {
+            return Ok(group.len().checked_sub(1));
+        };
+
+        let mut best: Option<(usize, Value)> = None;
+        for (index, row) in group.iter().enumerate() {
+            let scope = Scope {
+                columns,
+                row,
+                group: None,
+                outer,
+            };
+            let value = self.evaluate(argument, &scope)?;
+            if value == Value::Null {
+                continue;
+            }
+            if best
+                .as_ref()
+                .is_none_or(|(_, best)| value.cmp(best) == wanted)
+            {
+                best = Some((index, value));
+            }
+        }
+        Ok(best.map(|(index, _)| index).or(group.len().checked_sub(1)))
+    }
+
+    fn project(
+        &mut self,
+        projection: &[Projection],
+        scope: &Scope,
+    ) -> Result<Vec<Value>, DatabaseError> {
+        projection
+            .iter()
+            .map(|projection| match projection {
+                Projection::Column(index) => Ok(scope.row[*index].clone()),
+                Projection::Expression(expression) => self.evaluate(expression, scope),
+            })
+            .collect()
+    }
+
+    fn order_values(
+        &mut self,
+        keys: &[OrderKey],
+        output: &[Value],
+        scope: &Scope,
+    ) -> Result<Vec<Value>, DatabaseError> {
+        keys.iter()
+            .map(|key| match key {
+                OrderKey::Output(index) => Ok(output[*index].clone()),
+                OrderKey::Expression(expression) => self.evaluate(expression, scope),
+            })
+            .collect()
+    }
+
+    /// Returns the number of rows to skip and the most rows to return
+    fn limit(
+        &mut self,
+        limit: &Limit,
+        outer: Option<&Scope>,
+    ) -> Result<(usize, usize), DatabaseError> {
+        let scope = Scope {
+            columns: &[],
+            row: &[],
+            group: None,
+            outer,
+        };
+        let mut integer = |expression: &Expression| -> Result<i64, DatabaseError> {
+            match self.evaluate(expression, &scope)? {
+                Value::Integer(value) => Ok(value),
+                value => to_integer(&value)
+                    .filter(|integer| Value::Integer(*integer) == value)
+                    .ok_or_else(|| DatabaseError::ExecutionError("datatype mismatch".into())),
+            }
+        };
+        // A negative count means no limit, a negative offset none
+        let count = usize::try_from(integer(&limit.count)?).unwrap_or(usize::MAX);
+        let offset = match &limit.offset {
+            Some(offset) => usize::try_from(integer(offset)?).unwrap_or(0),
+            None => 0,
+        };
+        Ok((offset, count))
+    }
+
+    /// Reads the rows of a FROM clause. The constant terms of the WHERE clause choose how each
+    /// table is read, see [`plan::access`]
+    fn table_expression(
+        &mut self,
+        table: &TableExpression,
+        filter: Option<&Expression>,
+        outer: Option<&Scope>,
+    ) -> Result<Relation, DatabaseError> {
+        match table {
+            TableExpression::Table {
+                schema,
+                name,
+                alias,
+            } => {
+                let label = alias.as_ref().unwrap_or(name);
+                if schema.is_none() {
+                    let cte = self
+                        .ctes
+                        .iter()
+                        .rev()
+                        .find(|(cte, _)| cte.eq_ignore_ascii_case(name));
+                    if let Some((_, relation)) = cte {
+                        let mut relation = relation.clone();
+                        for column in &mut relation.columns {
+                            column.table = Some(label.clone());
+                        }
+                        return Ok(relation);
+                    }
+                }
+                let located = self
+                    .database
+                    .relation(schema.as_deref(), name)
+                    .ok_or_else(|| DatabaseError::NoSuchTable(qualified(schema, name)))?;
+                let object = &located.object;
+                if object.object_type == "view" {
+                    // The query of a view in an attached file refers to the objects of that file
+                    let database = std::mem::replace(&mut self.database, located.file);
+                    let relation = self.read_view(object, label);
+                    self.database = database;
+                    return relation;
+                }
+                object.readable()?;
+                let qualifier = (alias.is_none().then_some(located.schema), label.as_str());
+                let access = plan::access(located.file, object, qualifier, filter);
+                let mut relation = self.read_table(located.file, object, label, &access)?;
+                // `schema.table.column` names the columns of a table without an alias
+                if alias.is_none() {
+                    for column in &mut relation.columns {
+                        column.schema = Some(located.schema.into());
+                    }
+                }
+                Ok(relation)
+            }
+            TableExpression::Subquery { query, alias } => {
+                let result = self.query(query, outer)?;
+                let label = alias.as_deref().unwrap_or_default();
+                let mut relation = Relation::named(label, &result.columns, result.rows);
+                if alias.is_none() {
+                    for column in &mut relation.columns {
+                        column.table = None;
+                    }
+                }
+                Ok(relation)
+            }
+            TableExpression::Join {
+                left,
+                operator,
+                right,
+                constraint,
+            } => {
+                let left = self.table_expression(left, filter, outer)?;
+                let right = self.table_expression(right, filter, outer)?;
+                self.join(left, operator, right, constraint.as_ref(), outer)
+            }
+        }
+    }
+
+    /// Reads the rows of a table of the given file, found the way the access path says
+    pub(super) fn read_table(
+        &mut self,
+        file: &Database,
+        table: &SchemaObject,
+        label: &str,
+        access: &Access,
+    ) -> Result<Relation, DatabaseError> {
+        if let Some(key) = &table.without_rowid {
+            return self.read_without_rowid(file, table, key, label);
+        }
+        let encoding = file.header.text_encoding;
+        // The column each value of a record belongs to
+        let order: Vec<usize> = (0..table.columns.len())
+            .filter(|column| table.columns[*column].in_record)
+            .collect();
+        let generated = generation_order(table)?;
+        // Columns added after a row was written are missing from its record, and take their
+        // default. ALTER TABLE only allows constant ones then, evaluated once
+        let mut defaults: Option<Vec<Value>> = None;
+        let mut rows = vec![];
+        for row in self.find_rows(file, table, access)? {
+            let record = decode_record(&row.payload, encoding)?;
+            if record.len() < order.len() && defaults.is_none() {
+                defaults = Some(self.column_defaults(table)?);
+            }
+            let mut values = vec![Value::Null; table.columns.len()];
+            let mut record = record.into_iter();
+            for column in &order {
+                values[*column] = match (record.next(), &defaults) {
+                    (Some(value), _) => value,
+                    (None, Some(defaults)) => defaults[*column].clone(),
+                    (None, None) => Value::Null,
+                };
+            }
+            for (value, column) in values.iter_mut().zip(&table.columns) {
+                if column.rowid_alias {
+                    *value = Value::Integer(row.rowid);
+                }
+            }
+            self.complete_row(table, &generated, &mut values)?;
+            values.push(Value::Integer(row.rowid));
+            rows.push(values);
+        }
+
+        let columns = table_columns(table, label);
+        if columns.len() == table.columns.len() {
+            rows.iter_mut().for_each(|row| {
+                row.pop();
+            });
+        }
+        Ok(Relation { columns, rows })
+    }
+
+    /// Reads the rows of a WITHOUT ROWID table, in primary key order. Its records hold the key
+    /// columns first, and there is no rowid
+    fn read_without_rowid(
+        &mut self,
+        file: &Database,
+        table: &SchemaObject,
+        key: &[usize],
+        label: &str,
+    ) -> Result<Relation, DatabaseError> {
+        // The column each value of a record belongs to
+        let order: Vec<usize> = key
+            .iter()
+            .copied()
+            .chain(
+                (0..table.columns.len())
+                    .filter(|column| !key.contains(column) && table.columns[*column].in_record),
+            )
+            .collect();
+        let generated = generation_order(table)?;
+        let encoding = file.header.text_encoding;
+        let records = search_index(&file.pager, table.root_page, encoding, &mut |_| {
+            std::cmp::Ordering::Equal
+        })?;
+        let defaults = match records.iter().any(|record| record.len() < order.len()) {
+            true => self.column_defaults(table)?,
+            false => vec![Value::Null; table.columns.len()],
+        };
+        let mut rows = vec![];
+        for record in records {
+            let mut values = defaults.clone();
+            for (value, column) in record.into_iter().zip(&order) {
+                values[*column] = value;
+            }
+            self.complete_row(table, &generated, &mut values)?;
+            rows.push(values);
+        }
+        let columns = table
+            .columns
+            .iter()
+            .map(|column| RelationColumn::declared(label, column))
+            .collect();
+        Ok(Relation { columns, rows })
+    }
+
+    /// Reads the integers stored in the REAL columns of a row as reals, SQLite stores the
+    /// integral ones as integers, then computes its VIRTUAL columns in the given order
+    fn complete_row(
+        &mut self,
+        table: &SchemaObject,
+        generated: &[usize],
+        values: &mut [Value],
+    ) -> Result<(), DatabaseError> {
+        for (value, column) in values.iter_mut().zip(&table.columns) {
+            if let Value::Integer(integer) = value {
+                if Affinity::of(&column.declared_type) == Affinity::Real {
+                    *value = Value::Double(*integer as f64);
+                }
+            }
+        }
+        if generated.is_empty() {
+            return Ok(());
+        }
+        let columns: Vec<RelationColumn> = table
+            .columns
+            .iter()
+            .map(|column| RelationColumn::declared(&table.name, column))
+            .collect();
+        for position in generated {
+            let column = &table.columns[*position];
+            let Some(expression) = &column.generated else {
+                continue;
+            };
+            let scope = Scope {
+                columns: &columns,
+                row: values,
+                group: None,
+                outer: None,
+            };
+            let value = self.evaluate(expression, &scope)?;
+            values[*position] = apply_affinity(value, Affinity::of(&column.declared_type));
+        }
+        Ok(())
+    }
+
+    /// Evaluates the default of every column of a table, NULL for the columns without one
+    pub(super) fn column_defaults(
+        &mut self,
+        table: &SchemaObject,
+    ) -> Result<Vec<Value>, DatabaseError> {
+        let scope = Scope {
+            columns: &[],
+            row: &[],
+            group: None,
+            outer: None,
+        };
+        let mut defaults = vec![];
+        for column in &table.columns {
+            defaults.push(match &column.default {
+                Some(default) => self.evaluate(default, &scope)?,
+                None => Value::Null,
+            });
+        }
+        Ok(defaults)
+    }
+
+    /// Runs the query of a view, as a subquery that can't see the CTEs of the statement
+    fn read_view(&mut self, view: &SchemaObject, label: &str) -> Result<Relation, DatabaseError> {
+        let malformed = || DatabaseError::StateError(format!("Malformed view {}", view.name));
+        let definition = match Parser::new(view.sql.as_deref().ok_or_else(malformed)?).parse()? {
+            Ast::CreateView(definition) => definition,
+            _ => return Err(malformed()),
+        };
+        if self
+            .views
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(&view.name))
+        {
+            return Err(DatabaseError::ExecutionError(format!(
+                "view {} is circularly defined",
+                view.name
+            )));
+        }
+
+        self.views.push(view.name.clone());
+        let ctes = std::mem::take(&mut self.ctes);
+        let result = self.query(&definition.query, None);
+        self.ctes = ctes;
+        self.views.pop();
+        let result = result?;
+
+        let columns = match definition.columns.len() {
+            0 => result.columns,
+            count if count == result.columns.len() => definition.columns,
+            count => {
+                return Err(DatabaseError::ExecutionError(format!(
+                    "expected {count} columns for '{}' but got {}",
+                    view.name,
+                    result.columns.len()
+                )))
+            }
+        };
+        Ok(Relation::named(label, &unique_names(columns), result.rows))
+    }
+
+    fn join(
+        &mut self,
+        left: Relation,
+        operator: &JoinOperator,
+        right: Relation,
+        constraint: Option<&JoinConstraint>,
+        outer: Option<&Scope>,
+    ) -> Result<Relation, DatabaseError> {
+        let visible = |column: &RelationColumn| !column.hidden && !column.merged;
+        let names: Vec<String> = match constraint {
+            Some(JoinConstraint::Using(names)) => names.clone(),
+            _ if operator.natural => left
+                .columns
+                .iter()
+                .filter(|column| visible(column))
+                .filter(|column| {
+                    right.columns.iter().any(|other| {
+                        visible(other) && other.name.eq_ignore_ascii_case(&column.name)
+                    })
+                })
+                .map(|column| column.name.clone())
+                .collect(),
+            _ => vec![],
+        };
+        // The pairs of columns a USING or NATURAL join compares
+        let mut pairs = vec![];
+        for name in &names {
+            let position = |relation: &Relation| {
+                relation
+                    .columns
+                    .iter()
+                    .position(|column| visible(column) && column.name.eq_ignore_ascii_case(name))
+            };
+            match (position(&left), position(&right)) {
+                (Some(l), Some(r)) => pairs.push((l, left.columns.len() + r)),
+                _ => {
+                    return Err(DatabaseError::ExecutionError(format!(
+                        "cannot join using column {name} - column not present in both tables"
+                    )))
+                }
+            }
+        }
+
+        let (left_width, right_width) = (left.columns.len(), right.columns.len());
+        let mut columns = left.columns;
+        columns.extend(right.columns);
+        for (_, r) in &pairs {
+            columns[*r].merged = true;
+        }
+        let on = match constraint {
+            Some(JoinConstraint::On(condition)) => Some(condition),
+            _ => None,
+        };
+
+        let mut rows = vec![];
+        let mut right_matched = vec![false; right.rows.len()];
+        for l in &left.rows {
+            let mut matched = false;
+            for (index, r) in right.rows.iter().enumerate() {
+                let row: Vec<Value> = l.iter().chain(r).cloned().collect();
+                if !pairs
+                    .iter()
+                    .all(|(a, b)| row[*a] != Value::Null && row[*a] == row[*b])
+                {
+                    continue;
+                }
+                if let Some(condition) = on {
+                    let scope = Scope {
+                        columns: &columns,
+                        row: &row,
+                        group: None,
+                        outer,
+                    };
+                    if truth(&self.evaluate(condition, &scope)?) != Some(true) {
+                        continue;
+                    }
+                }
+                matched = true;
+                right_matched[index] = true;
+                rows.push(row);
+            }
+            if !matched && matches!(operator.kind, JoinKind::Left | JoinKind::Full) {
+                let mut row = l.clone();
+                row.resize(left_width + right_width, Value::Null);
+                rows.push(row);
+            }
+        }
+        if matches!(operator.kind, JoinKind::Right | JoinKind::Full) {
+            for (r, matched) in right.rows.into_iter().zip(right_matched) {
+                if !matched {
+                    let mut row = vec![Value::Null; left_width];
+                    row.extend(r);
+                    rows.push(row);
+                }
+            }
+        }
+        Ok(Relation { columns, rows })
+    }
+
+    /// Finds the CTE a query only reads the first rows of, and how many: the query reads the
+    /// CTE directly, and its LIMIT is all that stops it. A recursive CTE stops once it has them,
+    /// it may never stop otherwise
+    fn rows_wanted<'q>(
+        &mut self,
+        select: &'q Select,
+        outer: Option<&Scope>,
+    ) -> Result<Option<(&'q str, usize)>, DatabaseError> {
+        let (
+            Some(TableExpression::Table {
+                schema: None, name, ..
+            }),
+            Some(limit),
+        ) = (&select.from, &select.limit)
+        else {
+            return Ok(None);
+        };
+        let mut found = vec![];
+        for column in &select.columns {
+            aggregates(&column.expression, &mut found);
+        }
+        let direct = select.where_clause.is_none()
+            && select.group_by.is_none()
+            && select.having.is_none()
+            && !select.distinct
+            && select.compound.is_empty()
+            && select.order_by.is_none()
+            && found.is_empty()
+            && !select
+                .columns
+                .iter()
+                .any(|column| has_subquery(&column.expression));
+        if !direct {
+            return Ok(None);
+        }
+        let (offset, count) = self.limit(limit, outer)?;
+        Ok(Some((name, offset.saturating_add(count))))
+    }
+
+    /// Evaluates a CTE, of which at most `wanted` rows are needed if it is recursive
+    fn common_table(
+        &mut self,
+        cte: &CommonTableExpression,
+        wanted: Option<usize>,
+        outer: Option<&Scope>,
+    ) -> Result<Relation, DatabaseError> {
+        let query = &cte.query;
+        let cores =
+            std::iter::once(query.as_ref()).chain(query.compound.iter().map(|(_, core)| core));
+        let recursive = cores
+            .enumerate()
+            .find(|(_, core)| {
+                core.from
+                    .as_ref()
+                    .is_some_and(|from| references(from, &cte.name))
+            })
+            .map(|(index, _)| index);
+
+        let result = match recursive {
+            None => self.query(query, outer)?,
+            Some(0) => {
+                return Err(DatabaseError::ExecutionError(format!(
+                    "circular reference: {}",
+                    cte.name
+                )))
+            }
+            Some(start) => self.recursive_query(cte, start, wanted, outer)?,
+        };
+        Ok(Relation::named(
+            &cte.name,
+            &self.cte_columns(cte, result.columns)?,
+            result.rows,
+        ))
+    }
+
+    fn cte_columns(
+        &self,
+        cte: &CommonTableExpression,
+        columns: Vec<String>,
+    ) -> Result<Vec<String>, DatabaseError> {
+        if cte.columns.is_empty() {
+            return Ok(columns);
+        }
+        if cte.columns.len() != columns.len() {
+            return Err(DatabaseError::ExecutionError(format!(
+                "table {} has {} values for {} columns",
+                cte.name,
+                columns.len(),
+                cte.columns.len()
+            )));
+        }
+        Ok(cte.columns.clone())
+    }
+
+    /// Runs a recursive CTE: the cores before `start` seed the table, the rest run against
+    /// the rows added last until they produce no new ones, or the wanted rows
+    fn recursive_query(
+        &mut self,
+        cte: &CommonTableExpression,
+        start: usize,
+        wanted: Option<usize>,
+        outer: Option<&Scope>,
+    ) -> Result<ResultSet, DatabaseError> {
+        let query = &cte.query;
+        let (operators, recursive): (Vec<CompoundOperator>, Vec<&Select>) = query.compound
+            [start - 1..]
+            .iter()
+            .map(|(operator, core)| (*operator, core))
+            .unzip();
+        if operators.iter().any(|operator| {
+            !matches!(
+                operator,
+                CompoundOperator::Union | CompoundOperator::UnionAll
+            )
+        }) {
+            return Err(DatabaseError::ExecutionError(format!(
+                "recursive reference in a {} of {}",
+                operators[0], cte.name
+            )));
+        }
+        let distinct = operators[0] == CompoundOperator::Union;
+
+        let initial = Select {
+            compound: query.compound[..start - 1].to_vec(),
+            order_by: None,
+            limit: None,
+            ..Select::clone(query)
+        };
+        let seed = self.query(&initial, outer)?;
+        let columns = self.cte_columns(cte, seed.columns.clone())?;
+        let (offset, count) = match &query.limit {
+            Some(limit) => self.limit(limit, outer)?,
+            None => (0, usize::MAX),
+        };
+        let count = count.min(wanted.unwrap_or(usize::MAX));
+
+        let mut seen = HashSet::new();
+        let mut rows = vec![];
+        let mut queue = vec![];
+        let mut add = |batch: Vec<Vec<Value>>, rows: &mut Vec<Vec<Value>>, queue: &mut Vec<_>| {
+            for row in batch {
+                if !distinct || seen.insert(row.clone()) {
+                    rows.push(row.clone());
+                    queue.push(row);
+                }
+            }
+        };
+        add(seed.rows, &mut rows, &mut queue);
+
+        while !queue.is_empty() && rows.len() < offset.saturating_add(count) {
+            let working = Relation::named(&cte.name, &columns, std::mem::take(&mut queue));
+            self.ctes.push((cte.name.clone(), working));
+            let mut batch = vec![];
+            let mut result = Ok(());
+            for core in &recursive {
+                match self.core(core, &[], outer) {
+                    Ok(core) if core.columns.len() != columns.len() => {
+                        result = Err(DatabaseError::ExecutionError(format!(
+                            "SELECTs to the left and right of {} do not have the same number of result columns",
+                            operators[0]
+                        )));
+                        break;
+                    }
+                    Ok(core) => batch.extend(core.rows.into_iter().map(|(row, _)| row)),
+                    Err(err) => {
+                        result = Err(err);
+                        break;
+                    }
+                }
+            }
+            self.ctes.pop();
+            result?;
+            add(batch, &mut rows, &mut queue);
+        }
+
+        let rows = rows.into_iter().skip(offset).take(count).collect();
+        Ok(ResultSet {
+            columns: seed.columns,
+            rows,
+        })
+    }
+}
+
+/// Expands the wildcards of a result column list, returning the names of the columns, their
+/// aliases and where their values come from
+#[allow(clippy::type_complexity)]
+fn expand<'q>(
+    columns: &'q [ColumnLiteral],
+    source: &[RelationColumn],
+) -> Result<(Vec<String>, Vec<Option<&'q str>>, Vec<Projection<'q>>), DatabaseError> {
+    let (mut names, mut aliases, mut projection) = (vec![], vec![], vec![]);
+    for column in columns {
+        match &column.expression {
+            Expression::Wildcard(table) => {
+                if source.is_empty() {
+                    return Err(DatabaseError::ExecutionError("no tables specified".into()));
+                }
+                let mut found = false;
+                for (index, candidate) in source.iter().enumerate() {
+                    let included = match table {
+                        Some(table) => {
+                            !candidate.hidden
+                                && candidate
+                                    .table
+                                    .as_ref()
+                                    .is_some_and(|name| name.eq_ignore_ascii_case(table))
+                        }
+                        None => !candidate.hidden && !candidate.merged,
+                    };
+                    if included {
+                        found = true;
+                        names.push(candidate.name.clone());
+                        aliases.push(None);
+                        projection.push(Projection::Column(index));
+                    }
+                }
+                if let (Some(table), false) = (table, found) {
+                    return Err(DatabaseError::NoSuchTable(table.clone()));
+                }
+            }
+            expression => {
+                names.push(match (&column.alias, expression) {
+                    (Some(alias), _) => alias.clone(),
+                    (None, Expression::Column(column)) => column.name.clone(),
+                    (None, expression) => expression.to_string(),
+                });
+                aliases.push(column.alias.as_deref());
+                projection.push(Projection::Expression(expression));
+            }
+        }
+    }
+    Ok((names, aliases, projection))
+}
+
+/// An unqualified column name
+/// Tells apart the columns of a view with the same name, as SQLite does: `a`, `a:1`, `a:2`
+fn unique_names(columns: Vec<String>) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for column in columns {
+        let taken = |name: &str| names.iter().any(|other| other.eq_ignore_ascii_case(name));
+        let mut name = column.clone();
+        let mut suffix = 0;
+        while taken(&name) {
+            suffix += 1;
+            name = format!("{column}:{suffix}");
+        }
+        names.push(name);
+    }
+    names
+}
+
+fn bare_name(expression: &Expression) -> Option<&str> {
+    match expression {
+        Expression::Column(column) if column.table.is_none() => Some(&column.name),
+        _ => None,
+    }
+}
+
+/// The expression of a COLLATE clause, collations only matter to the comparisons
+fn without_collation(expression: &Expression) -> &Expression {
+    match expression {
+        Expression::Collate { expression, .. } => without_collation(expression),
+        expression => expression,
+    }
+}
+
+fn order_key<'q>(
+    index: usize,
+    term: &'q OrderingTerm,
+    aliases: &[Option<&str>],
+) -> Result<OrderKey<'q>, DatabaseError> {
+    let expression = without_collation(&term.expression);
+    if let Expression::Literal(Literal::Integer(number)) = expression {
+        return output_number(index, *number, aliases.len()).map(OrderKey::Output);
+    }
+    let alias = bare_name(expression).and_then(|name| {
+        aliases
+            .iter()
+            .position(|alias| alias.is_some_and(|alias| alias.eq_ignore_ascii_case(name)))
+    });
+    Ok(match alias {
+        Some(position) => OrderKey::Output(position),
+        None => OrderKey::Expression(&term.expression),
+    })
+}
+
+fn compound_order_key(
+    index: usize,
+    term: &OrderingTerm,
+    columns: &[String],
+) -> Result<usize, DatabaseError> {
+    let expression = without_collation(&term.expression);
+    if let Expression::Literal(Literal::Integer(number)) = expression {
+        return output_number(index, *number, columns.len());
+    }
+    let name = match bare_name(expression) {
+        Some(name) => name.to_string(),
+        None => expression.to_string(),
+    };
+    columns
+        .iter()
+        .position(|column| column.eq_ignore_ascii_case(&name))
+        .ok_or_else(|| {
+            DatabaseError::ExecutionError(format!(
+                "{} ORDER BY term does not match any column in the result set",
+                ordinal(index + 1)
+            ))
+        })
+}
+
+/// Resolves the 1-based number of a result column
+fn output_number(index: usize, number: i64, count: usize) -> Result<usize, DatabaseError> {
+    match usize::try_from(number) {
+        Ok(number) if (1..=count).contains(&number) => Ok(number - 1),
+        _ => Err(DatabaseError::ExecutionError(format!(
+            "{} ORDER BY term out of range - should be between 1 and {count}",
+            ordinal(index + 1)
+        ))),
+    }
+}
+
+/// A GROUP BY term can name a result column by its number or alias
+fn group_term<'q>(
+    term: &'q Expression,
+    columns: &'q [ColumnLiteral],
+    source: &[RelationColumn],
+) -> Result<&'q Expression, DatabaseError> {
+    if let Expression::Literal(Literal::Integer(number)) = term {
+        return match usize::try_from(*number) {
+            Ok(number) if (1..=columns.len()).contains(&number) => {
+                Ok(&columns[number - 1].expression)
+            }
+            _ => Err(DatabaseError::ExecutionError(format!(
+                "GROUP BY term out of range - should be between 1 and {}",
+                columns.len()
+            ))),
+        };
+    }
+    if let Some(name) = bare_name(term) {
+        let is_source = source
+            .iter()
+            .any(|column| !column.merged && column.name.eq_ignore_ascii_case(name));
+        let alias = columns.iter().find(|column| {
+            column
+                .alias
+                .as_ref()
+                .is_some_and(|alias| alias.eq_ignore_ascii_case(name))
+        });
+        if let (false, Some(column)) = (is_source, alias) {
+            return Ok(&column.expression);
+        }
+    }
+    Ok(term)
+}
+
+fn ordinal(number: usize) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{number}{suffix}")
+}
+
+/// Combines the rows of two cores. Apart from UNION ALL the result has no duplicates and
+/// comes out sorted
+fn compound(
+    operator: CompoundOperator,
+    left: Vec<Vec<Value>>,
+    right: impl Iterator<Item = Vec<Value>>,
+) -> Vec<Vec<Value>> {
+    match operator {
+        CompoundOperator::UnionAll => left.into_iter().chain(right).collect(),
+        CompoundOperator::Union => left
+            .into_iter()
+            .chain(right)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+        CompoundOperator::Intersect => {
+            let right: HashSet<_> = right.collect();
+            let left: BTreeSet<_> = left.into_iter().collect();
+            left.into_iter().filter(|row| right.contains(row)).collect()
+        }
+        CompoundOperator::Except => {
+            let right: HashSet<_> = right.collect();
+            let left: BTreeSet<_> = left.into_iter().collect();
+            left.into_iter()
+                .filter(|row| !right.contains(row))
+                .collect()
+        }
+    }
+}
+
+fn compare_keys(
+    a: &[Value],
+    b: &[Value],
+    order_by: &[OrderingTerm],
+    collations: &[Option<String>],
+) -> std::cmp::Ordering {
+    use std::cmp::Ordering::{Equal, Greater, Less};
+    for (((a, b), term), collation) in a.iter().zip(b).zip(order_by).zip(collations) {
+        let descending = term.ordering == Ordering::Descending;
+        let ordering = match (a, b) {
+            (Value::Null, Value::Null) => Equal,
+            (Value::Null, _) | (_, Value::Null) => {
+                // NULLs are the smallest values unless placed explicitly
+                let nulls_first = match term.nulls {
+                    Some(NullsOrdering::First) => true,
+                    Some(NullsOrdering::Last) => false,
+                    None => !descending,
+                };
+                match (a == &Value::Null, nulls_first) {
+                    (true, true) | (false, false) => Less,
+                    _ => Greater,
+                }
+            }
+            _ => {
+                let ordering = compare_collated(a, b, collation.as_deref());
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+        };
+        if ordering != Equal {
+            return ordering;
+        }
+    }
+    Equal
+}
+
+/// Returns true if the FROM clause reads the table directly, not through a subquery
+pub(super) fn references(table: &TableExpression, name: &str) -> bool {
+    match table {
+        TableExpression::Table {
+            schema: None,
+            name: table,
+            ..
+        } => table.eq_ignore_ascii_case(name),
+        TableExpression::Table { .. } | TableExpression::Subquery { .. } => false,
+        TableExpression::Join { left, right, .. } => {
+            references(left, name) || references(right, name)
+        }
+    }
+}
+
+/// The VIRTUAL columns of a table, each after the ones its expression uses
+fn generation_order(table: &SchemaObject) -> Result<Vec<usize>, DatabaseError> {
+    let mut pending: Vec<usize> = (0..table.columns.len())
+        .filter(|column| !table.columns[*column].in_record)
+        .collect();
+    let mut order = vec![];
+    while let Some(&first) = pending.first() {
+        let uses = |column: usize, other: usize| {
+            table.columns[column]
+                .generated
+                .as_ref()
+                .is_some_and(|expression| uses_column(expression, &table.columns[other].name))
+        };
+        let ready = pending
+            .iter()
+            .position(|column| !pending.iter().any(|other| uses(*column, *other)))
+            .ok_or_else(|| {
+                DatabaseError::ExecutionError(format!(
+                    "generated column loop on \"{}\"",
+                    table.columns[first].name
+                ))
+            })?;
+        order.push(pending.remove(ready));
+    }
+    Ok(order)
+}
+
+/// Returns true if an expression refers to the column of the given name
+fn uses_column(expression: &Expression, name: &str) -> bool {
+    match expression {
+        Expression::Column(column) => column.name.eq_ignore_ascii_case(name),
+        expression => plan::operands(expression)
+            .into_iter()
+            .any(|operand| uses_column(operand, name)),
+    }
+}
+
+/// Returns true if an expression runs a subquery
+fn has_subquery(expression: &Expression) -> bool {
+    matches!(
+        expression,
+        Expression::Subquery(_) | Expression::Exists(_) | Expression::InSubquery { .. }
+    ) || plan::operands(expression).into_iter().any(has_subquery)
+}
+
+/// The name of a table as written, with its schema if it has one
+pub(super) fn qualified(schema: &Option<String>, name: &str) -> String {
+    match schema {
+        Some(schema) => format!("{schema}.{name}"),
+        None => name.to_string(),
+    }
+}
+
+/// The columns of a table's rows, and its rowid unless a column has the name
+pub(super) fn table_columns(table: &SchemaObject, label: &str) -> Vec<RelationColumn> {
+    let mut columns: Vec<RelationColumn> = table
+        .columns
+        .iter()
+        .map(|column| RelationColumn::declared(label, column))
+        .collect();
+    if !columns
+        .iter()
+        .any(|column| column.name.eq_ignore_ascii_case("rowid"))
+    {
+        columns.push(RelationColumn {
+            hidden: true,
+            affinity: Some(Affinity::Integer),
+            ..RelationColumn::new(label, "rowid")
+        });
+    }
+    columns
+}
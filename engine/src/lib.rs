@@ -1,2 +1,5 @@
 pub mod errors;
+pub mod executor;
 pub mod sqlite;
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,883 @@
+use crate::errors::DatabaseError;
+use crate::executor::ResultSet;
+use crate::sqlite::btree::{scan_table, write_table};
+use crate::sqlite::ddl::SEQUENCE_TABLE;
+use crate::sqlite::record::encode_record;
+use crate::sqlite::rename::{Renamed, Renamer};
+use crate::sqlite::{decode_record, search_index, Database, SchemaObject};
+use common::types::Value;
+use sql::ast::{
+    AlterTable, AlterTableAction, Ast, ColumnConstraintKind, ColumnDefinition, CreateTable,
+    Expression, Literal, TableConstraintKind, TableDefinition, UnaryOperator,
+};
+use sql::{Lexer, Parser, Token};
+
+impl Database {
+    /// Renames a table or one of its columns, or adds or drops a column. The statements of
+    /// the objects referring to a renamed table or column are rewritten to keep them working
+    pub(crate) fn alter_table(&mut self, alter: &AlterTable) -> Result<ResultSet, DatabaseError> {
+        let name = match &alter.schema {
+            Some(schema) => format!("{schema}.{}", alter.table_name),
+            None => alter.table_name.clone(),
+        };
+        let in_main = alter
+            .schema
+            .as_ref()
+            .is_none_or(|schema| schema.eq_ignore_ascii_case("main"));
+        let temp_view = self
+            .temp
+            .iter()
+            .find(|object| object.name.eq_ignore_ascii_case(&alter.table_name))
+            .filter(|_| alter.schema.is_none() || !in_main);
+        let object = match temp_view {
+            Some(view) => Some(view.clone()),
+            None if in_main => self
+                .catalog
+                .table(&alter.table_name)
+                .or_else(|| self.catalog.object(&alter.table_name).cloned()),
+            None => None,
+        };
+        let table = match object {
+            Some(table) if table.object_type == "table" => table,
+            Some(view) if view.object_type == "view" => {
+                return Err(DatabaseError::ExecutionError(match &alter.action {
+                    AlterTableAction::AddColumn(_) => "Cannot add a column to a view".into(),
+                    AlterTableAction::DropColumn(_) => {
+                        format!("cannot drop column from view \"{}\"", view.name)
+                    }
+                    _ => format!("view {} may not be altered", view.name),
+                }));
+            }
+            _ => return Err(DatabaseError::NoSuchTable(name)),
+        };
+        table.readable()?;
+        if table.name.to_ascii_lowercase().starts_with("sqlite_") {
+            let name = match table.root_page {
+                1 => "sqlite_master",
+                _ => &table.name,
+            };
+            return Err(DatabaseError::ExecutionError(format!(
+                "table {name} may not be altered"
+            )));
+        }
+
+        match &alter.action {
+            AlterTableAction::RenameTable(new) => self.rename_table(&table, new)?,
+            AlterTableAction::RenameColumn { old, new } => self.rename_column(&table, old, new)?,
+            AlterTableAction::AddColumn(column) => self.add_column(&table, column)?,
+            AlterTableAction::DropColumn(column) => self.drop_column(&table, column)?,
+        }
+        self.schema_changed()?;
+        Ok(ResultSet::default())
+    }
+
+    fn rename_table(&mut self, table: &SchemaObject, new: &str) -> Result<(), DatabaseError> {
+        if new.to_ascii_lowercase().starts_with("sqlite_") {
+            return Err(DatabaseError::ExecutionError(format!(
+                "object name reserved for internal use: {new}"
+            )));
+        }
+        let taken = self
+            .catalog
+            .objects
+            .iter()
+            .chain(&self.temp)
+            .any(|object| object.name.eq_ignore_ascii_case(new));
+        if taken {
+            return Err(DatabaseError::ExecutionError(format!(
+                "there is already another table or index with this name: {new}"
+            )));
+        }
+
+        let renamer = Renamer::new(&table.name, Renamed::Table(new), &self.catalog.objects);
+        // The indexes SQLite creates for UNIQUE and PRIMARY KEY constraints are named after
+        // their table
+        let autoindex = format!("sqlite_autoindex_{}_", table.name.to_ascii_lowercase());
+        let mut changed = vec![];
+        for object in &self.catalog.objects {
+            let mut object = object.clone();
+            let mut renamed = false;
+            if object.rowid == table.rowid {
+                object.name = new.into();
+            }
+            if object.table_name.eq_ignore_ascii_case(&table.name) {
+                object.table_name = new.into();
+                renamed = true;
+            }
+            if object.name.to_ascii_lowercase().starts_with(&autoindex) {
+                object.name = format!("sqlite_autoindex_{new}_{}", &object.name[autoindex.len()..]);
+            }
+            if let Some(sql) = renamer.rewrite(&object)? {
+                object.sql = Some(sql);
+                renamed = true;
+            }
+            if renamed {
+                changed.push(object);
+            }
+        }
+        let temp = self.rewrite_temp(&renamer)?;
+        self.update_schema_rows(&changed)?;
+        self.rename_sequence(&table.name, new)?;
+        self.temp = temp;
+        Ok(())
+    }
+
+    fn rename_column(
+        &mut self,
+        table: &SchemaObject,
+        old: &str,
+        new: &str,
+    ) -> Result<(), DatabaseError> {
+        let Some(column) = table
+            .columns
+            .iter()
+            .find(|column| column.name.eq_ignore_ascii_case(old))
+        else {
+            return Err(DatabaseError::NoSuchColumn(format!("\"{old}\"")));
+        };
+        let duplicate = table.columns.iter().find(|other| {
+            other.name.eq_ignore_ascii_case(new) && !other.name.eq_ignore_ascii_case(old)
+        });
+        if let Some(duplicate) = duplicate {
+            return Err(DatabaseError::ExecutionError(format!(
+                "error in table {} after rename: duplicate column name: {}",
+                table.name, duplicate.name
+            )));
+        }
+
+        let renamed = Renamed::Column {
+            old: &column.name,
+            new,
+        };
+        let renamer = Renamer::new(&table.name, renamed, &self.catalog.objects);
+        let mut changed = vec![];
+        for object in &self.catalog.objects {
+            if let Some(sql) = renamer.rewrite(object)? {
+                changed.push(SchemaObject {
+                    sql: Some(sql),
+                    ..object.clone()
+                });
+            }
+        }
+        let temp = self.rewrite_temp(&renamer)?;
+        self.update_schema_rows(&changed)?;
+        self.temp = temp;
+        Ok(())
+    }
+
+    /// Appends a column to the table definition. The rows are not rewritten, reading a record
+    /// without the column gives its default
+    fn add_column(
+        &mut self,
+        table: &SchemaObject,
+        column: &ColumnDefinition,
+    ) -> Result<(), DatabaseError> {
+        let error = |message: &str| Err(DatabaseError::ExecutionError(message.into()));
+        if table
+            .columns
+            .iter()
+            .any(|other| other.name.eq_ignore_ascii_case(&column.name))
+        {
+            return Err(DatabaseError::ExecutionError(format!(
+                "duplicate column name: {}",
+                column.name
+            )));
+        }
+        let constraints: Vec<&ColumnConstraintKind> = column
+            .constraints
+            .iter()
+            .map(|constraint| &constraint.kind)
+            .collect();
+        let has = |matches: fn(&ColumnConstraintKind) -> bool| {
+            constraints.iter().any(|constraint| matches(constraint))
+        };
+        if has(|constraint| matches!(constraint, ColumnConstraintKind::PrimaryKey { .. })) {
+            return error("Cannot add a PRIMARY KEY column");
+        }
+        if has(|constraint| matches!(constraint, ColumnConstraintKind::Unique(_))) {
+            return error("Cannot add a UNIQUE column");
+        }
+
+        // The existing rows would need a value other than the default
+        let has_rows = match table.without_rowid {
+            Some(_) => !search_index(
+                &self.pager,
+                table.root_page,
+                self.header.text_encoding,
+                &mut |_| std::cmp::Ordering::Equal,
+            )?
+            .is_empty(),
+            None => !scan_table(&self.pager, table.root_page)?.is_empty(),
+        };
+        if has_rows {
+            let default = constraints.iter().find_map(|constraint| match constraint {
+                ColumnConstraintKind::Default(expression) => Some(expression),
+                _ => None,
+            });
+            let not_null = has(|constraint| matches!(constraint, ColumnConstraintKind::NotNull(_)));
+            let null_default = default.is_none_or(|default| {
+                matches!(unparenthesized(default), Expression::Literal(Literal::Null))
+            });
+            if not_null && null_default {
+                return error("Cannot add a NOT NULL column with default value NULL");
+            }
+            if default.is_some_and(|default| !is_constant(default)) {
+                return error("Cannot add a column with non-constant default");
+            }
+            if has(|constraint| {
+                matches!(
+                    constraint,
+                    ColumnConstraintKind::Generated { stored: true, .. }
+                )
+            }) {
+                return error("cannot add a STORED column");
+            }
+        }
+
+        // The column goes after the last one, as SQLite does: before the comma starting the
+        // table constraints, or before the parenthesis closing the definition. The rest of the
+        // definition stays as written
+        let sql = table.sql.as_deref().unwrap_or_default();
+        let malformed = || DatabaseError::StateError(format!("Malformed table definition: {sql}"));
+        let columns = match Parser::new(sql).parse()? {
+            Ast::CreateTable(CreateTable {
+                definition: TableDefinition::Columns { columns, .. },
+                ..
+            }) => columns.len(),
+            _ => return Err(malformed()),
+        };
+        let mut lexer = Lexer::new(sql);
+        let (mut depth, mut commas) = (0, 0);
+        let end = loop {
+            let Some((span, token)) = lexer.scan_spanned()? else {
+                return Err(malformed());
+            };
+            match token {
+                Token::OpenParen => depth += 1,
+                Token::CloseParen if depth == 1 => break span.start,
+                Token::CloseParen => depth -= 1,
+                Token::Comma if depth == 1 => {
+                    commas += 1;
+                    if commas == columns {
+                        break span.start;
+                    }
+                }
+                _ => {}
+            }
+        };
+        let sql = format!("{}, {column}{}", &sql[..end], &sql[end..]);
+        self.update_schema_rows(&[SchemaObject {
+            sql: Some(sql),
+            ..table.clone()
+        }])
+    }
+
+    /// Removes a column from the table definition and from every row, refusing if a key,
+    /// index, view or trigger still uses it
+    fn drop_column(&mut self, table: &SchemaObject, name: &str) -> Result<(), DatabaseError> {
+        let execution_error = |message: String| Err(DatabaseError::ExecutionError(message));
+        let malformed =
+            || DatabaseError::StateError(format!("Malformed table definition: {}", table.name));
+        let mut create = match Parser::new(table.sql.as_deref().ok_or_else(malformed)?).parse()? {
+            Ast::CreateTable(create) => create,
+            _ => return Err(malformed()),
+        };
+        let TableDefinition::Columns {
+            columns,
+            constraints,
+            without_rowid,
+            ..
+        } = &mut create.definition
+        else {
+            return Err(malformed());
+        };
+        let Some(position) = columns
+            .iter()
+            .position(|column| column.name.eq_ignore_ascii_case(name))
+        else {
+            return Err(DatabaseError::NoSuchColumn(format!("\"{name}\"")));
+        };
+        let name = columns[position].name.clone();
+
+        let column_key = columns[position]
+            .constraints
+            .iter()
+            .any(|constraint| matches!(constraint.kind, ColumnConstraintKind::PrimaryKey { .. }));
+        let table_key = constraints.iter().any(|constraint| match &constraint.kind {
+            TableConstraintKind::PrimaryKey { columns, .. } => columns.iter().any(|column| {
+                matches!(&column.expression, Expression::Column(column) if column.name.eq_ignore_ascii_case(&name))
+            }),
+            _ => false,
+        });
+        if column_key || table_key {
+            return execution_error(format!("cannot drop PRIMARY KEY column: \"{name}\""));
+        }
+        if columns[position]
+            .constraints
+            .iter()
+            .any(|constraint| matches!(constraint.kind, ColumnConstraintKind::Unique(_)))
+        {
+            return execution_error(format!("cannot drop UNIQUE column: \"{name}\""));
+        }
+        if columns.len() == 1 {
+            return execution_error(format!(
+                "cannot drop column \"{name}\": no other columns exist"
+            ));
+        }
+        if *without_rowid {
+            return execution_error(format!(
+                "cannot drop column \"{name}\": WITHOUT ROWID tables are not supported"
+            ));
+        }
+
+        // Virtual columns are computed when read, only the others are in the records
+        let record_index = in_record(&columns[position]).then(|| {
+            columns[..position]
+                .iter()
+                .filter(|column| in_record(column))
+                .count()
+        });
+        columns.remove(position);
+        let after_drop = |object_type: &str, object: &str, message: String| {
+            execution_error(format!(
+                "error in {object_type} {object} after drop column: {message}"
+            ))
+        };
+        if columns.iter().all(is_generated) {
+            return after_drop(
+                "table",
+                &table.name,
+                "must have at least one non-generated column".into(),
+            );
+        }
+        for constraint in constraints.iter() {
+            if let TableConstraintKind::ForeignKey { columns, .. } = &constraint.kind {
+                if columns
+                    .iter()
+                    .any(|column| column.eq_ignore_ascii_case(&name))
+                {
+                    return after_drop(
+                        "table",
+                        &table.name,
+                        format!("unknown column \"{name}\" in foreign key definition"),
+                    );
+                }
+            }
+        }
+
+        // Whatever still refers to the column would change if it was renamed
+        let renamer = Renamer::new(
+            &table.name,
+            Renamed::Column {
+                old: &name,
+                new: "",
+            },
+            &self.catalog.objects,
+        );
+        let no_such_column = format!("no such column: {name}");
+        let definition = Ast::CreateTable(create);
+        if renamer.statement(&mut definition.clone()) {
+            return after_drop("table", &table.name, no_such_column);
+        }
+        let others = self
+            .catalog
+            .objects
+            .iter()
+            .chain(&self.temp)
+            .filter(|object| match object.object_type.as_str() {
+                "index" => object.table_name.eq_ignore_ascii_case(&table.name),
+                "view" | "trigger" => true,
+                _ => false,
+            });
+        for object in others {
+            let Some(mut statement) = renamer.parse(object)? else {
+                continue;
+            };
+            if renamer.statement(&mut statement) {
+                return after_drop(&object.object_type, &object.name, no_such_column);
+            }
+        }
+
+        if let Some(index) = record_index {
+            self.remove_record_value(table.root_page, index)?;
+        }
+        self.update_schema_rows(&[SchemaObject {
+            sql: Some(definition.to_string()),
+            ..table.clone()
+        }])
+    }
+
+    /// Removes the value at an index from every record of a table
+    fn remove_record_value(&mut self, root: u32, index: usize) -> Result<(), DatabaseError> {
+        let encoding = self.header.text_encoding;
+        let mut rows = scan_table(&self.pager, root)?;
+        for row in &mut rows {
+            let mut values = decode_record(&row.payload, encoding)?;
+            if index < values.len() {
+                values.remove(index);
+                row.payload = encode_record(&values, encoding);
+            }
+        }
+        write_table(&mut self.pager, root, &rows)
+    }
+
+    /// Renames the references in the temp schema, returning the rewritten objects
+    fn rewrite_temp(&self, renamer: &Renamer) -> Result<Vec<SchemaObject>, DatabaseError> {
+        let mut temp = self.temp.clone();
+        for object in &mut temp {
+            if let Some(sql) = renamer.rewrite(object)? {
+                object.sql = Some(sql);
+            }
+        }
+        Ok(temp)
+    }
+
+    /// Moves the AUTOINCREMENT counter of a renamed table to its new name
+    fn rename_sequence(&mut self, table: &str, new: &str) -> Result<(), DatabaseError> {
+        let Some(sequence) = self.catalog.table(SEQUENCE_TABLE) else {
+            return Ok(());
+        };
+        let encoding = self.header.text_encoding;
+        let mut rows = scan_table(&self.pager, sequence.root_page)?;
+        for row in &mut rows {
+            let mut values = decode_record(&row.payload, encoding)?;
+            if matches!(values.first(), Some(Value::Text(name)) if name.eq_ignore_ascii_case(table))
+            {
+                values[0] = Value::Text(new.into());
+                row.payload = encode_record(&values, encoding);
+            }
+        }
+        write_table(&mut self.pager, sequence.root_page, &rows)
+    }
+}
+
+pub(super) fn is_generated(column: &ColumnDefinition) -> bool {
+    column
+        .constraints
+        .iter()
+        .any(|constraint| matches!(constraint.kind, ColumnConstraintKind::Generated { .. }))
+}
+
+fn in_record(column: &ColumnDefinition) -> bool {
+    column.constraints.iter().all(|constraint| {
+        !matches!(
+            constraint.kind,
+            ColumnConstraintKind::Generated { stored: false, .. }
+        )
+    })
+}
+
+fn unparenthesized(expression: &Expression) -> &Expression {
+    match expression {
+        Expression::Nested(expression) => unparenthesized(expression),
+        expression => expression,
+    }
+}
+
+/// Whether a default is a value by itself: a literal, or a signed number
+fn is_constant(expression: &Expression) -> bool {
+    match unparenthesized(expression) {
+        Expression::Literal(_) => true,
+        Expression::Unary {
+            operator: UnaryOperator::Minus | UnaryOperator::Plus,
+            operand,
+        } => matches!(
+            unparenthesized(operand),
+            Expression::Literal(Literal::Integer(_) | Literal::Float(_))
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{error, fixture, library, query, reload};
+
+    fn sql(database: &Database, name: &str) -> String {
+        let object = database.catalog.object(name).expect("No such object");
+        object.sql.clone().unwrap_or_default()
+    }
+
+    #[test]
+    fn test_rename_table() {
+        let mut database = library();
+        let cookie = database.header.schema_cookie;
+        database
+            .execute_sql("ALTER TABLE books RENAME TO volumes")
+            .unwrap();
+        let mut database = reload(&database);
+        assert_eq!(database.header.schema_cookie, cookie + 1);
+        assert!(database.catalog.object("books").is_none());
+        assert!(sql(&database, "volumes").starts_with("CREATE TABLE volumes ("));
+        assert_eq!(
+            sql(&database, "books_by_year"),
+            "CREATE INDEX books_by_year ON volumes (year, title)"
+        );
+        assert_eq!(
+            sql(&database, "recent_books"),
+            "CREATE VIEW recent_books AS SELECT title, year FROM volumes WHERE year >= 2000"
+        );
+        assert_eq!(
+            sql(&database, "books_touch"),
+            "CREATE TRIGGER books_touch AFTER INSERT ON volumes BEGIN \
+             UPDATE authors SET country = country WHERE id = new.author_id; END"
+        );
+        for name in ["books_by_year", "books_touch"] {
+            assert_eq!(database.catalog.object(name).unwrap().table_name, "volumes");
+        }
+        // Objects that don't refer to the table keep their statement as written
+        assert_eq!(
+            sql(&database, "authors"),
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL, country TEXT)"
+        );
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM volumes"),
+            vec!["300"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM recent_books"),
+            vec!["80"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT name FROM sqlite_sequence"),
+            vec!["volumes"]
+        );
+        assert!(matches!(
+            database.execute_sql("SELECT * FROM books"),
+            Err(DatabaseError::NoSuchTable(_))
+        ));
+    }
+
+    #[test]
+    fn test_rename_column() {
+        let mut database = library();
+        database
+            .execute_sql(
+                "ALTER TABLE authors RENAME COLUMN country TO nation; \
+                 ALTER TABLE books RENAME year TO published",
+            )
+            .unwrap();
+        let mut database = reload(&database);
+        assert_eq!(
+            sql(&database, "authors"),
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL, nation TEXT)"
+        );
+        assert_eq!(
+            sql(&database, "books_touch"),
+            "CREATE TRIGGER books_touch AFTER INSERT ON books BEGIN \
+             UPDATE authors SET nation = nation WHERE id = new.author_id; END"
+        );
+        assert_eq!(
+            sql(&database, "books_by_year"),
+            "CREATE INDEX books_by_year ON books (published, title)"
+        );
+        assert_eq!(
+            sql(&database, "recent_books"),
+            "CREATE VIEW recent_books AS SELECT title, published FROM books WHERE published >= 2000"
+        );
+        assert_eq!(
+            query(&mut database, "SELECT min(published) FROM recent_books"),
+            vec!["2000"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT count(nation) FROM authors"),
+            vec!["40"]
+        );
+    }
+
+    #[test]
+    fn test_add_column() {
+        let mut database = library();
+        database
+            .execute_sql(
+                "ALTER TABLE books ADD COLUMN rating REAL DEFAULT -2.5; \
+                 ALTER TABLE books ADD copies INT NOT NULL DEFAULT 3; \
+                 ALTER TABLE books ADD COLUMN notes",
+            )
+            .unwrap();
+        let mut database = reload(&database);
+        assert!(sql(&database, "books").ends_with(
+            "summary TEXT\n, rating REAL DEFAULT -2.5, copies INT NOT NULL DEFAULT 3, notes)"
+        ));
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT title, rating, copies, notes FROM books WHERE id = 7"
+            ),
+            vec!["Book 7|-2.5|3|"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM books WHERE copies = 3"),
+            vec!["300"]
+        );
+
+        // A VIRTUAL generated column is computed on read, the records stay as they are
+        database
+            .execute_sql("ALTER TABLE books ADD COLUMN decade AS (year / 10 * 10)")
+            .unwrap();
+        let mut database = reload(&database);
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT year, decade, notes FROM books WHERE id = 7"
+            ),
+            vec!["1957|1950|"]
+        );
+
+        // The column goes before the table constraints
+        database
+            .execute_sql("ALTER TABLE loans ADD COLUMN fee DEFAULT 7")
+            .unwrap();
+        let mut database = reload(&database);
+        assert_eq!(
+            sql(&database, "loans"),
+            "CREATE TABLE loans (book_id INTEGER NOT NULL, reader TEXT NOT NULL, due TEXT, \
+             fee DEFAULT 7, PRIMARY KEY (book_id, reader)) WITHOUT ROWID"
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT reader, fee FROM loans WHERE due IS NULL"
+            ),
+            vec!["cid|7"]
+        );
+    }
+
+    #[test]
+    fn test_drop_column() {
+        let mut database = library();
+        let pages = database.pager.page_count();
+        database
+            .execute_sql("ALTER TABLE books DROP COLUMN summary")
+            .unwrap();
+        let mut database = reload(&database);
+        assert_eq!(
+            sql(&database, "books"),
+            "CREATE TABLE books (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, \
+             author_id INTEGER REFERENCES authors (id), year INTEGER)"
+        );
+        assert_eq!(
+            query(&mut database, "SELECT * FROM books WHERE id = 7"),
+            vec!["7|Book 7|8|1957"]
+        );
+        // The rows without the summaries take fewer pages
+        assert!(database.header.freelist_page_count > 0);
+        assert_eq!(database.pager.page_count(), pages);
+
+        // A column between others shifts the values after it
+        database
+            .execute_sql("ALTER TABLE authors DROP COLUMN name")
+            .unwrap_err();
+        database
+            .execute_sql("DROP INDEX authors_by_name; ALTER TABLE authors DROP COLUMN name")
+            .unwrap();
+        assert_eq!(
+            query(&mut database, "SELECT * FROM authors WHERE id = 7"),
+            vec!["7|US"]
+        );
+    }
+
+    #[test]
+    fn test_alter_keeps_formatting() {
+        let mut database = fixture("inventory.db");
+        database
+            .execute_sql(
+                "ALTER TABLE \"Odd Names\" RENAME label TO \"the name\"; \
+                 ALTER TABLE \"Odd Names\" ADD COLUMN stock INT DEFAULT 0; \
+                 ALTER TABLE \"Odd Names\" RENAME TO goods",
+            )
+            .unwrap();
+        let mut database = reload(&database);
+        assert_eq!(
+            sql(&database, "goods"),
+            "CREATE TABLE \"goods\" ( -- priced labels\n  \
+             [id] INTEGER PRIMARY KEY,\n  \
+             \"the name\" TEXT COLLATE NOCASE CHECK (\"the name\" != ''), /* shown */\n  \
+             price REAL CHECK (price >= 0), stock INT DEFAULT 0,\n  \
+             CONSTRAINT one_label UNIQUE (\"the name\")\n)"
+        );
+        assert_eq!(
+            sql(&database, "cheap"),
+            "CREATE VIEW cheap AS\n  \
+             SELECT \"the name\" /* name */, price FROM \"goods\" WHERE price < 10"
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT cheap.\"the name\", stock FROM cheap JOIN goods USING (price)"
+            ),
+            vec!["pen|0"]
+        );
+    }
+
+    #[test]
+    fn test_rename_unparsable_object() {
+        let mut database = library();
+        database.pager.begin_journal();
+        for (name, sql) in [
+            ("odd", "CREATE VIEW odd AS VALUES (1)"),
+            (
+                "counts",
+                "CREATE VIEW counts AS VALUES ((SELECT count(summary) FROM books))",
+            ),
+        ] {
+            database
+                .add_schema_row(&[
+                    Value::Text("view".into()),
+                    Value::Text(name.into()),
+                    Value::Text(name.into()),
+                    Value::Integer(0),
+                    Value::Text(sql.into()),
+                ])
+                .unwrap();
+        }
+        database.schema_changed().unwrap();
+        let path = database.path().map(std::path::Path::to_path_buf);
+        database.pager.commit(path.as_deref()).unwrap();
+
+        let mut database = reload(&database);
+        // Objects that can't be parsed don't block the changes that don't concern them
+        database
+            .execute_sql(
+                "ALTER TABLE authors RENAME TO writers; \
+                 ALTER TABLE writers RENAME country TO nation; \
+                 ALTER TABLE books ADD COLUMN extra; \
+                 ALTER TABLE books DROP COLUMN extra",
+            )
+            .unwrap();
+        let cookie = database.header.schema_cookie;
+        // But they can't be left naming what no longer exists
+        for sql in [
+            "ALTER TABLE books RENAME TO volumes",
+            "ALTER TABLE books RENAME summary TO blurb",
+            "ALTER TABLE books DROP COLUMN summary",
+        ] {
+            assert!(
+                error(&mut database, sql).starts_with("error in view counts: "),
+                "{sql}"
+            );
+        }
+        assert_eq!(database.header.schema_cookie, cookie);
+        assert_eq!(
+            sql(&database, "counts"),
+            "CREATE VIEW counts AS VALUES ((SELECT count(summary) FROM books))"
+        );
+    }
+
+    #[test]
+    fn test_alter_errors() {
+        let mut database = library();
+        let cookie = database.header.schema_cookie;
+        database
+            .execute_sql("CREATE TEMP VIEW recent AS SELECT summary FROM books")
+            .unwrap();
+        for (sql, message) in [
+            ("ALTER TABLE nope RENAME TO x", "no such table: nope"),
+            ("ALTER TABLE temp.books ADD x", "no such table: temp.books"),
+            (
+                "ALTER TABLE sqlite_master RENAME TO x",
+                "table sqlite_master may not be altered",
+            ),
+            (
+                "ALTER TABLE sqlite_sequence ADD x",
+                "table sqlite_sequence may not be altered",
+            ),
+            (
+                "ALTER TABLE recent_books RENAME TO x",
+                "view recent_books may not be altered",
+            ),
+            ("ALTER TABLE recent ADD x", "Cannot add a column to a view"),
+            (
+                "ALTER TABLE recent_books DROP x",
+                "cannot drop column from view \"recent_books\"",
+            ),
+            (
+                "ALTER TABLE books RENAME TO Authors",
+                "there is already another table or index with this name: Authors",
+            ),
+            (
+                "ALTER TABLE books RENAME TO recent",
+                "there is already another table or index with this name: recent",
+            ),
+            (
+                "ALTER TABLE books RENAME TO sqlite_books",
+                "object name reserved for internal use: sqlite_books",
+            ),
+            ("ALTER TABLE books RENAME zz TO x", "no such column: \"zz\""),
+            (
+                "ALTER TABLE books RENAME title TO YEAR",
+                "error in table books after rename: duplicate column name: year",
+            ),
+            (
+                "ALTER TABLE books ADD Title",
+                "duplicate column name: Title",
+            ),
+            (
+                "ALTER TABLE books ADD x INTEGER PRIMARY KEY",
+                "Cannot add a PRIMARY KEY column",
+            ),
+            (
+                "ALTER TABLE books ADD x UNIQUE",
+                "Cannot add a UNIQUE column",
+            ),
+            (
+                "ALTER TABLE books ADD x NOT NULL",
+                "Cannot add a NOT NULL column with default value NULL",
+            ),
+            (
+                "ALTER TABLE books ADD x NOT NULL DEFAULT (NULL)",
+                "Cannot add a NOT NULL column with default value NULL",
+            ),
+            (
+                "ALTER TABLE books ADD x DEFAULT (1 + 1)",
+                "Cannot add a column with non-constant default",
+            ),
+            (
+                "ALTER TABLE books ADD x DEFAULT current_time",
+                "Cannot add a column with non-constant default",
+            ),
+            (
+                "ALTER TABLE books ADD x AS (year + 1) STORED",
+                "cannot add a STORED column",
+            ),
+            ("ALTER TABLE books DROP zz", "no such column: \"zz\""),
+            (
+                "ALTER TABLE books DROP id",
+                "cannot drop PRIMARY KEY column: \"id\"",
+            ),
+            (
+                "ALTER TABLE loans DROP reader",
+                "cannot drop PRIMARY KEY column: \"reader\"",
+            ),
+            (
+                "ALTER TABLE books DROP year",
+                "error in index books_by_year after drop column: no such column: year",
+            ),
+            (
+                "ALTER TABLE books DROP title",
+                "error in index books_by_year after drop column: no such column: title",
+            ),
+            (
+                "ALTER TABLE authors DROP country",
+                "error in trigger books_touch after drop column: no such column: country",
+            ),
+        ] {
+            assert_eq!(error(&mut database, sql), message, "{sql}");
+        }
+        assert_eq!(database.header.schema_cookie, cookie);
+
+        // Views are checked once the indexes are gone
+        database
+            .execute_sql("DROP INDEX books_by_year; ALTER TABLE books DROP title")
+            .unwrap_err();
+        assert_eq!(
+            error(&mut database, "ALTER TABLE books DROP year"),
+            "error in view recent_books after drop column: no such column: year"
+        );
+        assert_eq!(
+            error(&mut database, "ALTER TABLE books DROP summary"),
+            "error in view recent after drop column: no such column: summary"
+        );
+    }
+}
@@ -0,0 +1,383 @@
+use super::Database;
+use crate::errors::DatabaseError;
+use crate::executor::{Executor, ResultSet};
+use common::types::Value;
+use sql::ast::{AttachDatabase, Expression};
+
+/// The most files that can be attached at once, as in SQLite
+const MAX_ATTACHED: usize = 10;
+
+/// A database file attached to the connection, its objects are qualified with the name
+#[derive(Debug, Clone)]
+pub(crate) struct Attached {
+    pub name: String,
+    pub database: Database,
+}
+
+impl Database {
+    /// Opens another database file, its tables can then be used as `name.table`
+    pub(super) fn attach(&mut self, attach: &AttachDatabase) -> Result<ResultSet, DatabaseError> {
+        // A bare word is the file name itself
+        let file = match &attach.file {
+            Expression::Column(column) if column.table.is_none() => column.name.clone(),
+            file => match Executor::new(self).constant(file)? {
+                Value::Null => String::new(),
+                value => value.to_string(),
+            },
+        };
+        let name = &attach.name;
+        if self.schema_exists(name) {
+            return Err(DatabaseError::ExecutionError(format!(
+                "database {name} is already in use"
+            )));
+        }
+        if self.attached.len() >= MAX_ATTACHED {
+            return Err(DatabaseError::ExecutionError(format!(
+                "too many attached databases - max {MAX_ATTACHED}"
+            )));
+        }
+        let database = Database::open(&file).map_err(|err| match err {
+            DatabaseError::FileSystemError(_) => {
+                DatabaseError::ExecutionError(format!("unable to open database: {file}"))
+            }
+            err => err,
+        })?;
+
+        self.attached.push(Attached {
+            name: name.clone(),
+            database,
+        });
+        self.cover_attached();
+        Ok(ResultSet::default())
+    }
+
+    /// Closes an attached file, which can't be in use by the open transaction
+    pub(super) fn detach(&mut self, name: &str) -> Result<ResultSet, DatabaseError> {
+        if name.eq_ignore_ascii_case("main") {
+            return Err(DatabaseError::ExecutionError(format!(
+                "cannot detach database {name}"
+            )));
+        }
+        let Some(index) = self.attached_index(name) else {
+            return Err(DatabaseError::ExecutionError(format!(
+                "no such database: {name}"
+            )));
+        };
+        if !self.is_autocommit() {
+            return Err(DatabaseError::ExecutionError(format!(
+                "database {name} is locked"
+            )));
+        }
+        self.attached.remove(index);
+        Ok(ResultSet::default())
+    }
+
+    /// Returns true if the schema name is `main`, `temp` or the one of an attached file
+    pub(crate) fn schema_exists(&self, name: &str) -> bool {
+        name.eq_ignore_ascii_case("main")
+            || name.eq_ignore_ascii_case("temp")
+            || self.attached_index(name).is_some()
+    }
+
+    pub(super) fn attached_index(&self, name: &str) -> Option<usize> {
+        self.attached
+            .iter()
+            .position(|attached| attached.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Finds the attached file holding the object a statement is about: the one the schema
+    /// names, or for an unqualified name the first one having it, if neither the temp nor the
+    /// main schema does. `None` leaves the statement to the main file
+    pub(super) fn attached_target(&self, schema: Option<&str>, name: &str) -> Option<usize> {
+        match schema {
+            Some(schema) => self.attached_index(schema),
+            None if self.temp_object(name).is_some() || self.catalog.object(name).is_some() => None,
+            None => self
+                .attached
+                .iter()
+                .position(|attached| attached.database.catalog.object(name).is_some()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{copy, error, library, query, reload};
+    use sql::Parser;
+
+    #[test]
+    fn test_attach_detach() {
+        let mut database = library();
+        let (library, employees) = (copy("library.db"), copy("employees.db"));
+        database
+            .execute_sql(&format!(
+                "ATTACH '{employees}' AS staff; ATTACH DATABASE '{library}' AS \"old library\""
+            ))
+            .unwrap();
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM staff.employees"),
+            ["10"]
+        );
+        // Unqualified names are looked up in the attached files after main
+        assert_eq!(
+            query(&mut database, "SELECT max(salary) FROM employees"),
+            ["120"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM \"old library\".books AS b, main.authors AS a \
+                 WHERE b.author_id = a.id AND a.name = 'Author 10'"
+            ),
+            ["8"]
+        );
+
+        database.execute_sql("DETACH staff").unwrap();
+        assert_eq!(
+            error(&mut database, "SELECT * FROM employees"),
+            "no such table: employees"
+        );
+        assert_eq!(
+            error(&mut database, "SELECT * FROM staff.employees"),
+            "no such table: staff.employees"
+        );
+        // The name can be used again
+        database
+            .execute_sql(&format!("ATTACH '{employees}' AS STAFF"))
+            .unwrap();
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM staff.expenses"),
+            ["12"]
+        );
+    }
+
+    #[test]
+    fn test_attach_errors() {
+        let mut database = library();
+        let (library, employees) = (copy("library.db"), copy("employees.db"));
+        database
+            .execute_sql(&format!("ATTACH '{employees}' AS staff"))
+            .unwrap();
+        for (sql, message) in [
+            (
+                format!("ATTACH '{library}' AS main"),
+                "database main is already in use",
+            ),
+            (
+                format!("ATTACH '{library}' AS TEMP"),
+                "database TEMP is already in use",
+            ),
+            (
+                format!("ATTACH '{library}' AS Staff"),
+                "database Staff is already in use",
+            ),
+            (
+                "ATTACH 'missing.db' AS missing".into(),
+                "unable to open database: missing.db",
+            ),
+            ("DETACH main".into(), "cannot detach database main"),
+            ("DETACH temp".into(), "no such database: temp"),
+            ("DETACH missing".into(), "no such database: missing"),
+            ("BEGIN; DETACH staff".into(), "database staff is locked"),
+        ] {
+            assert_eq!(error(&mut database, &sql), message, "{sql}");
+        }
+        database.execute_sql("ROLLBACK").unwrap();
+
+        for number in 1..MAX_ATTACHED {
+            database
+                .execute_sql(&format!("ATTACH '{library}' AS library{number}"))
+                .unwrap();
+        }
+        assert_eq!(
+            error(&mut database, &format!("ATTACH '{library}' AS one_more")),
+            "too many attached databases - max 10"
+        );
+    }
+
+    #[test]
+    fn test_attach_cross_database() {
+        let mut database = library();
+        let library = copy("library.db");
+        database
+            .execute_sql(&format!(
+                "ATTACH '{library}' AS archive; \
+                 INSERT INTO archive.books (title, author_id, year) \
+                 SELECT title, author_id, year FROM main.books WHERE year < 1960"
+            ))
+            .unwrap();
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM archive.books"),
+            ["349"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM main.books"),
+            ["300"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT archive.books.id, main.books.id FROM archive.books, main.books \
+                 WHERE archive.books.title = main.books.title AND main.books.id = 1 \
+                 ORDER BY 1"
+            ),
+            ["1|1", "305|1"]
+        );
+
+        // Statements about the objects of an attached file run in that file
+        database
+            .execute_sql("CREATE INDEX archive.books_by_title ON books (title)")
+            .unwrap();
+        assert!(database.catalog.object("books_by_title").is_none());
+        let plan = database
+            .query_plan(
+                &Parser::new("SELECT * FROM archive.books WHERE title = 'Book 1'")
+                    .parse()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            plan.to_string(),
+            "QUERY PLAN\n`--SEARCH archive.books USING INDEX books_by_title (title=?)\n"
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT id FROM archive.books WHERE title = 'Book 1'"
+            ),
+            ["1", "305"]
+        );
+    }
+
+    #[test]
+    fn test_attach_transactions() {
+        let mut database = library();
+        let library = copy("library.db");
+        // The authors added to each file
+        let added = "SELECT (SELECT count(*) FROM main.authors WHERE name LIKE 'New %'), \
+                     (SELECT count(*) FROM archive.authors WHERE name LIKE 'New %')";
+        database
+            .execute_sql(&format!(
+                "BEGIN; INSERT INTO authors (name) VALUES ('New 1'); \
+                 ATTACH '{library}' AS archive; SAVEPOINT s; \
+                 INSERT INTO archive.authors (name) VALUES ('New 2'), ('New 3')"
+            ))
+            .unwrap();
+        assert_eq!(query(&mut database, added), ["1|2"]);
+        database.execute_sql("ROLLBACK TO s").unwrap();
+        assert_eq!(query(&mut database, added), ["1|0"]);
+
+        // A failed statement only undoes itself, in whichever file it ran
+        database
+            .execute_sql("INSERT INTO archive.authors (name) VALUES ('New 4')")
+            .unwrap();
+        assert_eq!(
+            error(
+                &mut database,
+                "INSERT INTO archive.authors (name) VALUES ('New 5'), ('New 4')"
+            ),
+            "UNIQUE constraint failed: authors.name"
+        );
+        assert_eq!(query(&mut database, added), ["1|1"]);
+
+        // The file stays attached, the changes to both files are gone
+        database.execute_sql("ROLLBACK").unwrap();
+        assert_eq!(query(&mut database, added), ["0|0"]);
+        database
+            .execute_sql("INSERT INTO archive.authors (name) VALUES ('New 6')")
+            .unwrap();
+        assert_eq!(query(&mut database, added), ["0|1"]);
+    }
+
+    #[test]
+    fn test_attach_commit() {
+        let mut database = library();
+        let employees = copy("employees.db");
+        let count = "SELECT count(*) FROM employees WHERE city = 'Oslo'";
+        database
+            .execute_sql(&format!(
+                "ATTACH '{employees}' AS staff; \
+                 INSERT INTO staff.employees (name, city, department, salary) \
+                 VALUES ('Olav', 'Oslo', 'it', 90)"
+            ))
+            .unwrap();
+        assert_eq!(
+            query(&mut Database::open(&employees).unwrap(), count),
+            ["1"]
+        );
+
+        // The files of a transaction are written when it commits
+        database
+            .execute_sql(
+                "BEGIN; INSERT INTO authors (name) VALUES ('New 1'); \
+                 INSERT INTO staff.employees (name, city, department, salary) \
+                 VALUES ('Ola', 'Oslo', 'hr', 60)",
+            )
+            .unwrap();
+        assert_eq!(
+            query(&mut Database::open(&employees).unwrap(), count),
+            ["1"]
+        );
+        database.execute_sql("COMMIT").unwrap();
+        assert_eq!(
+            query(&mut Database::open(&employees).unwrap(), count),
+            ["2"]
+        );
+        assert_eq!(
+            query(
+                &mut reload(&database),
+                "SELECT count(*) FROM authors WHERE name = 'New 1'"
+            ),
+            ["1"]
+        );
+
+        database
+            .execute_sql(
+                "BEGIN; INSERT INTO staff.employees (name, city, department, salary) \
+                 VALUES ('Kari', 'Oslo', 'hr', 60); ROLLBACK",
+            )
+            .unwrap();
+        assert_eq!(
+            query(&mut Database::open(&employees).unwrap(), count),
+            ["2"]
+        );
+    }
+
+    #[test]
+    fn test_attach_commit_error() {
+        let mut database = library();
+        let employees = copy("employees.db");
+        database
+            .execute_sql(&format!(
+                "ATTACH '{employees}' AS staff; BEGIN; \
+                 INSERT INTO authors (name) VALUES ('New 1'); \
+                 INSERT INTO staff.employees (name, city, department, salary) \
+                 VALUES ('Ola', 'Oslo', 'hr', 60)"
+            ))
+            .unwrap();
+        let path = database.path().unwrap().to_path_buf();
+        let file = std::fs::read(&path).unwrap();
+
+        // The main file is written first, then the second one can't be
+        std::fs::remove_file(&employees).unwrap();
+        assert!(matches!(
+            database.execute_sql("COMMIT"),
+            Err(DatabaseError::FileSystemError(_))
+        ));
+        assert!(
+            std::fs::read(&path).unwrap() == file,
+            "The main file was left changed"
+        );
+        assert!(!std::path::Path::new(&format!("{}-journal", path.display())).exists());
+        assert!(database.is_autocommit());
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM authors WHERE name = 'New 1'"
+            ),
+            ["0"]
+        );
+        std::fs::remove_file(format!("{employees}-journal")).ok();
+    }
+}
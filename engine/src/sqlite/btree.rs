@@ -0,0 +1,980 @@
+use crate::errors::DatabaseError;
+use crate::sqlite::freelist::{allocate_page, free_pages};
+use crate::sqlite::record::{decode_record, read_varint, write_varint};
+use crate::sqlite::{PageType, Pager, TextEncoding};
+use common::types::Value;
+use std::cmp::Ordering;
+use std::ops::RangeInclusive;
+
+/// Deeper trees than this can only come from a corrupt file with a cycle of pages
+const MAX_TREE_DEPTH: usize = 32;
+
+/// A row of a table b-tree, the payload is the record with the column values
+pub struct TableRow {
+    pub rowid: i64,
+    pub payload: Vec<u8>,
+}
+
+/// Reads every row of the table b-tree rooted at the given page, in rowid order
+pub fn scan_table(pager: &Pager, root: u32) -> Result<Vec<TableRow>, DatabaseError> {
+    search_table(pager, root, i64::MIN..=i64::MAX)
+}
+
+/// Reads the rows of the table b-tree whose rowid is in the range, in rowid order. Only the
+/// pages that can hold such rows are visited
+pub fn search_table(
+    pager: &Pager,
+    root: u32,
+    rowids: RangeInclusive<i64>,
+) -> Result<Vec<TableRow>, DatabaseError> {
+    let mut rows = vec![];
+    if !rowids.is_empty() {
+        search_table_page(pager, root, 0, &rowids, &mut rows)?;
+    }
+    Ok(rows)
+}
+
+fn search_table_page(
+    pager: &Pager,
+    number: u32,
+    depth: usize,
+    rowids: &RangeInclusive<i64>,
+    rows: &mut Vec<TableRow>,
+) -> Result<(), DatabaseError> {
+    if depth > MAX_TREE_DEPTH {
+        return Err(DatabaseError::StateError(format!(
+            "The b-tree at page {number} is too deep"
+        )));
+    }
+    let page = pager.page(number)?;
+    // The first page starts with the database header
+    let header = if number == 1 { 100 } else { 0 };
+    let page_type = PageType::try_from(page[header])?;
+    let cell_count = read_u16(page, header + 3) as usize;
+
+    match page_type {
+        PageType::LeafTablePage => {
+            for index in 0..cell_count {
+                let offset = read_u16(page, header + 8 + index * 2) as usize;
+                let (payload_size, length) = read_varint(&page[offset..])?;
+                let (rowid, rowid_length) = read_varint(&page[offset + length..])?;
+                if !rowids.contains(&(rowid as i64)) {
+                    continue;
+                }
+                let start = offset + length + rowid_length;
+                rows.push(TableRow {
+                    rowid: rowid as i64,
+                    payload: read_payload(pager, page, start, payload_size as usize, false)?,
+                });
+            }
+        }
+        PageType::InteriorTablePage => {
+            // A child holds the rowids up to its key, the right-most one those above the last
+            for index in 0..cell_count {
+                let offset = read_u16(page, header + 12 + index * 2) as usize;
+                let key = read_varint(&page[offset + 4..])?.0 as i64;
+                if key < *rowids.start() {
+                    continue;
+                }
+                search_table_page(pager, read_u32(page, offset), depth + 1, rowids, rows)?;
+                if key >= *rowids.end() {
+                    return Ok(());
+                }
+            }
+            search_table_page(pager, read_u32(page, header + 8), depth + 1, rowids, rows)?;
+        }
+        PageType::InteriorIndexPage | PageType::LeafIndexPage => {
+            return Err(DatabaseError::StateError(format!(
+                "Page {number} belongs to an index, not a table"
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// Reads the records of the index b-tree rooted at the given page that are in the searched
+/// range, in index order. `position` places a record before the range (`Less`), inside it
+/// (`Equal`) or after it (`Greater`), the pages holding records outside it are skipped
+pub fn search_index(
+    pager: &Pager,
+    root: u32,
+    encoding: TextEncoding,
+    position: &mut dyn FnMut(&[Value]) -> Ordering,
+) -> Result<Vec<Vec<Value>>, DatabaseError> {
+    let mut records = vec![];
+    search_index_page(pager, root, 0, encoding, position, &mut records)?;
+    Ok(records)
+}
+
+/// Searches a page of an index, returning true once a record after the range was seen
+fn search_index_page(
+    pager: &Pager,
+    number: u32,
+    depth: usize,
+    encoding: TextEncoding,
+    position: &mut dyn FnMut(&[Value]) -> Ordering,
+    records: &mut Vec<Vec<Value>>,
+) -> Result<bool, DatabaseError> {
+    if depth > MAX_TREE_DEPTH {
+        return Err(DatabaseError::StateError(format!(
+            "The b-tree at page {number} is too deep"
+        )));
+    }
+    let page = pager.page(number)?;
+    let page_type = PageType::try_from(page[0])?;
+    let cell_count = read_u16(page, 3) as usize;
+    let interior = match page_type {
+        PageType::InteriorIndexPage => true,
+        PageType::LeafIndexPage => false,
+        PageType::InteriorTablePage | PageType::LeafTablePage => {
+            return Err(DatabaseError::StateError(format!(
+                "Page {number} belongs to a table, not an index"
+            )))
+        }
+    };
+    let pointers = if interior { 12 } else { 8 };
+
+    for index in 0..cell_count {
+        let mut offset = read_u16(page, pointers + index * 2) as usize;
+        let child = interior.then(|| read_u32(page, offset));
+        if interior {
+            offset += 4;
+        }
+        let (size, length) = read_varint(&page[offset..])?;
+        let payload = read_payload(pager, page, offset + length, size as usize, true)?;
+        let record = decode_record(&payload, encoding)?;
+        let ordering = position(&record);
+        // The records of a child sort before the cell that points to it
+        if let Some(child) = child.filter(|_| ordering.is_ge()) {
+            if search_index_page(pager, child, depth + 1, encoding, position, records)? {
+                return Ok(true);
+            }
+        }
+        match ordering {
+            Ordering::Less => {}
+            Ordering::Equal => records.push(record),
+            Ordering::Greater => return Ok(true),
+        }
+    }
+    if interior {
+        return search_index_page(
+            pager,
+            read_u32(page, 8),
+            depth + 1,
+            encoding,
+            position,
+            records,
+        );
+    }
+    Ok(false)
+}
+
+/// Reads the payload of a cell. The part that doesn't fit into the page is spilled to a chain of
+/// overflow pages, each starting with the number of the next one
+fn read_payload(
+    pager: &Pager,
+    page: &[u8],
+    start: usize,
+    size: usize,
+    index: bool,
+) -> Result<Vec<u8>, DatabaseError> {
+    let usable = pager.usable_size();
+    let local = local_payload_size(usable, size, index);
+
+    let truncated = || DatabaseError::StateError("Truncated cell payload".into());
+    let mut payload = page
+        .get(start..start + local)
+        .ok_or_else(truncated)?
+        .to_vec();
+    if local < size {
+        let mut next = read_u32(page, start + local);
+        while payload.len() < size {
+            let overflow = pager.page(next)?;
+            let length = (size - payload.len()).min(usable - 4);
+            payload.extend_from_slice(&overflow[4..4 + length]);
+            next = read_u32(overflow, 0);
+        }
+    }
+    Ok(payload)
+}
+
+/// How much of a payload is stored in the cell itself, the rest goes to overflow pages.
+/// Index cells keep less of it, so that an index page always holds at least four of them
+fn local_payload_size(usable: usize, size: usize, index: bool) -> usize {
+    let max_local = match index {
+        true => (usable - 12) * 64 / 255 - 23,
+        false => usable - 35,
+    };
+    if size <= max_local {
+        return size;
+    }
+    let min_local = (usable - 12) * 32 / 255 - 23;
+    let local = min_local + (size - min_local) % (usable - 4);
+    if local <= max_local {
+        local
+    } else {
+        min_local
+    }
+}
+
+/// Lists every page of the table or index b-tree rooted at the given page, overflow pages
+/// included
+pub fn tree_pages(pager: &Pager, root: u32) -> Result<Vec<u32>, DatabaseError> {
+    let mut pages = vec![];
+    collect_pages(pager, root, 0, &mut pages)?;
+    Ok(pages)
+}
+
+fn collect_pages(
+    pager: &Pager,
+    number: u32,
+    depth: usize,
+    pages: &mut Vec<u32>,
+) -> Result<(), DatabaseError> {
+    if depth > MAX_TREE_DEPTH {
+        return Err(DatabaseError::StateError(format!(
+            "The b-tree at page {number} is too deep"
+        )));
+    }
+    pages.push(number);
+    let page = pager.page(number)?;
+    let header = if number == 1 { 100 } else { 0 };
+    let page_type = PageType::try_from(page[header])?;
+    let cell_count = read_u16(page, header + 3) as usize;
+    let interior = matches!(
+        page_type,
+        PageType::InteriorTablePage | PageType::InteriorIndexPage
+    );
+    let pointers = header + if interior { 12 } else { 8 };
+
+    for index in 0..cell_count {
+        let mut offset = read_u16(page, pointers + index * 2) as usize;
+        if interior {
+            collect_pages(pager, read_u32(page, offset), depth + 1, pages)?;
+            offset += 4;
+        }
+        // Interior table cells only hold a key, the other cells a payload
+        let index_cell = match page_type {
+            PageType::InteriorTablePage => continue,
+            PageType::LeafTablePage => false,
+            PageType::InteriorIndexPage | PageType::LeafIndexPage => true,
+        };
+        let (size, length) = read_varint(&page[offset..])?;
+        offset += length;
+        if !index_cell {
+            offset += read_varint(&page[offset..])?.1;
+        }
+        let size = size as usize;
+        let local = local_payload_size(pager.usable_size(), size, index_cell);
+        if local < size {
+            let overflow_pages = (size - local).div_ceil(pager.usable_size() - 4);
+            let mut next = read_u32(page, offset + local);
+            for _ in 0..overflow_pages {
+                pages.push(next);
+                next = read_u32(pager.page(next)?, 0);
+            }
+        }
+    }
+    if interior {
+        collect_pages(pager, read_u32(page, header + 8), depth + 1, pages)?;
+    }
+    Ok(())
+}
+
+/// Replaces the rows of the table b-tree rooted at the given page. The root keeps its number,
+/// which the schema refers to, the other pages of the old tree are freed first so the new one
+/// reuses them
+pub fn write_table(pager: &mut Pager, root: u32, rows: &[TableRow]) -> Result<(), DatabaseError> {
+    let old_pages: Vec<u32> = tree_pages(pager, root)?
+        .into_iter()
+        .filter(|&page| page != root)
+        .collect();
+    free_pages(pager, &old_pages)?;
+
+    let usable = pager.usable_size();
+    let root_space = usable - if root == 1 { 100 } else { 0 };
+    let mut cells = vec![];
+    for row in rows {
+        cells.push((row.rowid, table_leaf_cell(pager, row)?));
+    }
+
+    if fits(&cells, root_space - 8) {
+        return write_page(pager, root, PageType::LeafTablePage, &cells, None);
+    }
+
+    // Children of the next level up, with the largest rowid under each of them
+    let mut children = vec![];
+    for group in split(cells, usable - 8) {
+        let page = allocate_page(pager)?;
+        let key = group.last().map_or(0, |(rowid, _)| *rowid);
+        write_page(pager, page, PageType::LeafTablePage, &group, None)?;
+        children.push((key, page));
+    }
+    loop {
+        let cells: Vec<(i64, Vec<u8>)> = children[..children.len() - 1]
+            .iter()
+            .map(|&(key, page)| (key, interior_table_cell(page, key)))
+            .collect();
+        let (last_key, right_child) = children[children.len() - 1];
+        if fits(&cells, root_space - 12) {
+            return write_page(
+                pager,
+                root,
+                PageType::InteriorTablePage,
+                &cells,
+                Some(right_child),
+            );
+        }
+
+        let mut parents = vec![];
+        let mut groups = split(cells, usable - 12).into_iter().peekable();
+        let mut consumed = 0;
+        while let Some(mut group) = groups.next() {
+            consumed += group.len();
+            // The child after the group's last cell becomes the node's right child
+            let (key, right) = match groups.peek() {
+                Some(_) => {
+                    let (key, _) = group.pop().expect("Groups are never empty");
+                    (key, children[consumed - 1].1)
+                }
+                None => (last_key, right_child),
+            };
+            let page = allocate_page(pager)?;
+            write_page(
+                pager,
+                page,
+                PageType::InteriorTablePage,
+                &group,
+                Some(right),
+            )?;
+            parents.push((key, page));
+        }
+        children = parents;
+    }
+}
+
+/// Builds an index b-tree out of records sorted in index order, returning its root page. Unlike
+/// the keys of a table, every record is stored once: the ones between two child pages are the
+/// cells of their parent
+pub fn create_index_tree(pager: &mut Pager, records: &[Vec<u8>]) -> Result<u32, DatabaseError> {
+    let root = allocate_page(pager)?;
+    build_index_tree(pager, root, records)?;
+    Ok(root)
+}
+
+fn build_index_tree(
+    pager: &mut Pager,
+    root: u32,
+    records: &[Vec<u8>],
+) -> Result<(), DatabaseError> {
+    let usable = pager.usable_size();
+    let mut cells = vec![];
+    for record in records {
+        cells.push(((), index_cell(pager, record)?));
+    }
+    if fits(&cells, usable - 8) {
+        return write_page(pager, root, PageType::LeafIndexPage, &cells, None);
+    }
+
+    // The last cell of every group but the last one moves up, between its page and the next
+    let mut children = vec![];
+    let mut dividers = vec![];
+    let mut groups = split(cells, usable - 8).into_iter().peekable();
+    while let Some(mut group) = groups.next() {
+        if groups.peek().is_some() {
+            dividers.push(group.pop().expect("Groups are never empty").1);
+        }
+        let page = allocate_page(pager)?;
+        write_page(pager, page, PageType::LeafIndexPage, &group, None)?;
+        children.push(page);
+    }
+    loop {
+        // A divider becomes the cell pointing at the child before it
+        let cells: Vec<((), Vec<u8>)> = children
+            .iter()
+            .zip(&dividers)
+            .map(|(child, divider)| ((), [&child.to_be_bytes()[..], divider].concat()))
+            .collect();
+        let right_child = children[children.len() - 1];
+        if fits(&cells, usable - 12) {
+            return write_page(
+                pager,
+                root,
+                PageType::InteriorIndexPage,
+                &cells,
+                Some(right_child),
+            );
+        }
+
+        let (mut parents, mut parent_dividers) = (vec![], vec![]);
+        let mut groups = split(cells, usable - 12).into_iter().peekable();
+        while let Some(mut group) = groups.next() {
+            // The child of the moved cell becomes the node's right child
+            let right = match groups.peek() {
+                Some(_) => {
+                    let (_, cell) = group.pop().expect("Groups are never empty");
+                    parent_dividers.push(cell[4..].to_vec());
+                    read_u32(&cell, 0)
+                }
+                None => right_child,
+            };
+            let page = allocate_page(pager)?;
+            write_page(
+                pager,
+                page,
+                PageType::InteriorIndexPage,
+                &group,
+                Some(right),
+            )?;
+            parents.push(page);
+        }
+        children = parents;
+        dividers = parent_dividers;
+    }
+}
+
+/// Adds a row to the table b-tree rooted at the given page, or replaces the row of the same
+/// rowid. Only the pages on the way to its leaf are rewritten, split if the row doesn't fit
+pub fn insert_table_row(pager: &mut Pager, root: u32, row: &TableRow) -> Result<(), DatabaseError> {
+    let cell = table_leaf_cell(pager, row)?;
+    let mut path = vec![];
+    let mut number = root;
+    loop {
+        let mut node = read_node(pager, number, path.len())?;
+        let Some(right_child) = node.right_child else {
+            let mut position = node.cells.len();
+            for (index, (_, existing)) in node.cells.iter().enumerate() {
+                let rowid = leaf_rowid(existing)?;
+                if rowid < row.rowid {
+                    continue;
+                }
+                position = index;
+                if rowid == row.rowid {
+                    let old = node.cells.remove(index).1;
+                    let pages = overflow_pages(pager, PageType::LeafTablePage, &old)?;
+                    free_pages(pager, &pages)?;
+                }
+                break;
+            }
+            node.cells.insert(position, (row.rowid, cell));
+            return store(pager, path, number, node);
+        };
+        // A child holds the rowids up to its key, the right-most one those above the last
+        let mut child = (node.cells.len(), right_child);
+        for (index, (key, cell)) in node.cells.iter().enumerate() {
+            if *key >= row.rowid {
+                child = (index, read_u32(cell, 0));
+                break;
+            }
+        }
+        path.push((number, child.0));
+        number = child.1;
+    }
+}
+
+/// Adds a record to the index b-tree rooted at the given page. `position` places a record of
+/// the index before (`Less`) or after (`Greater`) the new one, which goes before the first
+/// record after it
+pub fn insert_index_record(
+    pager: &mut Pager,
+    root: u32,
+    encoding: TextEncoding,
+    record: &[u8],
+    position: &mut dyn FnMut(&[Value]) -> Ordering,
+) -> Result<(), DatabaseError> {
+    let cell = index_cell(pager, record)?;
+    let mut path = vec![];
+    let mut number = root;
+    loop {
+        let mut node = read_node(pager, number, path.len())?;
+        let start = if node.right_child.is_some() { 4 } else { 0 };
+        let mut index = node.cells.len();
+        for (cell_index, (_, existing)) in node.cells.iter().enumerate() {
+            let (size, length) = read_varint(&existing[start..])?;
+            let payload = read_payload(pager, existing, start + length, size as usize, true)?;
+            if position(&decode_record(&payload, encoding)?).is_gt() {
+                index = cell_index;
+                break;
+            }
+        }
+        let Some(right_child) = node.right_child else {
+            node.cells.insert(index, (0, cell));
+            return store(pager, path, number, node);
+        };
+        // The records of a child sort before the cell that points to it
+        let child = node
+            .cells
+            .get(index)
+            .map_or(right_child, |(_, cell)| read_u32(cell, 0));
+        path.push((number, index));
+        number = child;
+    }
+}
+
+/// The largest rowid of the table b-tree rooted at the given page, `None` if it is empty
+pub fn last_rowid(pager: &Pager, root: u32) -> Result<Option<i64>, DatabaseError> {
+    let mut number = root;
+    for depth in 0.. {
+        let node = read_node(pager, number, depth)?;
+        match node.right_child {
+            Some(child) => number = child,
+            None => return node.cells.last().map(|(key, _)| Ok(*key)).transpose(),
+        }
+    }
+    unreachable!("read_node stops at the maximum depth")
+}
+
+/// The cells of a b-tree page, to change and write back. A table cell comes with its rowid or
+/// key, an index cell with 0
+struct Node {
+    page_type: PageType,
+    cells: Vec<(i64, Vec<u8>)>,
+    right_child: Option<u32>,
+}
+
+fn read_node(pager: &Pager, number: u32, depth: usize) -> Result<Node, DatabaseError> {
+    if depth > MAX_TREE_DEPTH {
+        return Err(DatabaseError::StateError(format!(
+            "The b-tree at page {number} is too deep"
+        )));
+    }
+    let page = pager.page(number)?;
+    let header = if number == 1 { 100 } else { 0 };
+    let page_type = PageType::try_from(page[header])?;
+    let cell_count = read_u16(page, header + 3) as usize;
+    let right_child = match page_type {
+        PageType::InteriorTablePage | PageType::InteriorIndexPage => {
+            Some(read_u32(page, header + 8))
+        }
+        PageType::LeafTablePage | PageType::LeafIndexPage => None,
+    };
+    let pointers = header + if right_child.is_some() { 12 } else { 8 };
+
+    let mut cells = vec![];
+    for index in 0..cell_count {
+        let offset = read_u16(page, pointers + index * 2) as usize;
+        let length = cell_length(pager.usable_size(), page_type, &page[offset..])?;
+        let cell = page
+            .get(offset..offset + length)
+            .ok_or_else(|| DatabaseError::StateError("Truncated cell payload".into()))?;
+        let key = match page_type {
+            PageType::LeafTablePage => leaf_rowid(cell)?,
+            PageType::InteriorTablePage => read_varint(&cell[4..])?.0 as i64,
+            PageType::InteriorIndexPage | PageType::LeafIndexPage => 0,
+        };
+        cells.push((key, cell.to_vec()));
+    }
+    Ok(Node {
+        page_type,
+        cells,
+        right_child,
+    })
+}
+
+/// The size of a cell, up to the number of its first overflow page if it has one
+fn cell_length(usable: usize, page_type: PageType, cell: &[u8]) -> Result<usize, DatabaseError> {
+    let (child, index) = match page_type {
+        PageType::InteriorTablePage => return Ok(4 + read_varint(&cell[4..])?.1),
+        PageType::LeafTablePage => (0, false),
+        PageType::InteriorIndexPage => (4, true),
+        PageType::LeafIndexPage => (0, true),
+    };
+    let (size, length) = read_varint(&cell[child..])?;
+    let mut header = child + length;
+    if !index {
+        header += read_varint(&cell[header..])?.1;
+    }
+    let size = size as usize;
+    let local = local_payload_size(usable, size, index);
+    Ok(header + local + if local < size { 4 } else { 0 })
+}
+
+/// The rowid of a table leaf cell, after its payload size
+fn leaf_rowid(cell: &[u8]) -> Result<i64, DatabaseError> {
+    let length = read_varint(cell)?.1;
+    Ok(read_varint(&cell[length..])?.0 as i64)
+}
+
+/// The overflow pages holding the end of the payload of a cell
+fn overflow_pages(
+    pager: &Pager,
+    page_type: PageType,
+    cell: &[u8],
+) -> Result<Vec<u32>, DatabaseError> {
+    let usable = pager.usable_size();
+    let length = cell_length(usable, page_type, cell)?;
+    let (size, _) = read_varint(cell)?;
+    let index = !matches!(page_type, PageType::LeafTablePage);
+    let local = local_payload_size(usable, size as usize, index);
+    let mut pages = vec![];
+    if local < size as usize {
+        let mut next = read_u32(cell, length - 4);
+        for _ in 0..(size as usize - local).div_ceil(usable - 4) {
+            pages.push(next);
+            next = read_u32(pager.page(next)?, 0);
+        }
+    }
+    Ok(pages)
+}
+
+/// Writes a changed page of a b-tree, reached from the root through the pages and child
+/// indexes of the path. A page its cells don't fit into any more keeps the last of them, the
+/// others go to new pages before it, which the parent gets cells for. The root keeps its number,
+/// when it splits its cells all move to new pages below it
+fn store(
+    pager: &mut Pager,
+    mut path: Vec<(u32, usize)>,
+    mut number: u32,
+    mut node: Node,
+) -> Result<(), DatabaseError> {
+    let usable = pager.usable_size();
+    loop {
+        let header_size = if node.right_child.is_some() { 12 } else { 8 };
+        let header = if number == 1 { 100 } else { 0 };
+        if fits(&node.cells, usable - header - header_size) {
+            return write_page(pager, number, node.page_type, &node.cells, node.right_child);
+        }
+
+        let mut groups = split(node.cells, usable - header_size);
+        let last = groups.pop().expect("Groups are never empty");
+        // The cells pointing at the new pages, in the parent
+        let mut dividers = vec![];
+        for mut group in groups {
+            let page = allocate_page(pager)?;
+            // The last cell of a group moves up, except in a table leaf, whose parent only
+            // needs the largest rowid. Its child becomes the right child of the new page
+            let (divider, right_child) = match node.page_type {
+                PageType::LeafTablePage => {
+                    let key = group.last().map_or(0, |(rowid, _)| *rowid);
+                    ((key, interior_table_cell(page, key)), None)
+                }
+                PageType::InteriorTablePage => {
+                    let (key, cell) = group.pop().expect("Groups are never empty");
+                    (
+                        (key, interior_table_cell(page, key)),
+                        Some(read_u32(&cell, 0)),
+                    )
+                }
+                PageType::LeafIndexPage => {
+                    let (_, cell) = group.pop().expect("Groups are never empty");
+                    ((0, [&page.to_be_bytes()[..], &cell].concat()), None)
+                }
+                PageType::InteriorIndexPage => {
+                    let (_, cell) = group.pop().expect("Groups are never empty");
+                    let divider = [&page.to_be_bytes()[..], &cell[4..]].concat();
+                    ((0, divider), Some(read_u32(&cell, 0)))
+                }
+            };
+            write_page(pager, page, node.page_type, &group, right_child)?;
+            dividers.push(divider);
+        }
+
+        match path.pop() {
+            Some((parent, index)) => {
+                write_page(pager, number, node.page_type, &last, node.right_child)?;
+                let mut parent_node = read_node(pager, parent, path.len())?;
+                parent_node.cells.splice(index..index, dividers);
+                (number, node) = (parent, parent_node);
+            }
+            None => {
+                let page = allocate_page(pager)?;
+                write_page(pager, page, node.page_type, &last, node.right_child)?;
+                let page_type = match node.page_type {
+                    PageType::LeafTablePage | PageType::InteriorTablePage => {
+                        PageType::InteriorTablePage
+                    }
+                    PageType::LeafIndexPage | PageType::InteriorIndexPage => {
+                        PageType::InteriorIndexPage
+                    }
+                };
+                node = Node {
+                    page_type,
+                    cells: dividers,
+                    right_child: Some(page),
+                };
+            }
+        }
+    }
+}
+
+/// A table leaf cell: the payload size, the rowid and the payload
+fn table_leaf_cell(pager: &mut Pager, row: &TableRow) -> Result<Vec<u8>, DatabaseError> {
+    let mut cell = vec![];
+    write_varint(row.payload.len() as u64, &mut cell);
+    write_varint(row.rowid as u64, &mut cell);
+    spill(pager, &row.payload, false, &mut cell)?;
+    Ok(cell)
+}
+
+/// An index leaf cell: the payload size and the payload. Interior cells are the same, after the
+/// number of their child page
+fn index_cell(pager: &mut Pager, payload: &[u8]) -> Result<Vec<u8>, DatabaseError> {
+    let mut cell = vec![];
+    write_varint(payload.len() as u64, &mut cell);
+    spill(pager, payload, true, &mut cell)?;
+    Ok(cell)
+}
+
+/// Appends the part of the payload that fits into the cell, the rest goes to newly allocated
+/// overflow pages, and the cell ends with the number of the first one
+fn spill(
+    pager: &mut Pager,
+    payload: &[u8],
+    index: bool,
+    cell: &mut Vec<u8>,
+) -> Result<(), DatabaseError> {
+    let usable = pager.usable_size();
+    let local = local_payload_size(usable, payload.len(), index);
+    cell.extend_from_slice(&payload[..local]);
+    if local == payload.len() {
+        return Ok(());
+    }
+
+    let chunks: Vec<&[u8]> = payload[local..].chunks(usable - 4).collect();
+    let mut pages = vec![];
+    for _ in &chunks {
+        pages.push(allocate_page(pager)?);
+    }
+    for (index, chunk) in chunks.iter().enumerate() {
+        let next = pages.get(index + 1).copied().unwrap_or(0);
+        let data = pager.page_mut(pages[index])?;
+        data[0..4].copy_from_slice(&next.to_be_bytes());
+        data[4..4 + chunk.len()].copy_from_slice(chunk);
+    }
+    cell.extend_from_slice(&pages[0].to_be_bytes());
+    Ok(())
+}
+
+fn interior_table_cell(child: u32, key: i64) -> Vec<u8> {
+    let mut cell = child.to_be_bytes().to_vec();
+    write_varint(key as u64, &mut cell);
+    cell
+}
+
+/// Returns true if the cells and their 2-byte pointers fit into the given space
+fn fits<K>(cells: &[(K, Vec<u8>)], space: usize) -> bool {
+    cells.iter().map(|(_, cell)| cell.len() + 2).sum::<usize>() <= space
+}
+
+/// Splits cells into consecutive groups filling pages of the given space
+fn split<K>(cells: Vec<(K, Vec<u8>)>, space: usize) -> Vec<Vec<(K, Vec<u8>)>> {
+    let mut groups = vec![];
+    let mut group = vec![];
+    let mut used = 0;
+    for cell in cells {
+        let size = cell.1.len() + 2;
+        if used + size > space && !group.is_empty() {
+            groups.push(std::mem::take(&mut group));
+            used = 0;
+        }
+        used += size;
+        group.push(cell);
+    }
+    if !group.is_empty() {
+        groups.push(group);
+    }
+    groups
+}
+
+/// Writes a b-tree page: its header, the cell pointers, and the cells packed at the end
+fn write_page<K>(
+    pager: &mut Pager,
+    number: u32,
+    page_type: PageType,
+    cells: &[(K, Vec<u8>)],
+    right_child: Option<u32>,
+) -> Result<(), DatabaseError> {
+    let data = pager.page_mut(number)?;
+    let header = if number == 1 { 100 } else { 0 };
+    data[header..].fill(0);
+
+    let header_size = if right_child.is_some() { 12 } else { 8 };
+    let mut content = data.len();
+    for (index, (_, cell)) in cells.iter().enumerate() {
+        content -= cell.len();
+        data[content..content + cell.len()].copy_from_slice(cell);
+        let pointer = header + header_size + index * 2;
+        data[pointer..pointer + 2].copy_from_slice(&(content as u16).to_be_bytes());
+    }
+
+    data[header] = page_type as u8;
+    data[header + 3..header + 5].copy_from_slice(&(cells.len() as u16).to_be_bytes());
+    // A content area starting at 65536 is recorded as 0
+    data[header + 5..header + 7].copy_from_slice(&(content as u16).to_be_bytes());
+    if let Some(child) = right_child {
+        data[header + 8..header + 12].copy_from_slice(&child.to_be_bytes());
+    }
+    Ok(())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::header::DATABASE_PAGE_COUNT;
+    use crate::sqlite::record::encode_record;
+    use crate::sqlite::TextEncoding;
+    use common::types::Value;
+
+    /// Reads the payloads of an index b-tree in order, with the depth of the tree
+    fn scan_index(pager: &Pager, number: u32, payloads: &mut Vec<Vec<u8>>) -> usize {
+        let page = pager.page(number).unwrap();
+        let interior = matches!(
+            PageType::try_from(page[0]).unwrap(),
+            PageType::InteriorIndexPage
+        );
+        let pointers = if interior { 12 } else { 8 };
+        let mut depth = 0;
+        for index in 0..read_u16(page, 3) as usize {
+            let mut offset = read_u16(page, pointers + index * 2) as usize;
+            if interior {
+                depth = scan_index(pager, read_u32(page, offset), payloads);
+                offset += 4;
+            }
+            let (size, length) = read_varint(&page[offset..]).unwrap();
+            let payload = read_payload(pager, page, offset + length, size as usize, true);
+            payloads.push(payload.unwrap());
+        }
+        if interior {
+            depth = scan_index(pager, read_u32(page, 8), payloads);
+        }
+        depth + 1
+    }
+
+    #[test]
+    fn test_create_index_tree() {
+        let mut pager = Pager::new(vec![0; 512], 512, 0);
+        pager.set_header_field(DATABASE_PAGE_COUNT, 1);
+        // Every hundredth record overflows
+        let records: Vec<Vec<u8>> = (0..2000usize)
+            .map(|number| {
+                let text = "x".repeat(if number % 100 == 0 { 1500 } else { number % 40 });
+                let values = [Value::Integer(number as i64), Value::Text(text)];
+                encode_record(&values, TextEncoding::UTF_8)
+            })
+            .collect();
+        let root = create_index_tree(&mut pager, &records).unwrap();
+        assert_eq!(root, 2);
+
+        let mut payloads = vec![];
+        assert!(scan_index(&pager, root, &mut payloads) >= 3);
+        assert_eq!(payloads, records);
+        // No page is left out or used twice
+        let mut pages = tree_pages(&pager, root).unwrap();
+        pages.sort();
+        assert_eq!(pages, (2..=pager.page_count() as u32).collect::<Vec<_>>());
+
+        let root = create_index_tree(&mut pager, &records[..3]).unwrap();
+        let mut payloads = vec![];
+        assert_eq!(scan_index(&pager, root, &mut payloads), 1);
+        assert_eq!(payloads, &records[..3]);
+    }
+
+    #[test]
+    fn test_insert_table_row() {
+        let mut pager = Pager::new(vec![0; 512], 512, 0);
+        pager.set_header_field(DATABASE_PAGE_COUNT, 1);
+        let root = allocate_page(&mut pager).unwrap();
+        write_page::<()>(&mut pager, root, PageType::LeafTablePage, &[], None).unwrap();
+        assert_eq!(last_rowid(&pager, root).unwrap(), None);
+
+        // In an order that splits pages in the middle as well as at the end, every hundredth
+        // payload overflows
+        let payload = |rowid: i64, length: usize| {
+            let text = "x".repeat(if rowid % 100 == 0 { 1500 } else { length });
+            encode_record(
+                &[Value::Integer(rowid), Value::Text(text)],
+                TextEncoding::UTF_8,
+            )
+        };
+        for number in 1..=3000 {
+            let rowid = number * 7919 % 3001;
+            let payload = payload(rowid, rowid as usize % 40);
+            insert_table_row(&mut pager, root, &TableRow { rowid, payload }).unwrap();
+        }
+        // Replaced by longer rows
+        for rowid in (50..3000).step_by(50) {
+            let payload = payload(rowid, 200);
+            insert_table_row(&mut pager, root, &TableRow { rowid, payload }).unwrap();
+        }
+
+        let rows = scan_table(&pager, root).unwrap();
+        assert_eq!(
+            rows.iter().map(|row| row.rowid).collect::<Vec<_>>(),
+            (1..=3000).collect::<Vec<_>>()
+        );
+        for row in &rows {
+            let length = if row.rowid % 50 == 0 {
+                200
+            } else {
+                row.rowid as usize % 40
+            };
+            assert_eq!(row.payload, payload(row.rowid, length), "{}", row.rowid);
+        }
+        assert_eq!(last_rowid(&pager, root).unwrap(), Some(3000));
+        assert_eq!(search_table(&pager, root, 1234..=1235).unwrap().len(), 2);
+
+        // The overflow pages of the replaced rows were freed, no page is lost or used twice
+        let mut pages = tree_pages(&pager, root).unwrap();
+        pages.sort();
+        pages.dedup();
+        let free = pager.header_field(crate::sqlite::header::FREELIST_PAGE_COUNT) as usize;
+        assert_eq!(pages.len() + free, pager.page_count() - 1);
+    }
+
+    #[test]
+    fn test_insert_index_record() {
+        let mut pager = Pager::new(vec![0; 512], 512, 0);
+        pager.set_header_field(DATABASE_PAGE_COUNT, 1);
+        let root = create_index_tree(&mut pager, &[]).unwrap();
+
+        let record = |number: i64| {
+            let text = "x".repeat(if number % 100 == 0 {
+                1500
+            } else {
+                number as usize % 40
+            });
+            vec![Value::Text(text), Value::Integer(number)]
+        };
+        for number in 1..=2000 {
+            let new = record(number * 7919 % 2003);
+            let payload = encode_record(&new, TextEncoding::UTF_8);
+            insert_index_record(
+                &mut pager,
+                root,
+                TextEncoding::UTF_8,
+                &payload,
+                &mut |existing| existing.cmp(&new[..]),
+            )
+            .unwrap();
+        }
+
+        let mut expected: Vec<Vec<Value>> = (1..=2002)
+            .filter(|number| (1..=2000).any(|n| n * 7919 % 2003 == *number))
+            .map(record)
+            .collect();
+        expected.sort();
+        let mut payloads = vec![];
+        assert!(scan_index(&pager, root, &mut payloads) >= 3);
+        let records: Vec<Vec<Value>> = payloads
+            .iter()
+            .map(|payload| decode_record(payload, TextEncoding::UTF_8).unwrap())
+            .collect();
+        assert_eq!(records, expected);
+        let mut pages = tree_pages(&pager, root).unwrap();
+        pages.sort();
+        assert_eq!(pages, (2..=pager.page_count() as u32).collect::<Vec<_>>());
+    }
+}
@@ -1,12 +1,374 @@
 use super::DatabaseError;
+use crate::sqlite::btree::scan_table;
+use crate::sqlite::record::decode_record;
+use crate::sqlite::{Pager, TextEncoding};
+use common::types::Value;
+use sql::ast::{
+    Ast, ColumnConstraintKind, ColumnDefinition, CreateTable, Expression, IndexedColumn, Ordering,
+    TableConstraintKind, TableDefinition,
+};
+use sql::Parser;
 
-#[derive(Debug)]
+/// The table every other object of the schema is recorded in, always rooted at the first page
+pub const SCHEMA_TABLE: &str = "sqlite_schema";
+
+#[derive(Debug, Clone)]
 pub struct Catalog {
     pub tables_count: u16,
+    pub objects: Vec<SchemaObject>,
+}
+
+/// A row of the schema table: a table, index, view or trigger
+#[derive(Debug, Clone)]
+pub struct SchemaObject {
+    /// The rowid of the object's row in the schema table
+    pub rowid: i64,
+    pub object_type: String,
+    pub name: String,
+    pub table_name: String,
+    pub root_page: u32,
+    pub sql: Option<String>,
+    /// The columns of a table, empty for the other objects
+    pub columns: Vec<TableColumn>,
+    /// The positions of the primary key columns of a WITHOUT ROWID table, in key order. Its
+    /// b-tree is an index of records holding them first, then the other columns
+    pub without_rowid: Option<Vec<usize>>,
+    /// Why the SQL of a table couldn't be read, e.g. a virtual table. It stays in the catalog,
+    /// only the statements using it fail, see [`SchemaObject::readable`]
+    pub unreadable: Option<String>,
+}
+
+impl SchemaObject {
+    /// Fails for a table whose definition couldn't be read
+    pub fn readable(&self) -> Result<(), DatabaseError> {
+        match &self.unreadable {
+            Some(reason) => Err(DatabaseError::ExecutionError(format!(
+                "unable to use {} {}: {reason}",
+                self.object_type, self.name
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TableColumn {
+    pub name: String,
+    pub declared_type: String,
+    /// An `INTEGER PRIMARY KEY` column is stored as the rowid, not in the record
+    pub rowid_alias: bool,
+    /// The `COLLATE` of the column definition, its values are compared by it in indexes
+    pub collation: Option<String>,
+    /// The `DEFAULT` of the column definition, the value of a column added by ALTER TABLE in
+    /// the records written before
+    pub default: Option<Expression>,
+    /// The expression of a generated column
+    pub generated: Option<Expression>,
+    /// False for a VIRTUAL generated column, computed when the row is read instead
+    pub in_record: bool,
 }
 
 impl Catalog {
-    pub fn load(_data: &[u8]) -> Result<Self, DatabaseError> {
-        todo!()
+    pub fn load(pager: &Pager, encoding: TextEncoding) -> Result<Self, DatabaseError> {
+        let mut objects = vec![];
+        for row in scan_table(pager, 1)? {
+            let mut values = decode_record(&row.payload, encoding)?.into_iter();
+            let mut text = || match values.next() {
+                Some(Value::Text(text)) => Some(text),
+                _ => None,
+            };
+            let (object_type, name, table_name) = (text(), text(), text());
+            let root_page = match values.next() {
+                Some(Value::Integer(page)) => page as u32,
+                _ => 0,
+            };
+            let sql = match values.next() {
+                Some(Value::Text(sql)) => Some(sql),
+                _ => None,
+            };
+            let (Some(object_type), Some(name), Some(table_name)) = (object_type, name, table_name)
+            else {
+                return Err(DatabaseError::StateError(format!(
+                    "Malformed schema entry {}",
+                    row.rowid
+                )));
+            };
+            let (columns, without_rowid, unreadable) = match (object_type.as_str(), &sql) {
+                ("table", Some(sql)) => match table_columns(sql) {
+                    Ok((columns, without_rowid)) => (columns, without_rowid, None),
+                    Err(error) => (vec![], None, Some(error.to_string())),
+                },
+                _ => (vec![], None, None),
+            };
+            objects.push(SchemaObject {
+                rowid: row.rowid,
+                object_type,
+                name,
+                table_name,
+                root_page,
+                sql,
+                columns,
+                without_rowid,
+                unreadable,
+            });
+        }
+
+        let tables_count = objects
+            .iter()
+            .filter(|object| object.object_type == "table")
+            .count() as u16;
+        Ok(Self {
+            tables_count,
+            objects,
+        })
+    }
+
+    /// Finds a table by its case-insensitive name, the schema table included
+    pub fn table(&self, name: &str) -> Option<SchemaObject> {
+        if name.eq_ignore_ascii_case(SCHEMA_TABLE) || name.eq_ignore_ascii_case("sqlite_master") {
+            return Some(Catalog::schema_table());
+        }
+        self.objects
+            .iter()
+            .find(|object| object.object_type == "table" && object.name.eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// Finds an object of any kind by its case-insensitive name
+    pub fn object(&self, name: &str) -> Option<&SchemaObject> {
+        self.objects
+            .iter()
+            .find(|object| object.name.eq_ignore_ascii_case(name))
+    }
+
+    fn schema_table() -> SchemaObject {
+        let column = |name: &str, declared_type: &str| TableColumn {
+            name: name.into(),
+            declared_type: declared_type.into(),
+            rowid_alias: false,
+            collation: None,
+            default: None,
+            generated: None,
+            in_record: true,
+        };
+        SchemaObject {
+            rowid: 0,
+            object_type: "table".into(),
+            name: SCHEMA_TABLE.into(),
+            table_name: SCHEMA_TABLE.into(),
+            root_page: 1,
+            sql: None,
+            columns: vec![
+                column("type", "TEXT"),
+                column("name", "TEXT"),
+                column("tbl_name", "TEXT"),
+                column("rootpage", "INT"),
+                column("sql", "TEXT"),
+            ],
+            without_rowid: None,
+            unreadable: None,
+        }
+    }
+}
+
+/// Reads the column names and types out of a `CREATE TABLE` statement, and the primary key
+/// of a WITHOUT ROWID table
+fn table_columns(sql: &str) -> Result<(Vec<TableColumn>, Option<Vec<usize>>), DatabaseError> {
+    let malformed = || DatabaseError::StateError(format!("Malformed table definition: {sql}"));
+    let (columns, constraints, without_rowid) = match Parser::new(sql).parse()? {
+        Ast::CreateTable(CreateTable {
+            definition:
+                TableDefinition::Columns {
+                    columns,
+                    constraints,
+                    without_rowid,
+                    ..
+                },
+            ..
+        }) => (columns, constraints, without_rowid),
+        _ => return Err(malformed()),
+    };
+
+    let declared_type = |column: &ColumnDefinition| match &column.type_name {
+        Some(type_name) => type_name.name.to_ascii_uppercase(),
+        None => String::new(),
+    };
+    // A single column primary key of the table, e.g. `PRIMARY KEY (id)`
+    let table_key = constraints
+        .iter()
+        .find_map(|constraint| match &constraint.kind {
+            TableConstraintKind::PrimaryKey { columns, .. } => match columns.as_slice() {
+                [IndexedColumn {
+                    expression: Expression::Column(column),
+                    ..
+                }] if column.table.is_none() => Some(column.name.clone()),
+                _ => None,
+            },
+            _ => None,
+        });
+
+    let position = |name: &str| {
+        columns
+            .iter()
+            .position(|column| column.name.eq_ignore_ascii_case(name))
+    };
+    let without_rowid = match without_rowid {
+        true => {
+            let column_key = columns.iter().position(|column| {
+                column.constraints.iter().any(|constraint| {
+                    matches!(constraint.kind, ColumnConstraintKind::PrimaryKey { .. })
+                })
+            });
+            let table_key = constraints
+                .iter()
+                .find_map(|constraint| match &constraint.kind {
+                    TableConstraintKind::PrimaryKey { columns, .. } => Some(columns),
+                    _ => None,
+                });
+            let key = match (column_key, table_key) {
+                (Some(column), _) => vec![column],
+                (None, Some(key)) => key
+                    .iter()
+                    .map(|column| match &column.expression {
+                        Expression::Column(column) => position(&column.name),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(malformed)?,
+                // SQLite refuses to create a WITHOUT ROWID table without a primary key
+                (None, None) => return Err(malformed()),
+            };
+            Some(key)
+        }
+        false => None,
+    };
+
+    let columns = columns
+        .iter()
+        .map(|column| {
+            let declared_type = declared_type(column);
+            // `INTEGER PRIMARY KEY DESC` is an ordinary column, a quirk SQLite keeps
+            let column_key = column.constraints.iter().any(|constraint| {
+                matches!(
+                    constraint.kind,
+                    ColumnConstraintKind::PrimaryKey { ordering, .. }
+                        if ordering != Some(Ordering::Descending)
+                )
+            });
+            let table_key = table_key
+                .as_ref()
+                .is_some_and(|key| key.eq_ignore_ascii_case(&column.name));
+            let collation =
+                column
+                    .constraints
+                    .iter()
+                    .find_map(|constraint| match &constraint.kind {
+                        ColumnConstraintKind::Collate(collation) => Some(collation.clone()),
+                        _ => None,
+                    });
+            let default = column
+                .constraints
+                .iter()
+                .find_map(|constraint| match &constraint.kind {
+                    ColumnConstraintKind::Default(expression) => Some(expression.clone()),
+                    _ => None,
+                });
+            let generated =
+                column
+                    .constraints
+                    .iter()
+                    .find_map(|constraint| match &constraint.kind {
+                        ColumnConstraintKind::Generated { expression, stored } => {
+                            Some((expression.clone(), *stored))
+                        }
+                        _ => None,
+                    });
+            TableColumn {
+                name: column.name.clone(),
+                // A WITHOUT ROWID table has no rowid to alias
+                rowid_alias: declared_type == "INTEGER"
+                    && (column_key || table_key)
+                    && without_rowid.is_none(),
+                declared_type,
+                collation,
+                default,
+                in_record: generated.as_ref().is_none_or(|(_, stored)| *stored),
+                generated: generated.map(|(expression, _)| expression),
+            }
+        })
+        .collect();
+    Ok((columns, without_rowid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe(sql: &str) -> Vec<(String, String, bool)> {
+        table_columns(sql)
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|column| (column.name, column.declared_type, column.rowid_alias))
+            .collect()
+    }
+
+    #[test]
+    fn test_table_columns() {
+        let column = |name: &str, declared_type: &str, rowid_alias: bool| {
+            (name.to_string(), declared_type.to_string(), rowid_alias)
+        };
+        assert_eq!(
+            describe("CREATE TABLE t (id integer primary key, name varchar(10) not null, \"x y\")"),
+            vec![
+                column("id", "INTEGER", true),
+                column("name", "VARCHAR", false),
+                column("x y", "", false),
+            ]
+        );
+        assert_eq!(
+            describe("CREATE TABLE t (a INTEGER, b TEXT DEFAULT 'x', PRIMARY KEY (a DESC))"),
+            vec![column("a", "INTEGER", true), column("b", "TEXT", false)]
+        );
+        assert_eq!(
+            describe("CREATE TABLE t (a INTEGER PRIMARY KEY DESC, b INT PRIMARY KEY)"),
+            vec![column("a", "INTEGER", false), column("b", "INT", false)]
+        );
+        assert_eq!(
+            describe(
+                "CREATE TABLE t (a integer, b integer CHECK (b > 0), CONSTRAINT k PRIMARY KEY (a, b))"
+            ),
+            vec![column("a", "INTEGER", false), column("b", "INTEGER", false)]
+        );
+        assert_eq!(
+            describe("CREATE TABLE t (\"a\" INTEGER, PRIMARY KEY (\"A\")) WITHOUT ROWID"),
+            vec![column("a", "INTEGER", false)]
+        );
+        assert_eq!(
+            describe("CREATE TABLE _u (_id INTEGER PRIMARY KEY)"),
+            vec![column("_id", "INTEGER", true)]
+        );
+        let key = |sql: &str| table_columns(sql).unwrap().1;
+        assert_eq!(key("CREATE TABLE t (a, b)"), None);
+        assert_eq!(
+            key("CREATE TABLE t (a, b TEXT PRIMARY KEY) WITHOUT ROWID"),
+            Some(vec![1])
+        );
+        assert_eq!(
+            key("CREATE TABLE t (a, b, c, PRIMARY KEY (c, A)) WITHOUT ROWID"),
+            Some(vec![2, 0])
+        );
+
+        let collations: Vec<Option<String>> =
+            table_columns("CREATE TABLE t (a TEXT COLLATE NOCASE, b, c COLLATE rtrim NOT NULL)")
+                .unwrap()
+                .0
+                .into_iter()
+                .map(|column| column.collation)
+                .collect();
+        assert_eq!(
+            collations,
+            vec![Some("NOCASE".into()), None, Some("rtrim".into())]
+        );
     }
 }
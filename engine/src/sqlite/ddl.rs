@@ -0,0 +1,830 @@
+use crate::errors::DatabaseError;
+use crate::executor::{Executor, ResultSet};
+use crate::sqlite::btree::{create_index_tree, scan_table, tree_pages, write_table, TableRow};
+use crate::sqlite::freelist::free_pages;
+use crate::sqlite::header::{FILE_CHANGE_COUNTER, SCHEMA_COOKIE, VERSION_VALID_FOR};
+use crate::sqlite::record::encode_record;
+use crate::sqlite::{decode_record, Catalog, Database, Header, SchemaObject};
+use common::types::Value;
+use sql::ast::{
+    CreateIndex, CreateView, DropObject, Expression, Limit, Literal, ObjectKind, Select,
+};
+
+/// The table SQLite keeps the last rowid of every AUTOINCREMENT table in
+pub(super) const SEQUENCE_TABLE: &str = "sqlite_sequence";
+
+impl Database {
+    /// Runs the query of a view without rows, failing if it has another number of columns than
+    /// the view names. A query that fails is kept, as SQLite does the names it uses are only
+    /// resolved when the view is used
+    fn check_view_columns(&mut self, create: &CreateView) -> Result<(), DatabaseError> {
+        if create.columns.is_empty() {
+            return Ok(());
+        }
+        let query = Select {
+            limit: Some(Limit {
+                count: Expression::Literal(Literal::Integer(0)),
+                offset: None,
+            }),
+            ..(*create.query).clone()
+        };
+        match Executor::new(self).select(&query) {
+            Ok(result) if result.columns.len() != create.columns.len() => {
+                Err(DatabaseError::ExecutionError(format!(
+                    "expected {} columns for '{}' but got {}",
+                    create.columns.len(),
+                    create.name,
+                    result.columns.len()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Builds an index over the existing rows of a table and records it in the schema
+    pub(crate) fn create_index(
+        &mut self,
+        create: &CreateIndex,
+    ) -> Result<ResultSet, DatabaseError> {
+        if let Some(schema) = &create.schema {
+            if !schema.eq_ignore_ascii_case("main") {
+                return Err(DatabaseError::ExecutionError(format!(
+                    "unknown database {schema}"
+                )));
+            }
+        }
+        if create.name.to_ascii_lowercase().starts_with("sqlite_") {
+            return Err(DatabaseError::ExecutionError(format!(
+                "object name reserved for internal use: {}",
+                create.name
+            )));
+        }
+        if let Some(object) = self.catalog.object(&create.name) {
+            return match object.object_type.as_str() {
+                _ if create.if_not_exists => Ok(ResultSet::default()),
+                "index" => Err(DatabaseError::ExecutionError(format!(
+                    "index {} already exists",
+                    create.name
+                ))),
+                object_type => Err(DatabaseError::ExecutionError(format!(
+                    "there is already a {object_type} named {}",
+                    create.name
+                ))),
+            };
+        }
+
+        let Some(table) = self.catalog.table(&create.table_name) else {
+            return match self.catalog.object(&create.table_name) {
+                Some(object) if object.object_type == "view" => Err(DatabaseError::ExecutionError(
+                    "views may not be indexed".into(),
+                )),
+                _ => Err(DatabaseError::NoSuchTable(format!(
+                    "main.{}",
+                    create.table_name
+                ))),
+            };
+        };
+        table.readable()?;
+        if table.name.to_ascii_lowercase().starts_with("sqlite_") {
+            let name = match table.root_page {
+                1 => "sqlite_master",
+                _ => &table.name,
+            };
+            return Err(DatabaseError::ExecutionError(format!(
+                "table {name} may not be indexed"
+            )));
+        }
+        if table.without_rowid.is_some() {
+            return Err(DatabaseError::ExecutionError(format!(
+                "cannot index {}: WITHOUT ROWID tables are not supported",
+                table.name
+            )));
+        }
+
+        let encoding = self.header.text_encoding;
+        let records: Vec<Vec<u8>> = Executor::new(self)
+            .index_records(&table, create)?
+            .iter()
+            .map(|values| encode_record(values, encoding))
+            .collect();
+        let root = create_index_tree(&mut self.pager, &records)?;
+        // The schema keeps the statement without the schema name, as SQLite does
+        let sql = CreateIndex {
+            if_not_exists: false,
+            schema: None,
+            ..create.clone()
+        };
+        self.add_schema_row(&[
+            Value::Text("index".into()),
+            Value::Text(create.name.clone()),
+            Value::Text(table.name.clone()),
+            Value::Integer(root as i64),
+            Value::Text(sql.to_string()),
+        ])?;
+        self.schema_changed()?;
+        Ok(ResultSet::default())
+    }
+
+    /// Records a view in the schema, its query only runs when the view is used
+    pub(crate) fn create_view(&mut self, create: &CreateView) -> Result<ResultSet, DatabaseError> {
+        let in_temp = create
+            .schema
+            .as_ref()
+            .is_some_and(|schema| schema.eq_ignore_ascii_case("temp"));
+        match &create.schema {
+            Some(_) if create.temporary && !in_temp => {
+                return Err(DatabaseError::ExecutionError(
+                    "temporary table name must be unqualified".into(),
+                ))
+            }
+            Some(schema) if !in_temp && !schema.eq_ignore_ascii_case("main") => {
+                return Err(DatabaseError::ExecutionError(format!(
+                    "unknown database {schema}"
+                )))
+            }
+            _ => {}
+        }
+        if create.name.to_ascii_lowercase().starts_with("sqlite_") {
+            return Err(DatabaseError::ExecutionError(format!(
+                "object name reserved for internal use: {}",
+                create.name
+            )));
+        }
+        let temporary = create.temporary || in_temp;
+        let objects = if temporary {
+            &self.temp
+        } else {
+            &self.catalog.objects
+        };
+        if let Some(object) = objects
+            .iter()
+            .find(|object| object.name.eq_ignore_ascii_case(&create.name))
+        {
+            if create.if_not_exists {
+                return Ok(ResultSet::default());
+            }
+            return Err(DatabaseError::ExecutionError(format!(
+                "{} {} already exists",
+                object.object_type, create.name
+            )));
+        }
+        self.check_view_columns(create)?;
+
+        // The schema keeps the statement without TEMP and the schema name, as SQLite does
+        let sql = CreateView {
+            temporary: false,
+            if_not_exists: false,
+            schema: None,
+            ..create.clone()
+        }
+        .to_string();
+        if temporary {
+            let rowid = self.temp.last().map_or(1, |object| object.rowid + 1);
+            self.temp.push(SchemaObject {
+                rowid,
+                object_type: "view".into(),
+                name: create.name.clone(),
+                table_name: create.name.clone(),
+                root_page: 0,
+                sql: Some(sql),
+                columns: vec![],
+                without_rowid: None,
+                unreadable: None,
+            });
+            return Ok(ResultSet::default());
+        }
+        self.add_schema_row(&[
+            Value::Text("view".into()),
+            Value::Text(create.name.clone()),
+            Value::Text(create.name.clone()),
+            Value::Integer(0),
+            Value::Text(sql),
+        ])?;
+        self.schema_changed()?;
+        Ok(ResultSet::default())
+    }
+
+    /// Removes an object from the schema. Dropping a table drops its indexes and triggers too,
+    /// and every page of the dropped b-trees goes to the freelist
+    pub(crate) fn drop_object(&mut self, drop: &DropObject) -> Result<ResultSet, DatabaseError> {
+        let kind = drop.kind.type_name();
+        let name = match &drop.schema {
+            Some(schema) => format!("{schema}.{}", drop.name),
+            None => drop.name.clone(),
+        };
+        let internal = drop.name.to_ascii_lowercase().starts_with("sqlite_");
+        if drop.kind == ObjectKind::Table && internal {
+            let table = match self.catalog.table(&drop.name) {
+                Some(table) if table.root_page == 1 => "sqlite_master".to_string(),
+                Some(table) => table.name,
+                None => return Err(DatabaseError::NoSuchTable(name)),
+            };
+            return Err(DatabaseError::ExecutionError(format!(
+                "table {table} may not be dropped"
+            )));
+        }
+
+        let temp_object = self
+            .temp
+            .iter()
+            .find(|object| object.name.eq_ignore_ascii_case(&drop.name))
+            .cloned();
+        // Unqualified names are looked up in the temp schema first
+        let (object, temporary) = match drop.schema.as_deref() {
+            None if temp_object.is_some() => (temp_object, true),
+            Some(schema) if schema.eq_ignore_ascii_case("temp") => (temp_object, true),
+            Some(schema) if !schema.eq_ignore_ascii_case("main") => (None, false),
+            _ => (self.catalog.object(&drop.name).cloned(), false),
+        };
+        let object = match object {
+            Some(object) if object.object_type == kind => object,
+            // Tables and views share a namespace, a mix-up gets a hint
+            Some(object)
+                if matches!(drop.kind, ObjectKind::Table | ObjectKind::View)
+                    && matches!(object.object_type.as_str(), "table" | "view") =>
+            {
+                return Err(DatabaseError::ExecutionError(format!(
+                    "use DROP {} to delete {} {}",
+                    object.object_type.to_uppercase(),
+                    object.object_type,
+                    object.name
+                )));
+            }
+            _ if drop.if_exists => return Ok(ResultSet::default()),
+            _ if drop.kind == ObjectKind::Table => return Err(DatabaseError::NoSuchTable(name)),
+            _ => return Err(DatabaseError::NoSuchObject(kind, name)),
+        };
+        object.readable()?;
+        if drop.kind == ObjectKind::Index && object.sql.is_none() {
+            return Err(DatabaseError::ExecutionError(
+                "index associated with UNIQUE or PRIMARY KEY constraint cannot be dropped".into(),
+            ));
+        }
+        if temporary {
+            self.temp.retain(|other| other.rowid != object.rowid);
+            return Ok(ResultSet::default());
+        }
+
+        let mut dropped = vec![object.clone()];
+        if drop.kind == ObjectKind::Table {
+            dropped.extend(
+                self.catalog
+                    .objects
+                    .iter()
+                    .filter(|other| {
+                        other.rowid != object.rowid
+                            && other.table_name.eq_ignore_ascii_case(&object.name)
+                    })
+                    .cloned(),
+            );
+        }
+
+        let mut pages = vec![];
+        for object in dropped.iter().filter(|object| object.root_page != 0) {
+            pages.extend(tree_pages(&self.pager, object.root_page)?);
+        }
+        self.remove_schema_rows(&dropped)?;
+        if drop.kind == ObjectKind::Table {
+            self.remove_sequence(&object.name)?;
+        }
+        free_pages(&mut self.pager, &pages)?;
+        self.schema_changed()?;
+        Ok(ResultSet::default())
+    }
+
+    pub(super) fn add_schema_row(&mut self, values: &[Value]) -> Result<(), DatabaseError> {
+        let mut rows = scan_table(&self.pager, 1)?;
+        let rowid = rows.last().map_or(1, |row| row.rowid + 1);
+        rows.push(TableRow {
+            rowid,
+            payload: encode_record(values, self.header.text_encoding),
+        });
+        write_table(&mut self.pager, 1, &rows)
+    }
+
+    /// Writes changed objects back to their rows of the schema table
+    pub(super) fn update_schema_rows(
+        &mut self,
+        objects: &[SchemaObject],
+    ) -> Result<(), DatabaseError> {
+        let mut rows = scan_table(&self.pager, 1)?;
+        for row in &mut rows {
+            let Some(object) = objects.iter().find(|object| object.rowid == row.rowid) else {
+                continue;
+            };
+            let sql = match &object.sql {
+                Some(sql) => Value::Text(sql.clone()),
+                None => Value::Null,
+            };
+            row.payload = encode_record(
+                &[
+                    Value::Text(object.object_type.clone()),
+                    Value::Text(object.name.clone()),
+                    Value::Text(object.table_name.clone()),
+                    Value::Integer(object.root_page as i64),
+                    sql,
+                ],
+                self.header.text_encoding,
+            );
+        }
+        write_table(&mut self.pager, 1, &rows)
+    }
+
+    fn remove_schema_rows(&mut self, objects: &[SchemaObject]) -> Result<(), DatabaseError> {
+        let rows = scan_table(&self.pager, 1)?
+            .into_iter()
+            .filter(|row| !objects.iter().any(|object| object.rowid == row.rowid))
+            .collect::<Vec<_>>();
+        write_table(&mut self.pager, 1, &rows)
+    }
+
+    /// Forgets the AUTOINCREMENT counter of a dropped table
+    fn remove_sequence(&mut self, table: &str) -> Result<(), DatabaseError> {
+        let Some(sequence) = self.catalog.table(SEQUENCE_TABLE) else {
+            return Ok(());
+        };
+        let mut rows = vec![];
+        for row in scan_table(&self.pager, sequence.root_page)? {
+            let values = decode_record(&row.payload, self.header.text_encoding)?;
+            if !matches!(values.first(), Some(Value::Text(name)) if name.eq_ignore_ascii_case(table))
+            {
+                rows.push(row);
+            }
+        }
+        write_table(&mut self.pager, sequence.root_page, &rows)
+    }
+
+    /// Bumps the schema cookie and the change counter after a schema change, then reloads the
+    /// header and the catalog from the changed pages
+    pub(super) fn schema_changed(&mut self) -> Result<(), DatabaseError> {
+        let counter = self.pager.header_field(FILE_CHANGE_COUNTER).wrapping_add(1);
+        self.pager.set_header_field(FILE_CHANGE_COUNTER, counter);
+        // The page count in the header is only trusted if this matches the change counter
+        self.pager.set_header_field(VERSION_VALID_FOR, counter);
+        let cookie = self.pager.header_field(SCHEMA_COOKIE).wrapping_add(1);
+        self.pager.set_header_field(SCHEMA_COOKIE, cookie);
+        self.reload_schema()
+    }
+
+    /// Reads the header and the schema table again, after their pages changed
+    pub(super) fn reload_schema(&mut self) -> Result<(), DatabaseError> {
+        self.header = Header::load(&self.pager.data()[0..100])?;
+        self.catalog = Catalog::load(&self.pager, self.header.text_encoding)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{error, library, query, reload};
+
+    fn names(database: &Database) -> Vec<String> {
+        let mut names: Vec<String> = database
+            .catalog
+            .objects
+            .iter()
+            .map(|object| object.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_drop_table() {
+        let mut database = library();
+        let pages = database.pager.page_count() as u32;
+        let cookie = database.header.schema_cookie;
+        let tree = |database: &Database, name: &str| {
+            let root = database.catalog.object(name).unwrap().root_page;
+            tree_pages(&database.pager, root).unwrap().len()
+        };
+        let schema_pages = tree_pages(&database.pager, 1).unwrap().len();
+        let freed = tree(&database, "books") + tree(&database, "books_by_year");
+
+        database.execute_sql("DROP TABLE books").unwrap();
+        assert_eq!(
+            names(&database),
+            vec![
+                "authors",
+                "authors_by_name",
+                "loans",
+                "recent_books",
+                "sqlite_sequence"
+            ]
+        );
+        let reloaded = reload(&database);
+        // The smaller schema table may need fewer pages as well
+        let schema_freed = schema_pages - tree_pages(&database.pager, 1).unwrap().len();
+        assert_eq!(
+            reloaded.header.freelist_page_count as usize,
+            freed + schema_freed
+        );
+        assert_eq!(reloaded.header.database_page_count, pages);
+        assert_eq!(reloaded.header.schema_cookie, cookie + 1);
+        assert_eq!(
+            query(
+                &mut reload(&database),
+                "SELECT count(*) FROM sqlite_sequence"
+            ),
+            vec!["0"]
+        );
+        assert_eq!(
+            query(
+                &mut reload(&database),
+                "SELECT name FROM authors WHERE id = 7"
+            ),
+            vec!["Author 7"]
+        );
+        assert!(matches!(
+            database.execute_sql("SELECT * FROM books"),
+            Err(DatabaseError::NoSuchTable(_))
+        ));
+    }
+
+    #[test]
+    fn test_drop_other_objects() {
+        let mut database = library();
+        let schema_pages = tree_pages(&database.pager, 1).unwrap().len();
+        let freed = tree_pages(&database.pager, 5).unwrap().len();
+        database
+            .execute_sql(
+                "DROP INDEX authors_by_name; DROP VIEW main.recent_books; \
+                 DROP TRIGGER books_touch; DROP TABLE loans",
+            )
+            .unwrap();
+        assert_eq!(
+            names(&database),
+            vec!["authors", "books", "books_by_year", "sqlite_sequence"]
+        );
+        // The table without rowid is an index b-tree of one page
+        let schema_freed = schema_pages - tree_pages(&database.pager, 1).unwrap().len();
+        assert_eq!(
+            reload(&database).header.freelist_page_count as usize,
+            freed + 1 + schema_freed
+        );
+        assert_eq!(
+            query(&mut reload(&database), "SELECT count(*) FROM books"),
+            vec!["300"]
+        );
+    }
+
+    #[test]
+    fn test_drop_reuses_freed_pages() {
+        let mut database = library();
+        let pages = database.pager.page_count();
+        // Rewriting the schema table frees and reallocates pages, the file never grows
+        for name in ["books_by_year", "authors_by_name"] {
+            database.execute_sql(&format!("DROP INDEX {name}")).unwrap();
+            assert_eq!(database.pager.page_count(), pages);
+        }
+        let summaries = "SELECT sum(length(summary)) FROM books WHERE id % 50 = 0";
+        assert_eq!(query(&mut reload(&database), summaries), vec!["18000"]);
+    }
+
+    #[test]
+    fn test_drop_errors() {
+        let mut database = library();
+        for (sql, expected) in [
+            ("DROP TABLE nope", "no such table: nope"),
+            ("DROP TABLE other.books", "no such table: other.books"),
+            ("DROP INDEX books", "no such index: books"),
+            ("DROP TRIGGER books", "no such trigger: books"),
+            ("DROP VIEW books", "use DROP TABLE to delete table books"),
+            (
+                "DROP TABLE recent_books",
+                "use DROP VIEW to delete view recent_books",
+            ),
+            (
+                "DROP TABLE sqlite_schema",
+                "table sqlite_master may not be dropped",
+            ),
+            (
+                "DROP TABLE sqlite_sequence",
+                "table sqlite_sequence may not be dropped",
+            ),
+        ] {
+            assert_eq!(error(&mut database, sql), expected, "{sql}");
+        }
+        assert!(database
+            .execute_sql("DROP TABLE IF EXISTS nope; DROP INDEX IF EXISTS books")
+            .is_ok());
+        assert_eq!(database.catalog.objects.len(), 8);
+    }
+
+    fn index_records(database: &Database, sql: &str) -> Result<Vec<Vec<Value>>, DatabaseError> {
+        let Ok(sql::ast::Ast::CreateIndex(create)) = sql::Parser::new(sql).parse() else {
+            panic!("Expected a CREATE INDEX statement: {sql}");
+        };
+        let table = database.catalog.table(&create.table_name).unwrap();
+        Executor::new(database).index_records(&table, &create)
+    }
+
+    #[test]
+    fn test_create_index() {
+        let mut database = library();
+        let pages = database.pager.page_count();
+        let schema_pages = tree_pages(&database.pager, 1).unwrap().len();
+        let cookie = database.header.schema_cookie;
+        database
+            .execute_sql("CREATE INDEX main.by_author ON books (author_id, title DESC)")
+            .unwrap();
+
+        let reloaded = reload(&database);
+        let index = reloaded.catalog.object("by_author").unwrap();
+        assert_eq!(index.object_type, "index");
+        assert_eq!(index.table_name, "books");
+        assert_eq!(
+            index.sql.as_deref(),
+            Some("CREATE INDEX by_author ON books (author_id, title DESC)")
+        );
+        assert_eq!(reloaded.header.schema_cookie, cookie + 1);
+        // 300 short entries spill over a few pages, the schema table may need another one
+        let index_pages = tree_pages(&reloaded.pager, index.root_page).unwrap().len();
+        let schema_added = tree_pages(&reloaded.pager, 1).unwrap().len() - schema_pages;
+        assert!(index_pages > 1);
+        assert_eq!(
+            reloaded.pager.page_count(),
+            pages + index_pages + schema_added
+        );
+
+        assert!(database
+            .execute_sql("CREATE INDEX IF NOT EXISTS by_author ON authors (name)")
+            .is_ok());
+        assert_eq!(
+            query(
+                &mut reload(&database),
+                "SELECT count(*) FROM sqlite_schema WHERE type = 'index'"
+            ),
+            vec!["3"]
+        );
+    }
+
+    #[test]
+    fn test_index_records() {
+        let database = library();
+        let records = index_records(
+            &database,
+            "CREATE INDEX i ON authors (country COLLATE NOCASE DESC, id) WHERE id <= 3",
+        )
+        .unwrap();
+        let countries = query_rows(&database, "SELECT country, id FROM authors WHERE id <= 3");
+        let mut expected: Vec<Vec<Value>> = countries
+            .into_iter()
+            .map(|row| vec![row[0].clone(), row[1].clone(), row[1].clone()])
+            .collect();
+        expected.sort_by(|a, b| b[0].cmp(&a[0]).then(a[1].cmp(&b[1])));
+        assert_eq!(records, expected);
+
+        // Expressions are evaluated, the rowid comes last
+        let records = index_records(&database, "CREATE INDEX i ON authors (id * -1)").unwrap();
+        assert_eq!(records.len(), 40);
+        assert_eq!(records[0], vec![Value::Integer(-40), Value::Integer(40)]);
+    }
+
+    fn query_rows(database: &Database, sql: &str) -> Vec<Vec<Value>> {
+        let Ok(sql::ast::Ast::Select(select)) = sql::Parser::new(sql).parse() else {
+            panic!("Expected a SELECT statement: {sql}");
+        };
+        Executor::new(database).select(&select).unwrap().rows
+    }
+
+    #[test]
+    fn test_create_unique_index() {
+        let mut database = library();
+        database
+            .execute_sql("CREATE UNIQUE INDEX by_title ON books (title, year)")
+            .unwrap();
+        for (sql, expected) in [
+            (
+                "CREATE UNIQUE INDEX u ON books (author_id)",
+                "UNIQUE constraint failed: books.author_id",
+            ),
+            (
+                "CREATE UNIQUE INDEX u ON books (YEAR, (Author_Id) COLLATE NOCASE)",
+                "UNIQUE constraint failed: books.year, books.author_id",
+            ),
+            (
+                "CREATE UNIQUE INDEX u ON books (id % 2)",
+                "UNIQUE constraint failed: index 'u'",
+            ),
+        ] {
+            assert_eq!(error(&mut database, sql), expected, "{sql}");
+        }
+        // Rows left out of a partial index can't collide, neither can NULLs
+        database
+            .execute_sql(
+                "CREATE UNIQUE INDEX u ON books (author_id) WHERE id <= 40 AND author_id = id; \
+                 CREATE UNIQUE INDEX v ON authors (nullif(id, id))",
+            )
+            .unwrap();
+        assert_eq!(database.catalog.objects.len(), 11);
+    }
+
+    #[test]
+    fn test_create_index_errors() {
+        let mut database = library();
+        for (sql, expected) in [
+            ("CREATE INDEX i ON nope (a)", "no such table: main.nope"),
+            ("CREATE INDEX i ON books (nope)", "no such column: nope"),
+            (
+                "CREATE INDEX i ON books (title COLLATE nope)",
+                "no such collation sequence: nope",
+            ),
+            (
+                "CREATE INDEX books_by_year ON books (id)",
+                "index books_by_year already exists",
+            ),
+            (
+                "CREATE INDEX Books ON books (id)",
+                "there is already a table named Books",
+            ),
+            (
+                "CREATE INDEX sqlite_i ON books (id)",
+                "object name reserved for internal use: sqlite_i",
+            ),
+            (
+                "CREATE INDEX i ON sqlite_schema (name)",
+                "table sqlite_master may not be indexed",
+            ),
+            (
+                "CREATE INDEX i ON recent_books (title)",
+                "views may not be indexed",
+            ),
+            (
+                "CREATE INDEX other.i ON books (id)",
+                "unknown database other",
+            ),
+            (
+                "CREATE INDEX i ON books (id) WHERE max(id) > 1",
+                "misuse of aggregate function max()",
+            ),
+        ] {
+            assert_eq!(error(&mut database, sql), expected, "{sql}");
+        }
+        assert_eq!(database.catalog.objects.len(), 8);
+    }
+
+    #[test]
+    fn test_create_view() {
+        let mut database = library();
+        database
+            .execute_sql(
+                "CREATE VIEW main.old_books (name, published) AS \
+                 SELECT title, year FROM books WHERE year < 1960",
+            )
+            .unwrap();
+        let reloaded = reload(&database);
+        let view = reloaded.catalog.object("old_books").unwrap();
+        assert_eq!(
+            (
+                view.object_type.as_str(),
+                view.table_name.as_str(),
+                view.root_page
+            ),
+            ("view", "old_books", 0)
+        );
+        assert_eq!(
+            view.sql.as_deref(),
+            Some(
+                "CREATE VIEW old_books (name, published) AS \
+                 SELECT title, year FROM books WHERE year < 1960"
+            )
+        );
+        assert_eq!(
+            query(
+                &mut reload(&database),
+                "SELECT count(*) FROM old_books WHERE published < 1960"
+            ),
+            query(
+                &mut reload(&database),
+                "SELECT count(*) FROM books WHERE year < 1960"
+            )
+        );
+        assert!(database
+            .execute_sql("CREATE VIEW IF NOT EXISTS old_books AS SELECT 1")
+            .is_ok());
+        assert_eq!(database.catalog.objects.len(), 9);
+    }
+
+    #[test]
+    fn test_create_temp_view() {
+        let mut database = library();
+        // A temporary view hides a table of the same name, and is never written
+        database
+            .execute_sql(
+                "CREATE TEMP VIEW authors AS SELECT 'temp' AS name; \
+                 CREATE VIEW temp.first_author AS SELECT name FROM main.authors WHERE id = 1",
+            )
+            .unwrap();
+        assert_eq!(database.temp.len(), 2);
+        assert_eq!(
+            database.temp[0].sql.as_deref(),
+            Some("CREATE VIEW authors AS SELECT 'temp' AS name")
+        );
+        assert_eq!(database.catalog.objects.len(), 8);
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT a.name, f.name FROM authors a, temp.first_author f"
+            ),
+            vec!["temp|Author 1"]
+        );
+
+        database.execute_sql("DROP VIEW authors").unwrap();
+        assert_eq!(
+            query(&mut database, "SELECT count(*) FROM authors"),
+            vec!["40"]
+        );
+        assert_eq!(
+            database
+                .execute_sql("DROP VIEW temp.authors")
+                .unwrap_err()
+                .to_string(),
+            "no such view: temp.authors"
+        );
+        database.execute_sql("DROP VIEW temp.first_author").unwrap();
+        assert!(database.temp.is_empty());
+    }
+
+    #[test]
+    fn test_create_view_errors() {
+        let mut database = library();
+        for (sql, expected) in [
+            (
+                "CREATE VIEW recent_books AS SELECT 1",
+                "view recent_books already exists",
+            ),
+            (
+                "CREATE VIEW Books AS SELECT 1",
+                "table Books already exists",
+            ),
+            (
+                "CREATE VIEW sqlite_v AS SELECT 1",
+                "object name reserved for internal use: sqlite_v",
+            ),
+            (
+                "CREATE TEMP VIEW main.v AS SELECT 1",
+                "temporary table name must be unqualified",
+            ),
+            ("CREATE VIEW other.v AS SELECT 1", "unknown database other"),
+            (
+                "CREATE VIEW v3 (a) AS SELECT 1, 2",
+                "expected 1 columns for 'v3' but got 2",
+            ),
+            (
+                "CREATE TEMP VIEW v3 (a, b) AS SELECT * FROM books UNION SELECT * FROM books",
+                "expected 2 columns for 'v3' but got 5",
+            ),
+        ] {
+            assert_eq!(error(&mut database, sql), expected, "{sql}");
+        }
+        assert_eq!(database.catalog.objects.len(), 8);
+        assert!(database.temp.is_empty());
+        // The names the query uses are only resolved when the view is used
+        query(&mut database, "CREATE VIEW v4 (a) AS SELECT * FROM nope");
+        assert_eq!(
+            error(&mut database, "SELECT * FROM v4"),
+            "no such table: nope"
+        );
+    }
+
+    #[test]
+    fn test_unreadable_table() {
+        let mut database = library();
+        // A virtual table of a module this engine doesn't have
+        database.pager.begin_journal();
+        database
+            .add_schema_row(&[
+                Value::Text("table".into()),
+                Value::Text("docs".into()),
+                Value::Text("docs".into()),
+                Value::Integer(0),
+                Value::Text("CREATE VIRTUAL TABLE docs USING fts5(body)".into()),
+            ])
+            .unwrap();
+        database.schema_changed().unwrap();
+        let path = database.path().map(std::path::Path::to_path_buf);
+        database.pager.commit(path.as_deref()).unwrap();
+
+        let mut database = reload(&database);
+        assert!(database.catalog.object("docs").is_some());
+        assert_eq!(query(&mut database, "SELECT count(*) FROM books"), ["300"]);
+        for sql in [
+            "SELECT * FROM docs",
+            "INSERT INTO docs VALUES ('a')",
+            "CREATE INDEX docs_by_body ON docs (body)",
+            "ALTER TABLE docs ADD COLUMN c",
+            "DROP TABLE docs",
+        ] {
+            assert_eq!(
+                error(&mut database, sql),
+                "unable to use table docs: Unsupported: virtual",
+                "{sql}"
+            );
+        }
+
+        query(&mut database, "CREATE VIEW _v (_a) AS SELECT 1");
+        assert_eq!(query(&mut reload(&database), "SELECT _a FROM _v"), ["1"]);
+    }
+}
@@ -0,0 +1,585 @@
+use crate::errors::DatabaseError;
+use crate::executor::{key_order, Executor, NewRow, ResultSet};
+use crate::sqlite::alter::is_generated;
+use crate::sqlite::btree::{
+    insert_index_record, insert_table_row, last_rowid, scan_table, search_table, TableRow,
+};
+use crate::sqlite::ddl::SEQUENCE_TABLE;
+use crate::sqlite::header::{FILE_CHANGE_COUNTER, VERSION_VALID_FOR};
+use crate::sqlite::record::encode_record;
+use crate::sqlite::{decode_record, Database, Header, SchemaObject};
+use common::types::Value;
+use sql::ast::{
+    Ast, ColumnConstraintKind, ColumnDefinition, ColumnRef, ConflictResolution, CreateIndex,
+    CreateTable, Expression, IndexedColumn, Insert, TableConstraint, TableConstraintKind,
+    TableDefinition,
+};
+use sql::Parser;
+
+impl Database {
+    /// Adds rows to a table of any schema. The rows are computed with every schema in sight,
+    /// e.g. `INSERT INTO archive.t SELECT * FROM main.t`, and written to the file of the table
+    pub(crate) fn insert(&mut self, insert: &Insert) -> Result<ResultSet, DatabaseError> {
+        match insert.conflict {
+            None | Some(ConflictResolution::Abort) => {}
+            Some(conflict) => {
+                return Err(DatabaseError::ExecutionError(format!(
+                    "INSERT OR {conflict} is not supported"
+                )))
+            }
+        }
+        if !insert.upsert.is_empty() {
+            return Err(DatabaseError::ExecutionError(
+                "ON CONFLICT is not supported".into(),
+            ));
+        }
+        if !insert.returning.is_empty() {
+            return Err(DatabaseError::ExecutionError(
+                "RETURNING is not supported".into(),
+            ));
+        }
+
+        let Some(located) = self.relation(insert.schema.as_deref(), &insert.table_name) else {
+            return Err(DatabaseError::NoSuchTable(match &insert.schema {
+                Some(schema) => format!("{schema}.{}", insert.table_name),
+                None => insert.table_name.clone(),
+            }));
+        };
+        let (schema, table) = (located.schema.to_string(), located.object);
+        table.readable()?;
+        if table.object_type == "view" {
+            return Err(DatabaseError::ExecutionError(format!(
+                "cannot modify {} because it is a view",
+                table.name
+            )));
+        }
+        if table.name.to_ascii_lowercase().starts_with("sqlite_") {
+            let name = match table.root_page {
+                1 => "sqlite_master",
+                _ => &table.name,
+            };
+            return Err(DatabaseError::ExecutionError(format!(
+                "table {name} may not be modified"
+            )));
+        }
+
+        let (columns, constraints) = table_definition(&table)?;
+        let rows = Executor::new(self).insert_rows(&table, insert)?;
+        let file = match self.attached_index(&schema) {
+            Some(index) => &mut self.attached[index].database,
+            None => self,
+        };
+        file.append_rows(&table, &columns, &constraints, rows)?;
+        Ok(ResultSet::default())
+    }
+
+    /// Inserts new rows into a table of this file and its indexes, one at a time, assigning the
+    /// missing rowids
+    fn append_rows(
+        &mut self,
+        table: &SchemaObject,
+        columns: &[ColumnDefinition],
+        constraints: &[TableConstraint],
+        rows: Vec<NewRow>,
+    ) -> Result<(), DatabaseError> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let encoding = self.header.text_encoding;
+        let autoincrement = columns.iter().any(|column| {
+            column.constraints.iter().any(|constraint| {
+                matches!(
+                    constraint.kind,
+                    ColumnConstraintKind::PrimaryKey {
+                        autoincrement: true,
+                        ..
+                    }
+                )
+            })
+        });
+        let sequence = match autoincrement {
+            true => self.sequence(&table.name)?,
+            false => None,
+        };
+        let mut indexes = vec![];
+        for index in self.table_indexes(table, columns, constraints)? {
+            if let Some(object) = self.catalog.object(&index.name) {
+                indexes.push((object.root_page, index));
+            }
+        }
+
+        let mut last = last_rowid(&self.pager, table.root_page)?.unwrap_or(0);
+        let mut highest = sequence.unwrap_or(0).max(last);
+        for (rowid, mut values) in rows {
+            let rowid = match rowid {
+                Some(rowid) => rowid,
+                // SQLite would try random rowids after the largest one, this is the end
+                None => highest.checked_add(1).ok_or_else(|| {
+                    DatabaseError::ExecutionError("database or disk is full".into())
+                })?,
+            };
+            let alias = table.columns.iter().position(|column| column.rowid_alias);
+            if let Some(alias) = alias {
+                values[alias] = Value::Integer(rowid);
+            }
+            Executor::new(self).check_row(table, columns, constraints, &values)?;
+            if !search_table(&self.pager, table.root_page, rowid..=rowid)?.is_empty() {
+                let key = table.columns.iter().find(|column| column.rowid_alias);
+                return Err(DatabaseError::UniqueViolation(format!(
+                    "{}.{}",
+                    table.name,
+                    key.map_or("rowid", |column| column.name.as_str())
+                )));
+            }
+
+            // Every index is checked before any is written
+            let mut records = vec![];
+            for (root, index) in &indexes {
+                let record = Executor::new(self)
+                    .new_index_record(self, table, index, *root, &values, rowid)?;
+                records.push(record);
+            }
+            for ((root, index), record) in indexes.iter().zip(records) {
+                let Some(record) = record else {
+                    continue;
+                };
+                let compare = key_order(table, index);
+                let keys = index.columns.len();
+                insert_index_record(
+                    &mut self.pager,
+                    *root,
+                    encoding,
+                    &encode_record(&record, encoding),
+                    &mut |existing| {
+                        compare(existing, &record)
+                            .then_with(|| existing.get(keys).cmp(&record.get(keys)))
+                    },
+                )?;
+            }
+
+            if let Some(alias) = alias {
+                values[alias] = Value::Null;
+            }
+            let payload = encode_record(&values, encoding);
+            insert_table_row(
+                &mut self.pager,
+                table.root_page,
+                &TableRow { rowid, payload },
+            )?;
+            last = last.max(rowid);
+            highest = highest.max(rowid);
+        }
+        if autoincrement && sequence.is_none_or(|sequence| sequence < last) {
+            self.set_sequence(&table.name, last)?;
+        }
+        self.data_changed()
+    }
+
+    /// The indexes of a table: the ones created with CREATE INDEX, then the ones SQLite made for
+    /// its UNIQUE and PRIMARY KEY constraints, numbered in the order of the constraints. Both
+    /// come latest first, the order SQLite finds duplicates in
+    fn table_indexes(
+        &self,
+        table: &SchemaObject,
+        columns: &[ColumnDefinition],
+        constraints: &[TableConstraint],
+    ) -> Result<Vec<CreateIndex>, DatabaseError> {
+        let mut indexes = vec![];
+        for object in &self.catalog.objects {
+            if object.object_type != "index" || !object.table_name.eq_ignore_ascii_case(&table.name)
+            {
+                continue;
+            }
+            let Some(sql) = &object.sql else {
+                continue;
+            };
+            match Parser::new(sql).parse()? {
+                Ast::CreateIndex(index) => indexes.push(index),
+                _ => {
+                    return Err(DatabaseError::StateError(format!(
+                        "Malformed index {}",
+                        object.name
+                    )))
+                }
+            }
+        }
+
+        indexes.reverse();
+
+        let mut keys: Vec<Vec<IndexedColumn>> = vec![];
+        for column in columns {
+            for constraint in &column.constraints {
+                let ordering = match constraint.kind {
+                    ColumnConstraintKind::PrimaryKey { ordering, .. } => ordering,
+                    ColumnConstraintKind::Unique(_) => None,
+                    _ => continue,
+                };
+                keys.push(vec![IndexedColumn {
+                    expression: Expression::Column(ColumnRef::new(&column.name)),
+                    ordering,
+                }]);
+            }
+        }
+        for constraint in constraints {
+            match &constraint.kind {
+                TableConstraintKind::PrimaryKey { columns, .. }
+                | TableConstraintKind::Unique { columns, .. } => keys.push(columns.clone()),
+                _ => {}
+            }
+        }
+        // The rowid is the key of an INTEGER PRIMARY KEY, and a key declared twice gets a
+        // single index
+        let is_rowid = |key: &[IndexedColumn]| match key {
+            [column] => match &column.expression {
+                Expression::Column(reference) => table.columns.iter().any(|column| {
+                    column.rowid_alias && column.name.eq_ignore_ascii_case(&reference.name)
+                }),
+                _ => false,
+            },
+            _ => false,
+        };
+        let key_names = |key: &[IndexedColumn]| -> Vec<String> {
+            key.iter()
+                .map(|column| column.expression.to_string().to_ascii_lowercase())
+                .collect()
+        };
+        let (mut numbered, mut autoindexes): (Vec<Vec<String>>, _) = (vec![], vec![]);
+        for key in keys.into_iter().filter(|key| !is_rowid(key)) {
+            let names = key_names(&key);
+            if numbered.contains(&names) {
+                continue;
+            }
+            numbered.push(names);
+            autoindexes.push(CreateIndex {
+                unique: true,
+                if_not_exists: false,
+                schema: None,
+                name: format!("sqlite_autoindex_{}_{}", table.name, numbered.len()),
+                table_name: table.name.clone(),
+                columns: key,
+                where_clause: None,
+            });
+        }
+        indexes.extend(autoindexes.into_iter().rev());
+        Ok(indexes)
+    }
+
+    /// Reads the last rowid of an AUTOINCREMENT table from the sequence table
+    fn sequence(&self, table: &str) -> Result<Option<i64>, DatabaseError> {
+        let Some(sequence) = self.catalog.table(SEQUENCE_TABLE) else {
+            return Ok(None);
+        };
+        for row in scan_table(&self.pager, sequence.root_page)? {
+            let values = decode_record(&row.payload, self.header.text_encoding)?;
+            if matches!(values.first(), Some(Value::Text(name)) if name.eq_ignore_ascii_case(table))
+            {
+                return Ok(match values.get(1) {
+                    Some(Value::Integer(last)) => Some(*last),
+                    _ => Some(0),
+                });
+            }
+        }
+        Ok(None)
+    }
+
+    /// Records the last rowid of an AUTOINCREMENT table, adding its row if it has none
+    fn set_sequence(&mut self, table: &str, last: i64) -> Result<(), DatabaseError> {
+        let Some(sequence) = self.catalog.table(SEQUENCE_TABLE) else {
+            return Ok(());
+        };
+        let encoding = self.header.text_encoding;
+        let rows = scan_table(&self.pager, sequence.root_page)?;
+        let mut rowid = rows.last().map_or(1, |row| row.rowid + 1);
+        for row in &rows {
+            let values = decode_record(&row.payload, encoding)?;
+            if matches!(values.first(), Some(Value::Text(name)) if name.eq_ignore_ascii_case(table))
+            {
+                rowid = row.rowid;
+            }
+        }
+        let payload = encode_record(&[Value::Text(table.into()), Value::Integer(last)], encoding);
+        insert_table_row(
+            &mut self.pager,
+            sequence.root_page,
+            &TableRow { rowid, payload },
+        )
+    }
+
+    /// Bumps the change counter after the rows changed, then reloads the header
+    fn data_changed(&mut self) -> Result<(), DatabaseError> {
+        let counter = self.pager.header_field(FILE_CHANGE_COUNTER).wrapping_add(1);
+        self.pager.set_header_field(FILE_CHANGE_COUNTER, counter);
+        self.pager.set_header_field(VERSION_VALID_FOR, counter);
+        self.header = Header::load(&self.pager.data()[0..100])?;
+        Ok(())
+    }
+}
+
+/// The column definitions and table constraints of a table rows can be inserted into
+fn table_definition(
+    table: &SchemaObject,
+) -> Result<(Vec<ColumnDefinition>, Vec<TableConstraint>), DatabaseError> {
+    let malformed =
+        || DatabaseError::StateError(format!("Malformed table definition: {}", table.name));
+    let create = match Parser::new(table.sql.as_deref().ok_or_else(malformed)?).parse()? {
+        Ast::CreateTable(create) => create,
+        _ => return Err(malformed()),
+    };
+    let CreateTable {
+        definition:
+            TableDefinition::Columns {
+                columns,
+                constraints,
+                without_rowid,
+                ..
+            },
+        ..
+    } = create
+    else {
+        return Err(malformed());
+    };
+    if without_rowid {
+        return Err(DatabaseError::ExecutionError(format!(
+            "cannot insert into {}: WITHOUT ROWID tables are not supported",
+            table.name
+        )));
+    }
+    if columns.iter().any(is_generated) {
+        return Err(DatabaseError::ExecutionError(format!(
+            "cannot insert into {}: generated columns are not supported",
+            table.name
+        )));
+    }
+    Ok((columns, constraints))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{error, fixture, library, query, reload};
+
+    #[test]
+    fn test_insert_values() {
+        let mut database = fixture("inventory.db");
+        database
+            .execute_sql(
+                "INSERT INTO items (sku, name, price, weight) VALUES ('E-5', 'Eel', '3', '2.0'), \
+                 (NULL, 'anvil', 11, NULL); \
+                 INSERT INTO items VALUES (10, 'F-6', 'Fan', 15, 2, 'light'); \
+                 INSERT INTO tags SELECT id, 'new' FROM items WHERE price > 10",
+            )
+            .unwrap();
+        let mut database = reload(&database);
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT id, sku, price, typeof(price), quantity, weight, typeof(weight) \
+                 FROM items WHERE id > 3"
+            ),
+            [
+                "4|E-5|3.0|real|0|2|integer",
+                "5||11.0|real|0||null",
+                "10|F-6|15.0|real|2|light|text",
+            ]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT name FROM items WHERE price > 10 ORDER BY price"
+            ),
+            ["anvil", "Crate", "Fan", "Anvil"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT id FROM items WHERE sku = 'F-6'"),
+            ["10"]
+        );
+        assert_eq!(
+            query(&mut database, "SELECT tag FROM tags WHERE item_id = 10"),
+            ["new"]
+        );
+        assert_eq!(query(&mut database, "SELECT count(*) FROM tags"), ["8"]);
+    }
+
+    #[test]
+    fn test_insert_autoincrement() {
+        let mut database = library();
+        let sequence = |database: &mut Database| {
+            query(
+                database,
+                "SELECT seq FROM sqlite_sequence WHERE name = 'books'",
+            )
+        };
+        database
+            .execute_sql("INSERT INTO books (title, year) VALUES ('New', 2050)")
+            .unwrap();
+        assert_eq!(sequence(&mut database), ["301"]);
+        database
+            .execute_sql(
+                "INSERT INTO books (id, title) VALUES (1000, 'Far'); \
+                 INSERT INTO books (title, author_id, year) \
+                 SELECT 'Copy of ' || title, author_id, 2050 FROM books WHERE id <= 2",
+            )
+            .unwrap();
+        let mut database = reload(&database);
+        assert_eq!(sequence(&mut database), ["1002"]);
+        // Searched in books_by_year, which has the new rows as well
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT id, title FROM books WHERE year = 2050 ORDER BY id"
+            ),
+            ["301|New", "1001|Copy of Book 1", "1002|Copy of Book 2"]
+        );
+        assert_eq!(query(&mut database, "SELECT count(*) FROM books"), ["304"]);
+    }
+
+    #[test]
+    fn test_insert_incremental() {
+        let mut database = library();
+        let data = database.pager.data().to_vec();
+        database
+            .execute_sql("INSERT INTO books (title, author_id, year) VALUES ('New', 1, 1999)")
+            .unwrap();
+        // The header, the last leaf of books, a leaf of books_by_year and sqlite_sequence
+        let page_size = database.header.page_size as usize;
+        let changed = data
+            .chunks(page_size)
+            .zip(database.pager.data().chunks(page_size))
+            .filter(|(old, new)| old != new)
+            .count();
+        assert_eq!(changed, 4);
+
+        // Enough rows to split pages of the table and its indexes, some overflowing
+        database
+            .execute_sql(
+                "INSERT INTO books (title, author_id, year) \
+                 SELECT 'Copy ' || (x * 7919 % 3001) || CASE WHEN x % 50 = 0 \
+                 THEN (SELECT group_concat(title) FROM books) ELSE '' END, 1, 1900 + x % 120 \
+                 FROM (WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n \
+                 WHERE x < 3000) SELECT x FROM n); \
+                 INSERT INTO authors (name) SELECT 'Writer ' || title FROM books WHERE id > 301",
+            )
+            .unwrap();
+        let mut database = reload(&database);
+        assert_eq!(
+            query(&mut database, "SELECT count(*), max(id) FROM books"),
+            ["3301|3301"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM books WHERE year = 1950 AND title LIKE 'Copy %'"
+            ),
+            ["25"]
+        );
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT b.id FROM authors a, books b WHERE a.name = 'Writer Copy 1917' \
+                 AND b.title = 'Copy 1917'"
+            ),
+            ["302"]
+        );
+        assert_eq!(
+            error(
+                &mut database,
+                "INSERT INTO authors (name) VALUES ('Writer Copy 1917')"
+            ),
+            "UNIQUE constraint failed: authors.name"
+        );
+    }
+
+    #[test]
+    fn test_insert_errors() {
+        let mut database = fixture("inventory.db");
+        let data = database.pager.data().to_vec();
+        for (sql, message) in [
+            (
+                "INSERT INTO items (sku, name) VALUES ('A-1', 'x')",
+                "UNIQUE constraint failed: items.sku",
+            ),
+            (
+                "INSERT INTO items (name, sku) VALUES ('anvil', 'A-1')",
+                "UNIQUE constraint failed: items.name, items.sku",
+            ),
+            (
+                "INSERT INTO items (sku, name, price) VALUES ('Z', 'x', -1)",
+                "CHECK constraint failed: price >= 0",
+            ),
+            (
+                "INSERT INTO items (sku, name, quantity) VALUES ('Z', 'x', -1)",
+                "CHECK constraint failed: stocked",
+            ),
+            (
+                "INSERT INTO items (sku) VALUES ('Z')",
+                "NOT NULL constraint failed: items.name",
+            ),
+            (
+                "INSERT INTO items DEFAULT VALUES",
+                "NOT NULL constraint failed: items.name",
+            ),
+            (
+                "INSERT INTO items (id, name) VALUES (2, 'x')",
+                "UNIQUE constraint failed: items.id",
+            ),
+            (
+                "INSERT INTO tags VALUES (1, 'heavy')",
+                "UNIQUE constraint failed: tags.item_id, tags.tag",
+            ),
+            (
+                "INSERT INTO tags (rowid, tag) VALUES (1, 'x')",
+                "UNIQUE constraint failed: tags.rowid",
+            ),
+            (
+                "INSERT INTO items (rowid, name) VALUES ('x', 'x')",
+                "datatype mismatch",
+            ),
+            (
+                "INSERT INTO tags VALUES (1)",
+                "table tags has 2 columns but 1 values were supplied",
+            ),
+            (
+                "INSERT INTO tags (tag) SELECT 1, 2",
+                "2 values for 1 columns",
+            ),
+            (
+                "INSERT INTO tags (label) VALUES (1)",
+                "table tags has no column named label",
+            ),
+            ("INSERT INTO labels VALUES (1)", "no such table: labels"),
+            (
+                "INSERT INTO temp.tags VALUES (1, 2)",
+                "no such table: temp.tags",
+            ),
+            (
+                "INSERT INTO sqlite_master VALUES (1, 2, 3, 4, 5)",
+                "table sqlite_master may not be modified",
+            ),
+            (
+                "INSERT OR REPLACE INTO tags VALUES (1, 'heavy')",
+                "INSERT OR REPLACE is not supported",
+            ),
+            (
+                "INSERT INTO tags VALUES (5, 'x') RETURNING rowid",
+                "RETURNING is not supported",
+            ),
+        ] {
+            assert_eq!(error(&mut database, sql), message, "{sql}");
+            assert_eq!(database.pager.data(), data.as_slice(), "{sql}");
+        }
+
+        let mut database = library();
+        for (sql, message) in [
+            (
+                "INSERT INTO recent_books VALUES ('x', 2000)",
+                "cannot modify recent_books because it is a view",
+            ),
+            (
+                "INSERT INTO loans VALUES (1, 'x', NULL)",
+                "cannot insert into loans: WITHOUT ROWID tables are not supported",
+            ),
+        ] {
+            assert_eq!(error(&mut database, sql), message, "{sql}");
+        }
+    }
+}
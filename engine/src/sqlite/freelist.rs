@@ -0,0 +1,122 @@
+use crate::errors::DatabaseError;
+use crate::sqlite::header::{FIRST_FREELIST_TRUNK, FREELIST_PAGE_COUNT};
+use crate::sqlite::Pager;
+
+/// Returns pages to the freelist. The freelist is a chain of trunk pages, each starting with the
+/// number of the next trunk and the count of the leaf page numbers following it
+pub fn free_pages(pager: &mut Pager, pages: &[u32]) -> Result<(), DatabaseError> {
+    // Older SQLite versions read at most this many leaves per trunk
+    let max_leaves = pager.usable_size() / 4 - 8;
+    for &page in pages {
+        let trunk = pager.header_field(FIRST_FREELIST_TRUNK);
+        let leaves = match trunk {
+            0 => None,
+            _ => Some(read_u32(pager.page(trunk)?, 4) as usize),
+        };
+        match leaves {
+            Some(leaves) if leaves < max_leaves => {
+                let data = pager.page_mut(trunk)?;
+                write_u32(data, 8 + leaves * 4, page);
+                write_u32(data, 4, leaves as u32 + 1);
+            }
+            // The freed page becomes the new first trunk
+            _ => {
+                let data = pager.page_mut(page)?;
+                data.fill(0);
+                write_u32(data, 0, trunk);
+                pager.set_header_field(FIRST_FREELIST_TRUNK, page);
+            }
+        }
+        let count = pager.header_field(FREELIST_PAGE_COUNT);
+        pager.set_header_field(FREELIST_PAGE_COUNT, count + 1);
+    }
+    Ok(())
+}
+
+/// Takes a zeroed page off the freelist, or adds one to the end of the file if none is free
+pub fn allocate_page(pager: &mut Pager) -> Result<u32, DatabaseError> {
+    let trunk = pager.header_field(FIRST_FREELIST_TRUNK);
+    if trunk == 0 {
+        return Ok(pager.append_page());
+    }
+
+    let data = pager.page_mut(trunk)?;
+    let leaves = read_u32(data, 4);
+    let page = if leaves > 0 {
+        let leaf = read_u32(data, 4 + leaves as usize * 4);
+        write_u32(data, 4, leaves - 1);
+        leaf
+    } else {
+        let next = read_u32(data, 0);
+        pager.set_header_field(FIRST_FREELIST_TRUNK, next);
+        trunk
+    };
+    let count = pager.header_field(FREELIST_PAGE_COUNT);
+    pager.set_header_field(FREELIST_PAGE_COUNT, count.saturating_sub(1));
+    pager.page_mut(page)?.fill(0);
+    Ok(page)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::header::DATABASE_PAGE_COUNT;
+
+    fn pager(pages: u32) -> Pager {
+        let mut pager = Pager::new(vec![0; 512 * pages as usize], 512, 0);
+        pager.set_header_field(DATABASE_PAGE_COUNT, pages);
+        pager
+    }
+
+    #[test]
+    fn test_free_and_allocate() {
+        let mut pager = pager(6);
+        free_pages(&mut pager, &[3, 4, 5]).unwrap();
+        assert_eq!(pager.header_field(FREELIST_PAGE_COUNT), 3);
+        // The first freed page is the trunk, the others its leaves
+        assert_eq!(pager.header_field(FIRST_FREELIST_TRUNK), 3);
+        assert_eq!(read_u32(pager.page(3).unwrap(), 4), 2);
+
+        assert_eq!(allocate_page(&mut pager).unwrap(), 5);
+        assert_eq!(allocate_page(&mut pager).unwrap(), 4);
+        assert_eq!(allocate_page(&mut pager).unwrap(), 3);
+        assert_eq!(pager.header_field(FIRST_FREELIST_TRUNK), 0);
+        assert_eq!(pager.header_field(FREELIST_PAGE_COUNT), 0);
+
+        assert_eq!(allocate_page(&mut pager).unwrap(), 7);
+        assert_eq!(pager.header_field(DATABASE_PAGE_COUNT), 7);
+    }
+
+    #[test]
+    fn test_full_trunk_starts_another() {
+        let mut pager = pager(200);
+        let pages: Vec<u32> = (2..=200).collect();
+        free_pages(&mut pager, &pages).unwrap();
+        assert_eq!(pager.header_field(FREELIST_PAGE_COUNT), 199);
+
+        // 512 byte pages hold 120 leaves per trunk
+        let second = pager.header_field(FIRST_FREELIST_TRUNK);
+        assert_eq!(second, 123);
+        assert_eq!(read_u32(pager.page(second).unwrap(), 0), 2);
+
+        let mut allocated: Vec<u32> = (0..199)
+            .map(|_| allocate_page(&mut pager).unwrap())
+            .collect();
+        allocated.sort();
+        assert_eq!(allocated, pages);
+        assert_eq!(allocate_page(&mut pager).unwrap(), 201);
+    }
+}
@@ -3,8 +3,16 @@ use crate::sqlite::FileFormat;
 use crate::sqlite::SchemaFormat;
 use crate::sqlite::TextEncoding;
 
+/// Offsets of the header fields updated in place when the database changes
+pub const FILE_CHANGE_COUNTER: usize = 24;
+pub const DATABASE_PAGE_COUNT: usize = 28;
+pub const FIRST_FREELIST_TRUNK: usize = 32;
+pub const FREELIST_PAGE_COUNT: usize = 36;
+pub const SCHEMA_COOKIE: usize = 40;
+pub const VERSION_VALID_FOR: usize = 92;
+
 #[allow(unused)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Header {
     // 0	16	The header string: "SQLite format 3\000"
     pub header: String,
@@ -0,0 +1,242 @@
+//! The rollback journal a commit writes before changing the pages of a file: the pages as they
+//! were, in SQLite's format, in `<file>-journal`. The journal is deleted once the pages are
+//! written, one left behind by an interrupted commit is played back when the file is opened.
+//!
+//! A commit goes in three steps, so the journals of every file it changes can be written
+//! before any of the files: [`begin`], [`write`] and [`end`], or [`recover`] if one fails.
+
+use crate::errors::DatabaseError;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAGIC: [u8; 8] = [0xd9, 0xd5, 0x05, 0xf9, 0x20, 0xa1, 0x63, 0xd7];
+/// The size of the journal header, the page records start after it
+const SECTOR_SIZE: usize = 512;
+
+/// Writes the journal of a commit: the committed content of the pages `original` has, and the
+/// committed page count
+pub(super) fn begin(
+    file: &Path,
+    page_size: usize,
+    original: &BTreeMap<u32, Vec<u8>>,
+    page_count: usize,
+) -> Result<(), DatabaseError> {
+    write_journal(&journal_path(file), page_size, original, page_count)
+}
+
+/// Writes the changed pages of `data` into the file: the ones `original` has the committed
+/// content of, and the ones after the committed page count
+pub(super) fn write(
+    file: &Path,
+    data: &[u8],
+    page_size: usize,
+    original: &BTreeMap<u32, Vec<u8>>,
+    page_count: usize,
+) -> Result<(), DatabaseError> {
+    let changed = original
+        .keys()
+        .map(|number| *number as usize)
+        .chain(page_count + 1..=data.len() / page_size);
+    write_pages(file, data, page_size, changed)
+}
+
+/// Deletes the journal, the commit can't be undone anymore
+pub(super) fn end(file: &Path) -> Result<(), DatabaseError> {
+    let journal = journal_path(file);
+    if !journal.exists() {
+        return Ok(());
+    }
+    std::fs::remove_file(&journal).map_err(io_error)
+}
+
+/// Plays back the journal left behind by an interrupted commit, if there is one
+pub(super) fn recover(file: &Path) -> Result<(), DatabaseError> {
+    let journal = journal_path(file);
+    if !journal.exists() {
+        return Ok(());
+    }
+    play_back(file, &journal)
+}
+
+fn journal_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push("-journal");
+    PathBuf::from(path)
+}
+
+fn write_journal(
+    journal: &Path,
+    page_size: usize,
+    original: &BTreeMap<u32, Vec<u8>>,
+    page_count: usize,
+) -> Result<(), DatabaseError> {
+    // Tells the records of this journal from the leftovers of an older one
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    let mut content = Vec::with_capacity(SECTOR_SIZE + original.len() * (page_size + 8));
+    content.extend_from_slice(&MAGIC);
+    for field in [
+        original.len() as u32,
+        nonce,
+        page_count as u32,
+        SECTOR_SIZE as u32,
+        page_size as u32,
+    ] {
+        content.extend_from_slice(&field.to_be_bytes());
+    }
+    content.resize(SECTOR_SIZE, 0);
+    for (number, page) in original {
+        content.extend_from_slice(&number.to_be_bytes());
+        content.extend_from_slice(page);
+        content.extend_from_slice(&checksum(nonce, page).to_be_bytes());
+    }
+
+    let mut file = File::create(journal).map_err(io_error)?;
+    file.write_all(&content).map_err(io_error)?;
+    file.sync_all().map_err(io_error)
+}
+
+fn write_pages(
+    file: &Path,
+    data: &[u8],
+    page_size: usize,
+    pages: impl Iterator<Item = usize>,
+) -> Result<(), DatabaseError> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(file)
+        .map_err(io_error)?;
+    for number in pages {
+        let start = (number - 1) * page_size;
+        file.seek(SeekFrom::Start(start as u64)).map_err(io_error)?;
+        file.write_all(&data[start..start + page_size])
+            .map_err(io_error)?;
+    }
+    file.sync_all().map_err(io_error)
+}
+
+/// Writes the pages of the journal back into the file, and deletes the journal. The records
+/// after one whose checksum doesn't match were never completely written, nor their pages
+fn play_back(file: &Path, journal: &Path) -> Result<(), DatabaseError> {
+    let mut content = vec![];
+    File::open(journal)
+        .and_then(|mut journal| journal.read_to_end(&mut content))
+        .map_err(io_error)?;
+    let field = |offset: usize| {
+        content.get(offset..offset + 4).map_or(0, |bytes| {
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        })
+    };
+    let (records, nonce, page_count) = (field(8), field(12), field(16) as usize);
+    let (sector_size, page_size) = (field(20) as usize, field(24) as usize);
+    // A journal without a valid header was never used
+    let valid = (512..=65536).contains(&page_size)
+        && page_size.is_power_of_two()
+        && sector_size >= 28
+        && page_count > 0;
+    if content.starts_with(&MAGIC) && valid {
+        let mut database = OpenOptions::new()
+            .write(true)
+            .open(file)
+            .map_err(io_error)?;
+        let mut offset = sector_size;
+        for _ in 0..records {
+            let Some(record) = content.get(offset..offset + page_size + 8) else {
+                break;
+            };
+            let page = &record[4..4 + page_size];
+            let number = field(offset) as usize;
+            if number == 0 || checksum(nonce, page) != field(offset + 4 + page_size) {
+                break;
+            }
+            database
+                .seek(SeekFrom::Start(((number - 1) * page_size) as u64))
+                .map_err(io_error)?;
+            database.write_all(page).map_err(io_error)?;
+            offset += page_size + 8;
+        }
+        database
+            .set_len((page_count * page_size) as u64)
+            .map_err(io_error)?;
+        database.sync_all().map_err(io_error)?;
+    }
+    std::fs::remove_file(journal).map_err(io_error)
+}
+
+/// The checksum of a page record: the nonce plus every 200th byte, from the end of the page
+fn checksum(nonce: u32, page: &[u8]) -> u32 {
+    let mut checksum = nonce;
+    let mut index = page.len() as isize - 200;
+    while index > 0 {
+        checksum = checksum.wrapping_add(page[index as usize] as u32);
+        index -= 200;
+    }
+    checksum
+}
+
+fn io_error(error: std::io::Error) -> DatabaseError {
+    DatabaseError::FileSystemError(format!("{error:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::Database;
+    use crate::testing::{copy, query};
+
+    #[test]
+    fn test_play_back() {
+        let path = PathBuf::from(copy("library.db"));
+        let data = std::fs::read(&path).unwrap();
+        let page_size = 4096;
+        let page_count = data.len() / page_size;
+        let original: BTreeMap<u32, Vec<u8>> = [1, 2]
+            .into_iter()
+            .map(|number| {
+                let start = (number as usize - 1) * page_size;
+                (number, data[start..start + page_size].to_vec())
+            })
+            .collect();
+        write_journal(&journal_path(&path), page_size, &original, page_count).unwrap();
+
+        // The commit was interrupted after writing over the pages and adding one
+        let mut changed = data.clone();
+        changed[page_size..2 * page_size].fill(0xff);
+        changed.extend_from_slice(&vec![0; page_size]);
+        std::fs::write(&path, &changed).unwrap();
+
+        let mut database = Database::open(&path).unwrap();
+        assert_eq!(database.pager.data(), data.as_slice());
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert!(!journal_path(&path).exists());
+        assert_eq!(query(&mut database, "SELECT count(*) FROM books"), ["300"]);
+    }
+
+    #[test]
+    fn test_torn_journal() {
+        let path = PathBuf::from(copy("library.db"));
+        let data = std::fs::read(&path).unwrap();
+        let page_size = 4096;
+        let original = BTreeMap::from([(2, data[page_size..2 * page_size].to_vec())]);
+        write_journal(
+            &journal_path(&path),
+            page_size,
+            &original,
+            data.len() / page_size,
+        )
+        .unwrap();
+
+        // The record was only partly written, the file wasn't changed yet
+        let mut journal = std::fs::read(journal_path(&path)).unwrap();
+        journal[SECTOR_SIZE + 4..SECTOR_SIZE + 4 + page_size].fill(0);
+        std::fs::write(journal_path(&path), journal).unwrap();
+
+        let database = Database::open(&path).unwrap();
+        assert!(!journal_path(&path).exists());
+        assert_eq!(database.pager.data(), data.as_slice());
+    }
+}
@@ -1,52 +1,227 @@
+mod alter;
+mod attach;
+mod btree;
 mod catalog;
+mod ddl;
+mod dml;
 mod file_format;
+mod freelist;
 mod header;
+mod journal;
 mod page;
+mod pager;
+mod record;
+mod rename;
 mod schema_format;
 mod text_encoding;
+mod transaction;
 
 pub use catalog::*;
 pub use file_format::*;
 pub use header::*;
 pub use page::*;
+pub use pager::*;
 pub use schema_format::*;
 pub use text_encoding::*;
 
+pub(crate) use btree::{scan_table, search_index, search_table, TableRow};
+pub(crate) use record::decode_record;
+
 use crate::errors::*;
+use crate::executor::{Executor, Planner, QueryPlan, ResultSet};
+use attach::Attached;
+use sql::ast::{AlterTable, Ast, CreateIndex, CreateView, DropObject};
+use sql::Parser;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Database {
+    /// The file the database was opened from, which commits write to. `None` for a database
+    /// loaded from memory, whose changes are only kept in memory
+    path: Option<PathBuf>,
     pub header: Header,
+    pub pager: Pager,
     pub catalog: Catalog,
+    /// The objects of the temp schema, which only last as long as the database is open
+    pub temp: Vec<SchemaObject>,
+    /// The files attached to the connection, unqualified names are looked up in them in this
+    /// order after the main schema
+    attached: Vec<Attached>,
+    /// The savepoints of the open transaction, the one `BEGIN` takes first. Empty in
+    /// autocommit mode
+    savepoints: Vec<transaction::Savepoint>,
+}
+
+/// A table or view found by name, with the schema and the file it was found in
+pub(crate) struct Located<'d> {
+    pub schema: &'d str,
+    pub file: &'d Database,
+    pub object: SchemaObject,
 }
 
 impl Database {
     pub fn load(data: Vec<u8>) -> Result<Self, DatabaseError> {
-        let header = Header::load(&data[0..=100])?;
+        if data.len() < 100 {
+            return Err(DatabaseError::StateError("File is not a database".into()));
+        }
+        let header = Header::load(&data[0..100])?;
+        let pager = Pager::new(data, header.page_size, header.reserved_bytes);
+        let catalog = Catalog::load(&pager, header.text_encoding)?;
 
-        let catalog = Catalog::load(&data[100..header.page_size as usize])?;
-
-        Ok(Self { header, catalog })
+        Ok(Self {
+            path: None,
+            header,
+            pager,
+            catalog,
+            temp: vec![],
+            attached: vec![],
+            savepoints: vec![],
+        })
     }
 
-    pub fn open<P: AsRef<Path>>(file: P) -> Result<Self, DatabaseError> {
+    /// Opens a database file, playing back the journal an interrupted commit left behind first
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, DatabaseError> {
+        let path = path.as_ref();
         let mut file =
-            File::open(file).map_err(|e| DatabaseError::FileSystemError(format!("{e:?}")))?;
+            File::open(path).map_err(|e| DatabaseError::FileSystemError(format!("{e:?}")))?;
+        journal::recover(path)?;
         let metadata = file
             .metadata()
             .map_err(|e| DatabaseError::FileSystemError(format!("{e:?}")))?;
         let mut data = vec![0; metadata.len() as usize];
         file.read_exact(&mut data)
             .map_err(|e| DatabaseError::FileSystemError(format!("{e:?}")))?;
-        Database::load(data)
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            ..Database::load(data)?
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Runs every statement of the script, returning the result of each
+    pub fn execute_sql(&mut self, query: &str) -> Result<Vec<ResultSet>, DatabaseError> {
+        let mut results = vec![];
+        for statement in Parser::new(query).statements() {
+            results.push(self.execute_atomically(&statement?)?);
+        }
+        Ok(results)
+    }
+
+    fn execute(&mut self, statement: &Ast) -> Result<ResultSet, DatabaseError> {
+        if let Some((index, statement)) = self.attached_statement(statement) {
+            return self.attached[index].database.execute(&statement);
+        }
+        match statement {
+            Ast::Select(select) => Executor::new(self).select(select),
+            Ast::CreateIndex(create) => self.create_index(create),
+            Ast::CreateView(create) => self.create_view(create),
+            Ast::AlterTable(alter) => self.alter_table(alter),
+            Ast::Drop(drop) => self.drop_object(drop),
+            Ast::Begin(_) => self.begin(),
+            Ast::Commit => self.commit(),
+            Ast::Rollback(savepoint) => self.rollback(savepoint.as_deref()),
+            Ast::Savepoint(name) => self.savepoint(name),
+            Ast::Release(name) => self.release(name),
+            Ast::Attach(attach) => self.attach(attach),
+            Ast::Detach(name) => self.detach(name),
+            Ast::Insert(insert) => self.insert(insert),
+            // There is no bytecode to list, EXPLAIN shows the query plan as well
+            Ast::Explain(statement) | Ast::ExplainQueryPlan(statement) => {
+                self.query_plan(statement).map(ResultSet::from)
+            }
+            _ => Err(DatabaseError::ExecutionError(format!(
+                "Unsupported statement: {statement}"
+            ))),
+        }
+    }
+
+    /// Describes how the statement would run, without running it
+    pub fn query_plan(&self, statement: &Ast) -> Result<QueryPlan, DatabaseError> {
+        Planner::new(self).plan(statement)
+    }
+
+    /// Finds the attached file a statement about one of its objects runs in, and the statement
+    /// without the schema name, for that file to run as its own
+    fn attached_statement(&self, statement: &Ast) -> Option<(usize, Ast)> {
+        match statement {
+            // The index goes in the file of its table
+            Ast::CreateIndex(create) => Some((
+                self.attached_target(create.schema.as_deref(), &create.table_name)?,
+                Ast::CreateIndex(CreateIndex {
+                    schema: None,
+                    ..create.clone()
+                }),
+            )),
+            Ast::CreateView(create) if !create.temporary => Some((
+                self.attached_target(Some(create.schema.as_deref()?), &create.name)?,
+                Ast::CreateView(CreateView {
+                    schema: None,
+                    ..create.clone()
+                }),
+            )),
+            Ast::AlterTable(alter) => Some((
+                self.attached_target(alter.schema.as_deref(), &alter.table_name)?,
+                Ast::AlterTable(AlterTable {
+                    schema: None,
+                    ..alter.clone()
+                }),
+            )),
+            Ast::Drop(drop) => Some((
+                self.attached_target(drop.schema.as_deref(), &drop.name)?,
+                Ast::Drop(DropObject {
+                    schema: None,
+                    ..drop.clone()
+                }),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Finds the table or view a query refers to, and the file holding it. Unqualified names
+    /// are looked up in the temp schema first, whose objects hide the ones of the main schema,
+    /// then in the attached files
+    pub(crate) fn relation(&self, schema: Option<&str>, name: &str) -> Option<Located<'_>> {
+        let in_schema =
+            |expected: &str| schema.is_none_or(|schema| schema.eq_ignore_ascii_case(expected));
+        if let Some(object) = self.temp_object(name).filter(|_| in_schema("temp")) {
+            return Some(Located {
+                schema: "temp",
+                file: self,
+                object: object.clone(),
+            });
+        }
+        let files = std::iter::once(("main", self)).chain(
+            self.attached
+                .iter()
+                .map(|attached| (attached.name.as_str(), &attached.database)),
+        );
+        for (name_of_schema, file) in files.filter(|(name, _)| in_schema(name)) {
+            let object = file.catalog.table(name).or_else(|| {
+                file.catalog
+                    .object(name)
+                    .filter(|object| object.object_type == "view")
+                    .cloned()
+            });
+            if let Some(object) = object {
+                return Some(Located {
+                    schema: name_of_schema,
+                    file,
+                    object,
+                });
+            }
+        }
+        None
     }
 
-    pub fn execute_sql(&mut self, _query: String) -> Result<(), DatabaseError> {
-        println!("{:?}", &self);
-        todo!()
+    fn temp_object(&self, name: &str) -> Option<&SchemaObject> {
+        self.temp
+            .iter()
+            .find(|object| object.name.eq_ignore_ascii_case(name))
     }
 
     pub fn print_info(&self) {
@@ -0,0 +1,214 @@
+use super::journal;
+use crate::errors::DatabaseError;
+use crate::sqlite::header;
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::path::Path;
+
+/// The pages of a database file, numbered from 1
+#[derive(Clone)]
+pub struct Pager {
+    data: Vec<u8>,
+    page_size: usize,
+    /// The page size without the bytes reserved at the end of every page
+    usable_size: usize,
+    /// A journal per open savepoint and running statement, the innermost last
+    journals: Vec<Journal>,
+}
+
+/// The pages changed since a savepoint was taken or a statement started, as they were then
+#[derive(Clone, Default)]
+struct Journal {
+    original: BTreeMap<u32, Vec<u8>>,
+    /// The page count when the journal started, the pages after it are new
+    page_count: usize,
+}
+
+impl Pager {
+    pub fn new(data: Vec<u8>, page_size: u32, reserved_bytes: u8) -> Self {
+        Self {
+            data,
+            page_size: page_size as usize,
+            usable_size: page_size as usize - reserved_bytes as usize,
+            journals: vec![],
+        }
+    }
+
+    pub fn page(&self, number: u32) -> Result<&[u8], DatabaseError> {
+        let start = self.page_start(number)?;
+        Ok(&self.data[start..start + self.usable_size])
+    }
+
+    fn page_start(&self, number: u32) -> Result<usize, DatabaseError> {
+        (number as usize)
+            .checked_sub(1)
+            .map(|index| index * self.page_size)
+            .filter(|start| start + self.page_size <= self.data.len())
+            .ok_or_else(|| DatabaseError::StateError(format!("Page {number} is out of range")))
+    }
+
+    pub fn page_mut(&mut self, number: u32) -> Result<&mut [u8], DatabaseError> {
+        let start = self.page_start(number)?;
+        self.record(number);
+        Ok(&mut self.data[start..start + self.usable_size])
+    }
+
+    /// Keeps the content of a page about to change in the innermost journal, unless it has it
+    /// already or the page is newer than the journal
+    fn record(&mut self, number: u32) {
+        let Some(journal) = self.journals.last_mut() else {
+            return;
+        };
+        if number as usize <= journal.page_count {
+            let start = (number as usize - 1) * self.page_size;
+            let page = &self.data[start..start + self.page_size];
+            journal
+                .original
+                .entry(number)
+                .or_insert_with(|| page.to_vec());
+        }
+    }
+
+    /// Starts recording the pages changed from now on, to undo them if needed
+    pub fn begin_journal(&mut self) {
+        self.journals.push(Journal {
+            original: BTreeMap::new(),
+            page_count: self.page_count(),
+        });
+    }
+
+    /// The number of open journals
+    pub fn journal_depth(&self) -> usize {
+        self.journals.len()
+    }
+
+    /// Keeps the changes recorded by the journals after the first `depth` ones, which the
+    /// journal before them records in turn
+    pub fn release_journals(&mut self, depth: usize) {
+        while self.journals.len() > depth {
+            let journal = self.journals.pop().unwrap_or_default();
+            let Some(outer) = self.journals.last_mut() else {
+                continue;
+            };
+            for (number, page) in journal.original {
+                if number as usize <= outer.page_count {
+                    outer.original.entry(number).or_insert(page);
+                }
+            }
+        }
+    }
+
+    /// Undoes the changes recorded by the journals after the first `depth` ones, and closes them
+    pub fn rollback_journals(&mut self, depth: usize) {
+        while self.journals.len() > depth {
+            let journal = self.journals.pop().unwrap_or_default();
+            for (number, page) in journal.original {
+                let start = (number as usize - 1) * self.page_size;
+                self.data[start..start + self.page_size].copy_from_slice(&page);
+            }
+            self.data.truncate(journal.page_count * self.page_size);
+        }
+    }
+
+    /// Closes every journal, writing the pages they recorded and the new ones into the file,
+    /// if there is one. If the file can't be written the changes are undone, as the file still
+    /// has the pages it had before
+    pub fn commit(&mut self, file: Option<&Path>) -> Result<(), DatabaseError> {
+        let written = self
+            .begin_commit(file)
+            .and_then(|_| self.write_commit(file));
+        match written {
+            Ok(()) => self.end_commit(file),
+            Err(error) => {
+                self.abort_commit(file);
+                Err(error)
+            }
+        }
+    }
+
+    /// The first step of a commit, see [`Pager::commit`]: writes the journal of the changes
+    /// next to the file, which is left as it was
+    pub fn begin_commit(&mut self, file: Option<&Path>) -> Result<(), DatabaseError> {
+        self.release_journals(1);
+        match (self.journals.first(), file) {
+            (Some(journal), Some(file)) if !journal.original.is_empty() => {
+                journal::begin(file, self.page_size, &journal.original, journal.page_count)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the changes into the file, once [`Pager::begin_commit`] wrote their journal
+    pub fn write_commit(&self, file: Option<&Path>) -> Result<(), DatabaseError> {
+        match (self.journals.first(), file) {
+            (Some(journal), Some(file)) if !journal.original.is_empty() => journal::write(
+                file,
+                &self.data,
+                self.page_size,
+                &journal.original,
+                journal.page_count,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Ends a commit, deleting its journal. The changes can't be undone anymore
+    pub fn end_commit(&mut self, file: Option<&Path>) -> Result<(), DatabaseError> {
+        self.journals.clear();
+        file.map_or(Ok(()), journal::end)
+    }
+
+    /// Undoes a commit that failed: the file gets back the pages its journal has, or the
+    /// journal is left for the next open to do it, and the changes are undone
+    pub fn abort_commit(&mut self, file: Option<&Path>) {
+        if let Some(file) = file {
+            journal::recover(file).ok();
+        }
+        self.rollback_journals(0);
+    }
+
+    /// Adds a zeroed page at the end of the file, returning its number
+    pub fn append_page(&mut self) -> u32 {
+        self.data.resize(self.data.len() + self.page_size, 0);
+        let count = self.page_count() as u32;
+        self.set_header_field(header::DATABASE_PAGE_COUNT, count);
+        count
+    }
+
+    /// Reads a 4-byte field of the database header
+    pub fn header_field(&self, offset: usize) -> u32 {
+        u32::from_be_bytes([
+            self.data[offset],
+            self.data[offset + 1],
+            self.data[offset + 2],
+            self.data[offset + 3],
+        ])
+    }
+
+    pub fn set_header_field(&mut self, offset: usize, value: u32) {
+        self.record(1);
+        self.data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// The whole database file, as it would be written to disk
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn usable_size(&self) -> usize {
+        self.usable_size
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.data.len() / self.page_size
+    }
+}
+
+impl Debug for Pager {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pager")
+            .field("page_size", &self.page_size)
+            .field("page_count", &self.page_count())
+            .finish()
+    }
+}
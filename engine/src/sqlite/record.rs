@@ -0,0 +1,255 @@
+use crate::errors::DatabaseError;
+use crate::sqlite::TextEncoding;
+use common::types::Value;
+
+/// Reads a big-endian variable-length integer of 1 to 9 bytes, returns it with its length
+pub fn read_varint(data: &[u8]) -> Result<(u64, usize), DatabaseError> {
+    let mut value: u64 = 0;
+    for (index, byte) in data.iter().take(9).enumerate() {
+        // The ninth byte contributes all of its 8 bits
+        if index == 8 {
+            return Ok(((value << 8) | *byte as u64, 9));
+        }
+        value = (value << 7) | (*byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok((value, index + 1));
+        }
+    }
+    Err(DatabaseError::StateError("Truncated varint".into()))
+}
+
+/// Appends the variable-length encoding of a value, see [`read_varint`]
+pub fn write_varint(value: u64, out: &mut Vec<u8>) {
+    if value >> 56 != 0 {
+        // Eight 7-bit groups and a last byte of 8 bits
+        let mut bytes = [0; 9];
+        bytes[8] = value as u8;
+        let mut rest = value >> 8;
+        for byte in bytes[..8].iter_mut().rev() {
+            *byte = (rest & 0x7f) as u8 | 0x80;
+            rest >>= 7;
+        }
+        out.extend_from_slice(&bytes);
+        return;
+    }
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut rest = value >> 7;
+    while rest != 0 {
+        groups.push((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.extend(groups.iter().rev());
+}
+
+/// Decodes a record: a header of serial types, followed by the values they describe
+pub fn decode_record(payload: &[u8], encoding: TextEncoding) -> Result<Vec<Value>, DatabaseError> {
+    let truncated = || DatabaseError::StateError("Truncated record".into());
+
+    let (header_size, mut position) = read_varint(payload)?;
+    let header_size = header_size as usize;
+    let mut body = header_size;
+    let mut values = vec![];
+    while position < header_size {
+        let (serial_type, length) = read_varint(payload.get(position..).ok_or_else(truncated)?)?;
+        position += length;
+
+        let size = match serial_type {
+            0 | 8 | 9 => 0,
+            1..=4 => serial_type as usize,
+            5 => 6,
+            6 | 7 => 8,
+            10 | 11 => {
+                return Err(DatabaseError::StateError(format!(
+                    "Reserved serial type {serial_type}"
+                )))
+            }
+            _ => (serial_type as usize - 12) / 2,
+        };
+        let data = payload.get(body..body + size).ok_or_else(truncated)?;
+        body += size;
+
+        values.push(match serial_type {
+            0 => Value::Null,
+            1..=6 => {
+                // Sign-extends the big-endian two's complement integer
+                let fill = if data[0] & 0x80 != 0 { 0xff } else { 0 };
+                let mut bytes = [fill; 8];
+                bytes[8 - size..].copy_from_slice(data);
+                Value::Integer(i64::from_be_bytes(bytes))
+            }
+            7 => Value::Double(f64::from_be_bytes(
+                data.try_into().map_err(|_| truncated())?,
+            )),
+            8 => Value::Integer(0),
+            9 => Value::Integer(1),
+            _ if serial_type % 2 == 0 => Value::Blob(data.to_vec()),
+            _ => Value::Text(decode_text(data, encoding)),
+        });
+    }
+    Ok(values)
+}
+
+/// Encodes values into a record, integers take the fewest bytes that hold them
+pub fn encode_record(values: &[Value], encoding: TextEncoding) -> Vec<u8> {
+    let mut types = vec![];
+    let mut body = vec![];
+    for value in values {
+        let serial_type = match value {
+            Value::Null => 0,
+            Value::Integer(integer) => {
+                let (serial_type, size) = match *integer {
+                    -0x80..=0x7f => (1, 1),
+                    -0x8000..=0x7fff => (2, 2),
+                    -0x80_0000..=0x7f_ffff => (3, 3),
+                    -0x8000_0000..=0x7fff_ffff => (4, 4),
+                    -0x8000_0000_0000..=0x7fff_ffff_ffff => (5, 6),
+                    _ => (6, 8),
+                };
+                body.extend_from_slice(&integer.to_be_bytes()[8 - size..]);
+                serial_type
+            }
+            Value::Double(double) => {
+                body.extend_from_slice(&double.to_be_bytes());
+                7
+            }
+            Value::Text(text) => {
+                let data = encode_text(text, encoding);
+                body.extend_from_slice(&data);
+                data.len() as u64 * 2 + 13
+            }
+            Value::Blob(blob) => {
+                body.extend_from_slice(blob);
+                blob.len() as u64 * 2 + 12
+            }
+        };
+        write_varint(serial_type, &mut types);
+    }
+
+    // The header size counts its own varint
+    let mut header_size = types.len() + 1;
+    loop {
+        let mut size = vec![];
+        write_varint(header_size as u64, &mut size);
+        if size.len() + types.len() == header_size {
+            size.extend(types);
+            size.extend(body);
+            return size;
+        }
+        header_size = size.len() + types.len();
+    }
+}
+
+fn encode_text(text: &str, encoding: TextEncoding) -> Vec<u8> {
+    match encoding {
+        TextEncoding::UTF_8 => text.as_bytes().to_vec(),
+        TextEncoding::UTF_16le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        TextEncoding::UTF_16be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+    }
+}
+
+fn decode_text(data: &[u8], encoding: TextEncoding) -> String {
+    let units = |to_u16: fn([u8; 2]) -> u16| -> Vec<u16> {
+        data.chunks_exact(2)
+            .map(|pair| to_u16([pair[0], pair[1]]))
+            .collect()
+    };
+    match encoding {
+        TextEncoding::UTF_8 => String::from_utf8_lossy(data).into_owned(),
+        TextEncoding::UTF_16le => String::from_utf16_lossy(&units(u16::from_le_bytes)),
+        TextEncoding::UTF_16be => String::from_utf16_lossy(&units(u16::from_be_bytes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_varint() {
+        assert_eq!(read_varint(&[0x00]).unwrap(), (0, 1));
+        assert_eq!(read_varint(&[0x7f, 0xff]).unwrap(), (127, 1));
+        assert_eq!(read_varint(&[0x81, 0x00]).unwrap(), (128, 2));
+        assert_eq!(read_varint(&[0xff; 9]).unwrap(), (u64::MAX, 9));
+        assert!(read_varint(&[0x81]).is_err());
+    }
+
+    #[test]
+    fn test_write_varint() {
+        for value in [
+            0,
+            127,
+            128,
+            240,
+            2287,
+            16383,
+            16384,
+            1 << 55,
+            (1 << 56) - 1,
+            1 << 56,
+            u64::MAX,
+        ] {
+            let mut data = vec![];
+            write_varint(value, &mut data);
+            assert_eq!(read_varint(&data).unwrap(), (value, data.len()), "{value}");
+        }
+        let mut data = vec![];
+        write_varint(128, &mut data);
+        assert_eq!(data, vec![0x81, 0x00]);
+    }
+
+    #[test]
+    fn test_decode_record() {
+        // NULL, 1-byte -1, 0, 1, a float, 'hi' and a 1-byte blob
+        let mut payload = vec![8, 0, 1, 8, 9, 7, 17, 14, 0xff];
+        payload.extend(2.5f64.to_be_bytes());
+        payload.extend(b"hi");
+        payload.push(0xab);
+        assert_eq!(
+            decode_record(&payload, TextEncoding::UTF_8).unwrap(),
+            vec![
+                Value::Null,
+                Value::Integer(-1),
+                Value::Integer(0),
+                Value::Integer(1),
+                Value::Double(2.5),
+                Value::Text("hi".into()),
+                Value::Blob(vec![0xab]),
+            ]
+        );
+        assert!(decode_record(&payload[..payload.len() - 1], TextEncoding::UTF_8).is_err());
+    }
+
+    #[test]
+    fn test_encode_record() {
+        let values = vec![
+            Value::Null,
+            Value::Integer(-1),
+            Value::Integer(300),
+            Value::Integer(1 << 40),
+            Value::Integer(i64::MIN),
+            Value::Double(2.5),
+            Value::Text("hé".into()),
+            Value::Blob(vec![0xab; 100]),
+        ];
+        for encoding in [
+            TextEncoding::UTF_8,
+            TextEncoding::UTF_16le,
+            TextEncoding::UTF_16be,
+        ] {
+            let record = encode_record(&values, encoding);
+            assert_eq!(decode_record(&record, encoding).unwrap(), values);
+        }
+        assert_eq!(
+            encode_record(
+                &[Value::Integer(7), Value::Text("a".into())],
+                TextEncoding::UTF_8
+            ),
+            vec![3, 1, 15, 7, b'a']
+        );
+
+        // A header of 128 bytes or more needs two bytes for its size
+        let record = encode_record(&vec![Value::Null; 127], TextEncoding::UTF_8);
+        assert_eq!(record.len(), 129);
+        assert_eq!(read_varint(&record).unwrap(), (129, 2));
+    }
+}
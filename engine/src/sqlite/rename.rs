@@ -0,0 +1,863 @@
+use crate::errors::DatabaseError;
+use crate::sqlite::SchemaObject;
+use sql::ast::{
+    Ast, ColumnConstraintKind, ColumnRef, CreateIndex, CreateTable, CreateTrigger, Delete,
+    Expression, ForeignKeyClause, Insert, InsertSource, JoinConstraint, Name, Select,
+    TableConstraintKind, TableDefinition, TableExpression, TriggerEvent, Update, UpsertAction,
+};
+use sql::{Lexer, Parser, Token};
+use std::ops::Range;
+
+/// What ALTER TABLE renames: the table itself, or one of its columns
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Renamed<'a> {
+    Table(&'a str),
+    Column { old: &'a str, new: &'a str },
+}
+
+/// Rewrites the statements of the schema objects that refer to a renamed table or column
+pub(crate) struct Renamer<'a> {
+    /// The renamed table, or the one of the renamed column, under its old name
+    table: &'a str,
+    renamed: Renamed<'a>,
+    /// The objects of the schema, to tell which table an unqualified column belongs to
+    objects: &'a [SchemaObject],
+}
+
+/// A table a column reference can belong to: one of a FROM clause, the target of an INSERT,
+/// UPDATE or DELETE, or the NEW and OLD rows of a trigger
+struct Source {
+    /// The name columns are qualified with, the alias if there is one
+    label: String,
+    /// The renamed table
+    target: bool,
+    /// Qualified by the table name itself, which a table rename changes
+    unaliased: bool,
+    columns: Vec<String>,
+}
+
+/// The sources of a query, chained to the ones of the enclosing queries
+struct Level<'l> {
+    sources: Vec<Source>,
+    /// The renamed table is hidden by a CTE of the same name
+    hidden: bool,
+    outer: Option<&'l Level<'l>>,
+}
+
+impl Level<'_> {
+    /// Finds the source of a column reference, from the innermost query outwards
+    fn resolve(&self, column: &ColumnRef) -> Option<&Source> {
+        let found = match &column.table {
+            Some(table) => self
+                .sources
+                .iter()
+                .find(|source| source.label.eq_ignore_ascii_case(table)),
+            None => {
+                let mut matching = self.sources.iter().filter(|source| {
+                    source
+                        .columns
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(&column.name))
+                });
+                matching.next()
+            }
+        };
+        found.or_else(|| self.outer?.resolve(column))
+    }
+}
+
+impl<'a> Renamer<'a> {
+    pub fn new(table: &'a str, renamed: Renamed<'a>, objects: &'a [SchemaObject]) -> Self {
+        Renamer {
+            table,
+            renamed,
+            objects,
+        }
+    }
+
+    /// Renames the references in the statement of a schema object, returning the rewritten
+    /// statement if there were any. The names are replaced in the text as written, so its
+    /// formatting, comments and quoting stay
+    pub fn rewrite(&self, object: &SchemaObject) -> Result<Option<String>, DatabaseError> {
+        let Some(mut statement) = self.parse(object)? else {
+            return Ok(None);
+        };
+        if !self.statement(&mut statement) {
+            return Ok(None);
+        }
+        let rendered = statement.to_string();
+        let sql = object.sql.as_deref().unwrap_or_default();
+        Ok(Some(self.splice(sql, &rendered).unwrap_or(rendered)))
+    }
+
+    /// The old and the new name of what is renamed
+    fn names(&self) -> (&'a str, &'a str) {
+        match self.renamed {
+            Renamed::Table(new) => (self.table, new),
+            Renamed::Column { old, new } => (old, new),
+        }
+    }
+
+    /// Replaces the words of a statement that are references, given the statement with all of
+    /// them renamed. Usually every word with the old name is one, otherwise a word is one if
+    /// renaming it, then the others, gives that statement. None if the references can't be
+    /// told apart that way
+    fn splice(&self, sql: &str, renamed: &str) -> Option<String> {
+        let (old, new) = self.names();
+        let mut words = vec![];
+        let mut lexer = Lexer::new(sql);
+        while let Some((span, token)) = lexer.scan_spanned().ok()? {
+            let word: &str = match &token {
+                Token::Identifier(word) => word,
+                Token::QuotedIdentifier(word) => word,
+                _ => continue,
+            };
+            if word.eq_ignore_ascii_case(old) {
+                let replacement = quote(&sql[span.clone()], new);
+                words.push((span, replacement));
+            }
+        }
+        let replace = |words: &[&(Range<usize>, String)]| {
+            let mut spliced = String::with_capacity(sql.len());
+            let mut end = 0;
+            for (span, replacement) in words {
+                spliced.push_str(&sql[end..span.start]);
+                spliced.push_str(replacement);
+                end = span.end;
+            }
+            spliced.push_str(&sql[end..]);
+            spliced
+        };
+        let renders = |sql: &str, rename: bool| {
+            Parser::new(sql).parse().is_ok_and(|mut statement| {
+                if rename {
+                    self.statement(&mut statement);
+                }
+                statement.to_string() == renamed
+            })
+        };
+
+        let spliced = replace(&words.iter().collect::<Vec<_>>());
+        if renders(&spliced, false) {
+            return Some(spliced);
+        }
+        let references: Vec<_> = words
+            .iter()
+            .filter(|word| renders(&replace(&[word]), true))
+            .collect();
+        let spliced = replace(&references);
+        renders(&spliced, false).then_some(spliced)
+    }
+
+    /// Parses the statement of a schema object. One that can't be parsed is skipped if it
+    /// doesn't name what is renamed, and fails the ALTER otherwise, as it would be left
+    /// referring to the old name
+    pub fn parse(&self, object: &SchemaObject) -> Result<Option<Ast>, DatabaseError> {
+        let Some(sql) = &object.sql else {
+            return Ok(None);
+        };
+        let (name, _) = self.names();
+        match Parser::new(sql).parse() {
+            Ok(statement) => Ok(Some(statement)),
+            Err(_) if !mentions(sql, name) => Ok(None),
+            Err(error) => Err(DatabaseError::ExecutionError(format!(
+                "error in {} {}: {error}",
+                object.object_type, object.name
+            ))),
+        }
+    }
+
+    /// Renames the references in a statement, returning whether there were any
+    pub fn statement(&self, statement: &mut Ast) -> bool {
+        let original = statement.clone();
+        self.rename(statement, None);
+        *statement != original
+    }
+
+    fn rename(&self, statement: &mut Ast, outer: Option<&Level>) {
+        match statement {
+            Ast::Select(select) => self.select(select, outer),
+            Ast::CreateTable(create) => self.create_table(create),
+            Ast::CreateIndex(create) => self.create_index(create),
+            Ast::CreateView(create) => self.select(&mut create.query, None),
+            Ast::CreateTrigger(create) => self.create_trigger(create),
+            Ast::Insert(insert) => self.insert(insert, outer),
+            Ast::Update(update) => self.update(update, outer),
+            Ast::Delete(delete) => self.delete(delete, outer),
+            _ => {}
+        }
+    }
+
+    fn is_target(&self, schema: &Option<String>, name: &str) -> bool {
+        name.eq_ignore_ascii_case(self.table)
+            && schema
+                .as_ref()
+                .is_none_or(|schema| schema.eq_ignore_ascii_case("main"))
+    }
+
+    fn rename_table(&self, name: &mut String) {
+        if let Renamed::Table(new) = self.renamed {
+            *name = new.into();
+        }
+    }
+
+    fn rename_columns(&self, names: &mut [String]) {
+        if let Renamed::Column { old, new } = self.renamed {
+            for name in names
+                .iter_mut()
+                .filter(|name| name.eq_ignore_ascii_case(old))
+            {
+                *name = new.into();
+            }
+        }
+    }
+
+    fn source(&self, schema: &Option<String>, name: &str, alias: &Option<String>) -> Source {
+        let columns = self
+            .objects
+            .iter()
+            .find(|object| object.object_type == "table" && object.name.eq_ignore_ascii_case(name))
+            .map(|table| table.columns.iter().map(|column| column.name.clone()))
+            .into_iter()
+            .flatten()
+            .collect();
+        Source {
+            label: alias.clone().unwrap_or_else(|| name.into()),
+            target: self.is_target(schema, name),
+            unaliased: alias.is_none(),
+            columns,
+        }
+    }
+
+    fn select(&self, select: &mut Select, outer: Option<&Level>) {
+        let mut hidden = outer.is_some_and(|level| level.hidden);
+        if let Some(with) = &mut select.with {
+            for table in &mut with.tables {
+                hidden |= table.name.eq_ignore_ascii_case(self.table);
+                self.select(&mut table.query, outer);
+            }
+        }
+        let level = self.core(select, outer, hidden);
+        for (_, core) in &mut select.compound {
+            self.core(core, outer, hidden);
+        }
+        for term in select.order_by.iter_mut().flatten() {
+            self.expression(&mut term.expression, Some(&level));
+        }
+        if let Some(limit) = &mut select.limit {
+            self.expression(&mut limit.count, Some(&level));
+            if let Some(offset) = &mut limit.offset {
+                self.expression(offset, Some(&level));
+            }
+        }
+    }
+
+    /// Renames in the clauses of a SELECT core, returning the level of its FROM tables
+    fn core<'l>(
+        &self,
+        select: &mut Select,
+        outer: Option<&'l Level<'l>>,
+        hidden: bool,
+    ) -> Level<'l> {
+        let mut sources = vec![];
+        if let Some(from) = &mut select.from {
+            self.from(from, outer, hidden, &mut sources);
+        }
+        let level = Level {
+            sources,
+            hidden,
+            outer,
+        };
+        if let Some(from) = &mut select.from {
+            self.join_constraints(from, &level);
+        }
+        for column in &mut select.columns {
+            self.expression(&mut column.expression, Some(&level));
+        }
+        if let Some(condition) = &mut select.where_clause {
+            self.expression(condition, Some(&level));
+        }
+        for expression in select.group_by.iter_mut().flatten() {
+            self.expression(expression, Some(&level));
+        }
+        if let Some(condition) = &mut select.having {
+            self.expression(condition, Some(&level));
+        }
+        level
+    }
+
+    /// Renames the tables of a FROM clause, collecting them as sources
+    fn from(
+        &self,
+        from: &mut TableExpression,
+        outer: Option<&Level>,
+        hidden: bool,
+        sources: &mut Vec<Source>,
+    ) {
+        match from {
+            TableExpression::Table {
+                schema,
+                name,
+                alias,
+            } => {
+                let mut source = self.source(schema, name, alias);
+                source.target &= !hidden;
+                if source.target {
+                    self.rename_table(name);
+                }
+                sources.push(source);
+            }
+            TableExpression::Subquery { query, alias } => {
+                self.select(query, outer);
+                sources.push(Source {
+                    label: alias.clone().unwrap_or_default(),
+                    target: false,
+                    unaliased: false,
+                    columns: vec![],
+                });
+            }
+            TableExpression::Join { left, right, .. } => {
+                self.from(left, outer, hidden, sources);
+                self.from(right, outer, hidden, sources);
+            }
+        }
+    }
+
+    fn join_constraints(&self, from: &mut TableExpression, level: &Level) {
+        let TableExpression::Join {
+            left,
+            right,
+            constraint,
+            ..
+        } = from
+        else {
+            return;
+        };
+        self.join_constraints(left, level);
+        self.join_constraints(right, level);
+        match constraint {
+            Some(JoinConstraint::On(condition)) => self.expression(condition, Some(level)),
+            // The columns of USING are in both tables, the renamed one among them
+            Some(JoinConstraint::Using(columns))
+                if level.sources.iter().any(|source| source.target) =>
+            {
+                self.rename_columns(columns)
+            }
+            _ => {}
+        }
+    }
+
+    fn expression(&self, expression: &mut Expression, level: Option<&Level>) {
+        match expression {
+            Expression::Literal(_) | Expression::Wildcard(None) => {}
+            Expression::Column(column) => self.column(column, level),
+            Expression::Wildcard(Some(table)) => {
+                let reference = ColumnRef {
+                    schema: None,
+                    table: Some(table.clone()),
+                    name: String::new(),
+                };
+                let source = level.and_then(|level| level.resolve(&reference));
+                if source.is_some_and(|source| source.target && source.unaliased) {
+                    self.rename_table(table);
+                }
+            }
+            Expression::Unary { operand, .. } => self.expression(operand, level),
+            Expression::Binary { left, right, .. }
+            | Expression::IsDistinctFrom { left, right, .. } => {
+                self.expression(left, level);
+                self.expression(right, level);
+            }
+            Expression::Collate { expression, .. }
+            | Expression::IsNull { expression, .. }
+            | Expression::Cast { expression, .. }
+            | Expression::Nested(expression) => self.expression(expression, level),
+            Expression::Function {
+                arguments, filter, ..
+            } => {
+                for argument in arguments {
+                    self.expression(argument, level);
+                }
+                if let Some(filter) = filter {
+                    self.expression(filter, level);
+                }
+            }
+            Expression::Like {
+                expression,
+                pattern,
+                escape,
+                ..
+            } => {
+                self.expression(expression, level);
+                self.expression(pattern, level);
+                if let Some(escape) = escape {
+                    self.expression(escape, level);
+                }
+            }
+            Expression::Between {
+                expression,
+                low,
+                high,
+                ..
+            } => {
+                self.expression(expression, level);
+                self.expression(low, level);
+                self.expression(high, level);
+            }
+            Expression::InList {
+                expression, list, ..
+            } => {
+                self.expression(expression, level);
+                for item in list {
+                    self.expression(item, level);
+                }
+            }
+            Expression::InSubquery {
+                expression,
+                subquery,
+                ..
+            } => {
+                self.expression(expression, level);
+                self.select(subquery, level);
+            }
+            Expression::Subquery(query) | Expression::Exists(query) => self.select(query, level),
+            Expression::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                if let Some(operand) = operand {
+                    self.expression(operand, level);
+                }
+                for (condition, result) in branches {
+                    self.expression(condition, level);
+                    self.expression(result, level);
+                }
+                if let Some(result) = else_result {
+                    self.expression(result, level);
+                }
+            }
+        }
+    }
+
+    fn column(&self, column: &mut ColumnRef, level: Option<&Level>) {
+        let Some(source) = level.and_then(|level| level.resolve(column)) else {
+            return;
+        };
+        if !source.target {
+            return;
+        }
+        match self.renamed {
+            Renamed::Table(new) if column.table.is_some() && source.unaliased => {
+                column.table = Some(new.into());
+            }
+            Renamed::Column { old, new } if column.name.eq_ignore_ascii_case(old) => {
+                column.name = new.into();
+            }
+            _ => {}
+        }
+    }
+
+    /// A level of the single table a statement or a schema object is about
+    fn table_level<'l>(
+        &self,
+        schema: &Option<String>,
+        name: &str,
+        outer: Option<&'l Level<'l>>,
+    ) -> Level<'l> {
+        Level {
+            sources: vec![self.source(schema, name, &None)],
+            hidden: false,
+            outer,
+        }
+    }
+
+    fn create_table(&self, create: &mut CreateTable) {
+        let TableDefinition::Columns {
+            columns,
+            constraints,
+            ..
+        } = &mut create.definition
+        else {
+            return;
+        };
+        // Other tables only refer to the renamed one by their foreign keys
+        let own = self.is_target(&None, &create.table_name);
+        let level = self.table_level(&None, &create.table_name, None);
+        for column in columns.iter_mut() {
+            if own {
+                self.rename_columns(std::slice::from_mut(&mut column.name));
+            }
+            for constraint in &mut column.constraints {
+                match &mut constraint.kind {
+                    ColumnConstraintKind::Check(expression)
+                    | ColumnConstraintKind::Generated { expression, .. }
+                        if own =>
+                    {
+                        self.expression(expression, Some(&level))
+                    }
+                    ColumnConstraintKind::References(clause) => self.foreign_key(clause),
+                    _ => {}
+                }
+            }
+        }
+        for constraint in constraints {
+            match &mut constraint.kind {
+                TableConstraintKind::PrimaryKey { columns, .. }
+                | TableConstraintKind::Unique { columns, .. }
+                    if own =>
+                {
+                    for column in columns {
+                        self.expression(&mut column.expression, Some(&level));
+                    }
+                }
+                TableConstraintKind::Check(expression) if own => {
+                    self.expression(expression, Some(&level))
+                }
+                TableConstraintKind::ForeignKey { columns, clause } => {
+                    if own {
+                        self.rename_columns(columns);
+                    }
+                    self.foreign_key(clause);
+                }
+                _ => {}
+            }
+        }
+        if own {
+            self.rename_table(&mut create.table_name);
+        }
+    }
+
+    fn foreign_key(&self, clause: &mut ForeignKeyClause) {
+        if clause.table.eq_ignore_ascii_case(self.table) {
+            self.rename_columns(&mut clause.columns);
+            self.rename_table(&mut clause.table);
+        }
+    }
+
+    fn create_index(&self, create: &mut CreateIndex) {
+        if !self.is_target(&None, &create.table_name) {
+            return;
+        }
+        let level = self.table_level(&None, &create.table_name, None);
+        for column in &mut create.columns {
+            self.expression(&mut column.expression, Some(&level));
+        }
+        if let Some(condition) = &mut create.where_clause {
+            self.expression(condition, Some(&level));
+        }
+        self.rename_table(&mut create.table_name);
+    }
+
+    fn create_trigger(&self, create: &mut CreateTrigger) {
+        let mut level = self.table_level(&None, &create.table_name, None);
+        let target = level.sources.remove(0);
+        // The NEW and OLD rows have the columns of the table the trigger is on
+        for label in ["new", "old"] {
+            level.sources.push(Source {
+                label: label.into(),
+                unaliased: false,
+                columns: target.columns.clone(),
+                ..target
+            });
+        }
+        if let Some(condition) = &mut create.when {
+            self.expression(condition, Some(&level));
+        }
+        for statement in &mut create.body {
+            self.rename(statement, Some(&level));
+        }
+        if target.target {
+            if let TriggerEvent::Update(columns) = &mut create.event {
+                self.rename_columns(columns);
+            }
+            self.rename_table(&mut create.table_name);
+        }
+    }
+
+    fn insert(&self, insert: &mut Insert, outer: Option<&Level>) {
+        match &mut insert.source {
+            InsertSource::Values(rows) => {
+                for value in rows.iter_mut().flatten() {
+                    self.expression(value, outer);
+                }
+            }
+            InsertSource::Query(query) => self.select(query, outer),
+            InsertSource::DefaultValues => {}
+        }
+
+        let mut level = self.table_level(&insert.schema, &insert.table_name, outer);
+        let target = level.sources[0].target;
+        // An upsert sees the row that failed to insert as `excluded`
+        level.sources.push(Source {
+            label: "excluded".into(),
+            unaliased: false,
+            ..self.source(&insert.schema, &insert.table_name, &None)
+        });
+        for upsert in &mut insert.upsert {
+            for column in &mut upsert.target {
+                self.expression(&mut column.expression, Some(&level));
+            }
+            if let Some(condition) = &mut upsert.target_where {
+                self.expression(condition, Some(&level));
+            }
+            if let UpsertAction::Update {
+                set_clause,
+                where_clause,
+            } = &mut upsert.action
+            {
+                for assignment in set_clause {
+                    if target {
+                        self.rename_columns(&mut assignment.columns);
+                    }
+                    for value in &mut assignment.values {
+                        self.expression(value, Some(&level));
+                    }
+                }
+                if let Some(condition) = where_clause {
+                    self.expression(condition, Some(&level));
+                }
+            }
+        }
+        for column in &mut insert.returning {
+            self.expression(&mut column.expression, Some(&level));
+        }
+        if target {
+            self.rename_columns(&mut insert.columns);
+            self.rename_table(&mut insert.table_name);
+        }
+    }
+
+    fn update(&self, update: &mut Update, outer: Option<&Level>) {
+        let mut level = self.table_level(&update.schema, &update.table_name, outer);
+        let target = level.sources[0].target;
+        if let Some(from) = &mut update.from {
+            self.from(from, outer, false, &mut level.sources);
+            self.join_constraints(from, &level);
+        }
+        for assignment in &mut update.set_clause {
+            if target {
+                self.rename_columns(&mut assignment.columns);
+            }
+            for value in &mut assignment.values {
+                self.expression(value, Some(&level));
+            }
+        }
+        if let Some(condition) = &mut update.where_clause {
+            self.expression(condition, Some(&level));
+        }
+        for column in &mut update.returning {
+            self.expression(&mut column.expression, Some(&level));
+        }
+        for term in update.order_by.iter_mut().flatten() {
+            self.expression(&mut term.expression, Some(&level));
+        }
+        if target {
+            self.rename_table(&mut update.table_name);
+        }
+    }
+
+    fn delete(&self, delete: &mut Delete, outer: Option<&Level>) {
+        let level = self.table_level(&delete.schema, &delete.table_name, outer);
+        if let Some(condition) = &mut delete.where_clause {
+            self.expression(condition, Some(&level));
+        }
+        for column in &mut delete.returning {
+            self.expression(&mut column.expression, Some(&level));
+        }
+        for term in delete.order_by.iter_mut().flatten() {
+            self.expression(&mut term.expression, Some(&level));
+        }
+        if level.sources[0].target {
+            self.rename_table(&mut delete.table_name);
+        }
+    }
+}
+
+/// Writes a name in place of a word: double-quoted if the word was quoted, as SQLite does,
+/// like the AST otherwise
+fn quote(word: &str, name: &str) -> String {
+    match word.chars().next() {
+        Some('"' | '`' | '[') => format!("\"{}\"", name.replace('"', "\"\"")),
+        _ => Name(name).to_string(),
+    }
+}
+
+/// Returns true if a name is one of the words of the statement, or it can't be told
+fn mentions(sql: &str, name: &str) -> bool {
+    Lexer::new(sql).any(|token| match token {
+        Ok(Token::Identifier(word)) => word.eq_ignore_ascii_case(name),
+        Ok(Token::QuotedIdentifier(word) | Token::String(word)) => word.eq_ignore_ascii_case(name),
+        Ok(_) => false,
+        Err(_) => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::library;
+
+    fn object(sql: &str) -> SchemaObject {
+        SchemaObject {
+            rowid: 0,
+            object_type: "view".to_string(),
+            name: "v".to_string(),
+            table_name: "v".to_string(),
+            root_page: 0,
+            sql: Some(sql.to_string()),
+            columns: Vec::new(),
+            without_rowid: None,
+            unreadable: None,
+        }
+    }
+
+    fn rewrite(renamer: &Renamer, sql: &str) -> String {
+        renamer
+            .rewrite(&object(sql))
+            .unwrap()
+            .unwrap_or_else(|| sql.to_string())
+    }
+
+    #[test]
+    fn test_rename_table() {
+        let database = library();
+        let renamer = Renamer::new(
+            "books",
+            Renamed::Table("volumes"),
+            &database.catalog.objects,
+        );
+        for (sql, expected) in [
+            (
+                "SELECT books.title, b.year FROM books, books AS b WHERE books.id = b.id",
+                "SELECT volumes.title, b.year FROM volumes, volumes AS b WHERE volumes.id = b.id",
+            ),
+            (
+                "SELECT books.* FROM main.books JOIN authors ON books.author_id = authors.id",
+                "SELECT volumes.* FROM main.volumes JOIN authors ON volumes.author_id = authors.id",
+            ),
+            (
+                "SELECT name FROM authors WHERE EXISTS (SELECT 1 FROM books WHERE author_id = authors.id)",
+                "SELECT name FROM authors WHERE EXISTS (SELECT 1 FROM volumes WHERE author_id = authors.id)",
+            ),
+            (
+                "WITH books AS (SELECT 1 AS id) SELECT books.id FROM books",
+                "WITH books AS (SELECT 1 AS id) SELECT books.id FROM books",
+            ),
+            (
+                "CREATE TABLE reviews (book INTEGER REFERENCES books (id), text)",
+                "CREATE TABLE reviews (book INTEGER REFERENCES volumes (id), text)",
+            ),
+            (
+                "CREATE TRIGGER t AFTER DELETE ON books BEGIN \
+                 DELETE FROM loans WHERE book_id = old.id; INSERT INTO books (title) VALUES (old.title); END",
+                "CREATE TRIGGER t AFTER DELETE ON volumes BEGIN \
+                 DELETE FROM loans WHERE book_id = old.id; INSERT INTO volumes (title) VALUES (old.title); END",
+            ),
+        ] {
+            assert_eq!(rewrite(&renamer, sql), expected);
+        }
+        assert_eq!(
+            renamer
+                .rewrite(&object("SELECT books FROM authors AS books"))
+                .unwrap(),
+            None
+        );
+
+        // A statement that can't be parsed is only an error if it names the table
+        assert_eq!(renamer.rewrite(&object("VALUES (1)")).unwrap(), None);
+        assert_eq!(
+            renamer
+                .rewrite(&object("VALUES ((SELECT 1 FROM \"Books\"))"))
+                .unwrap_err()
+                .to_string(),
+            "error in view v: Unexpected keyword: values"
+        );
+    }
+
+    #[test]
+    fn test_rename_column() {
+        let database = library();
+        let renamer = Renamer::new(
+            "authors",
+            Renamed::Column {
+                old: "country",
+                new: "nation",
+            },
+            &database.catalog.objects,
+        );
+        for (sql, expected) in [
+            (
+                "SELECT country, a.country, b.title FROM authors AS a JOIN books AS b ON a.id = b.author_id",
+                "SELECT nation, a.nation, b.title FROM authors AS a JOIN books AS b ON a.id = b.author_id",
+            ),
+            (
+                "SELECT title FROM books WHERE author_id IN (SELECT id FROM authors WHERE country = 'NL')",
+                "SELECT title FROM books WHERE author_id IN (SELECT id FROM authors WHERE nation = 'NL')",
+            ),
+            (
+                "SELECT (SELECT country FROM books) FROM authors",
+                "SELECT (SELECT nation FROM books) FROM authors",
+            ),
+            (
+                "CREATE INDEX by_country ON authors (country COLLATE NOCASE) WHERE country IS NOT NULL",
+                "CREATE INDEX by_country ON authors (nation COLLATE NOCASE) WHERE nation IS NOT NULL",
+            ),
+            (
+                "CREATE TRIGGER t UPDATE OF country ON authors WHEN new.country != old.country BEGIN \
+                 UPDATE authors SET country = upper(country) WHERE id = new.id; END",
+                "CREATE TRIGGER t UPDATE OF nation ON authors WHEN new.nation != old.nation BEGIN \
+                 UPDATE authors SET nation = upper(nation) WHERE id = new.id; END",
+            ),
+            (
+                "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL, country TEXT)",
+                "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL, nation TEXT)",
+            ),
+        ] {
+            assert_eq!(rewrite(&renamer, sql), expected);
+        }
+        // Only the words that are references are replaced, the rest stays as written
+        assert_eq!(
+            rewrite(
+                &renamer,
+                "CREATE TABLE authors (\n  id INTEGER PRIMARY KEY, -- key\n  \
+                 [Country] country CHECK (country <> 'country')\n)"
+            ),
+            "CREATE TABLE authors (\n  id INTEGER PRIMARY KEY, -- key\n  \
+             \"nation\" country CHECK (nation <> 'country')\n)"
+        );
+        let renamer = Renamer::new(
+            "books",
+            Renamed::Column {
+                old: "id",
+                new: "book_id",
+            },
+            &database.catalog.objects,
+        );
+        assert_eq!(
+            rewrite(
+                &renamer,
+                "CREATE TABLE books (id INTEGER PRIMARY KEY, sequel INT REFERENCES books (id), \
+                 pages INT CHECK (pages > id), UNIQUE (id, pages), FOREIGN KEY (id) REFERENCES loans)"
+            ),
+            "CREATE TABLE books (book_id INTEGER PRIMARY KEY, sequel INT REFERENCES books (book_id), \
+             pages INT CHECK (pages > book_id), UNIQUE (book_id, pages), FOREIGN KEY (book_id) REFERENCES loans)"
+        );
+        assert_eq!(
+            rewrite(
+                &renamer,
+                "CREATE TABLE reviews (book INT REFERENCES books (id), id INT CHECK (id > 0))"
+            ),
+            "CREATE TABLE reviews (book INT REFERENCES books (book_id), id INT CHECK (id > 0))"
+        );
+
+        // Columns of the same name in other tables stay
+        for sql in [
+            "SELECT country FROM loans",
+            "CREATE INDEX i ON books (country)",
+            "CREATE TRIGGER t INSERT ON books BEGIN SELECT new.country; END",
+        ] {
+            assert_eq!(renamer.rewrite(&object(sql)).unwrap(), None, "{sql}");
+        }
+    }
+}
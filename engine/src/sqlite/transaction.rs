@@ -0,0 +1,423 @@
+use super::{Database, Pager, SchemaObject};
+use crate::errors::DatabaseError;
+use crate::executor::ResultSet;
+use sql::ast::Ast;
+use std::path::Path;
+
+/// A savepoint of the open transaction. The pages changed since it was taken are kept by the
+/// journal it started in every file
+#[derive(Debug, Clone)]
+pub(crate) struct Savepoint {
+    /// `None` for the transaction `BEGIN` opened, which can't be released by name
+    name: Option<String>,
+    /// The temp schema as it was, it has no pages
+    temp: Vec<SchemaObject>,
+}
+
+impl Database {
+    /// Returns true outside an explicit transaction, where every statement commits on its own
+    pub fn is_autocommit(&self) -> bool {
+        self.savepoints.is_empty()
+    }
+
+    /// Runs a statement as a whole or not at all, a failed one leaves the database unchanged.
+    /// Outside a transaction it is committed as soon as it succeeds.
+    pub(super) fn execute_atomically(
+        &mut self,
+        statement: &Ast,
+    ) -> Result<ResultSet, DatabaseError> {
+        match statement {
+            Ast::Select(_) | Ast::Explain(_) | Ast::ExplainQueryPlan(_) => {
+                return self.execute(statement)
+            }
+            // They start and end the journals themselves
+            Ast::Begin(_)
+            | Ast::Commit
+            | Ast::Rollback(_)
+            | Ast::Savepoint(_)
+            | Ast::Release(_) => return self.execute(statement),
+            _ => {}
+        }
+        let depth = self.savepoints.len();
+        let temp = self.temp.clone();
+        self.begin_journals();
+        let result = self
+            .execute(statement)
+            .and_then(|result| match self.is_autocommit() {
+                true => self.commit_journals().map(|_| result),
+                false => {
+                    self.release_journals(depth);
+                    Ok(result)
+                }
+            });
+        if result.is_err() {
+            self.temp = temp;
+            self.rollback_journals(depth)?;
+        }
+        result
+    }
+
+    pub(super) fn begin(&mut self) -> Result<ResultSet, DatabaseError> {
+        if !self.is_autocommit() {
+            return Err(DatabaseError::ExecutionError(
+                "cannot start a transaction within a transaction".into(),
+            ));
+        }
+        self.push_savepoint(None);
+        Ok(ResultSet::default())
+    }
+
+    pub(super) fn commit(&mut self) -> Result<ResultSet, DatabaseError> {
+        if self.is_autocommit() {
+            return Err(DatabaseError::ExecutionError(
+                "cannot commit - no transaction is active".into(),
+            ));
+        }
+        let savepoint = self.savepoints.swap_remove(0);
+        self.savepoints.clear();
+        if let Err(err) = self.commit_journals() {
+            self.temp = savepoint.temp;
+            self.rollback_journals(0)?;
+            return Err(err);
+        }
+        Ok(ResultSet::default())
+    }
+
+    /// Rolls back the whole transaction, or to the savepoint, which stays open
+    pub(super) fn rollback(&mut self, savepoint: Option<&str>) -> Result<ResultSet, DatabaseError> {
+        let Some(name) = savepoint else {
+            if self.is_autocommit() {
+                return Err(DatabaseError::ExecutionError(
+                    "cannot rollback - no transaction is active".into(),
+                ));
+            }
+            let savepoint = self.savepoints.swap_remove(0);
+            self.savepoints.clear();
+            self.temp = savepoint.temp;
+            self.rollback_journals(0)?;
+            return Ok(ResultSet::default());
+        };
+        let index = self.find_savepoint(name)?;
+        self.savepoints.truncate(index + 1);
+        self.temp = self.savepoints[index].temp.clone();
+        self.rollback_journals(index)?;
+        self.begin_journals();
+        Ok(ResultSet::default())
+    }
+
+    /// Opens a savepoint, starting a transaction if there is none
+    pub(super) fn savepoint(&mut self, name: &str) -> Result<ResultSet, DatabaseError> {
+        self.push_savepoint(Some(name.into()));
+        Ok(ResultSet::default())
+    }
+
+    /// Forgets the savepoint and the ones taken after it. Releasing the savepoint that started
+    /// the transaction commits it
+    pub(super) fn release(&mut self, name: &str) -> Result<ResultSet, DatabaseError> {
+        let index = self.find_savepoint(name)?;
+        if index == 0 {
+            return self.commit();
+        }
+        self.savepoints.truncate(index);
+        self.release_journals(index);
+        Ok(ResultSet::default())
+    }
+
+    /// Finds the latest savepoint of the given case-insensitive name
+    fn find_savepoint(&self, name: &str) -> Result<usize, DatabaseError> {
+        self.savepoints
+            .iter()
+            .rposition(|savepoint| {
+                savepoint
+                    .name
+                    .as_ref()
+                    .is_some_and(|savepoint| savepoint.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| DatabaseError::ExecutionError(format!("no such savepoint: {name}")))
+    }
+
+    /// Starts the journals of the file attached last, one per open savepoint and one for the
+    /// running ATTACH, as if it was attached all along. Rolling back to one of the savepoints
+    /// undoes the changes made to it since it was attached
+    pub(super) fn cover_attached(&mut self) {
+        let depth = self.savepoints.len() + 1;
+        let Some(attached) = self.attached.last_mut() else {
+            return;
+        };
+        while attached.database.pager.journal_depth() < depth {
+            attached.database.pager.begin_journal();
+        }
+    }
+
+    fn push_savepoint(&mut self, name: Option<String>) {
+        let temp = self.temp.clone();
+        self.savepoints.push(Savepoint { name, temp });
+        self.begin_journals();
+    }
+
+    /// Starts a journal in every file, for a savepoint or a statement
+    fn begin_journals(&mut self) {
+        self.pager.begin_journal();
+        for attached in &mut self.attached {
+            attached.database.pager.begin_journal();
+        }
+    }
+
+    /// Keeps the changes made since the first `depth` journals of every file started
+    fn release_journals(&mut self, depth: usize) {
+        self.pager.release_journals(depth);
+        for attached in &mut self.attached {
+            attached.database.pager.release_journals(depth);
+        }
+    }
+
+    /// Undoes the changes made since the first `depth` journals of every file started
+    fn rollback_journals(&mut self, depth: usize) -> Result<(), DatabaseError> {
+        self.pager.rollback_journals(depth);
+        self.reload_schema()?;
+        for attached in &mut self.attached {
+            attached.database.pager.rollback_journals(depth);
+            attached.database.reload_schema()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the changes of the transaction into the files. The journal of every file is
+    /// written before any file changes, so if one can't be written the others are put back.
+    ///
+    /// Each file is only recovered from its own journal: without SQLite's super-journal, a
+    /// crash while the journals are deleted can leave some of the files committed and the
+    /// others rolled back when they are opened again
+    fn commit_journals(&mut self) -> Result<(), DatabaseError> {
+        let mut files: Vec<(&mut Pager, Option<&Path>)> =
+            std::iter::once((&mut self.pager, self.path.as_deref()))
+                .chain(self.attached.iter_mut().map(|attached| {
+                    let database = &mut attached.database;
+                    (&mut database.pager, database.path.as_deref())
+                }))
+                .collect();
+        let written = files
+            .iter_mut()
+            .try_for_each(|(pager, file)| pager.begin_commit(*file))
+            .and_then(|_| {
+                files
+                    .iter()
+                    .try_for_each(|(pager, file)| pager.write_commit(*file))
+            });
+        if let Err(error) = written {
+            for (pager, file) in files {
+                pager.abort_commit(file);
+            }
+            return Err(error);
+        }
+        let mut ended = Ok(());
+        for (pager, file) in files {
+            ended = ended.and(pager.end_commit(file));
+        }
+        ended
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{error, library, query, reload};
+
+    fn has_object(database: &Database, name: &str) -> bool {
+        database.catalog.object(name).is_some()
+            || database.temp.iter().any(|object| object.name == name)
+    }
+
+    #[test]
+    fn test_autocommit() {
+        let mut database = library();
+        let data = database.pager.data().to_vec();
+        assert!(database.is_autocommit());
+
+        // The index is built before the duplicates are found, none of it is kept
+        assert_eq!(
+            error(&mut database, "CREATE UNIQUE INDEX u ON books (year)"),
+            "UNIQUE constraint failed: books.year"
+        );
+        assert_eq!(database.pager.data(), data.as_slice());
+
+        // The statements before a failed one of a script are committed
+        assert_eq!(
+            error(
+                &mut database,
+                "CREATE INDEX a ON books (title); CREATE INDEX a ON books (year)"
+            ),
+            "index a already exists"
+        );
+        assert!(has_object(&database, "a"));
+        assert!(database.is_autocommit());
+    }
+
+    #[test]
+    fn test_begin_commit_rollback() {
+        let mut database = library();
+        let data = database.pager.data().to_vec();
+
+        database
+            .execute_sql(
+                "BEGIN; CREATE INDEX a ON books (title); CREATE TEMP VIEW v AS SELECT 1; \
+                 DROP VIEW recent_books",
+            )
+            .unwrap();
+        assert!(!database.is_autocommit());
+        assert!(has_object(&database, "a") && has_object(&database, "v"));
+        database.execute_sql("ROLLBACK").unwrap();
+        assert!(database.is_autocommit());
+        assert_eq!(database.pager.data(), data.as_slice());
+        assert!(!has_object(&database, "a") && !has_object(&database, "v"));
+        assert!(has_object(&database, "recent_books"));
+
+        database
+            .execute_sql("BEGIN IMMEDIATE; CREATE INDEX a ON books (title); END")
+            .unwrap();
+        assert!(database.is_autocommit());
+        assert!(has_object(&database, "a"));
+
+        // A failed statement inside a transaction only undoes itself
+        database
+            .execute_sql("BEGIN; CREATE INDEX b ON books (year)")
+            .unwrap();
+        error(&mut database, "CREATE UNIQUE INDEX u ON books (year)");
+        assert!(!database.is_autocommit());
+        database.execute_sql("COMMIT").unwrap();
+        assert!(has_object(&database, "b") && !has_object(&database, "u"));
+    }
+
+    #[test]
+    fn test_savepoints() {
+        let mut database = library();
+        database
+            .execute_sql(
+                "BEGIN; CREATE INDEX a ON books (title); SAVEPOINT one; \
+                 CREATE INDEX b ON books (year); SAVEPOINT two; DROP INDEX a; \
+                 ROLLBACK TO ONE",
+            )
+            .unwrap();
+        assert!(has_object(&database, "a") && !has_object(&database, "b"));
+        assert!(!database.is_autocommit());
+
+        // The savepoint rolled back to stays open, the later ones are gone
+        database
+            .execute_sql("CREATE INDEX c ON books (year); ROLLBACK TO one")
+            .unwrap();
+        assert!(!has_object(&database, "c"));
+        assert_eq!(
+            error(&mut database, "RELEASE two"),
+            "no such savepoint: two"
+        );
+        database
+            .execute_sql("CREATE INDEX c ON books (year); RELEASE one")
+            .unwrap();
+        assert!(!database.is_autocommit());
+        database.execute_sql("COMMIT").unwrap();
+        assert!(has_object(&database, "a") && has_object(&database, "c"));
+
+        // A savepoint outside a transaction starts one, released it commits
+        database
+            .execute_sql("SAVEPOINT first; CREATE INDEX d ON books (year); SAVEPOINT second")
+            .unwrap();
+        assert!(!database.is_autocommit());
+        database.execute_sql("RELEASE first").unwrap();
+        assert!(database.is_autocommit());
+        assert!(has_object(&database, "d"));
+
+        database
+            .execute_sql("SAVEPOINT s; DROP INDEX d; SAVEPOINT s; DROP INDEX c; ROLLBACK TO s")
+            .unwrap();
+        assert!(has_object(&database, "c") && !has_object(&database, "d"));
+        database.execute_sql("ROLLBACK").unwrap();
+        assert!(database.is_autocommit());
+        assert!(has_object(&database, "c") && has_object(&database, "d"));
+    }
+
+    #[test]
+    fn test_commit_writes_file() {
+        let mut database = library();
+        let count = "SELECT count(*) FROM authors WHERE name LIKE 'New %'";
+        database
+            .execute_sql("INSERT INTO authors (name) VALUES ('New 1')")
+            .unwrap();
+        assert_eq!(query(&mut reload(&database), count), ["1"]);
+
+        // Only the committed changes are in the file
+        database
+            .execute_sql(
+                "BEGIN; INSERT INTO authors (name) VALUES ('New 2'); SAVEPOINT s; \
+                 CREATE INDEX a ON books (title); INSERT INTO authors (name) VALUES ('New 3')",
+            )
+            .unwrap();
+        assert_eq!(query(&mut reload(&database), count), ["1"]);
+        database.execute_sql("ROLLBACK TO s; COMMIT").unwrap();
+        let mut reloaded = reload(&database);
+        assert_eq!(query(&mut reloaded, count), ["2"]);
+        assert!(!has_object(&reloaded, "a"));
+        assert_eq!(reloaded.pager.data(), database.pager.data());
+
+        database
+            .execute_sql("BEGIN; INSERT INTO authors (name) VALUES ('New 4'); ROLLBACK")
+            .unwrap();
+        assert_eq!(query(&mut reload(&database), count), ["2"]);
+    }
+
+    #[test]
+    fn test_commit_error() {
+        let mut database = library();
+        let data = database.pager.data().to_vec();
+        let path = database.path().unwrap().to_path_buf();
+        std::fs::remove_file(&path).unwrap();
+
+        // The file is gone, the statement can't be committed and is undone
+        assert!(matches!(
+            database.execute_sql("INSERT INTO authors (name) VALUES ('New 1')"),
+            Err(DatabaseError::FileSystemError(_))
+        ));
+        assert_eq!(database.pager.data(), data.as_slice());
+        assert_eq!(
+            query(
+                &mut database,
+                "SELECT count(*) FROM authors WHERE name = 'New 1'"
+            ),
+            ["0"]
+        );
+        std::fs::remove_file(format!("{}-journal", path.display())).ok();
+    }
+
+    #[test]
+    fn test_transaction_errors() {
+        let mut database = library();
+        for (sql, message) in [
+            ("COMMIT", "cannot commit - no transaction is active"),
+            (
+                "END TRANSACTION",
+                "cannot commit - no transaction is active",
+            ),
+            ("ROLLBACK", "cannot rollback - no transaction is active"),
+            ("ROLLBACK TO s", "no such savepoint: s"),
+            ("RELEASE s", "no such savepoint: s"),
+            (
+                "BEGIN; BEGIN",
+                "cannot start a transaction within a transaction",
+            ),
+            (
+                "SAVEPOINT s; BEGIN",
+                "cannot start a transaction within a transaction",
+            ),
+            (
+                "SAVEPOINT s; RELEASE s; COMMIT",
+                "cannot commit - no transaction is active",
+            ),
+        ] {
+            assert_eq!(error(&mut database, sql), message, "{sql}");
+            database.savepoints.clear();
+        }
+
+        database.execute_sql("BEGIN").unwrap();
+        assert_eq!(error(&mut database, "RELEASE s"), "no such savepoint: s");
+        assert!(!database.is_autocommit());
+    }
+}
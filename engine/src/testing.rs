@@ -0,0 +1,69 @@
+//! Helpers shared by the tests of the engine
+
+use crate::sqlite::Database;
+use common::types::Value;
+use std::cell::Cell;
+use std::path::PathBuf;
+
+thread_local! {
+    /// The number of fixtures the running test copied so far
+    static COPIES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Copies a database of `misc/sqlite`, so the tests can change it freely, returning the path
+/// of the copy. The copy is named after the test, each test runs in a thread of its own name
+pub fn copy(name: &str) -> String {
+    let source = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../misc/sqlite")
+        .join(name);
+    let directory = std::env::temp_dir().join("engine-tests");
+    std::fs::create_dir_all(&directory).expect("Unable to create the test directory");
+    let copy = COPIES.with(|copies| copies.replace(copies.get() + 1));
+    let test = std::thread::current()
+        .name()
+        .unwrap_or("test")
+        .replace("::", "-");
+    let path = directory.join(format!("{test}-{copy}-{name}"));
+    std::fs::copy(&source, &path).expect("Unable to copy the test database");
+    path.to_string_lossy().into_owned()
+}
+
+/// Opens a copy of a database of `misc/sqlite`
+pub fn fixture(name: &str) -> Database {
+    Database::open(copy(name)).expect("Unable to open the test database")
+}
+
+pub fn library() -> Database {
+    fixture("library.db")
+}
+
+/// Opens the file of the database again, to read what the commits wrote into it
+pub fn reload(database: &Database) -> Database {
+    let path = database.path().expect("The database has no file");
+    Database::open(path).expect("Unable to reload the database")
+}
+
+/// Runs the statements, returning the rows of the last one with their values separated by `|`
+pub fn query(database: &mut Database, sql: &str) -> Vec<String> {
+    let mut results = database
+        .execute_sql(sql)
+        .unwrap_or_else(|err| panic!("{sql}: {err}"));
+    results
+        .pop()
+        .unwrap_or_default()
+        .rows
+        .iter()
+        .map(|row| {
+            let values: Vec<String> = row.iter().map(Value::to_string).collect();
+            values.join("|")
+        })
+        .collect()
+}
+
+/// Runs statements that should fail, returning the message of the error
+pub fn error(database: &mut Database, sql: &str) -> String {
+    match database.execute_sql(sql) {
+        Err(err) => err.to_string(),
+        Ok(_) => panic!("`{sql}` should fail"),
+    }
+}
@@ -0,0 +1,67 @@
+//! Measures lexing and parsing throughput on a large generated script.
+//!
+//! Run with `cargo bench -p sql`. Plain `Instant` based timing is used, so the benchmark
+//! works on stable toolchains without extra dependencies.
+
+use sql::{Lexer, Parser};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const STATEMENTS: usize = 200_000;
+
+fn insert_script(statements: usize) -> String {
+    (0..statements)
+        .map(|i| {
+            format!(
+                "INSERT INTO movies (id, title, released, rating) VALUES ({i}, 'Movie ''{i}''', {}, {}.5);\n",
+                1900 + i % 120,
+                i % 10
+            )
+        })
+        .collect()
+}
+
+fn select_script(statements: usize) -> String {
+    (0..statements)
+        .map(|i| format!("SELECT DISTINCT movies.id AS id_{i}, title, 'const' FROM movies;\n"))
+        .collect()
+}
+
+fn report(name: &str, bytes: usize, items: usize, elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    println!(
+        "{name:<24} {items:>10} items {:>10.2?} {:>10.2} MiB/s {:>12.0} items/s",
+        elapsed,
+        bytes as f64 / seconds / (1024.0 * 1024.0),
+        items as f64 / seconds,
+    );
+}
+
+fn bench_lexer(script: &str) {
+    let start = Instant::now();
+    let mut tokens = 0;
+    for token in Lexer::new(script) {
+        black_box(token.expect("Benchmark script must be valid"));
+        tokens += 1;
+    }
+    report("lex insert script", script.len(), tokens, start.elapsed());
+}
+
+fn bench_parser(name: &str, script: &str) {
+    let start = Instant::now();
+    let statements = Parser::new(script)
+        .parse_all()
+        .expect("Benchmark script must be valid");
+    let elapsed = start.elapsed();
+    report(name, script.len(), statements.len(), elapsed);
+    black_box(statements);
+}
+
+fn main() {
+    let script = insert_script(STATEMENTS);
+    bench_lexer(&script);
+    bench_parser("parse insert script", &script);
+
+    let script = select_script(STATEMENTS);
+    bench_parser("parse select script", &script);
+}
@@ -1,51 +1,1161 @@
-use crate::ast::{ColumnLiteral, Expression};
+use crate::ast::{Ast, ColumnLiteral, Expression, List, Name, TypeName};
+use std::fmt::{Display, Formatter, Result};
 
-#[derive(Debug, PartialEq)]
+/// A SELECT core, followed by the cores it is compounded with. ORDER BY and LIMIT apply to the
+/// whole compound, the compounded cores never have them
+#[derive(Debug, Clone, PartialEq)]
 pub struct Select {
+    pub with: Option<With>,
     pub columns: Vec<ColumnLiteral>,
-    pub from: String,
+    pub from: Option<TableExpression>,
     pub where_clause: Option<Expression>,
-    pub group_by: Option<Vec<String>>,
+    pub group_by: Option<Vec<Expression>>,
     pub having: Option<Expression>,
-    pub order_by: Option<Vec<(String, Ordering)>>,
-    pub limit: Option<usize>,
+    /// Evaluated left to right, `a UNION b EXCEPT c` is `(a UNION b) EXCEPT c`
+    pub compound: Vec<(CompoundOperator, Select)>,
+    pub order_by: Option<Vec<OrderingTerm>>,
+    pub limit: Option<Limit>,
     pub distinct: bool,
 }
 
-#[derive(Debug, PartialEq)]
+/// `WITH [RECURSIVE] cte, ...`, the tables a statement can refer to besides the schema ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct With {
+    pub recursive: bool,
+    pub tables: Vec<CommonTableExpression>,
+}
+
+/// `name [(columns)] AS [[NOT] MATERIALIZED] (select)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommonTableExpression {
+    pub name: String,
+    pub columns: Vec<String>,
+    /// The `[NOT] MATERIALIZED` hint, if given
+    pub materialized: Option<bool>,
+    pub query: Box<Select>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompoundOperator {
+    Union,
+    UnionAll,
+    Intersect,
+    Except,
+}
+
+/// `CREATE [TEMP] TABLE [IF NOT EXISTS] [schema.]name ...`
+#[derive(Debug, Clone, PartialEq)]
 pub struct CreateTable {
+    pub temporary: bool,
+    pub if_not_exists: bool,
+    pub schema: Option<String>,
     pub table_name: String,
-    pub columns: Vec<ColumnLiteral>,
+    pub definition: TableDefinition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableDefinition {
+    /// `(columns, table constraints) [WITHOUT ROWID] [, STRICT]`
+    Columns {
+        columns: Vec<ColumnDefinition>,
+        constraints: Vec<TableConstraint>,
+        without_rowid: bool,
+        strict: bool,
+    },
+    /// `AS select`, the columns are the ones of its result
+    Query(Box<Select>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDefinition {
+    pub name: String,
+    /// The declared type, columns may have none: `CREATE TABLE t (a, b)`
+    pub type_name: Option<TypeName>,
+    pub constraints: Vec<ColumnConstraint>,
+}
+
+/// A column or table constraint, optionally named by `CONSTRAINT name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint<T> {
+    pub name: Option<String>,
+    pub kind: T,
+}
+
+pub type ColumnConstraint = Constraint<ColumnConstraintKind>;
+
+pub type TableConstraint = Constraint<TableConstraintKind>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnConstraintKind {
+    PrimaryKey {
+        ordering: Option<Ordering>,
+        conflict: Option<ConflictResolution>,
+        autoincrement: bool,
+    },
+    NotNull(Option<ConflictResolution>),
+    Unique(Option<ConflictResolution>),
+    Check(Expression),
+    /// A literal, a signed number or a parenthesized expression
+    Default(Expression),
+    Collate(String),
+    References(ForeignKeyClause),
+    /// `[GENERATED ALWAYS] AS (expr) [STORED | VIRTUAL]`
+    Generated {
+        expression: Expression,
+        stored: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableConstraintKind {
+    PrimaryKey {
+        columns: Vec<IndexedColumn>,
+        conflict: Option<ConflictResolution>,
+    },
+    Unique {
+        columns: Vec<IndexedColumn>,
+        conflict: Option<ConflictResolution>,
+    },
+    Check(Expression),
+    ForeignKey {
+        columns: Vec<String>,
+        clause: ForeignKeyClause,
+    },
+}
+
+/// A column of a key or index. A collation is kept in the expression as a COLLATE
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedColumn {
+    pub expression: Expression,
+    pub ordering: Option<Ordering>,
+}
+
+/// `REFERENCES table [(columns)] [ON DELETE|UPDATE action] [[NOT] DEFERRABLE ...]`, the
+/// MATCH clause SQLite ignores is left out
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKeyClause {
+    pub table: String,
+    /// The referenced columns, the primary key of the table if empty
+    pub columns: Vec<String>,
+    pub on_delete: Option<ForeignKeyAction>,
+    pub on_update: Option<ForeignKeyAction>,
+    /// `DEFERRABLE INITIALLY DEFERRED`, the key is checked on commit instead of per statement
+    pub deferred: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForeignKeyAction {
+    SetNull,
+    SetDefault,
+    Cascade,
+    Restrict,
+    NoAction,
+}
+
+/// The `ON CONFLICT` algorithm of a constraint, or the `OR` one of an INSERT or UPDATE
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictResolution {
+    Rollback,
+    Abort,
+    Fail,
+    Ignore,
+    Replace,
+}
+
+/// `CREATE [UNIQUE] INDEX [IF NOT EXISTS] [schema.]name ON table (columns) [WHERE expr]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateIndex {
+    pub unique: bool,
+    pub if_not_exists: bool,
+    pub schema: Option<String>,
+    pub name: String,
+    pub table_name: String,
+    pub columns: Vec<IndexedColumn>,
+    /// The condition of a partial index, only the rows satisfying it are indexed
+    pub where_clause: Option<Expression>,
+}
+
+/// `CREATE [TEMP] VIEW [IF NOT EXISTS] [schema.]name [(columns)] AS select`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateView {
+    pub temporary: bool,
+    pub if_not_exists: bool,
+    pub schema: Option<String>,
+    pub name: String,
+    /// Names for the result columns of the query, which keep their own if empty
+    pub columns: Vec<String>,
+    pub query: Box<Select>,
+}
+
+/// `CREATE [TEMP] TRIGGER [IF NOT EXISTS] [schema.]name [timing] event ON table
+/// [FOR EACH ROW] [WHEN expr] BEGIN statements END`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTrigger {
+    pub temporary: bool,
+    pub if_not_exists: bool,
+    pub schema: Option<String>,
+    pub name: String,
+    /// When the trigger fires relative to the event, SQLite defaults to BEFORE
+    pub timing: Option<TriggerTiming>,
+    pub event: TriggerEvent,
+    pub table_name: String,
+    /// SQLite only has row triggers, `FOR EACH ROW` is optional
+    pub for_each_row: bool,
+    pub when: Option<Expression>,
+    /// The UPDATE, INSERT, DELETE and SELECT statements run for every row
+    pub body: Vec<Ast>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerTiming {
+    Before,
+    After,
+    InsteadOf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerEvent {
+    Delete,
+    Insert,
+    /// `UPDATE [OF columns]`, any column if empty
+    Update(Vec<String>),
 }
 
-#[derive(Debug, PartialEq)]
-pub struct DropTable {
+/// `ALTER TABLE [schema.]name action`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterTable {
+    pub schema: Option<String>,
     pub table_name: String,
+    pub action: AlterTableAction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterTableAction {
+    /// `RENAME TO name`
+    RenameTable(String),
+    /// `RENAME [COLUMN] old TO new`
+    RenameColumn { old: String, new: String },
+    /// `ADD [COLUMN] definition`
+    AddColumn(ColumnDefinition),
+    /// `DROP [COLUMN] name`
+    DropColumn(String),
+}
+
+/// `DROP TABLE|INDEX|VIEW|TRIGGER [IF EXISTS] [schema.]name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropObject {
+    pub kind: ObjectKind,
     pub if_exists: bool,
+    pub schema: Option<String>,
+    pub name: String,
+}
+
+/// The kinds of objects recorded in the schema table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectKind {
+    Table,
+    Index,
+    View,
+    Trigger,
+}
+
+impl ObjectKind {
+    /// The kind as stored in the `type` column of the schema table
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ObjectKind::Table => "table",
+            ObjectKind::Index => "index",
+            ObjectKind::View => "view",
+            ObjectKind::Trigger => "trigger",
+        }
+    }
+}
+
+/// `ATTACH [DATABASE] file AS name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachDatabase {
+    /// An expression evaluated to the file name, usually a string
+    pub file: Expression,
+    /// The schema name the objects of the file are qualified with
+    pub name: String,
+}
+
+/// The locks `BEGIN` takes right away, a deferred transaction takes them on first use
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransactionMode {
+    Deferred,
+    Immediate,
+    Exclusive,
 }
 
-#[derive(Debug, PartialEq)]
+/// `DELETE FROM [schema.]name [WHERE expr] [RETURNING ...] [ORDER BY ... LIMIT ...]`
+#[derive(Debug, Clone, PartialEq)]
 pub struct Delete {
+    pub schema: Option<String>,
     pub table_name: String,
     pub where_clause: Option<Expression>,
+    /// The `RETURNING` columns, nothing is returned if empty
+    pub returning: Vec<ColumnLiteral>,
+    pub order_by: Option<Vec<OrderingTerm>>,
+    pub limit: Option<Limit>,
 }
 
-#[derive(Debug, PartialEq)]
+/// `INSERT [OR conflict] INTO [schema.]name [(columns)] source [upsert...]`
+#[derive(Debug, Clone, PartialEq)]
 pub struct Insert {
+    /// The `OR` algorithm, `REPLACE INTO` is `INSERT OR REPLACE INTO`
+    pub conflict: Option<ConflictResolution>,
+    pub schema: Option<String>,
     pub table_name: String,
+    /// The columns the values go into, all of the table if empty
     pub columns: Vec<String>,
-    pub values: Vec<Vec<Expression>>,
+    pub source: InsertSource,
+    /// The `ON CONFLICT` clauses, tried in order
+    pub upsert: Vec<Upsert>,
+    /// The `RETURNING` columns, nothing is returned if empty
+    pub returning: Vec<ColumnLiteral>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InsertSource {
+    /// `VALUES (...), (...)`, every row having the same number of terms
+    Values(Vec<Vec<Expression>>),
+    Query(Box<Select>),
+    DefaultValues,
+}
+
+/// `ON CONFLICT [(columns) [WHERE expr]] DO NOTHING | DO UPDATE SET ... [WHERE expr]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upsert {
+    /// The columns of the uniqueness constraint handled, any constraint if empty
+    pub target: Vec<IndexedColumn>,
+    /// Restricts the target to a partial index
+    pub target_where: Option<Expression>,
+    pub action: UpsertAction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpsertAction {
+    Nothing,
+    Update {
+        set_clause: Vec<Assignment>,
+        where_clause: Option<Expression>,
+    },
 }
 
-#[derive(Debug, PartialEq)]
+/// `UPDATE [OR conflict] [schema.]name SET ... [FROM ...] [WHERE expr] [RETURNING ...]
+/// [ORDER BY ... LIMIT ...]`
+#[derive(Debug, Clone, PartialEq)]
 pub struct Update {
+    pub conflict: Option<ConflictResolution>,
+    pub schema: Option<String>,
     pub table_name: String,
-    pub set_clause: Vec<(String, Expression)>,
+    pub set_clause: Vec<Assignment>,
+    /// Other tables joined to the updated one, as in a SELECT
+    pub from: Option<TableExpression>,
     pub where_clause: Option<Expression>,
+    /// The `RETURNING` columns, nothing is returned if empty
+    pub returning: Vec<ColumnLiteral>,
+    pub order_by: Option<Vec<OrderingTerm>>,
+    pub limit: Option<Limit>,
+}
+
+/// A `column = expr` or `(columns) = (exprs)` term of a SET clause. A row of columns may also
+/// be assigned a single subquery, its result columns are then assigned in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub columns: Vec<String>,
+    pub values: Vec<Expression>,
+}
+
+/// The FROM clause, a tree of joined tables
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableExpression {
+    /// `[schema.]name [[AS] alias]`
+    Table {
+        schema: Option<String>,
+        name: String,
+        alias: Option<String>,
+    },
+    /// `(SELECT ...) [[AS] alias]`
+    Subquery {
+        query: Box<Select>,
+        alias: Option<String>,
+    },
+    /// Joins are left-associative, `a JOIN b JOIN c` is `(a JOIN b) JOIN c`
+    Join {
+        left: Box<TableExpression>,
+        operator: JoinOperator,
+        right: Box<TableExpression>,
+        constraint: Option<JoinConstraint>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JoinOperator {
+    pub natural: bool,
+    pub kind: JoinKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    /// `a, b`
+    Comma,
+    /// `a JOIN b` or `a INNER JOIN b`
+    Inner,
+    /// `a LEFT [OUTER] JOIN b`
+    Left,
+    /// `a RIGHT [OUTER] JOIN b`
+    Right,
+    /// `a FULL [OUTER] JOIN b`
+    Full,
+    /// `a CROSS JOIN b`, the same as the comma, except SQLite keeps the order of the tables
+    Cross,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinConstraint {
+    On(Expression),
+    Using(Vec<String>),
+}
+
+impl TableExpression {
+    pub fn table(name: &str) -> TableExpression {
+        TableExpression::Table {
+            schema: None,
+            name: name.into(),
+            alias: None,
+        }
+    }
+
+    pub fn aliased(name: &str, alias: &str) -> TableExpression {
+        TableExpression::Table {
+            schema: None,
+            name: name.into(),
+            alias: Some(alias.into()),
+        }
+    }
+
+    pub fn join(
+        left: TableExpression,
+        kind: JoinKind,
+        right: TableExpression,
+        constraint: Option<JoinConstraint>,
+    ) -> TableExpression {
+        TableExpression::Join {
+            left: Box::new(left),
+            operator: JoinOperator {
+                natural: false,
+                kind,
+            },
+            right: Box::new(right),
+            constraint,
+        }
+    }
+
+    /// The aliases given to the joined tables and subqueries, from left to right
+    pub fn aliases(&self) -> Vec<&str> {
+        match self {
+            TableExpression::Table { alias, .. } | TableExpression::Subquery { alias, .. } => {
+                alias.iter().map(String::as_str).collect()
+            }
+            TableExpression::Join { left, right, .. } => {
+                let mut aliases = left.aliases();
+                aliases.extend(right.aliases());
+                aliases
+            }
+        }
+    }
+}
+
+/// An `expr [ASC | DESC] [NULLS FIRST | NULLS LAST]` term of ORDER BY. An integer literal
+/// refers to a result column by its 1-based position
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderingTerm {
+    pub expression: Expression,
+    pub ordering: Ordering,
+    pub nulls: Option<NullsOrdering>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Ordering {
     Ascending,
     Descending,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NullsOrdering {
+    First,
+    Last,
+}
+
+/// `LIMIT count [OFFSET offset]`, the `LIMIT offset, count` form is parsed into the same
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limit {
+    pub count: Expression,
+    pub offset: Option<Expression>,
+}
+
+impl Display for Select {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if let Some(with) = &self.with {
+            write!(f, "{with} ")?;
+        }
+        f.write_str("SELECT ")?;
+        if self.distinct {
+            f.write_str("DISTINCT ")?;
+        }
+        List(&self.columns).fmt(f)?;
+        if let Some(from) = &self.from {
+            write!(f, " FROM {from}")?;
+        }
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {where_clause}")?;
+        }
+        if let Some(group_by) = &self.group_by {
+            write!(f, " GROUP BY {}", List(group_by))?;
+        }
+        if let Some(having) = &self.having {
+            write!(f, " HAVING {having}")?;
+        }
+        for (operator, select) in &self.compound {
+            write!(f, " {operator} {select}")?;
+        }
+        if let Some(order_by) = &self.order_by {
+            write!(f, " ORDER BY {}", List(order_by))?;
+        }
+        if let Some(limit) = &self.limit {
+            write!(f, " {limit}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for CreateTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("CREATE ")?;
+        if self.temporary {
+            f.write_str("TEMP ")?;
+        }
+        f.write_str("TABLE ")?;
+        if self.if_not_exists {
+            f.write_str("IF NOT EXISTS ")?;
+        }
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        Name(&self.table_name).fmt(f)?;
+        match &self.definition {
+            TableDefinition::Columns {
+                columns,
+                constraints,
+                without_rowid,
+                strict,
+            } => {
+                write!(f, " ({}", List(columns))?;
+                for constraint in constraints {
+                    write!(f, ", {constraint}")?;
+                }
+                f.write_str(")")?;
+                match (without_rowid, strict) {
+                    (true, true) => f.write_str(" WITHOUT ROWID, STRICT"),
+                    (true, false) => f.write_str(" WITHOUT ROWID"),
+                    (false, true) => f.write_str(" STRICT"),
+                    (false, false) => Ok(()),
+                }
+            }
+            TableDefinition::Query(query) => write!(f, " AS {query}"),
+        }
+    }
+}
+
+impl Display for ColumnDefinition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Name(&self.name).fmt(f)?;
+        if let Some(type_name) = &self.type_name {
+            write!(f, " {type_name}")?;
+        }
+        for constraint in &self.constraints {
+            write!(f, " {constraint}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Display> Display for Constraint<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if let Some(name) = &self.name {
+            write!(f, "CONSTRAINT {} ", Name(name))?;
+        }
+        self.kind.fmt(f)
+    }
+}
+
+/// Renders ` ON CONFLICT algorithm` if there is one
+struct OnConflict<'a>(&'a Option<ConflictResolution>);
+
+impl Display for OnConflict<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0 {
+            Some(conflict) => write!(f, " ON CONFLICT {conflict}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for ColumnConstraintKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ColumnConstraintKind::PrimaryKey {
+                ordering,
+                conflict,
+                autoincrement,
+            } => {
+                f.write_str("PRIMARY KEY")?;
+                if let Some(ordering) = ordering {
+                    write!(f, " {ordering}")?;
+                }
+                OnConflict(conflict).fmt(f)?;
+                if *autoincrement {
+                    f.write_str(" AUTOINCREMENT")?;
+                }
+                Ok(())
+            }
+            ColumnConstraintKind::NotNull(conflict) => {
+                write!(f, "NOT NULL{}", OnConflict(conflict))
+            }
+            ColumnConstraintKind::Unique(conflict) => write!(f, "UNIQUE{}", OnConflict(conflict)),
+            ColumnConstraintKind::Check(expression) => write!(f, "CHECK ({expression})"),
+            ColumnConstraintKind::Default(expression) => write!(f, "DEFAULT {expression}"),
+            ColumnConstraintKind::Collate(collation) => write!(f, "COLLATE {}", Name(collation)),
+            ColumnConstraintKind::References(clause) => clause.fmt(f),
+            ColumnConstraintKind::Generated { expression, stored } => {
+                let storage = if *stored { "STORED" } else { "VIRTUAL" };
+                write!(f, "GENERATED ALWAYS AS ({expression}) {storage}")
+            }
+        }
+    }
+}
+
+impl Display for TableConstraintKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            TableConstraintKind::PrimaryKey { columns, conflict } => {
+                write!(f, "PRIMARY KEY ({}){}", List(columns), OnConflict(conflict))
+            }
+            TableConstraintKind::Unique { columns, conflict } => {
+                write!(f, "UNIQUE ({}){}", List(columns), OnConflict(conflict))
+            }
+            TableConstraintKind::Check(expression) => write!(f, "CHECK ({expression})"),
+            TableConstraintKind::ForeignKey { columns, clause } => {
+                let names: Vec<Name> = columns.iter().map(|name| Name(name)).collect();
+                write!(f, "FOREIGN KEY ({}) {clause}", List(&names))
+            }
+        }
+    }
+}
+
+impl Display for IndexedColumn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.expression.fmt(f)?;
+        if let Some(ordering) = &self.ordering {
+            write!(f, " {ordering}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ForeignKeyClause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "REFERENCES {}", Name(&self.table))?;
+        if !self.columns.is_empty() {
+            let names: Vec<Name> = self.columns.iter().map(|name| Name(name)).collect();
+            write!(f, " ({})", List(&names))?;
+        }
+        if let Some(action) = &self.on_delete {
+            write!(f, " ON DELETE {action}")?;
+        }
+        if let Some(action) = &self.on_update {
+            write!(f, " ON UPDATE {action}")?;
+        }
+        if self.deferred {
+            f.write_str(" DEFERRABLE INITIALLY DEFERRED")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ForeignKeyAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            ForeignKeyAction::SetNull => "SET NULL",
+            ForeignKeyAction::SetDefault => "SET DEFAULT",
+            ForeignKeyAction::Cascade => "CASCADE",
+            ForeignKeyAction::Restrict => "RESTRICT",
+            ForeignKeyAction::NoAction => "NO ACTION",
+        })
+    }
+}
+
+impl Display for ConflictResolution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            ConflictResolution::Rollback => "ROLLBACK",
+            ConflictResolution::Abort => "ABORT",
+            ConflictResolution::Fail => "FAIL",
+            ConflictResolution::Ignore => "IGNORE",
+            ConflictResolution::Replace => "REPLACE",
+        })
+    }
+}
+
+impl Display for CreateIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("CREATE ")?;
+        if self.unique {
+            f.write_str("UNIQUE ")?;
+        }
+        f.write_str("INDEX ")?;
+        if self.if_not_exists {
+            f.write_str("IF NOT EXISTS ")?;
+        }
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        write!(
+            f,
+            "{} ON {} ({})",
+            Name(&self.name),
+            Name(&self.table_name),
+            List(&self.columns)
+        )?;
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {where_clause}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for CreateView {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("CREATE ")?;
+        if self.temporary {
+            f.write_str("TEMP ")?;
+        }
+        f.write_str("VIEW ")?;
+        if self.if_not_exists {
+            f.write_str("IF NOT EXISTS ")?;
+        }
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        Name(&self.name).fmt(f)?;
+        if !self.columns.is_empty() {
+            let names: Vec<Name> = self.columns.iter().map(|name| Name(name)).collect();
+            write!(f, " ({})", List(&names))?;
+        }
+        write!(f, " AS {}", self.query)
+    }
+}
+
+impl Display for CreateTrigger {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("CREATE ")?;
+        if self.temporary {
+            f.write_str("TEMP ")?;
+        }
+        f.write_str("TRIGGER ")?;
+        if self.if_not_exists {
+            f.write_str("IF NOT EXISTS ")?;
+        }
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        Name(&self.name).fmt(f)?;
+        if let Some(timing) = &self.timing {
+            write!(f, " {timing}")?;
+        }
+        write!(f, " {} ON {}", self.event, Name(&self.table_name))?;
+        if self.for_each_row {
+            f.write_str(" FOR EACH ROW")?;
+        }
+        if let Some(when) = &self.when {
+            write!(f, " WHEN {when}")?;
+        }
+        f.write_str(" BEGIN")?;
+        for statement in &self.body {
+            write!(f, " {statement};")?;
+        }
+        f.write_str(" END")
+    }
+}
+
+impl Display for TriggerTiming {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            TriggerTiming::Before => "BEFORE",
+            TriggerTiming::After => "AFTER",
+            TriggerTiming::InsteadOf => "INSTEAD OF",
+        })
+    }
+}
+
+impl Display for TriggerEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            TriggerEvent::Delete => f.write_str("DELETE"),
+            TriggerEvent::Insert => f.write_str("INSERT"),
+            TriggerEvent::Update(columns) if columns.is_empty() => f.write_str("UPDATE"),
+            TriggerEvent::Update(columns) => {
+                let names: Vec<Name> = columns.iter().map(|name| Name(name)).collect();
+                write!(f, "UPDATE OF {}", List(&names))
+            }
+        }
+    }
+}
+
+impl Display for AlterTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("ALTER TABLE ")?;
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        write!(f, "{} ", Name(&self.table_name))?;
+        match &self.action {
+            AlterTableAction::RenameTable(name) => write!(f, "RENAME TO {}", Name(name)),
+            AlterTableAction::RenameColumn { old, new } => {
+                write!(f, "RENAME COLUMN {} TO {}", Name(old), Name(new))
+            }
+            AlterTableAction::AddColumn(column) => write!(f, "ADD COLUMN {column}"),
+            AlterTableAction::DropColumn(name) => write!(f, "DROP COLUMN {}", Name(name)),
+        }
+    }
+}
+
+impl Display for DropObject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "DROP {} ", self.kind)?;
+        if self.if_exists {
+            f.write_str("IF EXISTS ")?;
+        }
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        Name(&self.name).fmt(f)
+    }
+}
+
+impl Display for ObjectKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(&self.type_name().to_uppercase())
+    }
+}
+
+impl Display for AttachDatabase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "ATTACH DATABASE {} AS {}", self.file, Name(&self.name))
+    }
+}
+
+impl Display for TransactionMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            TransactionMode::Deferred => "DEFERRED",
+            TransactionMode::Immediate => "IMMEDIATE",
+            TransactionMode::Exclusive => "EXCLUSIVE",
+        })
+    }
+}
+
+impl Display for Delete {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("DELETE FROM ")?;
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        Name(&self.table_name).fmt(f)?;
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {where_clause}")?;
+        }
+        Tail(&self.returning, &self.order_by, &self.limit).fmt(f)
+    }
+}
+
+/// Renders the `RETURNING`, `ORDER BY` and `LIMIT` clauses ending an UPDATE or DELETE
+struct Tail<'a>(
+    &'a [ColumnLiteral],
+    &'a Option<Vec<OrderingTerm>>,
+    &'a Option<Limit>,
+);
+
+impl Display for Tail<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Returning(self.0).fmt(f)?;
+        if let Some(order_by) = self.1 {
+            write!(f, " ORDER BY {}", List(order_by))?;
+        }
+        if let Some(limit) = self.2 {
+            write!(f, " {limit}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders ` RETURNING columns` if there are any
+struct Returning<'a>(&'a [ColumnLiteral]);
+
+impl Display for Returning<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        write!(f, " RETURNING {}", List(self.0))
+    }
+}
+
+impl Display for Insert {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("INSERT ")?;
+        if let Some(conflict) = &self.conflict {
+            write!(f, "OR {conflict} ")?;
+        }
+        f.write_str("INTO ")?;
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        Name(&self.table_name).fmt(f)?;
+        if !self.columns.is_empty() {
+            let names: Vec<Name> = self.columns.iter().map(|name| Name(name)).collect();
+            write!(f, " ({})", List(&names))?;
+        }
+        match &self.source {
+            InsertSource::Values(rows) => {
+                f.write_str(" VALUES ")?;
+                for (i, row) in rows.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "({})", List(row))?;
+                }
+            }
+            InsertSource::Query(query) => write!(f, " {query}")?,
+            InsertSource::DefaultValues => f.write_str(" DEFAULT VALUES")?,
+        }
+        for upsert in &self.upsert {
+            write!(f, " {upsert}")?;
+        }
+        Returning(&self.returning).fmt(f)
+    }
+}
+
+impl Display for Upsert {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("ON CONFLICT")?;
+        if !self.target.is_empty() {
+            write!(f, " ({})", List(&self.target))?;
+        }
+        if let Some(condition) = &self.target_where {
+            write!(f, " WHERE {condition}")?;
+        }
+        match &self.action {
+            UpsertAction::Nothing => f.write_str(" DO NOTHING"),
+            UpsertAction::Update {
+                set_clause,
+                where_clause,
+            } => {
+                write!(f, " DO UPDATE SET {}", List(set_clause))?;
+                if let Some(condition) = where_clause {
+                    write!(f, " WHERE {condition}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for Update {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("UPDATE ")?;
+        if let Some(conflict) = &self.conflict {
+            write!(f, "OR {conflict} ")?;
+        }
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        write!(
+            f,
+            "{} SET {}",
+            Name(&self.table_name),
+            List(&self.set_clause)
+        )?;
+        if let Some(from) = &self.from {
+            write!(f, " FROM {from}")?;
+        }
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {where_clause}")?;
+        }
+        Tail(&self.returning, &self.order_by, &self.limit).fmt(f)
+    }
+}
+
+impl Display for Assignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match (self.columns.as_slice(), self.values.as_slice()) {
+            ([column], [value]) => write!(f, "{} = {value}", Name(column)),
+            (columns, values) => {
+                let names: Vec<Name> = columns.iter().map(|name| Name(name)).collect();
+                match values {
+                    // A subquery already renders its parentheses
+                    [value @ Expression::Subquery(_)] => write!(f, "({}) = {value}", List(&names)),
+                    _ => write!(f, "({}) = ({})", List(&names), List(values)),
+                }
+            }
+        }
+    }
+}
+
+impl Display for With {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("WITH ")?;
+        if self.recursive {
+            f.write_str("RECURSIVE ")?;
+        }
+        List(&self.tables).fmt(f)
+    }
+}
+
+impl Display for CommonTableExpression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Name(&self.name).fmt(f)?;
+        if !self.columns.is_empty() {
+            let names: Vec<Name> = self.columns.iter().map(|name| Name(name)).collect();
+            write!(f, "({})", List(&names))?;
+        }
+        f.write_str(" AS ")?;
+        match self.materialized {
+            Some(true) => f.write_str("MATERIALIZED ")?,
+            Some(false) => f.write_str("NOT MATERIALIZED ")?,
+            None => {}
+        }
+        write!(f, "({})", self.query)
+    }
+}
+
+impl Display for CompoundOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            CompoundOperator::Union => "UNION",
+            CompoundOperator::UnionAll => "UNION ALL",
+            CompoundOperator::Intersect => "INTERSECT",
+            CompoundOperator::Except => "EXCEPT",
+        })
+    }
+}
+
+impl Display for TableExpression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            TableExpression::Table {
+                schema,
+                name,
+                alias,
+            } => {
+                if let Some(schema) = schema {
+                    write!(f, "{}.", Name(schema))?;
+                }
+                Name(name).fmt(f)?;
+                if let Some(alias) = alias {
+                    write!(f, " AS {}", Name(alias))?;
+                }
+                Ok(())
+            }
+            TableExpression::Subquery { query, alias } => {
+                write!(f, "({query})")?;
+                if let Some(alias) = alias {
+                    write!(f, " AS {}", Name(alias))?;
+                }
+                Ok(())
+            }
+            TableExpression::Join {
+                left,
+                operator,
+                right,
+                constraint,
+            } => {
+                write!(f, "{left}{operator}")?;
+                // Joins are left-associative, one on the right needs parentheses
+                match right.as_ref() {
+                    TableExpression::Join { .. } => write!(f, "({right})")?,
+                    _ => right.fmt(f)?,
+                }
+                match constraint {
+                    Some(JoinConstraint::On(expression)) => write!(f, " ON {expression}"),
+                    Some(JoinConstraint::Using(columns)) => {
+                        let names: Vec<Name> = columns.iter().map(|name| Name(name)).collect();
+                        write!(f, " USING ({})", List(&names))
+                    }
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl Display for JoinOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if self.kind == JoinKind::Comma {
+            return f.write_str(", ");
+        }
+        if self.natural {
+            f.write_str(" NATURAL")?;
+        }
+        f.write_str(match self.kind {
+            JoinKind::Comma | JoinKind::Inner => " JOIN ",
+            JoinKind::Left => " LEFT JOIN ",
+            JoinKind::Right => " RIGHT JOIN ",
+            JoinKind::Full => " FULL JOIN ",
+            JoinKind::Cross => " CROSS JOIN ",
+        })
+    }
+}
+
+impl Display for OrderingTerm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.expression.fmt(f)?;
+        if self.ordering == Ordering::Descending {
+            f.write_str(" DESC")?;
+        }
+        if let Some(nulls) = &self.nulls {
+            write!(f, " {nulls}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "LIMIT {}", self.count)?;
+        if let Some(offset) = &self.offset {
+            write!(f, " OFFSET {offset}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for NullsOrdering {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            NullsOrdering::First => f.write_str("NULLS FIRST"),
+            NullsOrdering::Last => f.write_str("NULLS LAST"),
+        }
+    }
+}
+
+impl Display for Ordering {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Ordering::Ascending => f.write_str("ASC"),
+            Ordering::Descending => f.write_str("DESC"),
+        }
+    }
+}
@@ -1,16 +1,661 @@
-#[derive(Debug, PartialEq)]
+use crate::ast::{List, Name, Quoted, Select};
+use crate::token::Keyword;
+use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
-    Value,
-    Operation,
     Literal(Literal),
+    /// A `[[schema.]table.]column` reference
+    Column(ColumnRef),
+    /// A `*` or `table.*`, only valid as a result column
+    Wildcard(Option<String>),
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+    },
+    Binary {
+        left: Box<Expression>,
+        operator: BinaryOperator,
+        right: Box<Expression>,
+    },
+    /// `expr COLLATE name`
+    Collate {
+        expression: Box<Expression>,
+        collation: String,
+    },
+    /// A function call, e.g. `MAX(rating)`. The `*` of `COUNT(*)` is kept as its single
+    /// wildcard argument
+    Function {
+        name: String,
+        arguments: Vec<Expression>,
+        distinct: bool,
+        /// `FILTER (WHERE expr)`, limits the rows an aggregate sees
+        filter: Option<Box<Expression>>,
+    },
+    /// `expr [NOT] LIKE pattern [ESCAPE escape]`, and the same for GLOB, REGEXP and MATCH
+    Like {
+        expression: Box<Expression>,
+        negated: bool,
+        operator: PatternOperator,
+        pattern: Box<Expression>,
+        escape: Option<Box<Expression>>,
+    },
+    /// `expr [NOT] BETWEEN low AND high`
+    Between {
+        expression: Box<Expression>,
+        negated: bool,
+        low: Box<Expression>,
+        high: Box<Expression>,
+    },
+    /// `expr [NOT] IN (list)`
+    InList {
+        expression: Box<Expression>,
+        negated: bool,
+        list: Vec<Expression>,
+    },
+    /// `expr [NOT] IN (SELECT ...)`
+    InSubquery {
+        expression: Box<Expression>,
+        negated: bool,
+        subquery: Box<Select>,
+    },
+    /// A `(SELECT ...)` used as a value, the first column of its first row
+    Subquery(Box<Select>),
+    /// `EXISTS (SELECT ...)`, `NOT EXISTS` is a `NOT` on top of it
+    Exists(Box<Select>),
+    /// `expr IS [NOT] NULL`, also written as `expr ISNULL`, `expr NOTNULL` or `expr NOT NULL`
+    IsNull {
+        expression: Box<Expression>,
+        negated: bool,
+    },
+    /// `left IS [NOT] DISTINCT FROM right`
+    IsDistinctFrom {
+        left: Box<Expression>,
+        negated: bool,
+        right: Box<Expression>,
+    },
+    /// `CASE [operand] WHEN condition THEN result ... [ELSE result] END`. With an operand the
+    /// conditions are the values it is compared to
+    Case {
+        operand: Option<Box<Expression>>,
+        branches: Vec<(Expression, Expression)>,
+        else_result: Option<Box<Expression>>,
+    },
+    /// `CAST(expr AS type)`
+    Cast {
+        expression: Box<Expression>,
+        data_type: TypeName,
+    },
+    /// A parenthesized expression
+    Nested(Box<Expression>),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
     Null,
     String(String),
-    Number(f64),
+    Integer(i64),
+    Float(f64),
     Boolean(bool),
+    Blob(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnRef {
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    /// `-expr`
+    Minus,
+    /// `+expr`
+    Plus,
+    /// `~expr`
+    BitNot,
+    /// `NOT expr`
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Concat,
+    Multiply,
+    Divide,
+    Modulo,
+    Add,
+    Subtract,
+    BitAnd,
+    BitOr,
+    ShiftLeft,
+    ShiftRight,
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+    Equals,
+    NotEquals,
+    /// `IS`, the NULL-aware `=`
+    Is,
+    /// `IS NOT`, the NULL-aware `!=`
+    IsNot,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternOperator {
+    Like,
+    Glob,
+    Regexp,
+    Match,
+}
+
+/// A declared type such as `INTEGER`, `VARCHAR(255)` or `UNSIGNED BIG INT`, kept the way it
+/// was written since SQLite derives the column affinity from the name
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeName {
+    /// The words of the name, separated by single spaces
+    pub name: String,
+    /// The signed numbers in parentheses after the name, e.g. the `255` of `VARCHAR(255)`
+    pub arguments: Vec<String>,
+}
+
+/// Operator precedence levels, from the loosest to the tightest binding one.
+/// Derived from the `SQL As Understood By SQLite` operators table.
+pub mod precedence {
+    pub const OR: u8 = 1;
+    pub const AND: u8 = 2;
+    pub const NOT: u8 = 3;
+    pub const EQUALITY: u8 = 4;
+    pub const COMPARISON: u8 = 5;
+    pub const BITWISE: u8 = 6;
+    pub const ADDITIVE: u8 = 7;
+    pub const MULTIPLICATIVE: u8 = 8;
+    pub const CONCAT: u8 = 9;
+    pub const COLLATE: u8 = 10;
+    pub const UNARY: u8 = 11;
+    pub const PRIMARY: u8 = 12;
+}
+
+impl BinaryOperator {
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Or => precedence::OR,
+            BinaryOperator::And => precedence::AND,
+            BinaryOperator::Equals
+            | BinaryOperator::NotEquals
+            | BinaryOperator::Is
+            | BinaryOperator::IsNot => precedence::EQUALITY,
+            BinaryOperator::LessThan
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterOrEqual => precedence::COMPARISON,
+            BinaryOperator::BitAnd
+            | BinaryOperator::BitOr
+            | BinaryOperator::ShiftLeft
+            | BinaryOperator::ShiftRight => precedence::BITWISE,
+            BinaryOperator::Add | BinaryOperator::Subtract => precedence::ADDITIVE,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => {
+                precedence::MULTIPLICATIVE
+            }
+            BinaryOperator::Concat => precedence::CONCAT,
+        }
+    }
+}
+
+impl UnaryOperator {
+    pub fn precedence(&self) -> u8 {
+        match self {
+            UnaryOperator::Not => precedence::NOT,
+            _ => precedence::UNARY,
+        }
+    }
+}
+
+impl Expression {
+    /// The precedence of the outermost operator, used to decide where parentheses are needed
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Expression::Unary { operator, .. } => operator.precedence(),
+            Expression::Binary { operator, .. } => operator.precedence(),
+            Expression::Collate { .. } => precedence::COLLATE,
+            Expression::Like { .. }
+            | Expression::Between { .. }
+            | Expression::InList { .. }
+            | Expression::InSubquery { .. }
+            | Expression::IsNull { .. }
+            | Expression::IsDistinctFrom { .. } => precedence::EQUALITY,
+            _ => precedence::PRIMARY,
+        }
+    }
+
+    /// Prefix operators chain without parentheses, e.g. `- NOT x`
+    fn parenthesize_operand(operator: UnaryOperator, operand: &Expression) -> bool {
+        operand.precedence() < operator.precedence() && !matches!(operand, Expression::Unary { .. })
+    }
+
+    /// A trailing `NOT x` would swallow the operator after the left operand, unless it binds
+    /// looser
+    fn parenthesize_left(precedence: u8, left: &Expression) -> bool {
+        left.precedence() < precedence || (precedence > precedence::NOT && left.ends_with_not())
+    }
+
+    /// All binary operators are left-associative. A prefix operator on the right side can't
+    /// be misread, so `a = NOT b` is kept as it is
+    fn parenthesize_right(precedence: u8, right: &Expression) -> bool {
+        right.precedence() <= precedence && !matches!(right, Expression::Unary { .. })
+    }
+
+    /// `a IS (NOT b)` and `a IS (NULL)` would read as `IS NOT` and `IS NULL` otherwise
+    fn parenthesize_binary_right(operator: BinaryOperator, right: &Expression) -> bool {
+        let ambiguous = matches!(operator, BinaryOperator::Is | BinaryOperator::IsNot)
+            && matches!(
+                right,
+                Expression::Literal(Literal::Null)
+                    | Expression::Unary {
+                        operator: UnaryOperator::Not,
+                        ..
+                    }
+            );
+        ambiguous || Expression::parenthesize_right(operator.precedence(), right)
+    }
+
+    /// Returns true if the rendered expression ends with an unparenthesized `NOT` operand
+    fn ends_with_not(&self) -> bool {
+        match self {
+            Expression::Unary {
+                operator: UnaryOperator::Not,
+                ..
+            } => true,
+            Expression::Unary { operator, operand } => {
+                !Expression::parenthesize_operand(*operator, operand) && operand.ends_with_not()
+            }
+            Expression::Binary {
+                operator, right, ..
+            } => !Expression::parenthesize_binary_right(*operator, right) && right.ends_with_not(),
+            Expression::Like {
+                pattern, escape, ..
+            } => {
+                let last = escape.as_ref().unwrap_or(pattern);
+                !Expression::parenthesize_right(precedence::EQUALITY, last) && last.ends_with_not()
+            }
+            Expression::Between { high: right, .. } | Expression::IsDistinctFrom { right, .. } => {
+                !Expression::parenthesize_right(precedence::EQUALITY, right)
+                    && right.ends_with_not()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn unary(operator: UnaryOperator, operand: Expression) -> Expression {
+        Expression::Unary {
+            operator,
+            operand: Box::new(operand),
+        }
+    }
+
+    /// A plain call, without DISTINCT or FILTER
+    pub fn function(name: &str, arguments: Vec<Expression>) -> Expression {
+        Expression::Function {
+            name: name.into(),
+            arguments,
+            distinct: false,
+            filter: None,
+        }
+    }
+
+    pub fn binary(left: Expression, operator: BinaryOperator, right: Expression) -> Expression {
+        Expression::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+}
+
+impl ColumnRef {
+    pub fn new(name: &str) -> ColumnRef {
+        ColumnRef {
+            schema: None,
+            table: None,
+            name: name.into(),
+        }
+    }
+
+    pub fn qualified(table: &str, name: &str) -> ColumnRef {
+        ColumnRef {
+            schema: None,
+            table: Some(table.into()),
+            name: name.into(),
+        }
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Expression::Literal(literal) => literal.fmt(f),
+            Expression::Column(column) => column.fmt(f),
+            Expression::Wildcard(None) => f.write_str("*"),
+            Expression::Wildcard(Some(table)) => write!(f, "{}.*", Name(table)),
+            Expression::Unary { operator, operand } => {
+                operator.fmt(f)?;
+                // Keeps `- -x` apart, `--` would start a comment
+                if matches!(
+                    operand.as_ref(),
+                    Expression::Unary {
+                        operator: UnaryOperator::Minus | UnaryOperator::Plus,
+                        ..
+                    } | Expression::Literal(Literal::Integer(i64::MIN..=-1))
+                ) || matches!(operand.as_ref(), Expression::Literal(Literal::Float(float)) if float.is_sign_negative())
+                {
+                    f.write_str(" ")?;
+                }
+                if Expression::parenthesize_operand(*operator, operand) {
+                    write!(f, "({operand})")
+                } else {
+                    operand.fmt(f)
+                }
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let precedence = operator.precedence();
+                Operand(left, Expression::parenthesize_left(precedence, left)).fmt(f)?;
+                write!(f, " {operator} ")?;
+                let parenthesize = Expression::parenthesize_binary_right(*operator, right);
+                Operand(right, parenthesize).fmt(f)
+            }
+            Expression::Like {
+                expression,
+                negated,
+                operator,
+                pattern,
+                escape,
+            } => {
+                Predicate(expression).fmt(f)?;
+                // A LIKE trailing a NOT operand would take the ESCAPE for its own
+                let parenthesize = Expression::parenthesize_right(precedence::EQUALITY, pattern)
+                    || (escape.is_some() && pattern.ends_with_not());
+                write!(f, " {}{operator} ", Not(*negated))?;
+                Operand(pattern, parenthesize).fmt(f)?;
+                if let Some(escape) = escape {
+                    write!(f, " ESCAPE {}", Argument(escape))?;
+                }
+                Ok(())
+            }
+            Expression::Between {
+                expression,
+                negated,
+                low,
+                high,
+            } => {
+                Predicate(expression).fmt(f)?;
+                write!(
+                    f,
+                    " {}BETWEEN {} AND {}",
+                    Not(*negated),
+                    Argument(low),
+                    Argument(high)
+                )
+            }
+            Expression::InList {
+                expression,
+                negated,
+                list,
+            } => {
+                Predicate(expression).fmt(f)?;
+                write!(f, " {}IN ({})", Not(*negated), List(list))
+            }
+            Expression::InSubquery {
+                expression,
+                negated,
+                subquery,
+            } => {
+                Predicate(expression).fmt(f)?;
+                write!(f, " {}IN ({subquery})", Not(*negated))
+            }
+            Expression::Subquery(subquery) => write!(f, "({subquery})"),
+            Expression::Exists(subquery) => write!(f, "EXISTS ({subquery})"),
+            Expression::IsNull {
+                expression,
+                negated,
+            } => {
+                Predicate(expression).fmt(f)?;
+                write!(f, " IS {}NULL", Not(*negated))
+            }
+            Expression::IsDistinctFrom {
+                left,
+                negated,
+                right,
+            } => {
+                Predicate(left).fmt(f)?;
+                write!(f, " IS {}DISTINCT FROM {}", Not(*negated), Argument(right))
+            }
+            Expression::Case {
+                operand,
+                branches,
+                else_result,
+            } => {
+                f.write_str("CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {operand}")?;
+                }
+                for (condition, result) in branches {
+                    write!(f, " WHEN {condition} THEN {result}")?;
+                }
+                if let Some(else_result) = else_result {
+                    write!(f, " ELSE {else_result}")?;
+                }
+                f.write_str(" END")
+            }
+            Expression::Cast {
+                expression,
+                data_type,
+            } => write!(f, "CAST({expression} AS {data_type})"),
+            Expression::Collate {
+                expression,
+                collation,
+            } => {
+                if expression.precedence() < precedence::COLLATE || expression.ends_with_not() {
+                    write!(f, "({expression})")?;
+                } else {
+                    expression.fmt(f)?;
+                }
+                write!(f, " COLLATE {}", Name(collation))
+            }
+            Expression::Function {
+                name,
+                arguments,
+                distinct,
+                filter,
+            } => {
+                write!(f, "{}(", Name(name))?;
+                if *distinct {
+                    f.write_str("DISTINCT ")?;
+                }
+                write!(f, "{})", List(arguments))?;
+                if let Some(filter) = filter {
+                    write!(f, " FILTER (WHERE {filter})")?;
+                }
+                Ok(())
+            }
+            Expression::Nested(expression) => write!(f, "({expression})"),
+        }
+    }
+}
+
+/// Renders the expression, in parentheses if asked to
+struct Operand<'a>(&'a Expression, bool);
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.1 {
+            true => write!(f, "({})", self.0),
+            false => self.0.fmt(f),
+        }
+    }
+}
+
+/// The left-hand side of LIKE, BETWEEN, IN and IS, which bind as tight as `=`
+struct Predicate<'a>(&'a Expression);
+
+impl Display for Predicate<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Operand(
+            self.0,
+            Expression::parenthesize_left(precedence::EQUALITY, self.0),
+        )
+        .fmt(f)
+    }
+}
+
+/// An operand following LIKE, BETWEEN, its AND, ESCAPE or IS DISTINCT FROM
+struct Argument<'a>(&'a Expression);
+
+impl Display for Argument<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Operand(
+            self.0,
+            Expression::parenthesize_right(precedence::EQUALITY, self.0),
+        )
+        .fmt(f)
+    }
+}
+
+/// Renders `NOT ` for negated predicates
+struct Not(bool);
+
+impl Display for Not {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0 {
+            true => f.write_str("NOT "),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Display for UnaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            UnaryOperator::Minus => f.write_str("-"),
+            UnaryOperator::Plus => f.write_str("+"),
+            UnaryOperator::BitNot => f.write_str("~"),
+            UnaryOperator::Not => f.write_str("NOT "),
+        }
+    }
+}
+
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            BinaryOperator::Concat => "||",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::BitAnd => "&",
+            BinaryOperator::BitOr => "|",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterOrEqual => ">=",
+            BinaryOperator::Equals => "=",
+            BinaryOperator::NotEquals => "!=",
+            BinaryOperator::Is => "IS",
+            BinaryOperator::IsNot => "IS NOT",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Or => "OR",
+        })
+    }
+}
+
+impl Display for PatternOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            PatternOperator::Like => "LIKE",
+            PatternOperator::Glob => "GLOB",
+            PatternOperator::Regexp => "REGEXP",
+            PatternOperator::Match => "MATCH",
+        })
+    }
+}
+
+impl Display for TypeName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        // Bare words are scanned back one by one, anything else is kept as a single name
+        let is_word = |word: &str| {
+            let mut chars = word.chars();
+            chars.next().is_some_and(|c| c.is_alphabetic())
+                && chars.all(|c| c.is_alphanumeric() || c == '_')
+                && Keyword::from_str(word).map_or(true, |keyword| !keyword.is_reserved())
+        };
+        if self.name.split(' ').all(is_word) {
+            f.write_str(&self.name)?;
+        } else {
+            write!(f, "\"{}\"", self.name.replace('"', "\"\""))?;
+        }
+        if !self.arguments.is_empty() {
+            write!(f, "({})", List(&self.arguments))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ColumnRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", Name(schema))?;
+        }
+        if let Some(table) = &self.table {
+            write!(f, "{}.", Name(table))?;
+        }
+        Name(&self.name).fmt(f)
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Literal::Null => f.write_str("NULL"),
+            Literal::String(string) => Quoted(string).fmt(f),
+            // Only the smallest INTEGER is read back from its negative decimal, see
+            // `Parser::parse_prefix`, the other negative ones are written as hex
+            Literal::Integer(integer) if *integer < 0 && *integer != i64::MIN => {
+                write!(f, "{:#x}", *integer as u64)
+            }
+            Literal::Integer(integer) => integer.fmt(f),
+            // The same way SQLite renders them
+            Literal::Float(float) if float.is_nan() => f.write_str("NULL"),
+            Literal::Float(float) if float.is_infinite() && *float > 0.0 => f.write_str("9e999"),
+            Literal::Float(float) if float.is_infinite() => f.write_str("-9e999"),
+            Literal::Float(float) => {
+                // Keeps the value a float when scanned back, e.g. `1.0` instead of `1`
+                let text = float.to_string();
+                if text.contains(['.', 'e', 'E']) {
+                    f.write_str(&text)
+                } else {
+                    write!(f, "{text}.0")
+                }
+            }
+            Literal::Boolean(true) => f.write_str("TRUE"),
+            Literal::Boolean(false) => f.write_str("FALSE"),
+            Literal::Blob(blob) => {
+                f.write_str("X'")?;
+                blob.iter().try_for_each(|byte| write!(f, "{byte:02X}"))?;
+                f.write_str("'")
+            }
+        }
+    }
 }
 
 impl From<String> for Literal {
@@ -25,7 +670,7 @@ impl From<&str> for Literal {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ColumnLiteral {
     pub expression: Expression,
     pub alias: Option<String>,
@@ -46,3 +691,13 @@ impl ColumnLiteral {
         }
     }
 }
+
+impl Display for ColumnLiteral {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.expression.fmt(f)?;
+        if let Some(alias) = &self.alias {
+            write!(f, " AS {}", Name(alias))?;
+        }
+        Ok(())
+    }
+}
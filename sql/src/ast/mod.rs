@@ -1,16 +1,994 @@
 mod entities;
 mod expression;
 
-pub use entities::{CreateTable, Delete, DropTable, Insert, Ordering, Select, Update};
-pub use expression::{ColumnLiteral, Expression, Literal};
+use crate::token::Keyword;
+use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq)]
+pub use entities::{
+    AlterTable, AlterTableAction, Assignment, AttachDatabase, ColumnConstraint,
+    ColumnConstraintKind, ColumnDefinition, CommonTableExpression, CompoundOperator,
+    ConflictResolution, Constraint, CreateIndex, CreateTable, CreateTrigger, CreateView, Delete,
+    DropObject, ForeignKeyAction, ForeignKeyClause, IndexedColumn, Insert, InsertSource,
+    JoinConstraint, JoinKind, JoinOperator, Limit, NullsOrdering, ObjectKind, Ordering,
+    OrderingTerm, Select, TableConstraint, TableConstraintKind, TableDefinition, TableExpression,
+    TransactionMode, TriggerEvent, TriggerTiming, Update, Upsert, UpsertAction, With,
+};
+pub use expression::{
+    precedence, BinaryOperator, ColumnLiteral, ColumnRef, Expression, Literal, PatternOperator,
+    TypeName, UnaryOperator,
+};
+
+// Statements are short-lived, boxing SELECT would only add indirection to every match on it
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Ast {
     Select(Select),
     CreateTable(CreateTable),
-    DropTable(DropTable),
+    CreateIndex(CreateIndex),
+    CreateView(CreateView),
+    CreateTrigger(CreateTrigger),
+    AlterTable(AlterTable),
+    Drop(DropObject),
     Delete(Delete),
     Insert(Insert),
     Update(Update),
     Explain(Box<Ast>),
+    ExplainQueryPlan(Box<Ast>),
+    /// `BEGIN [mode] [TRANSACTION]`
+    Begin(Option<TransactionMode>),
+    /// `COMMIT|END [TRANSACTION]`
+    Commit,
+    /// `ROLLBACK [TRANSACTION] [TO [SAVEPOINT] name]`, to the savepoint if there is one
+    Rollback(Option<String>),
+    /// `SAVEPOINT name`
+    Savepoint(String),
+    /// `RELEASE [SAVEPOINT] name`
+    Release(String),
+    Attach(AttachDatabase),
+    /// `DETACH [DATABASE] name`
+    Detach(String),
+}
+
+impl Display for Ast {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Ast::Select(select) => select.fmt(f),
+            Ast::CreateTable(create_table) => create_table.fmt(f),
+            Ast::CreateIndex(create_index) => create_index.fmt(f),
+            Ast::CreateView(create_view) => create_view.fmt(f),
+            Ast::CreateTrigger(create_trigger) => create_trigger.fmt(f),
+            Ast::AlterTable(alter_table) => alter_table.fmt(f),
+            Ast::Drop(drop) => drop.fmt(f),
+            Ast::Delete(delete) => delete.fmt(f),
+            Ast::Insert(insert) => insert.fmt(f),
+            Ast::Update(update) => update.fmt(f),
+            Ast::Explain(ast) => write!(f, "EXPLAIN {ast}"),
+            Ast::ExplainQueryPlan(ast) => write!(f, "EXPLAIN QUERY PLAN {ast}"),
+            Ast::Begin(None) => f.write_str("BEGIN"),
+            Ast::Begin(Some(mode)) => write!(f, "BEGIN {mode}"),
+            Ast::Commit => f.write_str("COMMIT"),
+            Ast::Rollback(None) => f.write_str("ROLLBACK"),
+            Ast::Rollback(Some(name)) => write!(f, "ROLLBACK TO SAVEPOINT {}", Name(name)),
+            Ast::Savepoint(name) => write!(f, "SAVEPOINT {}", Name(name)),
+            Ast::Release(name) => write!(f, "RELEASE SAVEPOINT {}", Name(name)),
+            Ast::Attach(attach) => attach.fmt(f),
+            Ast::Detach(name) => write!(f, "DETACH DATABASE {}", Name(name)),
+        }
+    }
+}
+
+/// Renders a name the way the lexer scans it back: bare if it is a plain identifier that is
+/// not a keyword, double-quoted otherwise
+pub struct Name<'a>(pub &'a str);
+
+impl Name<'_> {
+    fn is_bare(name: &str) -> bool {
+        let mut chars = name.chars();
+        chars.next().is_some_and(|c| c.is_alphabetic())
+            && chars.all(|c| c.is_alphanumeric() || c == '_')
+            && Keyword::from_str(name).is_err()
+    }
+}
+
+impl Display for Name<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if Name::is_bare(self.0) {
+            f.write_str(self.0)
+        } else {
+            write!(f, "\"{}\"", self.0.replace('"', "\"\""))
+        }
+    }
+}
+
+/// Renders a single-quoted string literal, doubling the quotes inside
+struct Quoted<'a>(&'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "'{}'", self.0.replace('\'', "''"))
+    }
+}
+
+/// Renders the items separated by commas
+struct List<'a, T>(&'a [T]);
+
+impl<T: Display> Display for List<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            item.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Random;
+    use crate::Parser;
+
+    const NAMES: &[&str] = &[
+        "id", "title", "movies", "Col_1", "key", "action", "some id", "👋", "select", "from",
+        "it\"s", "",
+    ];
+
+    const STRINGS: &[&str] = &[
+        "abc",
+        "it's",
+        "users.col1",
+        "a.*",
+        "😀",
+        "",
+        "select",
+        "x y",
+        "1abc",
+        "key",
+    ];
+
+    const UNARY_OPERATORS: &[UnaryOperator] = &[
+        UnaryOperator::Minus,
+        UnaryOperator::Plus,
+        UnaryOperator::BitNot,
+        UnaryOperator::Not,
+    ];
+
+    const BINARY_OPERATORS: &[BinaryOperator] = &[
+        BinaryOperator::Concat,
+        BinaryOperator::Multiply,
+        BinaryOperator::Divide,
+        BinaryOperator::Modulo,
+        BinaryOperator::Add,
+        BinaryOperator::Subtract,
+        BinaryOperator::BitAnd,
+        BinaryOperator::BitOr,
+        BinaryOperator::ShiftLeft,
+        BinaryOperator::ShiftRight,
+        BinaryOperator::LessThan,
+        BinaryOperator::LessOrEqual,
+        BinaryOperator::GreaterThan,
+        BinaryOperator::GreaterOrEqual,
+        BinaryOperator::Equals,
+        BinaryOperator::NotEquals,
+        BinaryOperator::Is,
+        BinaryOperator::IsNot,
+        BinaryOperator::And,
+        BinaryOperator::Or,
+    ];
+
+    fn random_literal(random: &mut Random) -> Literal {
+        match random.below(6) {
+            0 => Literal::Null,
+            1 => Literal::String(random.pick(STRINGS).to_string()),
+            // Negative literals come from hex and overflowing magnitudes, e.g. `-9e999`
+            2 if random.chance(20) => Literal::Integer(-1 - random.below(1_000_000) as i64),
+            2 => Literal::Integer(random.below(1_000_000) as i64),
+            3 if random.chance(10) => Literal::Float(f64::NEG_INFINITY),
+            3 => Literal::Float(random.below(1_000_000) as f64 / 1000.0),
+            4 => Literal::Blob(
+                (0..random.below(4))
+                    .map(|_| random.below(256) as u8)
+                    .collect(),
+            ),
+            _ => Literal::Boolean(random.chance(50)),
+        }
+    }
+
+    fn random_column_ref(random: &mut Random) -> ColumnRef {
+        let table = random.chance(30).then(|| random.pick(NAMES).to_string());
+        ColumnRef {
+            schema: table
+                .is_some()
+                .then(|| random.chance(30).then(|| random.pick(NAMES).to_string()))
+                .flatten(),
+            table,
+            name: random.pick(NAMES).to_string(),
+        }
+    }
+
+    const TYPE_NAMES: &[&str] = &[
+        "INTEGER",
+        "varchar",
+        "unsigned big int",
+        "key",
+        "from",
+        "a b",
+    ];
+
+    const PATTERN_OPERATORS: &[PatternOperator] = &[
+        PatternOperator::Like,
+        PatternOperator::Glob,
+        PatternOperator::Regexp,
+        PatternOperator::Match,
+    ];
+
+    /// Generates a tree without `Nested` nodes, the parentheses are up to the renderer
+    fn random_expression(random: &mut Random, depth: usize) -> Expression {
+        let boxed = |random: &mut Random| Box::new(random_expression(random, depth - 1));
+        let list = |random: &mut Random, min: usize| -> Vec<Expression> {
+            (0..min + random.below(3))
+                .map(|_| random_expression(random, depth - 1))
+                .collect()
+        };
+
+        if depth >= 3 && random.chance(5) {
+            let subquery = Box::new(random_select(random, depth - 2));
+            return match random.below(3) {
+                0 => Expression::Subquery(subquery),
+                1 => Expression::Exists(subquery),
+                _ => Expression::InSubquery {
+                    expression: boxed(random),
+                    negated: random.chance(50),
+                    subquery,
+                },
+            };
+        }
+
+        match random.below(if depth == 0 { 2 } else { 13 }) {
+            0 => Expression::Literal(random_literal(random)),
+            1 => Expression::Column(random_column_ref(random)),
+            2 if random.chance(20) => {
+                let name = *random.pick(NAMES);
+                Expression::function(name, vec![Expression::Wildcard(None)])
+            }
+            2 => {
+                let arguments = list(random, 0);
+                Expression::Function {
+                    name: random.pick(NAMES).to_string(),
+                    distinct: !arguments.is_empty() && random.chance(20),
+                    arguments,
+                    filter: random.chance(20).then(|| boxed(random)),
+                }
+            }
+            3 => Expression::Collate {
+                expression: boxed(random),
+                collation: random.pick(NAMES).to_string(),
+            },
+            4 => Expression::unary(
+                *random.pick(UNARY_OPERATORS),
+                random_expression(random, depth - 1),
+            ),
+            5 => Expression::Like {
+                expression: boxed(random),
+                negated: random.chance(50),
+                operator: *random.pick(PATTERN_OPERATORS),
+                pattern: boxed(random),
+                escape: random.chance(30).then(|| boxed(random)),
+            },
+            6 => Expression::Between {
+                expression: boxed(random),
+                negated: random.chance(50),
+                low: boxed(random),
+                high: boxed(random),
+            },
+            7 => Expression::InList {
+                expression: boxed(random),
+                negated: random.chance(50),
+                list: list(random, 0),
+            },
+            8 => Expression::IsNull {
+                expression: boxed(random),
+                negated: random.chance(50),
+            },
+            9 => Expression::IsDistinctFrom {
+                left: boxed(random),
+                negated: random.chance(50),
+                right: boxed(random),
+            },
+            10 => Expression::Case {
+                operand: random.chance(50).then(|| boxed(random)),
+                branches: (0..1 + random.below(2))
+                    .map(|_| {
+                        (
+                            random_expression(random, depth - 1),
+                            random_expression(random, depth - 1),
+                        )
+                    })
+                    .collect(),
+                else_result: random.chance(50).then(|| boxed(random)),
+            },
+            11 => Expression::Cast {
+                expression: boxed(random),
+                data_type: TypeName {
+                    name: random.pick(TYPE_NAMES).to_string(),
+                    arguments: (0..random.below(3))
+                        .map(|_| random.pick(&["255", "-1", "+2.5"]).to_string())
+                        .collect(),
+                },
+            },
+            _ => Expression::binary(
+                random_expression(random, depth - 1),
+                *random.pick(BINARY_OPERATORS),
+                random_expression(random, depth - 1),
+            ),
+        }
+    }
+
+    fn strip_nested(expression: Expression) -> Expression {
+        let strip = |expression: Box<Expression>| Box::new(strip_nested(*expression));
+        let strip_all = |list: Vec<Expression>| list.into_iter().map(strip_nested).collect();
+        match expression {
+            Expression::Nested(expression) => strip_nested(*expression),
+            Expression::Unary { operator, operand } => {
+                Expression::unary(operator, strip_nested(*operand))
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => Expression::binary(strip_nested(*left), operator, strip_nested(*right)),
+            Expression::Collate {
+                expression,
+                collation,
+            } => Expression::Collate {
+                expression: strip(expression),
+                collation,
+            },
+            Expression::Function {
+                name,
+                arguments,
+                distinct,
+                filter,
+            } => Expression::Function {
+                name,
+                arguments: strip_all(arguments),
+                distinct,
+                filter: filter.map(strip),
+            },
+            Expression::Like {
+                expression,
+                negated,
+                operator,
+                pattern,
+                escape,
+            } => Expression::Like {
+                expression: strip(expression),
+                negated,
+                operator,
+                pattern: strip(pattern),
+                escape: escape.map(strip),
+            },
+            Expression::Between {
+                expression,
+                negated,
+                low,
+                high,
+            } => Expression::Between {
+                expression: strip(expression),
+                negated,
+                low: strip(low),
+                high: strip(high),
+            },
+            Expression::InList {
+                expression,
+                negated,
+                list,
+            } => Expression::InList {
+                expression: strip(expression),
+                negated,
+                list: strip_all(list),
+            },
+            Expression::IsNull {
+                expression,
+                negated,
+            } => Expression::IsNull {
+                expression: strip(expression),
+                negated,
+            },
+            Expression::IsDistinctFrom {
+                left,
+                negated,
+                right,
+            } => Expression::IsDistinctFrom {
+                left: strip(left),
+                negated,
+                right: strip(right),
+            },
+            Expression::Case {
+                operand,
+                branches,
+                else_result,
+            } => Expression::Case {
+                operand: operand.map(strip),
+                branches: branches
+                    .into_iter()
+                    .map(|(condition, result)| (strip_nested(condition), strip_nested(result)))
+                    .collect(),
+                else_result: else_result.map(strip),
+            },
+            Expression::Cast {
+                expression,
+                data_type,
+            } => Expression::Cast {
+                expression: strip(expression),
+                data_type,
+            },
+            Expression::InSubquery {
+                expression,
+                negated,
+                mut subquery,
+            } => {
+                strip_nested_select(&mut subquery);
+                Expression::InSubquery {
+                    expression: strip(expression),
+                    negated,
+                    subquery,
+                }
+            }
+            Expression::Subquery(mut subquery) => {
+                strip_nested_select(&mut subquery);
+                Expression::Subquery(subquery)
+            }
+            Expression::Exists(mut subquery) => {
+                strip_nested_select(&mut subquery);
+                Expression::Exists(subquery)
+            }
+            expression => expression,
+        }
+    }
+
+    fn random_column(random: &mut Random, depth: usize) -> ColumnLiteral {
+        ColumnLiteral {
+            expression: random_expression(random, depth),
+            alias: random.chance(40).then(|| random.pick(NAMES).to_string()),
+        }
+    }
+
+    /// Every table gets a name or alias unique within the tree, like the parser requires
+    fn random_table(random: &mut Random, tables: &mut usize, depth: usize) -> TableExpression {
+        *tables += 1;
+        let unique = format!("{}{tables}", random.pick(NAMES));
+        if depth >= 3 && random.chance(10) {
+            return TableExpression::Subquery {
+                query: Box::new(random_select(random, depth - 2)),
+                alias: random.chance(80).then_some(unique),
+            };
+        }
+        let (name, alias) = match random.chance(50) {
+            true => (random.pick(NAMES).to_string(), Some(unique)),
+            false => (unique, None),
+        };
+        TableExpression::Table {
+            schema: random.chance(20).then(|| random.pick(NAMES).to_string()),
+            name,
+            alias,
+        }
+    }
+
+    fn random_from(random: &mut Random, tables: &mut usize, depth: usize) -> TableExpression {
+        let mut from = random_table(random, tables, depth);
+        for _ in 0..random.below(4) {
+            let right = match depth > 1 && random.chance(15) {
+                true => random_from(random, tables, depth - 1),
+                false => random_table(random, tables, depth),
+            };
+            let kind = *random.pick(&[
+                JoinKind::Comma,
+                JoinKind::Inner,
+                JoinKind::Left,
+                JoinKind::Right,
+                JoinKind::Full,
+                JoinKind::Cross,
+            ]);
+            let natural = kind != JoinKind::Comma && kind != JoinKind::Cross && random.chance(20);
+            let constraint = match natural || kind == JoinKind::Comma || random.chance(30) {
+                true => None,
+                false if random.chance(70) => {
+                    Some(JoinConstraint::On(random_expression(random, depth - 1)))
+                }
+                false => Some(JoinConstraint::Using(
+                    (0..=random.below(2))
+                        .map(|_| random.pick(NAMES).to_string())
+                        .collect(),
+                )),
+            };
+            from = TableExpression::Join {
+                left: Box::new(from),
+                operator: JoinOperator { natural, kind },
+                right: Box::new(right),
+                constraint,
+            };
+        }
+        from
+    }
+
+    fn strip_nested_from(from: &mut TableExpression) {
+        match from {
+            TableExpression::Table { .. } => {}
+            TableExpression::Subquery { query, .. } => strip_nested_select(query),
+            TableExpression::Join {
+                left,
+                right,
+                constraint,
+                ..
+            } => {
+                strip_nested_from(left);
+                strip_nested_from(right);
+                if let Some(JoinConstraint::On(expression)) = constraint {
+                    *expression = strip_nested(expression.clone());
+                }
+            }
+        }
+    }
+
+    fn strip_nested_select(select: &mut Select) {
+        if let Some(with) = &mut select.with {
+            for table in &mut with.tables {
+                strip_nested_select(&mut table.query);
+            }
+        }
+        for column in &mut select.columns {
+            column.expression = strip_nested(column.expression.clone());
+        }
+        if let Some(from) = &mut select.from {
+            strip_nested_from(from);
+        }
+        select.where_clause = select.where_clause.take().map(strip_nested);
+        if let Some(group_by) = &mut select.group_by {
+            for expression in group_by {
+                *expression = strip_nested(expression.clone());
+            }
+        }
+        select.having = select.having.take().map(strip_nested);
+        for (_, core) in &mut select.compound {
+            strip_nested_select(core);
+        }
+        if let Some(order_by) = &mut select.order_by {
+            for term in order_by {
+                term.expression = strip_nested(term.expression.clone());
+            }
+        }
+        if let Some(limit) = &mut select.limit {
+            limit.count = strip_nested(limit.count.clone());
+            limit.offset = limit.offset.take().map(strip_nested);
+        }
+    }
+
+    /// A core with `count` columns, or a wildcard. Expressions in it are at most `depth` deep,
+    /// subqueries included
+    fn random_select_core(random: &mut Random, depth: usize, count: usize) -> Select {
+        let columns = if random.chance(10) {
+            vec![ColumnLiteral::from_expression(Expression::Wildcard(
+                random.chance(50).then(|| random.pick(NAMES).to_string()),
+            ))]
+        } else {
+            (0..count).map(|_| random_column(random, depth)).collect()
+        };
+        let mut tables = 0;
+        Select {
+            with: None,
+            columns,
+            from: random
+                .chance(80)
+                .then(|| random_from(random, &mut tables, depth)),
+            where_clause: random.chance(50).then(|| random_expression(random, depth)),
+            group_by: random.chance(30).then(|| {
+                (0..=random.below(2))
+                    .map(|_| random_expression(random, depth - 1))
+                    .collect()
+            }),
+            having: random.chance(30).then(|| random_expression(random, depth)),
+            compound: vec![],
+            order_by: None,
+            limit: None,
+            distinct: random.chance(30),
+        }
+    }
+
+    fn random_select(random: &mut Random, depth: usize) -> Select {
+        let count = 1 + random.below(4);
+        let mut select = random_select_core(random, depth, count);
+        if depth >= 3 && random.chance(10) {
+            let mut tables: Vec<CommonTableExpression> = vec![];
+            for index in 0..=random.below(2) {
+                let columns = random.below(3);
+                tables.push(CommonTableExpression {
+                    name: format!("{}{index}", random.pick(NAMES)),
+                    columns: (0..columns)
+                        .map(|_| random.pick(NAMES).to_string())
+                        .collect(),
+                    materialized: *random.pick(&[None, Some(true), Some(false)]),
+                    query: Box::new(random_select(random, depth - 2)),
+                });
+            }
+            select.with = Some(With {
+                recursive: random.chance(50),
+                tables,
+            });
+        }
+        if random.chance(20) {
+            select.compound = (0..=random.below(2))
+                .map(|_| {
+                    let operator = *random.pick(&[
+                        CompoundOperator::Union,
+                        CompoundOperator::UnionAll,
+                        CompoundOperator::Intersect,
+                        CompoundOperator::Except,
+                    ]);
+                    (operator, random_select_core(random, depth, count))
+                })
+                .collect();
+        }
+        select.order_by = random.chance(30).then(|| {
+            (0..=random.below(2))
+                .map(|_| OrderingTerm {
+                    expression: random_expression(random, depth - 1),
+                    ordering: *random.pick(&[Ordering::Ascending, Ordering::Descending]),
+                    nulls: random
+                        .chance(30)
+                        .then(|| *random.pick(&[NullsOrdering::First, NullsOrdering::Last])),
+                })
+                .collect()
+        });
+        select.limit = random.chance(30).then(|| Limit {
+            count: random_expression(random, depth.saturating_sub(2)),
+            offset: random
+                .chance(50)
+                .then(|| random_expression(random, depth.saturating_sub(2))),
+        });
+        select
+    }
+
+    fn parse(sql: &str) -> Ast {
+        Parser::new(sql)
+            .parse()
+            .unwrap_or_else(|err| panic!("Unable to parse `{sql}`: {err}"))
+    }
+
+    fn assert_round_trip(ast: &Ast) {
+        let sql = ast.to_string();
+        let mut parsed = parse(&sql);
+        match &mut parsed {
+            Ast::Select(select) => strip_nested_select(select),
+            Ast::Delete(delete) => {
+                delete.where_clause = delete.where_clause.take().map(strip_nested);
+            }
+            Ast::Update(update) => {
+                for assignment in &mut update.set_clause {
+                    for value in &mut assignment.values {
+                        *value = strip_nested(value.clone());
+                    }
+                }
+                update.where_clause = update.where_clause.take().map(strip_nested);
+            }
+            _ => {}
+        }
+        assert_eq!(&parsed, ast, "Round trip of `{sql}`");
+    }
+
+    #[test]
+    fn select_round_trip() {
+        let mut random = Random::new(0x5eed);
+        for _ in 0..2000 {
+            assert_round_trip(&Ast::Select(random_select(&mut random, 4)));
+        }
+    }
+
+    #[test]
+    fn delete_and_update_round_trip() {
+        let mut random = Random::new(0xde1e7e);
+        for _ in 0..1000 {
+            assert_round_trip(&Ast::Delete(Delete {
+                schema: None,
+                table_name: random.pick(NAMES).to_string(),
+                where_clause: random.chance(70).then(|| random_expression(&mut random, 4)),
+                returning: vec![],
+                order_by: None,
+                limit: None,
+            }));
+            assert_round_trip(&Ast::Update(Update {
+                conflict: None,
+                schema: None,
+                table_name: random.pick(NAMES).to_string(),
+                set_clause: (0..=random.below(3))
+                    .map(|_| {
+                        let columns = 1 + random.below(3);
+                        Assignment {
+                            columns: (0..columns)
+                                .map(|_| random.pick(NAMES).to_string())
+                                .collect(),
+                            values: (0..columns)
+                                .map(|_| random_expression(&mut random, 4))
+                                .collect(),
+                        }
+                    })
+                    .collect(),
+                from: None,
+                where_clause: random.chance(70).then(|| random_expression(&mut random, 4)),
+                returning: vec![],
+                order_by: None,
+                limit: None,
+            }));
+        }
+    }
+
+    #[test]
+    fn expression_round_trip() {
+        let mut random = Random::new(0xe8e8);
+        for _ in 0..2000 {
+            let expression = random_expression(&mut random, 6);
+            let sql = format!("SELECT {expression}");
+            // Printing what was parsed keeps the explicit parentheses
+            let printed = parse(&sql).to_string();
+            assert_eq!(parse(&printed).to_string(), printed, "Reprinting `{sql}`");
+        }
+    }
+
+    #[test]
+    fn expression_rendering() {
+        let cases = [
+            ("select 1 + 2 * 3", "SELECT 1 + 2 * 3"),
+            ("select (1 + 2) * 3", "SELECT (1 + 2) * 3"),
+            ("select 1 - (2 - 3)", "SELECT 1 - (2 - 3)"),
+            ("select - - 1", "SELECT - -1"),
+            ("select not a = b", "SELECT NOT a = b"),
+            ("select a == b <> c", "SELECT a = b != c"),
+            ("select 'a' || \"b c\".d", "SELECT 'a' || \"b c\".d"),
+            ("select 1.5e3, 2., 0.5", "SELECT 1500.0, 2.0, 0.5"),
+            (
+                "select - -9223372036854775808, 0x10, x'0aff'",
+                "SELECT - -9223372036854775808, 16, X'0AFF'",
+            ),
+            (
+                "select 0xffffffffffffffff, -1, -9e999, - 9e999",
+                "SELECT 0xffffffffffffffff, -1, -9e999, -9e999",
+            ),
+            (
+                "select -a collate x, -(a collate x), (not a) collate x",
+                "SELECT -a COLLATE x, -(a COLLATE x), (NOT a) COLLATE x",
+            ),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(parse(sql).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn select_rendering() {
+        let sql = r#"select distinct users.col1, 'it''s' as "some id", 2 AS key from users"#;
+        let ast = Parser::new(sql)
+            .parse()
+            .expect("Expected valid select statement");
+        assert_eq!(
+            ast.to_string(),
+            r#"SELECT DISTINCT users.col1, 'it''s' AS "some id", 2 AS "key" FROM users"#
+        );
+
+        let sql = "select * from main.movies m natural left outer join (genres, countries c) \
+                   inner join studios using (id) cross join x on m.id = x.id";
+        let ast = Parser::new(sql)
+            .parse()
+            .expect("Expected valid select statement");
+        assert_eq!(
+            ast.to_string(),
+            "SELECT * FROM main.movies AS m NATURAL LEFT JOIN (genres, countries AS c) \
+             JOIN studios USING (id) CROSS JOIN x ON m.id = x.id"
+        );
+
+        let sql = "select (select 1) x, not exists (select * from t) \
+                   from (select a from b) c where a not in (select 1)";
+        let ast = Parser::new(sql)
+            .parse()
+            .expect("Expected valid select statement");
+        assert_eq!(
+            ast.to_string(),
+            "SELECT (SELECT 1) AS x, NOT EXISTS (SELECT * FROM t) \
+             FROM (SELECT a FROM b) AS c WHERE a NOT IN (SELECT 1)"
+        );
+
+        let sql = "select a from b union all select 1 except select (select 2 union select 3) \
+                   order by 1 limit 2";
+        let ast = Parser::new(sql)
+            .parse()
+            .expect("Expected valid select statement");
+        assert_eq!(
+            ast.to_string(),
+            "SELECT a FROM b UNION ALL SELECT 1 EXCEPT SELECT (SELECT 2 UNION SELECT 3) \
+             ORDER BY 1 LIMIT 2"
+        );
+
+        let sql =
+            "with recursive t(n) as not materialized (select 1 union all select n + 1 from t), \
+                   \"select\" as (select 1) select * from t limit 3";
+        let ast = Parser::new(sql)
+            .parse()
+            .expect("Expected valid select statement");
+        assert_eq!(
+            ast.to_string(),
+            "WITH RECURSIVE t(n) AS NOT MATERIALIZED (SELECT 1 UNION ALL SELECT n + 1 FROM t), \
+             \"select\" AS (SELECT 1) SELECT * FROM t LIMIT 3"
+        );
+    }
+
+    #[test]
+    fn statements_rendering() {
+        let drop = Ast::Drop(DropObject {
+            kind: ObjectKind::Table,
+            if_exists: true,
+            schema: None,
+            name: "movies".into(),
+        });
+        assert_eq!(drop.to_string(), "DROP TABLE IF EXISTS movies");
+
+        let drop = Ast::Drop(DropObject {
+            kind: ObjectKind::Trigger,
+            if_exists: false,
+            schema: Some("main".into()),
+            name: "index".into(),
+        });
+        assert_eq!(drop.to_string(), r#"DROP TRIGGER main."index""#);
+
+        let create_table = Ast::CreateTable(CreateTable {
+            temporary: false,
+            if_not_exists: false,
+            schema: None,
+            table_name: "select".into(),
+            definition: TableDefinition::Columns {
+                columns: vec![ColumnDefinition {
+                    name: "id".into(),
+                    type_name: Some(TypeName {
+                        name: "INTEGER".into(),
+                        arguments: vec![],
+                    }),
+                    constraints: vec![Constraint {
+                        name: None,
+                        kind: ColumnConstraintKind::NotNull(Some(ConflictResolution::Abort)),
+                    }],
+                }],
+                constraints: vec![Constraint {
+                    name: Some("pk".into()),
+                    kind: TableConstraintKind::PrimaryKey {
+                        columns: vec![IndexedColumn {
+                            expression: Expression::Column(ColumnRef::new("id")),
+                            ordering: Some(Ordering::Descending),
+                        }],
+                        conflict: None,
+                    },
+                }],
+                without_rowid: true,
+                strict: false,
+            },
+        });
+        assert_eq!(
+            create_table.to_string(),
+            r#"CREATE TABLE "select" (id INTEGER NOT NULL ON CONFLICT ABORT, CONSTRAINT pk PRIMARY KEY (id DESC)) WITHOUT ROWID"#
+        );
+
+        let create_index = Ast::CreateIndex(CreateIndex {
+            unique: true,
+            if_not_exists: true,
+            schema: Some("main".into()),
+            name: "by_title".into(),
+            table_name: "movies".into(),
+            columns: vec![
+                IndexedColumn {
+                    expression: Expression::Collate {
+                        expression: Box::new(Expression::Column(ColumnRef::new("title"))),
+                        collation: "NOCASE".into(),
+                    },
+                    ordering: None,
+                },
+                IndexedColumn {
+                    expression: Expression::Column(ColumnRef::new("year")),
+                    ordering: Some(Ordering::Descending),
+                },
+            ],
+            where_clause: Some(Expression::Binary {
+                left: Box::new(Expression::Column(ColumnRef::new("year"))),
+                operator: BinaryOperator::GreaterThan,
+                right: Box::new(Expression::Literal(Literal::Integer(2000))),
+            }),
+        });
+        assert_eq!(
+            create_index.to_string(),
+            "CREATE UNIQUE INDEX IF NOT EXISTS main.by_title ON movies (title COLLATE NOCASE, year DESC) WHERE year > 2000"
+        );
+
+        let insert = Ast::Insert(Insert {
+            conflict: None,
+            schema: None,
+            table_name: "movies".into(),
+            columns: vec!["id".into(), "title".into()],
+            source: InsertSource::Values(vec![
+                vec![
+                    Expression::Literal(Literal::Integer(1)),
+                    Expression::Literal(Literal::String("it's".into())),
+                ],
+                vec![
+                    Expression::Literal(Literal::Float(2.5)),
+                    Expression::Literal(Literal::Null),
+                ],
+            ]),
+            upsert: vec![],
+            returning: vec![],
+        });
+        assert_eq!(
+            insert.to_string(),
+            "INSERT INTO movies (id, title) VALUES (1, 'it''s'), (2.5, NULL)"
+        );
+
+        let insert = Ast::Insert(Insert {
+            conflict: Some(ConflictResolution::Ignore),
+            schema: Some("main".into()),
+            table_name: "movies".into(),
+            columns: vec![],
+            source: InsertSource::Values(vec![vec![Expression::Literal(Literal::Integer(1))]]),
+            upsert: vec![Upsert {
+                target: vec![IndexedColumn {
+                    expression: Expression::Column(ColumnRef::new("id")),
+                    ordering: None,
+                }],
+                target_where: None,
+                action: UpsertAction::Update {
+                    set_clause: vec![Assignment {
+                        columns: vec!["title".into()],
+                        values: vec![Expression::Column(ColumnRef::qualified(
+                            "excluded", "title",
+                        ))],
+                    }],
+                    where_clause: None,
+                },
+            }],
+            returning: vec![ColumnLiteral::from_expression(Expression::Wildcard(None))],
+        });
+        assert_eq!(
+            insert.to_string(),
+            "INSERT OR IGNORE INTO main.movies VALUES (1) ON CONFLICT (id) DO UPDATE SET title = excluded.title RETURNING *"
+        );
+
+        let update = Ast::Update(Update {
+            conflict: None,
+            schema: None,
+            table_name: "movies".into(),
+            set_clause: vec![Assignment {
+                columns: vec!["ultrahd".into(), "rating".into()],
+                values: vec![
+                    Expression::Literal(Literal::Boolean(true)),
+                    Expression::Literal(Literal::Integer(5)),
+                ],
+            }],
+            from: None,
+            where_clause: Some(Expression::Literal(Literal::Boolean(false))),
+            returning: vec![],
+            order_by: None,
+            limit: None,
+        });
+        assert_eq!(
+            update.to_string(),
+            "UPDATE movies SET (ultrahd, rating) = (TRUE, 5) WHERE FALSE"
+        );
+
+        let delete = Ast::Explain(Box::new(Ast::Delete(Delete {
+            schema: None,
+            table_name: "order".into(),
+            where_clause: None,
+            returning: vec![],
+            order_by: None,
+            limit: None,
+        })));
+        assert_eq!(delete.to_string(), r#"EXPLAIN DELETE FROM "order""#);
+    }
 }
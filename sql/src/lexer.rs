@@ -1,20 +1,24 @@
-use std::{iter::Peekable, str::Chars};
+use std::borrow::Cow;
+use std::ops::Range;
 
 use crate::Token;
 use common::errors::ParsingError;
 
-#[derive(Debug)]
+/// Splits an sql string into tokens. Tokens borrow their text from the input, so scanning
+/// does not allocate, except for string literals containing escaped quotes.
+#[derive(Debug, Clone)]
 pub struct Lexer<'a> {
-    iter: Peekable<Chars<'a>>,
+    input: &'a str,
+    position: usize,
 }
 
 impl<'a> Iterator for Lexer<'a> {
-    type Item = Result<Token, ParsingError>;
+    type Item = Result<Token<'a>, ParsingError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.scan() {
             Ok(Some(token)) => Some(Ok(token)),
-            Ok(None) => self.iter.peek().map(|c| {
+            Ok(None) => self.next_char().map(|c| {
                 Err(ParsingError::UnexpectedToken(format!(
                     "Unexpected token {}",
                     c
@@ -27,102 +31,199 @@ impl<'a> Iterator for Lexer<'a> {
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
-        Self {
-            iter: input.chars().peekable(),
-        }
+        Self { input, position: 0 }
     }
 
-    pub fn scan(&mut self) -> Result<Option<Token>, ParsingError> {
+    pub fn scan(&mut self) -> Result<Option<Token<'a>>, ParsingError> {
         self.consume_whitespaces();
 
-        match self.iter.peek() {
-            Some(c) if *c == '"' => self.scan_string('"'),
-            Some(c) if *c == '\'' => self.scan_string('\''),
+        match self.peek() {
+            Some('\'') => self.scan_string('\'', '\''),
+            Some('"') => self.scan_quoted_identifier('"', '"'),
+            Some('`') => self.scan_quoted_identifier('`', '`'),
+            Some('[') => self.scan_quoted_identifier('[', ']'),
+            Some('x' | 'X') if self.peek_nth(1) == Some('\'') => self.scan_blob(),
             Some(c) if c.is_ascii_digit() => Ok(self.scan_number()),
-            Some(c) if c.is_alphabetic() => Ok(self.scan_ident()),
+            Some('.') if self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) => {
+                Ok(self.scan_number())
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => Ok(self.scan_ident()),
             Some(_) => Ok(self.scan_symbol()),
             None => Ok(None),
         }
     }
 
-    fn next_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<String> {
-        let mut value = String::new();
-        while let Some(c) = self.next_if(&predicate) {
-            value.push(c)
+    /// Scans the next token along with the byte range of its text in the input, to edit the
+    /// input around it
+    pub fn scan_spanned(&mut self) -> Result<Option<(Range<usize>, Token<'a>)>, ParsingError> {
+        self.consume_whitespaces();
+        let start = self.position;
+        match self.scan()? {
+            Some(token) => Ok(Some((start..self.position, token))),
+            None => match self.next_char() {
+                Some(c) => Err(ParsingError::UnexpectedToken(format!(
+                    "Unexpected token {c}"
+                ))),
+                None => Ok(None),
+            },
         }
-        Some(value).filter(|v| !v.is_empty())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.position..].chars().nth(n)
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn next_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<&'a str> {
+        let start = self.position;
+        while self.next_if(&predicate).is_some() {}
+        Some(&self.input[start..self.position]).filter(|v| !v.is_empty())
     }
 
     fn next_if<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<char> {
-        self.iter.peek().filter(|&c| predicate(*c))?;
-        self.iter.next()
+        self.peek().filter(|&c| predicate(c))?;
+        self.next_char()
     }
 
-    fn next_if_token<F: Fn(char) -> Option<Token>>(&mut self, tokenizer: F) -> Option<Token> {
-        let token = self.iter.peek().and_then(|&c| tokenizer(c))?;
-        self.iter.next();
+    fn next_if_token<F: Fn(char) -> Option<Token<'a>>>(
+        &mut self,
+        tokenizer: F,
+    ) -> Option<Token<'a>> {
+        let token = self.peek().and_then(tokenizer)?;
+        self.next_char();
         Some(token)
     }
 
+    /// Skips the whitespace and the comments before the next token
     fn consume_whitespaces(&mut self) {
-        self.next_while(|c| c.is_whitespace());
+        loop {
+            self.next_while(|c| c.is_whitespace());
+            let rest = &self.input[self.position..];
+            if rest.starts_with("--") {
+                self.position += rest.find('\n').unwrap_or(rest.len());
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                // Like in SQLite, an unterminated comment runs to the end of the input
+                self.position += comment.find("*/").map_or(rest.len(), |end| end + 4);
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn scan_string(
+        &mut self,
+        opening: char,
+        closing: char,
+    ) -> Result<Option<Token<'a>>, ParsingError> {
+        Ok(self.scan_quoted(opening, closing)?.map(Token::String))
+    }
+
+    fn scan_quoted_identifier(
+        &mut self,
+        opening: char,
+        closing: char,
+    ) -> Result<Option<Token<'a>>, ParsingError> {
+        Ok(self
+            .scan_quoted(opening, closing)?
+            .map(Token::QuotedIdentifier))
     }
 
-    fn scan_string(&mut self, opening: char) -> Result<Option<Token>, ParsingError> {
+    /// Scans the text between the quotes, a doubled closing quote stands for itself unless
+    /// the quotes are brackets
+    fn scan_quoted(
+        &mut self,
+        opening: char,
+        closing: char,
+    ) -> Result<Option<Cow<'a, str>>, ParsingError> {
         if self.next_if(|c| c == opening).is_none() {
             return Ok(None);
         }
 
-        let mut result = String::new();
+        let start = self.position;
+        let mut escaped = false;
         loop {
-            match self.iter.next() {
-                Some(c) if c == opening => {
-                    if let Some(c) = self.next_if(|c| c == opening) {
-                        result.push(c)
+            match self.next_char() {
+                Some(c) if c == closing => {
+                    if opening == closing && self.next_if(|c| c == closing).is_some() {
+                        escaped = true;
                     } else {
                         break;
                     }
                 }
-                Some(c) => result.push(c),
+                Some(_) => {}
                 None => {
                     return Err(ParsingError::UnexpectedEOF);
                 }
             }
         }
 
-        Ok(Some(Token::String(result)))
+        let raw = &self.input[start..self.position - closing.len_utf8()];
+        if escaped {
+            let mut quote = [0; 4];
+            let quote = closing.encode_utf8(&mut quote);
+            Ok(Some(Cow::Owned(raw.replace(&quote.repeat(2), quote))))
+        } else {
+            Ok(Some(Cow::Borrowed(raw)))
+        }
     }
 
-    fn scan_number(&mut self) -> Option<Token> {
-        let mut num = self.next_while(|c| c.is_ascii_digit())?;
+    /// Scans a blob literal, an even number of hex digits quoted after an `x`
+    fn scan_blob(&mut self) -> Result<Option<Token<'a>>, ParsingError> {
+        let start = self.position;
+        self.next_char();
+        self.scan_quoted('\'', '\'')?;
+        let literal = &self.input[start..self.position];
+        let digits = &literal[2..literal.len() - 1];
+        if !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParsingError::UnexpectedToken(format!(
+                "Malformed blob literal {literal}"
+            )));
+        }
+        Ok(Some(Token::Blob(digits)))
+    }
 
-        if let Some(sep) = self.next_if(|c| c == '.') {
-            num.push(sep);
-            while let Some(dec) = self.next_if(|c| c.is_ascii_digit()) {
-                num.push(dec)
-            }
+    /// Scans a decimal number, which may start with its `.`, or a `0x` hex integer
+    fn scan_number(&mut self) -> Option<Token<'a>> {
+        let start = self.position;
+        let rest = &self.input[start..];
+        let hex = rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X"));
+        if hex.is_some_and(|digits| digits.starts_with(|c: char| c.is_ascii_hexdigit())) {
+            self.position += 2;
+            self.next_while(|c| c.is_ascii_hexdigit());
+            return Some(Token::Number(&self.input[start..self.position]));
         }
-        if let Some(exp) = self.next_if(|c| c == 'e' || c == 'E') {
-            num.push(exp);
-            if let Some(sign) = self.next_if(|c| c == '+' || c == '-') {
-                num.push(sign)
-            }
-            while let Some(c) = self.next_if(|c| c.is_ascii_digit()) {
-                num.push(c)
-            }
+
+        if self.next_while(|c| c.is_ascii_digit()).is_none() && self.peek() != Some('.') {
+            return None;
         }
-        Some(Token::Number(num))
-    }
 
-    fn scan_ident(&mut self) -> Option<Token> {
-        let mut name = self.next_if(|c| c.is_alphabetic())?.to_string();
-        while let Some(c) = self.next_if(|c| c.is_alphanumeric() || c == '_') {
-            name.push(c)
+        if self.next_if(|c| c == '.').is_some() {
+            self.next_while(|c| c.is_ascii_digit());
+        }
+        if self.next_if(|c| c == 'e' || c == 'E').is_some() {
+            self.next_if(|c| c == '+' || c == '-');
+            self.next_while(|c| c.is_ascii_digit());
         }
-        Some(Token::Identifier(name))
+        Some(Token::Number(&self.input[start..self.position]))
     }
 
-    fn scan_symbol(&mut self) -> Option<Token> {
+    fn scan_ident(&mut self) -> Option<Token<'a>> {
+        let start = self.position;
+        self.next_if(|c| c.is_alphabetic() || c == '_')?;
+        self.next_while(|c| c.is_alphanumeric() || c == '_');
+        Some(Token::Identifier(&self.input[start..self.position]))
+    }
+
+    fn scan_symbol(&mut self) -> Option<Token<'a>> {
         self.next_if_token(|c| match c {
             '*' => Some(Token::Asterisk),
             '.' => Some(Token::Period),
@@ -140,6 +241,8 @@ impl<'a> Lexer<'a> {
             ',' => Some(Token::Comma),
             ';' => Some(Token::SemiColon),
             '|' => Some(Token::Pipe),
+            '&' => Some(Token::Ampersand),
+            '~' => Some(Token::Tilde),
             _ => None,
         })
         .map(|token| match token {
@@ -147,7 +250,9 @@ impl<'a> Lexer<'a> {
             Token::Exclamation if self.next_if(|c| c == '=').is_some() => Token::NotEquals,
             Token::LessThan if self.next_if(|c| c == '>').is_some() => Token::NotEquals,
             Token::LessThan if self.next_if(|c| c == '=').is_some() => Token::LessOrEqual,
+            Token::LessThan if self.next_if(|c| c == '<').is_some() => Token::ShiftLeft,
             Token::GreaterThan if self.next_if(|c| c == '=').is_some() => Token::GreaterOrEqual,
+            Token::GreaterThan if self.next_if(|c| c == '>').is_some() => Token::ShiftRight,
             Token::Pipe if self.next_if(|c| c == '|').is_some() => Token::DoublePipe,
             _ => token,
         })
@@ -168,8 +273,8 @@ mod tests {
         assert_scan(
             r#"A "literal string with 'single' and ''double'' quotes inside 😀"."#,
             vec![
-                Token::Identifier("A".into()),
-                Token::String(
+                Token::Identifier("A"),
+                Token::QuotedIdentifier(
                     r#"literal string with 'single' and ''double'' quotes inside 😀"#.into(),
                 ),
                 Token::Period,
@@ -177,24 +282,179 @@ mod tests {
         );
     }
 
+    #[test]
+    fn literal_string_escaped_quotes() {
+        let mut lexer = Lexer::new("'plain' 'it''s' 'say \"hi\"'");
+        assert!(matches!(
+            lexer.next(),
+            Some(Ok(Token::String(Cow::Borrowed("plain"))))
+        ));
+        assert!(matches!(
+            lexer.next(),
+            Some(Ok(Token::String(Cow::Owned(s)))) if s == "it's"
+        ));
+        assert!(matches!(
+            lexer.next(),
+            Some(Ok(Token::String(Cow::Borrowed("say \"hi\""))))
+        ));
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn spans() {
+        let input = "SELECT /* a */ [x y], 'it''s'\n-- end";
+        let mut lexer = Lexer::new(input);
+        let mut spans = vec![];
+        while let Some((span, _)) = lexer.scan_spanned().unwrap() {
+            spans.push(&input[span]);
+        }
+        assert_eq!(spans, ["SELECT", "[x y]", ",", "'it''s'"]);
+        assert!(Lexer::new("a ?").scan_spanned().is_ok());
+        let mut lexer = Lexer::new("a \u{1}");
+        lexer.scan_spanned().unwrap();
+        assert!(lexer.scan_spanned().is_err());
+    }
+
+    #[test]
+    fn quoted_identifiers() {
+        assert_scan(
+            r#""some id" `it``s` [a "b"] "say ""hi""" [x]"#,
+            vec![
+                Token::QuotedIdentifier("some id".into()),
+                Token::QuotedIdentifier("it`s".into()),
+                Token::QuotedIdentifier(r#"a "b""#.into()),
+                Token::QuotedIdentifier(r#"say "hi""#.into()),
+                Token::QuotedIdentifier("x".into()),
+            ],
+        );
+    }
+
+    #[test]
+    fn literal_string_unterminated() {
+        let tokens: Vec<_> = Lexer::new("'abc").collect();
+        assert_eq!(tokens, vec![Err(ParsingError::UnexpectedEOF)]);
+    }
+
+    #[test]
+    fn unexpected_character() {
+        let tokens: Vec<_> = Lexer::new("a @ b").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Ok(Token::Identifier("a")),
+                Err(ParsingError::UnexpectedToken("Unexpected token @".into())),
+                Ok(Token::Identifier("b")),
+            ]
+        );
+    }
+
     #[test]
     fn literal_number() {
         assert_scan(
             "0 00 1 3.14 293. -2.718 3.14e3 2.718E-2",
             vec![
-                Token::Number("0".into()),
-                Token::Number("00".into()),
-                Token::Number("1".into()),
-                Token::Number("3.14".into()),
-                Token::Number("293.".into()),
+                Token::Number("0"),
+                Token::Number("00"),
+                Token::Number("1"),
+                Token::Number("3.14"),
+                Token::Number("293."),
                 Token::Minus,
-                Token::Number("2.718".into()),
-                Token::Number("3.14e3".into()),
-                Token::Number("2.718E-2".into()),
+                Token::Number("2.718"),
+                Token::Number("3.14e3"),
+                Token::Number("2.718E-2"),
             ],
         )
     }
 
+    #[test]
+    fn literal_number_forms() {
+        assert_scan(
+            ".5 1.e2 0x1F 0XfF 0x 0.x",
+            vec![
+                Token::Number(".5"),
+                Token::Number("1.e2"),
+                Token::Number("0x1F"),
+                Token::Number("0XfF"),
+                Token::Number("0"),
+                Token::Identifier("x"),
+                Token::Number("0."),
+                Token::Identifier("x"),
+            ],
+        );
+        assert_scan(
+            "t.a",
+            vec![
+                Token::Identifier("t"),
+                Token::Period,
+                Token::Identifier("a"),
+            ],
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_scan(
+            "_u a_1 __x_ é9",
+            vec![
+                Token::Identifier("_u"),
+                Token::Identifier("a_1"),
+                Token::Identifier("__x_"),
+                Token::Identifier("é9"),
+            ],
+        );
+    }
+
+    #[test]
+    fn literal_blob() {
+        assert_scan(
+            "x'00' X'aBcD' x''",
+            vec![Token::Blob("00"), Token::Blob("aBcD"), Token::Blob("")],
+        );
+        assert_scan(
+            "x 'a'",
+            vec![Token::Identifier("x"), Token::String("a".into())],
+        );
+        for malformed in ["x'0'", "x'0g'"] {
+            let tokens: Vec<_> = Lexer::new(malformed).collect();
+            assert_eq!(
+                tokens,
+                vec![Err(ParsingError::UnexpectedToken(format!(
+                    "Malformed blob literal {malformed}"
+                )))]
+            );
+        }
+        let tokens: Vec<_> = Lexer::new("x'00").collect();
+        assert_eq!(tokens, vec![Err(ParsingError::UnexpectedEOF)]);
+    }
+
+    #[test]
+    fn comments() {
+        assert_scan(
+            "SELECT a -- note\n, /* multi\nline */ b--",
+            vec![
+                Token::Identifier("SELECT"),
+                Token::Identifier("a"),
+                Token::Comma,
+                Token::Identifier("b"),
+            ],
+        );
+        assert_scan("a/* unterminated", vec![Token::Identifier("a")]);
+        assert_scan("/**/a/*/ */", vec![Token::Identifier("a")]);
+        assert_scan(
+            "1 - -2",
+            vec![
+                Token::Number("1"),
+                Token::Minus,
+                Token::Minus,
+                Token::Number("2"),
+            ],
+        );
+        assert_scan(
+            "4 / 2",
+            vec![Token::Number("4"), Token::Slash, Token::Number("2")],
+        );
+    }
+
     #[test]
     fn test_special_characters() {
         for (c, token) in [
@@ -211,7 +471,7 @@ mod tests {
             assert_scan(&input, vec![token.clone()]);
 
             let input = format!("{c}abc");
-            assert_scan(&input, vec![token, Token::Identifier("abc".into())]);
+            assert_scan(&input, vec![token, Token::Identifier("abc")]);
         }
     }
 
@@ -219,8 +479,8 @@ mod tests {
     fn test_space() {
         assert_scan(" ", vec![]);
         assert_scan("      ", vec![]);
-        assert_scan("a", vec![Token::Identifier("a".into())]);
-        assert_scan("     a", vec![Token::Identifier("a".into())]);
+        assert_scan("a", vec![Token::Identifier("a")]);
+        assert_scan("     a", vec![Token::Identifier("a")]);
 
         assert_scan("\t", vec![]);
         assert_scan("\n", vec![]);
@@ -255,6 +515,10 @@ mod tests {
             ("<=", Token::LessOrEqual),
             (">=", Token::GreaterOrEqual),
             ("||", Token::DoublePipe),
+            ("&", Token::Ampersand),
+            ("~", Token::Tilde),
+            ("<<", Token::ShiftLeft),
+            (">>", Token::ShiftRight),
         ] {
             assert_scan(s, vec![token]);
         }
@@ -270,63 +534,63 @@ mod tests {
             WHERE album.genre != 'country' AND album.release_year >= 1980
             ORDER BY artist.name ASC, age DESC",
             vec![
-                Identifier("SELECT".into()),
-                Identifier("artist".into()),
+                Identifier("SELECT"),
+                Identifier("artist"),
                 Period,
-                Identifier("name".into()),
+                Identifier("name"),
                 Comma,
-                Identifier("album".into()),
+                Identifier("album"),
                 Period,
-                Identifier("name".into()),
+                Identifier("name"),
                 Comma,
-                Identifier("EXTRACT".into()),
+                Identifier("EXTRACT"),
                 OpenParen,
-                Identifier("YEAR".into()),
-                Identifier("FROM".into()),
-                Identifier("NOW".into()),
+                Identifier("YEAR"),
+                Identifier("FROM"),
+                Identifier("NOW"),
                 OpenParen,
                 CloseParen,
                 CloseParen,
                 Minus,
-                Identifier("album".into()),
+                Identifier("album"),
                 Period,
-                Identifier("release_year".into()),
-                Identifier("AS".into()),
-                Identifier("age".into()),
-                Identifier("FROM".into()),
-                Identifier("artist".into()),
-                Identifier("INNER".into()),
-                Identifier("JOIN".into()),
-                Identifier("album".into()),
-                Identifier("ON".into()),
-                Identifier("album".into()),
+                Identifier("release_year"),
+                Identifier("AS"),
+                Identifier("age"),
+                Identifier("FROM"),
+                Identifier("artist"),
+                Identifier("INNER"),
+                Identifier("JOIN"),
+                Identifier("album"),
+                Identifier("ON"),
+                Identifier("album"),
                 Period,
-                Identifier("artist_id".into()),
+                Identifier("artist_id"),
                 Equals,
-                Identifier("artist".into()),
+                Identifier("artist"),
                 Period,
-                Identifier("id".into()),
-                Identifier("WHERE".into()),
-                Identifier("album".into()),
+                Identifier("id"),
+                Identifier("WHERE"),
+                Identifier("album"),
                 Period,
-                Identifier("genre".into()),
+                Identifier("genre"),
                 NotEquals,
                 String("country".into()),
-                Identifier("AND".into()),
-                Identifier("album".into()),
+                Identifier("AND"),
+                Identifier("album"),
                 Period,
-                Identifier("release_year".into()),
+                Identifier("release_year"),
                 GreaterOrEqual,
-                Number("1980".into()),
-                Identifier("ORDER".into()),
-                Identifier("BY".into()),
-                Identifier("artist".into()),
+                Number("1980"),
+                Identifier("ORDER"),
+                Identifier("BY"),
+                Identifier("artist"),
                 Period,
-                Identifier("name".into()),
-                Identifier("ASC".into()),
+                Identifier("name"),
+                Identifier("ASC"),
                 Comma,
-                Identifier("age".into()),
-                Identifier("DESC".into()),
+                Identifier("age"),
+                Identifier("DESC"),
             ],
         )
     }
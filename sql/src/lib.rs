@@ -1,8 +1,11 @@
-mod ast;
+pub mod ast;
 mod lexer;
 mod parser;
 mod token;
 
+#[cfg(test)]
+mod testing;
+
 pub use lexer::Lexer;
-pub use parser::Parser;
+pub use parser::{Parser, Statements};
 pub use token::Token;
@@ -0,0 +1,188 @@
+use crate::ast::{AlterTable, AlterTableAction, Ast};
+use crate::parser::create::CreateQueryParser;
+use crate::parser::Parser;
+use crate::token::Keyword;
+use common::errors::ParsingError;
+
+pub trait AlterQueryParser<'a> {
+    fn parse_alter(&mut self) -> Result<Ast, ParsingError>;
+}
+
+impl<'a> AlterQueryParser<'a> for Parser<'a> {
+    fn parse_alter(&mut self) -> Result<Ast, ParsingError> {
+        self.expect_keyword(Keyword::Alter)?;
+        self.expect_keyword(Keyword::Table)?;
+        let (schema, table_name) = self.parse_qualified_name()?;
+
+        let action = match self.current_keyword()? {
+            Some(Keyword::Rename) => {
+                self.eat()?;
+                if self.eat_keyword(Keyword::To)? {
+                    AlterTableAction::RenameTable(self.parse_identifier()?)
+                } else {
+                    self.eat_keyword(Keyword::Column)?;
+                    let old = self.parse_identifier()?;
+                    self.expect_keyword(Keyword::To)?;
+                    let new = self.parse_identifier()?;
+                    AlterTableAction::RenameColumn { old, new }
+                }
+            }
+            Some(Keyword::Add) => {
+                self.eat()?;
+                self.eat_keyword(Keyword::Column)?;
+                AlterTableAction::AddColumn(self.parse_column_definition()?)
+            }
+            Some(Keyword::Drop) => {
+                self.eat()?;
+                self.eat_keyword(Keyword::Column)?;
+                AlterTableAction::DropColumn(self.parse_identifier()?)
+            }
+            _ => return Err(self.unexpected()),
+        };
+
+        Ok(Ast::AlterTable(AlterTable {
+            schema,
+            table_name,
+            action,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{ColumnConstraintKind, ColumnDefinition, Constraint, Expression, Literal};
+
+    fn parse_query(query: &str) -> Result<AlterTable, ParsingError> {
+        match Parser::new(query).parse()? {
+            Ast::AlterTable(alter_table) => Ok(alter_table),
+            _ => Err(ParsingError::UnexpectedToken("Expected alter AST".into())),
+        }
+    }
+
+    fn alter(table_name: &str, action: AlterTableAction) -> AlterTable {
+        AlterTable {
+            schema: None,
+            table_name: table_name.into(),
+            action,
+        }
+    }
+
+    #[test]
+    fn test_alter_table_rename() {
+        assert_eq!(
+            parse_query("ALTER TABLE main.movies RENAME TO films"),
+            Ok(AlterTable {
+                schema: Some("main".into()),
+                table_name: "movies".into(),
+                action: AlterTableAction::RenameTable("films".into()),
+            })
+        );
+        for query in [
+            "ALTER TABLE movies RENAME COLUMN title TO name",
+            "alter table movies rename title to name",
+        ] {
+            assert_eq!(
+                parse_query(query),
+                Ok(alter(
+                    "movies",
+                    AlterTableAction::RenameColumn {
+                        old: "title".into(),
+                        new: "name".into(),
+                    }
+                )),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_alter_table_columns() {
+        for query in [
+            "ALTER TABLE movies ADD COLUMN rating REAL DEFAULT 0",
+            "ALTER TABLE movies ADD rating REAL DEFAULT 0",
+        ] {
+            let AlterTableAction::AddColumn(column) = parse_query(query).unwrap().action else {
+                panic!("{query}: expected ADD COLUMN");
+            };
+            assert_eq!(column.name, "rating", "{query}");
+            assert_eq!(
+                column.constraints,
+                vec![Constraint {
+                    name: None,
+                    kind: ColumnConstraintKind::Default(Expression::Literal(Literal::Integer(0))),
+                }],
+                "{query}"
+            );
+        }
+        assert_eq!(
+            parse_query("ALTER TABLE movies ADD COLUMN notes"),
+            Ok(alter(
+                "movies",
+                AlterTableAction::AddColumn(ColumnDefinition {
+                    name: "notes".into(),
+                    type_name: None,
+                    constraints: vec![],
+                })
+            ))
+        );
+        for query in [
+            "ALTER TABLE movies DROP COLUMN rating",
+            "ALTER TABLE movies DROP rating",
+        ] {
+            assert_eq!(
+                parse_query(query),
+                Ok(alter(
+                    "movies",
+                    AlterTableAction::DropColumn("rating".into())
+                )),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_alter_table_round_trip() {
+        for query in [
+            "ALTER TABLE main.movies RENAME TO \"old movies\"",
+            "ALTER TABLE movies RENAME COLUMN title TO name",
+            "ALTER TABLE movies ADD COLUMN rating REAL NOT NULL DEFAULT -2.5 CHECK (rating < 10)",
+            "ALTER TABLE movies DROP COLUMN \"select\"",
+        ] {
+            assert_eq!(parse_query(query).unwrap().to_string(), query);
+        }
+    }
+
+    #[test]
+    fn test_alter_table_malformed() {
+        for (query, error) in [
+            ("ALTER TABLE movies", ParsingError::UnexpectedEOF),
+            (
+                "ALTER movies RENAME TO films",
+                ParsingError::UnexpectedToken("movies".into()),
+            ),
+            (
+                "ALTER TABLE movies RENAME films",
+                ParsingError::UnexpectedEOF,
+            ),
+            (
+                "ALTER TABLE movies RENAME COLUMN a b",
+                ParsingError::UnexpectedToken("b".into()),
+            ),
+            (
+                "ALTER TABLE movies ADD CONSTRAINT c UNIQUE (a)",
+                ParsingError::UnexpectedKeyword("constraint".into()),
+            ),
+            (
+                "ALTER TABLE movies DROP CONSTRAINT c",
+                ParsingError::UnexpectedKeyword("constraint".into()),
+            ),
+            (
+                "ALTER TABLE movies SET title = 1",
+                ParsingError::UnexpectedKeyword("set".into()),
+            ),
+        ] {
+            assert_eq!(Parser::new(query).parse(), Err(error), "{query}");
+        }
+    }
+}
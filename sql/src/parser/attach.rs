@@ -0,0 +1,116 @@
+use crate::ast::{Ast, AttachDatabase};
+use crate::parser::expression::ExpressionParser;
+use crate::parser::Parser;
+use crate::token::Keyword;
+use common::errors::ParsingError;
+
+pub trait AttachQueryParser<'a> {
+    fn parse_attach(&mut self) -> Result<Ast, ParsingError>;
+}
+
+impl<'a> AttachQueryParser<'a> for Parser<'a> {
+    fn parse_attach(&mut self) -> Result<Ast, ParsingError> {
+        if self.eat_keyword(Keyword::Detach)? {
+            self.eat_keyword(Keyword::Database)?;
+            return Ok(Ast::Detach(self.parse_identifier()?));
+        }
+        self.expect_keyword(Keyword::Attach)?;
+        self.eat_keyword(Keyword::Database)?;
+        let file = self.parse_expression()?;
+        self.expect_keyword(Keyword::As)?;
+        let name = self.parse_identifier()?;
+        Ok(Ast::Attach(AttachDatabase { file, name }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BinaryOperator, Expression, Literal};
+
+    fn parse_query(query: &str) -> Result<Ast, ParsingError> {
+        Parser::new(query).parse()
+    }
+
+    fn attach(file: Expression, name: &str) -> Ast {
+        Ast::Attach(AttachDatabase {
+            file,
+            name: name.into(),
+        })
+    }
+
+    fn string(text: &str) -> Expression {
+        Expression::Literal(Literal::String(text.into()))
+    }
+
+    #[test]
+    fn test_attach_detach() {
+        for (query, ast) in [
+            (
+                "ATTACH 'archive.db' AS archive",
+                attach(string("archive.db"), "archive"),
+            ),
+            (
+                "attach database 'archive.db' as \"old data\"",
+                attach(string("archive.db"), "old data"),
+            ),
+            (
+                "ATTACH DATABASE 'archive' || '.db' AS 'archive'",
+                attach(
+                    Expression::Binary {
+                        left: Box::new(string("archive")),
+                        operator: BinaryOperator::Concat,
+                        right: Box::new(string(".db")),
+                    },
+                    "archive",
+                ),
+            ),
+            ("DETACH archive", Ast::Detach("archive".into())),
+            (
+                "detach database \"old data\"",
+                Ast::Detach("old data".into()),
+            ),
+        ] {
+            assert_eq!(parse_query(query), Ok(ast), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_attach_round_trip() {
+        for query in [
+            "ATTACH DATABASE 'archive.db' AS archive",
+            "ATTACH DATABASE ':memory:' AS \"old data\"",
+            "DETACH DATABASE archive",
+        ] {
+            assert_eq!(parse_query(query).unwrap().to_string(), query);
+        }
+    }
+
+    #[test]
+    fn test_attach_malformed() {
+        for (query, error) in [
+            ("ATTACH", ParsingError::UnexpectedEOF),
+            ("ATTACH DATABASE 'archive.db'", ParsingError::UnexpectedEOF),
+            (
+                "ATTACH 'archive.db' archive",
+                ParsingError::UnexpectedToken("archive".into()),
+            ),
+            (
+                "ATTACH 'archive.db' AS select",
+                ParsingError::UnexpectedKeyword("select".into()),
+            ),
+            ("DETACH", ParsingError::UnexpectedEOF),
+            ("DETACH DATABASE", ParsingError::UnexpectedEOF),
+            (
+                "DETACH archive.db",
+                ParsingError::UnexpectedToken(".".into()),
+            ),
+        ] {
+            assert_eq!(
+                Parser::new(query).parse_all(),
+                Err(ParsingError::InStatement(1, Box::new(error.clone()))),
+                "{query}"
+            );
+        }
+    }
+}
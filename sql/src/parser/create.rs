@@ -0,0 +1,1153 @@
+use crate::ast::{
+    Ast, ColumnConstraint, ColumnConstraintKind, ColumnDefinition, ConflictResolution, Constraint,
+    CreateIndex, CreateTable, CreateTrigger, CreateView, Expression, ForeignKeyAction,
+    ForeignKeyClause, IndexedColumn, Ordering, TableConstraint, TableConstraintKind,
+    TableDefinition, TriggerEvent, TriggerTiming, UnaryOperator,
+};
+use crate::parser::expression::ExpressionParser;
+use crate::parser::select::SelectQueryParser;
+use crate::parser::Parser;
+use crate::token::{Keyword, Token};
+use common::errors::ParsingError;
+
+pub trait CreateQueryParser<'a> {
+    fn parse_create(&mut self) -> Result<Ast, ParsingError>;
+
+    fn parse_create_table(&mut self, temporary: bool) -> Result<CreateTable, ParsingError>;
+
+    fn parse_create_index(&mut self) -> Result<CreateIndex, ParsingError>;
+
+    fn parse_create_view(&mut self, temporary: bool) -> Result<CreateView, ParsingError>;
+
+    fn parse_create_trigger(&mut self, temporary: bool) -> Result<CreateTrigger, ParsingError>;
+
+    /// Parses the `IF NOT EXISTS` of a CREATE statement
+    fn parse_if_not_exists(&mut self) -> Result<bool, ParsingError>;
+
+    /// Parses a `[schema.]name`, returning the schema and the name
+    fn parse_qualified_name(&mut self) -> Result<(Option<String>, String), ParsingError>;
+
+    /// Parses the parenthesized columns and table constraints, and the table options after them
+    fn parse_table_definition(&mut self) -> Result<TableDefinition, ParsingError>;
+
+    fn parse_column_definition(&mut self) -> Result<ColumnDefinition, ParsingError>;
+
+    /// Parses the next constraint of a column definition, if there is one
+    fn parse_column_constraint(&mut self) -> Result<Option<ColumnConstraint>, ParsingError>;
+
+    fn parse_table_constraint(&mut self) -> Result<TableConstraint, ParsingError>;
+
+    fn parse_foreign_key_clause(&mut self) -> Result<ForeignKeyClause, ParsingError>;
+
+    fn parse_foreign_key_action(&mut self) -> Result<ForeignKeyAction, ParsingError>;
+
+    /// Parses an optional `ON CONFLICT algorithm`
+    fn parse_conflict_clause(&mut self) -> Result<Option<ConflictResolution>, ParsingError>;
+
+    fn parse_conflict_resolution(&mut self) -> Result<ConflictResolution, ParsingError>;
+
+    /// Parses a parenthesized list of key or index columns
+    fn parse_indexed_columns(&mut self) -> Result<Vec<IndexedColumn>, ParsingError>;
+
+    fn parse_ordering(&mut self) -> Result<Option<Ordering>, ParsingError>;
+
+    /// Parses a parenthesized expression, e.g. the one of a CHECK
+    fn parse_parenthesized(&mut self) -> Result<Expression, ParsingError>;
+}
+
+/// Keywords ending the declared type of a column, where its constraints begin
+const COLUMN_CONSTRAINT_KEYWORDS: &[Keyword] = &[
+    Keyword::Constraint,
+    Keyword::Primary,
+    Keyword::Not,
+    Keyword::Null,
+    Keyword::Unique,
+    Keyword::Check,
+    Keyword::Default,
+    Keyword::Collate,
+    Keyword::References,
+    Keyword::Generated,
+    Keyword::As,
+];
+
+/// Keywords starting a table constraint instead of a column definition
+const TABLE_CONSTRAINT_KEYWORDS: &[Keyword] = &[
+    Keyword::Constraint,
+    Keyword::Primary,
+    Keyword::Unique,
+    Keyword::Check,
+    Keyword::Foreign,
+];
+
+impl<'a> CreateQueryParser<'a> for Parser<'a> {
+    fn parse_create(&mut self) -> Result<Ast, ParsingError> {
+        self.expect_keyword(Keyword::Create)?;
+        let temporary = self.eat_keyword(Keyword::Temp)? || self.eat_keyword(Keyword::Temporary)?;
+        match self.current_keyword()? {
+            Some(Keyword::Table) => Ok(Ast::CreateTable(self.parse_create_table(temporary)?)),
+            // Indexes live in the schema of their table, there are no temporary ones
+            Some(Keyword::Index | Keyword::Unique) if !temporary => {
+                Ok(Ast::CreateIndex(self.parse_create_index()?))
+            }
+            Some(Keyword::View) => Ok(Ast::CreateView(self.parse_create_view(temporary)?)),
+            Some(Keyword::Trigger) => Ok(Ast::CreateTrigger(self.parse_create_trigger(temporary)?)),
+            Some(Keyword::Virtual) => Err(ParsingError::Unsupported(Keyword::Virtual.to_string())),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_create_table(&mut self, temporary: bool) -> Result<CreateTable, ParsingError> {
+        self.expect_keyword(Keyword::Table)?;
+        let if_not_exists = self.parse_if_not_exists()?;
+        let (schema, table_name) = self.parse_qualified_name()?;
+        let definition = if self.eat_keyword(Keyword::As)? {
+            TableDefinition::Query(Box::new(self.parse_query()?))
+        } else {
+            self.parse_table_definition()?
+        };
+
+        Ok(CreateTable {
+            temporary,
+            if_not_exists,
+            schema,
+            table_name,
+            definition,
+        })
+    }
+
+    fn parse_create_index(&mut self) -> Result<CreateIndex, ParsingError> {
+        let unique = self.eat_keyword(Keyword::Unique)?;
+        self.expect_keyword(Keyword::Index)?;
+        let if_not_exists = self.parse_if_not_exists()?;
+        let (schema, name) = self.parse_qualified_name()?;
+        self.expect_keyword(Keyword::On)?;
+        let table_name = self.parse_identifier()?;
+        let columns = self.parse_indexed_columns()?;
+        let where_clause = match self.eat_keyword(Keyword::Where)? {
+            true => Some(self.parse_expression()?),
+            false => None,
+        };
+
+        Ok(CreateIndex {
+            unique,
+            if_not_exists,
+            schema,
+            name,
+            table_name,
+            columns,
+            where_clause,
+        })
+    }
+
+    fn parse_create_view(&mut self, temporary: bool) -> Result<CreateView, ParsingError> {
+        self.expect_keyword(Keyword::View)?;
+        let if_not_exists = self.parse_if_not_exists()?;
+        let (schema, name) = self.parse_qualified_name()?;
+        let columns = match *self.current_token()? == Token::OpenParen {
+            true => self.parse_identifier_list()?,
+            false => vec![],
+        };
+        self.expect_keyword(Keyword::As)?;
+
+        Ok(CreateView {
+            temporary,
+            if_not_exists,
+            schema,
+            name,
+            columns,
+            query: Box::new(self.parse_query()?),
+        })
+    }
+
+    fn parse_create_trigger(&mut self, temporary: bool) -> Result<CreateTrigger, ParsingError> {
+        self.expect_keyword(Keyword::Trigger)?;
+        let if_not_exists = self.parse_if_not_exists()?;
+        let (schema, name) = self.parse_qualified_name()?;
+        let timing = match self.current_keyword()? {
+            Some(Keyword::Before) => Some(TriggerTiming::Before),
+            Some(Keyword::After) => Some(TriggerTiming::After),
+            Some(Keyword::Instead) => Some(TriggerTiming::InsteadOf),
+            _ => None,
+        };
+        if timing.is_some() {
+            self.eat()?;
+        }
+        if timing == Some(TriggerTiming::InsteadOf) {
+            self.expect_keyword(Keyword::Of)?;
+        }
+        let event = match self.current_keyword()? {
+            Some(Keyword::Delete) => TriggerEvent::Delete,
+            Some(Keyword::Insert) => TriggerEvent::Insert,
+            Some(Keyword::Update) => TriggerEvent::Update(vec![]),
+            _ => return Err(self.unexpected()),
+        };
+        self.eat()?;
+        let event = match event {
+            TriggerEvent::Update(_) if self.eat_keyword(Keyword::Of)? => {
+                let mut columns = vec![self.parse_identifier()?];
+                while self.eat_token(Token::Comma)? {
+                    columns.push(self.parse_identifier()?);
+                }
+                TriggerEvent::Update(columns)
+            }
+            event => event,
+        };
+        self.expect_keyword(Keyword::On)?;
+        let table_name = self.parse_identifier()?;
+        let for_each_row = match self.eat_keyword(Keyword::For)? {
+            true => {
+                self.expect_keyword(Keyword::Each)?;
+                self.expect_keyword(Keyword::Row)?;
+                true
+            }
+            false => false,
+        };
+        let when = match self.eat_keyword(Keyword::When)? {
+            true => Some(self.parse_expression()?),
+            false => None,
+        };
+
+        // Every statement of the body ends with a semicolon, and there is at least one
+        self.expect_keyword(Keyword::Begin)?;
+        let mut body = vec![];
+        while body.is_empty() || !self.eat_keyword(Keyword::End)? {
+            match self.current_keyword()? {
+                Some(
+                    Keyword::Select
+                    | Keyword::With
+                    | Keyword::Insert
+                    | Keyword::Replace
+                    | Keyword::Update
+                    | Keyword::Delete,
+                ) => body.push(self.parse_statement_body()?),
+                _ => return Err(self.unexpected()),
+            }
+            self.expect_token(Token::SemiColon)?;
+        }
+
+        Ok(CreateTrigger {
+            temporary,
+            if_not_exists,
+            schema,
+            name,
+            timing,
+            event,
+            table_name,
+            for_each_row,
+            when,
+            body,
+        })
+    }
+
+    fn parse_if_not_exists(&mut self) -> Result<bool, ParsingError> {
+        if !self.eat_keyword(Keyword::If)? {
+            return Ok(false);
+        }
+        self.expect_keyword(Keyword::Not)?;
+        self.expect_keyword(Keyword::Exists)?;
+        Ok(true)
+    }
+
+    fn parse_qualified_name(&mut self) -> Result<(Option<String>, String), ParsingError> {
+        let name = self.parse_identifier()?;
+        if self.eat_token(Token::Period)? {
+            Ok((Some(name), self.parse_identifier()?))
+        } else {
+            Ok((None, name))
+        }
+    }
+
+    fn parse_table_definition(&mut self) -> Result<TableDefinition, ParsingError> {
+        self.expect_token(Token::OpenParen)?;
+        let mut columns: Vec<ColumnDefinition> = vec![];
+        loop {
+            let column = self.parse_column_definition()?;
+            if columns
+                .iter()
+                .any(|other| other.name.eq_ignore_ascii_case(&column.name))
+            {
+                return Err(ParsingError::DuplicateColumn(column.name));
+            }
+            columns.push(column);
+            if !self.eat_token(Token::Comma)? {
+                break;
+            }
+            if self
+                .current_keyword()?
+                .is_some_and(|keyword| TABLE_CONSTRAINT_KEYWORDS.contains(&keyword))
+            {
+                break;
+            }
+        }
+
+        let mut constraints = vec![];
+        if *self.current_token()? != Token::CloseParen {
+            // The commas between table constraints are optional, as in SQLite
+            loop {
+                constraints.push(self.parse_table_constraint()?);
+                if !self.eat_token(Token::Comma)? && *self.current_token()? == Token::CloseParen {
+                    break;
+                }
+            }
+        }
+        self.expect_token(Token::CloseParen)?;
+
+        let (mut without_rowid, mut strict) = (false, false);
+        let at_option = self.has_next_token()
+            && (self.current_keyword()? == Some(Keyword::Without) || self.at_word("strict"));
+        if at_option {
+            loop {
+                if self.eat_keyword(Keyword::Without)? {
+                    if !self.eat_word("rowid")? {
+                        return Err(self.unexpected());
+                    }
+                    without_rowid = true;
+                } else if self.eat_word("strict")? {
+                    strict = true;
+                } else {
+                    return Err(self.unexpected());
+                }
+                if !self.eat_token(Token::Comma)? {
+                    break;
+                }
+            }
+        }
+
+        Ok(TableDefinition::Columns {
+            columns,
+            constraints,
+            without_rowid,
+            strict,
+        })
+    }
+
+    fn parse_column_definition(&mut self) -> Result<ColumnDefinition, ParsingError> {
+        let name = self.parse_identifier()?;
+        // ALTER TABLE ... ADD COLUMN can end with the name
+        let at_type = self.has_next_token()
+            && matches!(
+                self.current_token()?,
+                Token::Identifier(_) | Token::QuotedIdentifier(_) | Token::String(_)
+            )
+            && !self
+                .current_keyword()?
+                .is_some_and(|keyword| COLUMN_CONSTRAINT_KEYWORDS.contains(&keyword));
+        let type_name = match at_type {
+            true => Some(self.parse_type_name()?),
+            false => None,
+        };
+
+        let mut constraints = vec![];
+        while let Some(constraint) = self.parse_column_constraint()? {
+            constraints.push(constraint);
+        }
+
+        Ok(ColumnDefinition {
+            name,
+            type_name,
+            constraints,
+        })
+    }
+
+    fn parse_column_constraint(&mut self) -> Result<Option<ColumnConstraint>, ParsingError> {
+        let name = match self.eat_keyword(Keyword::Constraint)? {
+            true => Some(self.parse_identifier()?),
+            false => None,
+        };
+        let keyword = match self.has_next_token() {
+            true => self.current_keyword()?,
+            false => None,
+        };
+
+        let kind = match keyword {
+            Some(Keyword::Primary) => {
+                self.eat()?;
+                self.expect_keyword(Keyword::Key)?;
+                ColumnConstraintKind::PrimaryKey {
+                    ordering: self.parse_ordering()?,
+                    conflict: self.parse_conflict_clause()?,
+                    autoincrement: self.eat_keyword(Keyword::Autoincrement)?,
+                }
+            }
+            Some(Keyword::Not) => {
+                self.eat()?;
+                self.expect_keyword(Keyword::Null)?;
+                ColumnConstraintKind::NotNull(self.parse_conflict_clause()?)
+            }
+            // A bare NULL only restates the default, it constrains nothing
+            Some(Keyword::Null) if name.is_none() => {
+                self.eat()?;
+                self.parse_conflict_clause()?;
+                return self.parse_column_constraint();
+            }
+            Some(Keyword::Unique) => {
+                self.eat()?;
+                ColumnConstraintKind::Unique(self.parse_conflict_clause()?)
+            }
+            Some(Keyword::Check) => {
+                self.eat()?;
+                ColumnConstraintKind::Check(self.parse_parenthesized()?)
+            }
+            Some(Keyword::Default) => {
+                self.eat()?;
+                // A literal or a parenthesized expression, a COLLATE after it is a constraint
+                let operator = match self.current_token()? {
+                    Token::Minus => Some(UnaryOperator::Minus),
+                    Token::Plus => Some(UnaryOperator::Plus),
+                    _ => None,
+                };
+                let expression = match operator {
+                    Some(operator) => {
+                        self.eat()?;
+                        Expression::unary(operator, self.parse_primary()?)
+                    }
+                    None => self.parse_primary()?,
+                };
+                ColumnConstraintKind::Default(expression)
+            }
+            Some(Keyword::Collate) => {
+                self.eat()?;
+                ColumnConstraintKind::Collate(self.parse_identifier()?)
+            }
+            Some(Keyword::References) => {
+                ColumnConstraintKind::References(self.parse_foreign_key_clause()?)
+            }
+            Some(Keyword::Generated | Keyword::As) => {
+                if self.eat_keyword(Keyword::Generated)? {
+                    self.expect_keyword(Keyword::Always)?;
+                }
+                self.expect_keyword(Keyword::As)?;
+                let expression = self.parse_parenthesized()?;
+                let stored = if self.eat_word("stored")? {
+                    true
+                } else {
+                    self.eat_keyword(Keyword::Virtual)?;
+                    false
+                };
+                ColumnConstraintKind::Generated { expression, stored }
+            }
+            _ if name.is_some() => return Err(self.unexpected()),
+            _ => return Ok(None),
+        };
+        Ok(Some(Constraint { name, kind }))
+    }
+
+    fn parse_table_constraint(&mut self) -> Result<TableConstraint, ParsingError> {
+        let name = match self.eat_keyword(Keyword::Constraint)? {
+            true => Some(self.parse_identifier()?),
+            false => None,
+        };
+
+        let kind = match self.current_keyword()? {
+            Some(Keyword::Primary) => {
+                self.eat()?;
+                self.expect_keyword(Keyword::Key)?;
+                TableConstraintKind::PrimaryKey {
+                    columns: self.parse_indexed_columns()?,
+                    conflict: self.parse_conflict_clause()?,
+                }
+            }
+            Some(Keyword::Unique) => {
+                self.eat()?;
+                TableConstraintKind::Unique {
+                    columns: self.parse_indexed_columns()?,
+                    conflict: self.parse_conflict_clause()?,
+                }
+            }
+            Some(Keyword::Check) => {
+                self.eat()?;
+                TableConstraintKind::Check(self.parse_parenthesized()?)
+            }
+            Some(Keyword::Foreign) => {
+                self.eat()?;
+                self.expect_keyword(Keyword::Key)?;
+                TableConstraintKind::ForeignKey {
+                    columns: self.parse_identifier_list()?,
+                    clause: self.parse_foreign_key_clause()?,
+                }
+            }
+            _ => return Err(self.unexpected()),
+        };
+        Ok(Constraint { name, kind })
+    }
+
+    fn parse_foreign_key_clause(&mut self) -> Result<ForeignKeyClause, ParsingError> {
+        self.expect_keyword(Keyword::References)?;
+        let table = self.parse_identifier()?;
+        let columns = match self.has_next_token() && *self.current_token()? == Token::OpenParen {
+            true => self.parse_identifier_list()?,
+            false => vec![],
+        };
+
+        let (mut on_delete, mut on_update) = (None, None);
+        loop {
+            if self.eat_keyword(Keyword::On)? {
+                if self.eat_keyword(Keyword::Delete)? {
+                    on_delete = Some(self.parse_foreign_key_action()?);
+                } else {
+                    self.expect_keyword(Keyword::Update)?;
+                    on_update = Some(self.parse_foreign_key_action()?);
+                }
+            } else if self.eat_keyword(Keyword::Match)? {
+                // SQLite parses the MATCH clause but ignores it
+                self.parse_identifier()?;
+            } else {
+                break;
+            }
+        }
+
+        let not_deferrable = self.current_keyword()? == Some(Keyword::Not)
+            && self
+                .peek_nth_token(1)
+                .is_some_and(|token| Keyword::try_from(&token) == Ok(Keyword::Deferrable));
+        if not_deferrable {
+            self.eat()?;
+        }
+        let mut deferred = false;
+        if self.eat_keyword(Keyword::Deferrable)? && self.eat_keyword(Keyword::Initially)? {
+            deferred = self.eat_keyword(Keyword::Deferred)?;
+            if !deferred {
+                self.expect_keyword(Keyword::Immediate)?;
+            }
+        }
+
+        Ok(ForeignKeyClause {
+            table,
+            columns,
+            on_delete,
+            on_update,
+            deferred: deferred && !not_deferrable,
+        })
+    }
+
+    fn parse_foreign_key_action(&mut self) -> Result<ForeignKeyAction, ParsingError> {
+        let action = match self.current_keyword()? {
+            Some(Keyword::Set) => {
+                self.eat()?;
+                if self.eat_keyword(Keyword::Null)? {
+                    return Ok(ForeignKeyAction::SetNull);
+                }
+                self.expect_keyword(Keyword::Default)?;
+                return Ok(ForeignKeyAction::SetDefault);
+            }
+            Some(Keyword::No) => {
+                self.eat()?;
+                self.expect_keyword(Keyword::Action)?;
+                return Ok(ForeignKeyAction::NoAction);
+            }
+            Some(Keyword::Cascade) => ForeignKeyAction::Cascade,
+            Some(Keyword::Restrict) => ForeignKeyAction::Restrict,
+            _ => return Err(self.unexpected()),
+        };
+        self.eat()?;
+        Ok(action)
+    }
+
+    fn parse_conflict_clause(&mut self) -> Result<Option<ConflictResolution>, ParsingError> {
+        let at_conflict = self.current_keyword().ok().flatten() == Some(Keyword::On)
+            && self
+                .peek_nth_token(1)
+                .is_some_and(|token| Keyword::try_from(&token) == Ok(Keyword::Conflict));
+        if !at_conflict {
+            return Ok(None);
+        }
+        self.eat()?;
+        self.eat()?;
+        Ok(Some(self.parse_conflict_resolution()?))
+    }
+
+    fn parse_conflict_resolution(&mut self) -> Result<ConflictResolution, ParsingError> {
+        let resolution = match self.current_keyword()? {
+            Some(Keyword::Rollback) => ConflictResolution::Rollback,
+            Some(Keyword::Abort) => ConflictResolution::Abort,
+            Some(Keyword::Fail) => ConflictResolution::Fail,
+            Some(Keyword::Ignore) => ConflictResolution::Ignore,
+            Some(Keyword::Replace) => ConflictResolution::Replace,
+            _ => return Err(self.unexpected()),
+        };
+        self.eat()?;
+        Ok(resolution)
+    }
+
+    fn parse_indexed_columns(&mut self) -> Result<Vec<IndexedColumn>, ParsingError> {
+        self.expect_token(Token::OpenParen)?;
+        let mut columns = vec![];
+        loop {
+            columns.push(IndexedColumn {
+                expression: self.parse_expression()?,
+                ordering: self.parse_ordering()?,
+            });
+            if !self.eat_token(Token::Comma)? {
+                break;
+            }
+        }
+        self.expect_token(Token::CloseParen)?;
+        Ok(columns)
+    }
+
+    fn parse_ordering(&mut self) -> Result<Option<Ordering>, ParsingError> {
+        if self.eat_keyword(Keyword::Asc)? {
+            Ok(Some(Ordering::Ascending))
+        } else if self.eat_keyword(Keyword::Desc)? {
+            Ok(Some(Ordering::Descending))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_parenthesized(&mut self) -> Result<Expression, ParsingError> {
+        self.expect_token(Token::OpenParen)?;
+        let expression = self.parse_expression()?;
+        self.expect_token(Token::CloseParen)?;
+        Ok(expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BinaryOperator, ColumnRef, Literal, TypeName};
+
+    fn parse_query(query: &str) -> Result<CreateTable, ParsingError> {
+        match Parser::new(query).parse()? {
+            Ast::CreateTable(create_table) => Ok(create_table),
+            _ => Err(ParsingError::UnexpectedToken(
+                "Expected create table AST".into(),
+            )),
+        }
+    }
+
+    fn type_name(name: &str, arguments: &[&str]) -> Option<TypeName> {
+        Some(TypeName {
+            name: name.into(),
+            arguments: arguments
+                .iter()
+                .map(|argument| argument.to_string())
+                .collect(),
+        })
+    }
+
+    fn constraint<T>(kind: T) -> Constraint<T> {
+        Constraint { name: None, kind }
+    }
+
+    fn column(name: &str) -> Expression {
+        Expression::Column(ColumnRef::new(name))
+    }
+
+    fn columns(create_table: CreateTable) -> (Vec<ColumnDefinition>, Vec<TableConstraint>) {
+        match create_table.definition {
+            TableDefinition::Columns {
+                columns,
+                constraints,
+                ..
+            } => (columns, constraints),
+            TableDefinition::Query(_) => panic!("Expected a column list"),
+        }
+    }
+
+    #[test]
+    fn test_create_table() {
+        assert_eq!(
+            parse_query("CREATE TABLE movies (id INTEGER PRIMARY KEY, title varchar(255), year)"),
+            Ok(CreateTable {
+                temporary: false,
+                if_not_exists: false,
+                schema: None,
+                table_name: "movies".into(),
+                definition: TableDefinition::Columns {
+                    columns: vec![
+                        ColumnDefinition {
+                            name: "id".into(),
+                            type_name: type_name("INTEGER", &[]),
+                            constraints: vec![constraint(ColumnConstraintKind::PrimaryKey {
+                                ordering: None,
+                                conflict: None,
+                                autoincrement: false,
+                            })],
+                        },
+                        ColumnDefinition {
+                            name: "title".into(),
+                            type_name: type_name("varchar", &["255"]),
+                            constraints: vec![],
+                        },
+                        ColumnDefinition {
+                            name: "year".into(),
+                            type_name: None,
+                            constraints: vec![],
+                        },
+                    ],
+                    constraints: vec![],
+                    without_rowid: false,
+                    strict: false,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_create_table_header() {
+        let create_table =
+            parse_query("create temporary table if not exists main.\"my movies\" (id)").unwrap();
+        assert!(create_table.temporary);
+        assert!(create_table.if_not_exists);
+        assert_eq!(create_table.schema, Some("main".into()));
+        assert_eq!(create_table.table_name, "my movies");
+
+        let create_table = parse_query("CREATE TEMP TABLE t (a) WITHOUT ROWID, STRICT").unwrap();
+        assert!(create_table.temporary);
+        assert!(matches!(
+            create_table.definition,
+            TableDefinition::Columns {
+                without_rowid: true,
+                strict: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_create_table_column_constraints() {
+        let (columns, _) = columns(
+            parse_query(
+                "CREATE TABLE t (
+                    id INTEGER CONSTRAINT pk PRIMARY KEY DESC ON CONFLICT REPLACE AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE ON CONFLICT IGNORE COLLATE nocase,
+                    price REAL NULL DEFAULT -1.5 CHECK (price > 0),
+                    total GENERATED ALWAYS AS (price * 2) STORED,
+                    owner INT REFERENCES users (id) ON DELETE SET NULL ON UPDATE CASCADE
+                        DEFERRABLE INITIALLY DEFERRED
+                )",
+            )
+            .unwrap(),
+        );
+        let constraints: Vec<Vec<ColumnConstraint>> = columns
+            .into_iter()
+            .map(|column| column.constraints)
+            .collect();
+        assert_eq!(
+            constraints,
+            vec![
+                vec![Constraint {
+                    name: Some("pk".into()),
+                    kind: ColumnConstraintKind::PrimaryKey {
+                        ordering: Some(Ordering::Descending),
+                        conflict: Some(ConflictResolution::Replace),
+                        autoincrement: true,
+                    },
+                }],
+                vec![
+                    constraint(ColumnConstraintKind::NotNull(None)),
+                    constraint(ColumnConstraintKind::Unique(Some(
+                        ConflictResolution::Ignore
+                    ))),
+                    constraint(ColumnConstraintKind::Collate("nocase".into())),
+                ],
+                vec![
+                    constraint(ColumnConstraintKind::Default(Expression::unary(
+                        UnaryOperator::Minus,
+                        Expression::Literal(Literal::Float(1.5)),
+                    ))),
+                    constraint(ColumnConstraintKind::Check(Expression::binary(
+                        column("price"),
+                        BinaryOperator::GreaterThan,
+                        Expression::Literal(Literal::Integer(0)),
+                    ))),
+                ],
+                vec![constraint(ColumnConstraintKind::Generated {
+                    expression: Expression::binary(
+                        column("price"),
+                        BinaryOperator::Multiply,
+                        Expression::Literal(Literal::Integer(2)),
+                    ),
+                    stored: true,
+                })],
+                vec![constraint(ColumnConstraintKind::References(
+                    ForeignKeyClause {
+                        table: "users".into(),
+                        columns: vec!["id".into()],
+                        on_delete: Some(ForeignKeyAction::SetNull),
+                        on_update: Some(ForeignKeyAction::Cascade),
+                        deferred: true,
+                    }
+                ))],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_table_constraints() {
+        let (columns, constraints) = columns(
+            parse_query(
+                "CREATE TABLE t (a, b TEXT, c,
+                    CONSTRAINT key PRIMARY KEY (a, b DESC) ON CONFLICT ROLLBACK,
+                    UNIQUE (c COLLATE nocase)
+                    CHECK (a <> b),
+                    FOREIGN KEY (c) REFERENCES other NOT DEFERRABLE)",
+            )
+            .unwrap(),
+        );
+        assert_eq!(columns.len(), 3);
+        assert_eq!(
+            constraints,
+            vec![
+                Constraint {
+                    name: Some("key".into()),
+                    kind: TableConstraintKind::PrimaryKey {
+                        columns: vec![
+                            IndexedColumn {
+                                expression: column("a"),
+                                ordering: None,
+                            },
+                            IndexedColumn {
+                                expression: column("b"),
+                                ordering: Some(Ordering::Descending),
+                            },
+                        ],
+                        conflict: Some(ConflictResolution::Rollback),
+                    },
+                },
+                constraint(TableConstraintKind::Unique {
+                    columns: vec![IndexedColumn {
+                        expression: Expression::Collate {
+                            expression: Box::new(column("c")),
+                            collation: "nocase".into(),
+                        },
+                        ordering: None,
+                    }],
+                    conflict: None,
+                }),
+                constraint(TableConstraintKind::Check(Expression::binary(
+                    column("a"),
+                    BinaryOperator::NotEquals,
+                    column("b"),
+                ))),
+                constraint(TableConstraintKind::ForeignKey {
+                    columns: vec!["c".into()],
+                    clause: ForeignKeyClause {
+                        table: "other".into(),
+                        columns: vec![],
+                        on_delete: None,
+                        on_update: None,
+                        deferred: false,
+                    },
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_table_as_select() {
+        let create_table = parse_query("CREATE TABLE copy AS SELECT * FROM movies").unwrap();
+        assert!(matches!(create_table.definition, TableDefinition::Query(_)));
+        assert_eq!(
+            create_table.to_string(),
+            "CREATE TABLE copy AS SELECT * FROM movies"
+        );
+    }
+
+    #[test]
+    fn test_create_table_malformed() {
+        for (query, error) in [
+            ("CREATE TABLE", ParsingError::UnexpectedEOF),
+            ("CREATE TABLE t", ParsingError::UnexpectedEOF),
+            (
+                "CREATE TABLE t ()",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            (
+                "CREATE TABLE t (a,)",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            (
+                "CREATE TABLE t (PRIMARY KEY (a))",
+                ParsingError::UnexpectedKeyword("primary".into()),
+            ),
+            (
+                "CREATE TABLE t (a, A)",
+                ParsingError::DuplicateColumn("A".into()),
+            ),
+            (
+                "CREATE TABLE t (a CONSTRAINT c)",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            (
+                "CREATE TABLE t (a PRIMARY)",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            ("CREATE TABLE t (a) WITHOUT", ParsingError::UnexpectedEOF),
+            ("CREATE TABLE t (a) STRICT,", ParsingError::UnexpectedEOF),
+            (
+                "CREATE TABLE t (a REFERENCES u ON INSERT CASCADE)",
+                ParsingError::UnexpectedKeyword("insert".into()),
+            ),
+            (
+                "CREATE TABLE t (a NOT NULL ON CONFLICT NOTHING)",
+                ParsingError::UnexpectedKeyword("nothing".into()),
+            ),
+            (
+                "CREATE VIRTUAL TABLE t USING fts5 (a)",
+                ParsingError::Unsupported("virtual".into()),
+            ),
+        ] {
+            assert_eq!(Parser::new(query).parse(), Err(error), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_create_table_round_trip() {
+        for query in [
+            "CREATE TABLE movies (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL)",
+            "CREATE TEMP TABLE IF NOT EXISTS main.t (a, b DEFAULT 'x' COLLATE NOCASE) STRICT",
+            "CREATE TABLE t (a INT CHECK (a > 0) UNIQUE ON CONFLICT FAIL, b GENERATED ALWAYS AS (a + 1) VIRTUAL)",
+            "CREATE TABLE t (a, b, PRIMARY KEY (a, b DESC), FOREIGN KEY (b) REFERENCES u (id) \
+             ON DELETE NO ACTION DEFERRABLE INITIALLY DEFERRED) WITHOUT ROWID",
+            "CREATE TABLE t (\"a b\" unsigned big int(10, -2) DEFAULT (1 + 2))",
+        ] {
+            let rendered = parse_query(query).unwrap().to_string();
+            assert_eq!(rendered, query);
+            assert_eq!(parse_query(&rendered).unwrap().to_string(), rendered);
+        }
+    }
+
+    #[test]
+    fn test_create_index() {
+        let index = |query: &str| match Parser::new(query).parse() {
+            Ok(Ast::CreateIndex(create_index)) => create_index,
+            other => panic!("{query}: expected create index AST, got {other:?}"),
+        };
+        assert_eq!(
+            index("CREATE INDEX by_year ON movies (year)"),
+            CreateIndex {
+                unique: false,
+                if_not_exists: false,
+                schema: None,
+                name: "by_year".into(),
+                table_name: "movies".into(),
+                columns: vec![IndexedColumn {
+                    expression: column("year"),
+                    ordering: None,
+                }],
+                where_clause: None,
+            }
+        );
+        assert_eq!(
+            index(
+                "create unique index if not exists main.by_title on movies \
+                 (title collate nocase asc, length(title) desc) where year > 2000"
+            ),
+            CreateIndex {
+                unique: true,
+                if_not_exists: true,
+                schema: Some("main".into()),
+                name: "by_title".into(),
+                table_name: "movies".into(),
+                columns: vec![
+                    IndexedColumn {
+                        expression: Expression::Collate {
+                            expression: Box::new(column("title")),
+                            collation: "nocase".into(),
+                        },
+                        ordering: Some(Ordering::Ascending),
+                    },
+                    IndexedColumn {
+                        expression: Expression::Function {
+                            name: "length".into(),
+                            arguments: vec![column("title")],
+                            distinct: false,
+                            filter: None,
+                        },
+                        ordering: Some(Ordering::Descending),
+                    },
+                ],
+                where_clause: Some(Expression::Binary {
+                    left: Box::new(column("year")),
+                    operator: BinaryOperator::GreaterThan,
+                    right: Box::new(Expression::Literal(Literal::Integer(2000))),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_create_index_malformed() {
+        for (query, error) in [
+            ("CREATE INDEX i ON t", ParsingError::UnexpectedEOF),
+            (
+                "CREATE INDEX i ON t ()",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            (
+                "CREATE UNIQUE i ON t (a)",
+                ParsingError::UnexpectedToken("i".into()),
+            ),
+            (
+                "CREATE INDEX i t (a)",
+                ParsingError::UnexpectedToken("t".into()),
+            ),
+            (
+                "CREATE TEMP INDEX i ON t (a)",
+                ParsingError::UnexpectedKeyword("index".into()),
+            ),
+            (
+                "CREATE INDEX i ON main.t (a)",
+                ParsingError::UnexpectedToken(".".into()),
+            ),
+            ("CREATE INDEX i ON t (a) WHERE", ParsingError::UnexpectedEOF),
+        ] {
+            assert_eq!(Parser::new(query).parse(), Err(error), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_create_view() {
+        let view = |query: &str| match Parser::new(query).parse() {
+            Ok(Ast::CreateView(create_view)) => create_view,
+            other => panic!("{query}: expected create view AST, got {other:?}"),
+        };
+        let select = |query: &str| match Parser::new(query).parse() {
+            Ok(Ast::Select(select)) => Box::new(select),
+            other => panic!("{query}: expected select AST, got {other:?}"),
+        };
+        assert_eq!(
+            view("CREATE VIEW recent AS SELECT title FROM movies WHERE year > 2000"),
+            CreateView {
+                temporary: false,
+                if_not_exists: false,
+                schema: None,
+                name: "recent".into(),
+                columns: vec![],
+                query: select("SELECT title FROM movies WHERE year > 2000"),
+            }
+        );
+        assert_eq!(
+            view("create temp view if not exists temp.pairs (a, \"b c\") as select 1, 2"),
+            CreateView {
+                temporary: true,
+                if_not_exists: true,
+                schema: Some("temp".into()),
+                name: "pairs".into(),
+                columns: vec!["a".into(), "b c".into()],
+                query: select("SELECT 1, 2"),
+            }
+        );
+        for query in [
+            "CREATE VIEW v AS WITH t AS (SELECT 1) SELECT * FROM t UNION SELECT 2",
+            "CREATE TEMP VIEW IF NOT EXISTS main.v (a, \"select\") AS SELECT 1, 2",
+        ] {
+            assert_eq!(view(query).to_string(), query);
+        }
+    }
+
+    #[test]
+    fn test_create_view_malformed() {
+        for (query, error) in [
+            ("CREATE VIEW v", ParsingError::UnexpectedEOF),
+            (
+                "CREATE VIEW v SELECT 1",
+                ParsingError::UnexpectedKeyword("select".into()),
+            ),
+            (
+                "CREATE VIEW v () AS SELECT 1",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            ("CREATE VIEW v AS", ParsingError::UnexpectedEOF),
+            (
+                "CREATE VIEW v AS DELETE FROM t",
+                ParsingError::UnexpectedToken("DELETE".into()),
+            ),
+        ] {
+            assert_eq!(Parser::new(query).parse(), Err(error), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_create_trigger() {
+        let trigger = |query: &str| match Parser::new(query).parse() {
+            Ok(Ast::CreateTrigger(create_trigger)) => create_trigger,
+            other => panic!("{query}: expected create trigger AST, got {other:?}"),
+        };
+        let statement = |query: &str| Parser::new(query).parse().unwrap();
+        assert_eq!(
+            trigger(
+                "CREATE TRIGGER touch AFTER UPDATE OF title, year ON movies \
+                 FOR EACH ROW WHEN new.year > 2000 BEGIN \
+                 UPDATE stats SET changes = changes + 1; DELETE FROM cache WHERE id = old.id; END"
+            ),
+            CreateTrigger {
+                temporary: false,
+                if_not_exists: false,
+                schema: None,
+                name: "touch".into(),
+                timing: Some(TriggerTiming::After),
+                event: TriggerEvent::Update(vec!["title".into(), "year".into()]),
+                table_name: "movies".into(),
+                for_each_row: true,
+                when: Some(Expression::Binary {
+                    left: Box::new(Expression::Column(ColumnRef {
+                        schema: None,
+                        table: Some("new".into()),
+                        name: "year".into(),
+                    })),
+                    operator: BinaryOperator::GreaterThan,
+                    right: Box::new(Expression::Literal(Literal::Integer(2000))),
+                }),
+                body: vec![
+                    statement("UPDATE stats SET changes = changes + 1"),
+                    statement("DELETE FROM cache WHERE id = old.id"),
+                ],
+            }
+        );
+        for query in [
+            "CREATE TRIGGER t INSERT ON movies BEGIN SELECT 1; END",
+            "CREATE TEMP TRIGGER IF NOT EXISTS main.t INSTEAD OF DELETE ON v BEGIN \
+             INSERT INTO log VALUES (old.id); SELECT 2; END",
+            "CREATE TRIGGER t BEFORE UPDATE ON movies WHEN old.title <> new.title BEGIN \
+             REPLACE INTO log (id) VALUES (new.id); END",
+        ] {
+            let rendered = trigger(query).to_string();
+            assert_eq!(trigger(&rendered).to_string(), rendered, "{query}");
+        }
+        assert_eq!(
+            trigger("create trigger t before delete on movies begin select 1; select 2; end")
+                .to_string(),
+            "CREATE TRIGGER t BEFORE DELETE ON movies BEGIN SELECT 1; SELECT 2; END"
+        );
+    }
+
+    #[test]
+    fn test_create_trigger_malformed() {
+        for (query, error) in [
+            (
+                "CREATE TRIGGER t ON movies BEGIN SELECT 1; END",
+                ParsingError::UnexpectedKeyword("on".into()),
+            ),
+            (
+                "CREATE TRIGGER t INSTEAD DELETE ON v BEGIN SELECT 1; END",
+                ParsingError::UnexpectedKeyword("delete".into()),
+            ),
+            (
+                "CREATE TRIGGER t DELETE ON movies BEGIN END",
+                ParsingError::UnexpectedKeyword("end".into()),
+            ),
+            (
+                "CREATE TRIGGER t DELETE ON movies BEGIN DELETE FROM log END",
+                ParsingError::UnexpectedKeyword("end".into()),
+            ),
+            (
+                "CREATE TRIGGER t DELETE ON movies BEGIN DROP TABLE movies; END",
+                ParsingError::UnexpectedKeyword("drop".into()),
+            ),
+            (
+                "CREATE TRIGGER t DELETE ON movies BEGIN SELECT 1;",
+                ParsingError::UnexpectedEOF,
+            ),
+            (
+                "CREATE TRIGGER t DELETE ON movies FOR ROW BEGIN SELECT 1; END",
+                ParsingError::UnexpectedKeyword("row".into()),
+            ),
+        ] {
+            assert_eq!(Parser::new(query).parse(), Err(error), "{query}");
+        }
+    }
+}
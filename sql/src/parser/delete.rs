@@ -0,0 +1,117 @@
+use crate::ast::{Ast, Delete};
+use crate::parser::create::CreateQueryParser;
+use crate::parser::expression::ExpressionParser;
+use crate::parser::select::SelectQueryParser;
+use crate::parser::update::UpdateQueryParser;
+use crate::parser::Parser;
+use crate::token::Keyword;
+use common::errors::ParsingError;
+
+pub trait DeleteQueryParser<'a> {
+    fn parse_delete(&mut self) -> Result<Ast, ParsingError>;
+}
+
+impl<'a> DeleteQueryParser<'a> for Parser<'a> {
+    fn parse_delete(&mut self) -> Result<Ast, ParsingError> {
+        self.expect_keyword(Keyword::Delete)?;
+        self.expect_keyword(Keyword::From)?;
+        let (schema, table_name) = self.parse_qualified_name()?;
+
+        Ok(Ast::Delete(Delete {
+            schema,
+            table_name,
+            where_clause: self.parse_where_clause()?,
+            returning: self.parse_returning()?,
+            order_by: self.parse_order_by_clause()?,
+            limit: self.parse_limit()?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BinaryOperator, ColumnLiteral, ColumnRef, Expression, Literal};
+
+    fn parse_query(query: &str) -> Result<Delete, ParsingError> {
+        match Parser::new(query).parse()? {
+            Ast::Delete(delete) => Ok(delete),
+            _ => Err(ParsingError::UnexpectedToken("Expected delete AST".into())),
+        }
+    }
+
+    #[test]
+    fn test_delete_all() {
+        assert_eq!(
+            parse_query("DELETE FROM movies"),
+            Ok(Delete {
+                schema: None,
+                table_name: "movies".into(),
+                where_clause: None,
+                returning: vec![],
+                order_by: None,
+                limit: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_delete_where() {
+        assert_eq!(
+            parse_query("delete from movies where id = 3"),
+            Ok(Delete {
+                schema: None,
+                table_name: "movies".into(),
+                where_clause: Some(Expression::binary(
+                    Expression::Column(ColumnRef::new("id")),
+                    BinaryOperator::Equals,
+                    Expression::Literal(Literal::Integer(3)),
+                )),
+                returning: vec![],
+                order_by: None,
+                limit: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_delete_returning_limit() {
+        let delete =
+            parse_query("DELETE FROM main.movies WHERE id > 3 RETURNING *, title AS t LIMIT 2, 1")
+                .unwrap();
+        assert_eq!(delete.schema, Some("main".into()));
+        assert_eq!(
+            delete.returning,
+            vec![
+                ColumnLiteral::from_expression(Expression::Wildcard(None)),
+                ColumnLiteral {
+                    expression: Expression::Column(ColumnRef::new("title")),
+                    alias: Some("t".into()),
+                },
+            ]
+        );
+        assert_eq!(delete.order_by, None);
+        assert!(delete.limit.is_some());
+        assert_eq!(
+            delete.to_string(),
+            "DELETE FROM main.movies WHERE id > 3 RETURNING *, title AS t LIMIT 1 OFFSET 2"
+        );
+    }
+
+    #[test]
+    fn test_delete_malformed() {
+        assert_eq!(
+            parse_query("DELETE movies"),
+            Err(ParsingError::UnexpectedToken("movies".into()))
+        );
+        assert_eq!(parse_query("DELETE FROM"), Err(ParsingError::UnexpectedEOF));
+        assert_eq!(
+            parse_query("DELETE FROM movies WHERE"),
+            Err(ParsingError::UnexpectedEOF)
+        );
+        assert_eq!(
+            parse_query("DELETE FROM movies RETURNING"),
+            Err(ParsingError::UnexpectedEOF)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::ast::{Ast, DropObject, ObjectKind};
+use crate::parser::create::CreateQueryParser;
+use crate::parser::Parser;
+use crate::token::Keyword;
+use common::errors::ParsingError;
+
+pub trait DropQueryParser<'a> {
+    fn parse_drop(&mut self) -> Result<Ast, ParsingError>;
+}
+
+impl<'a> DropQueryParser<'a> for Parser<'a> {
+    fn parse_drop(&mut self) -> Result<Ast, ParsingError> {
+        self.expect_keyword(Keyword::Drop)?;
+        let kind = match self.current_keyword()? {
+            Some(Keyword::Table) => ObjectKind::Table,
+            Some(Keyword::Index) => ObjectKind::Index,
+            Some(Keyword::View) => ObjectKind::View,
+            Some(Keyword::Trigger) => ObjectKind::Trigger,
+            _ => return Err(self.unexpected()),
+        };
+        self.eat()?;
+
+        let if_exists = match self.eat_keyword(Keyword::If)? {
+            true => {
+                self.expect_keyword(Keyword::Exists)?;
+                true
+            }
+            false => false,
+        };
+        let (schema, name) = self.parse_qualified_name()?;
+
+        Ok(Ast::Drop(DropObject {
+            kind,
+            if_exists,
+            schema,
+            name,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_query(query: &str) -> Result<DropObject, ParsingError> {
+        match Parser::new(query).parse()? {
+            Ast::Drop(drop) => Ok(drop),
+            _ => Err(ParsingError::UnexpectedToken("Expected drop AST".into())),
+        }
+    }
+
+    #[test]
+    fn test_drop_table() {
+        assert_eq!(
+            parse_query("DROP TABLE movies"),
+            Ok(DropObject {
+                kind: ObjectKind::Table,
+                if_exists: false,
+                schema: None,
+                name: "movies".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_drop_object_kinds() {
+        for (query, kind) in [
+            ("drop index if exists main.by_title", ObjectKind::Index),
+            ("DROP VIEW IF EXISTS main.by_title", ObjectKind::View),
+            ("DROP TRIGGER IF EXISTS main.by_title", ObjectKind::Trigger),
+        ] {
+            assert_eq!(
+                parse_query(query),
+                Ok(DropObject {
+                    kind,
+                    if_exists: true,
+                    schema: Some("main".into()),
+                    name: "by_title".into(),
+                }),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_drop_malformed() {
+        assert_eq!(parse_query("DROP"), Err(ParsingError::UnexpectedEOF));
+        assert_eq!(
+            parse_query("DROP movies"),
+            Err(ParsingError::UnexpectedToken("movies".into()))
+        );
+        assert_eq!(
+            parse_query("DROP TABLE IF movies"),
+            Err(ParsingError::UnexpectedToken("movies".into()))
+        );
+        assert_eq!(
+            parse_query("DROP TABLE main."),
+            Err(ParsingError::UnexpectedEOF)
+        );
+        assert_eq!(
+            parse_query("DROP COLUMN a"),
+            Err(ParsingError::UnexpectedKeyword("column".into()))
+        );
+    }
+}
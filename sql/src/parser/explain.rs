@@ -0,0 +1,92 @@
+use crate::ast::Ast;
+use crate::parser::Parser;
+use crate::token::Keyword;
+use common::errors::ParsingError;
+
+pub trait ExplainQueryParser<'a> {
+    fn parse_explain(&mut self) -> Result<Ast, ParsingError>;
+}
+
+impl<'a> ExplainQueryParser<'a> for Parser<'a> {
+    fn parse_explain(&mut self) -> Result<Ast, ParsingError> {
+        self.expect_keyword(Keyword::Explain)?;
+        let query_plan = self.eat_keyword(Keyword::Query)?;
+        if query_plan {
+            self.expect_keyword(Keyword::Plan)?;
+        }
+        // EXPLAIN only prefixes a whole statement, it can't explain itself
+        if self.current_keyword()? == Some(Keyword::Explain) {
+            return Err(self.unexpected());
+        }
+        let statement = Box::new(self.parse_statement_body()?);
+        Ok(match query_plan {
+            true => Ast::ExplainQueryPlan(statement),
+            false => Ast::Explain(statement),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{DropObject, ObjectKind};
+
+    #[test]
+    fn test_explain() {
+        let drop = Ast::Drop(DropObject {
+            kind: ObjectKind::View,
+            if_exists: false,
+            schema: None,
+            name: "v".into(),
+        });
+        assert_eq!(
+            Parser::new("EXPLAIN DROP VIEW v").parse(),
+            Ok(Ast::Explain(Box::new(drop.clone())))
+        );
+        assert_eq!(
+            Parser::new("explain query plan DROP VIEW v").parse(),
+            Ok(Ast::ExplainQueryPlan(Box::new(drop)))
+        );
+        assert_eq!(
+            Parser::new("EXPLAIN QUERY PLAN BEGIN").parse(),
+            Ok(Ast::ExplainQueryPlan(Box::new(Ast::Begin(None))))
+        );
+    }
+
+    #[test]
+    fn test_explain_round_trip() {
+        for query in [
+            "EXPLAIN SELECT * FROM movies WHERE id = 1",
+            "EXPLAIN QUERY PLAN SELECT title FROM movies ORDER BY year",
+            "EXPLAIN QUERY PLAN DELETE FROM movies WHERE id > 3",
+        ] {
+            assert_eq!(Parser::new(query).parse().unwrap().to_string(), query);
+        }
+    }
+
+    #[test]
+    fn test_explain_malformed() {
+        for (query, error) in [
+            ("EXPLAIN", ParsingError::UnexpectedEOF),
+            ("EXPLAIN QUERY", ParsingError::UnexpectedEOF),
+            (
+                "EXPLAIN QUERY SELECT 1",
+                ParsingError::UnexpectedKeyword("select".into()),
+            ),
+            (
+                "EXPLAIN PLAN SELECT 1",
+                ParsingError::UnexpectedKeyword("plan".into()),
+            ),
+            (
+                "EXPLAIN EXPLAIN SELECT 1",
+                ParsingError::UnexpectedKeyword("explain".into()),
+            ),
+            (
+                "EXPLAIN QUERY PLAN EXPLAIN SELECT 1",
+                ParsingError::UnexpectedKeyword("explain".into()),
+            ),
+        ] {
+            assert_eq!(Parser::new(query).parse(), Err(error), "{query}");
+        }
+    }
+}
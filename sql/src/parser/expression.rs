@@ -0,0 +1,1190 @@
+use crate::ast::{
+    precedence, BinaryOperator, ColumnRef, Expression, Literal, PatternOperator, Select, TypeName,
+    UnaryOperator,
+};
+use crate::parser::select::SelectQueryParser;
+use crate::parser::Parser;
+use crate::token::{Keyword, Token};
+use common::errors::ParsingError;
+use std::str::FromStr;
+
+/// The deepest expression nesting accepted, keeps the recursive descent within a thread's stack
+pub const MAX_EXPRESSION_DEPTH: usize = 200;
+
+pub trait ExpressionParser<'a> {
+    fn parse_expression(&mut self) -> Result<Expression, ParsingError>;
+
+    /// Parses an expression made of operators binding at least as tight as the given
+    /// precedence, see [`precedence`]
+    fn parse_expression_with(&mut self, min_precedence: u8) -> Result<Expression, ParsingError>;
+
+    /// Parses an optional `WHERE expr`, shared by SELECT, UPDATE and DELETE
+    fn parse_where_clause(&mut self) -> Result<Option<Expression>, ParsingError>;
+
+    fn parse_operators(&mut self, min_precedence: u8) -> Result<Expression, ParsingError>;
+
+    /// Returns true if a LIKE, BETWEEN, IN, IS or a similar predicate follows
+    fn at_predicate(&mut self) -> Result<bool, ParsingError>;
+
+    fn parse_predicate(&mut self, expression: Expression) -> Result<Expression, ParsingError>;
+
+    fn parse_prefix(&mut self) -> Result<Expression, ParsingError>;
+
+    fn parse_primary(&mut self) -> Result<Expression, ParsingError>;
+
+    /// Parses a parenthesized SELECT. It is boxed right away, keeping the stack frames of the
+    /// recursive descent small
+    fn parse_subquery(&mut self) -> Result<Box<Select>, ParsingError>;
+
+    fn parse_number(&mut self) -> Result<Expression, ParsingError>;
+
+    fn parse_function(&mut self) -> Result<Expression, ParsingError>;
+
+    fn parse_case(&mut self) -> Result<Expression, ParsingError>;
+
+    fn parse_cast(&mut self) -> Result<Expression, ParsingError>;
+
+    fn parse_type_name(&mut self) -> Result<TypeName, ParsingError>;
+
+    fn parse_column_ref(&mut self) -> Result<ColumnRef, ParsingError>;
+
+    fn current_binary_operator(&mut self) -> Result<Option<BinaryOperator>, ParsingError>;
+}
+
+impl<'a> ExpressionParser<'a> for Parser<'a> {
+    fn parse_expression(&mut self) -> Result<Expression, ParsingError> {
+        self.parse_expression_with(precedence::OR)
+    }
+
+    fn parse_expression_with(&mut self, min_precedence: u8) -> Result<Expression, ParsingError> {
+        self.nested(|parser| parser.parse_operators(min_precedence))
+    }
+
+    fn parse_where_clause(&mut self) -> Result<Option<Expression>, ParsingError> {
+        match self.eat_keyword(Keyword::Where)? {
+            true => Ok(Some(self.parse_expression()?)),
+            false => Ok(None),
+        }
+    }
+
+    /// Precedence climbing over the binary and postfix operators following a prefix expression
+    fn parse_operators(&mut self, min_precedence: u8) -> Result<Expression, ParsingError> {
+        let mut expression = self.parse_prefix()?;
+        loop {
+            if let Some(operator) = self.current_binary_operator()? {
+                let precedence = operator.precedence();
+                if precedence < min_precedence {
+                    break;
+                }
+                self.eat()?;
+                // All binary operators are left-associative
+                let right = self.parse_expression_with(precedence + 1)?;
+                expression = Expression::binary(expression, operator, right);
+            } else if self.has_next_token() && self.current_keyword()? == Some(Keyword::Collate) {
+                if precedence::COLLATE < min_precedence {
+                    break;
+                }
+                self.eat()?;
+                expression = Expression::Collate {
+                    expression: Box::new(expression),
+                    collation: self.parse_identifier()?,
+                };
+            } else if self.at_predicate()? {
+                // The predicates bind as tight as `=`, and are left-associative as well
+                if precedence::EQUALITY < min_precedence {
+                    break;
+                }
+                expression = self.parse_predicate(expression)?;
+            } else {
+                break;
+            }
+        }
+        Ok(expression)
+    }
+
+    fn at_predicate(&mut self) -> Result<bool, ParsingError> {
+        if !self.has_next_token() {
+            return Ok(false);
+        }
+        Ok(match self.current_keyword()? {
+            Some(
+                Keyword::Is
+                | Keyword::Isnull
+                | Keyword::Notnull
+                | Keyword::Like
+                | Keyword::Glob
+                | Keyword::Regexp
+                | Keyword::Match
+                | Keyword::Between
+                | Keyword::In,
+            ) => true,
+            // Otherwise a NOT after an expression ends it
+            Some(Keyword::Not) => matches!(
+                self.peek_nth_token(1)
+                    .and_then(|token| Keyword::try_from(&token).ok()),
+                Some(
+                    Keyword::Like
+                        | Keyword::Glob
+                        | Keyword::Regexp
+                        | Keyword::Match
+                        | Keyword::Between
+                        | Keyword::In
+                        | Keyword::Null
+                )
+            ),
+            _ => false,
+        })
+    }
+
+    fn parse_predicate(&mut self, expression: Expression) -> Result<Expression, ParsingError> {
+        let expression = Box::new(expression);
+        let negated = self.eat_keyword(Keyword::Not)?;
+        let keyword = self.current_keyword()?;
+        self.eat()?;
+        // The operands bind tighter than the predicate itself
+        let operand = precedence::EQUALITY + 1;
+
+        let operator = match keyword {
+            Some(Keyword::Isnull) => {
+                return Ok(Expression::IsNull {
+                    expression,
+                    negated: false,
+                })
+            }
+            Some(Keyword::Notnull | Keyword::Null) => {
+                return Ok(Expression::IsNull {
+                    expression,
+                    negated: true,
+                })
+            }
+            Some(Keyword::Is) => {
+                let negated = self.eat_keyword(Keyword::Not)?;
+                if self.eat_keyword(Keyword::Distinct)? {
+                    self.expect_keyword(Keyword::From)?;
+                    return Ok(Expression::IsDistinctFrom {
+                        left: expression,
+                        negated,
+                        right: Box::new(self.parse_expression_with(operand)?),
+                    });
+                }
+                let right = self.parse_expression_with(operand)?;
+                if right == Expression::Literal(Literal::Null) {
+                    return Ok(Expression::IsNull {
+                        expression,
+                        negated,
+                    });
+                }
+                let operator = match negated {
+                    true => BinaryOperator::IsNot,
+                    false => BinaryOperator::Is,
+                };
+                return Ok(Expression::binary(*expression, operator, right));
+            }
+            Some(Keyword::Between) => {
+                let low = Box::new(self.parse_expression_with(operand)?);
+                self.expect_keyword(Keyword::And)?;
+                return Ok(Expression::Between {
+                    expression,
+                    negated,
+                    low,
+                    high: Box::new(self.parse_expression_with(operand)?),
+                });
+            }
+            Some(Keyword::In) => {
+                if self.at_subquery() {
+                    return Ok(Expression::InSubquery {
+                        expression,
+                        negated,
+                        subquery: self.parse_subquery()?,
+                    });
+                }
+                self.expect_token(Token::OpenParen)?;
+                let mut list = vec![];
+                if !self.eat_token(Token::CloseParen)? {
+                    list.push(self.parse_expression()?);
+                    while self.eat_token(Token::Comma)? {
+                        list.push(self.parse_expression()?);
+                    }
+                    self.expect_token(Token::CloseParen)?;
+                }
+                return Ok(Expression::InList {
+                    expression,
+                    negated,
+                    list,
+                });
+            }
+            Some(Keyword::Like) => PatternOperator::Like,
+            Some(Keyword::Glob) => PatternOperator::Glob,
+            Some(Keyword::Regexp) => PatternOperator::Regexp,
+            _ => PatternOperator::Match,
+        };
+
+        let pattern = Box::new(self.parse_expression_with(operand)?);
+        let escape = match self.eat_keyword(Keyword::Escape)? {
+            true => Some(Box::new(self.parse_expression_with(operand)?)),
+            false => None,
+        };
+        Ok(Expression::Like {
+            expression,
+            negated,
+            operator,
+            pattern,
+            escape,
+        })
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expression, ParsingError> {
+        let operator = match self.current_token()? {
+            Token::Minus => UnaryOperator::Minus,
+            Token::Plus => UnaryOperator::Plus,
+            Token::Tilde => UnaryOperator::BitNot,
+            token if Keyword::try_from(token) == Ok(Keyword::Not) => UnaryOperator::Not,
+            _ => return self.parse_primary(),
+        };
+        self.eat()?;
+        if operator == UnaryOperator::Minus {
+            match self.lexer.peek() {
+                // The magnitude of the smallest INTEGER doesn't fit one, only its negation does
+                Some(Ok(Token::Number("9223372036854775808"))) => {
+                    self.eat()?;
+                    return Ok(Expression::Literal(Literal::Integer(i64::MIN)));
+                }
+                // An overflowing magnitude is read as the negative infinity it renders as
+                Some(Ok(Token::Number(number)))
+                    if number.parse::<f64>().is_ok_and(f64::is_infinite) =>
+                {
+                    self.eat()?;
+                    return Ok(Expression::Literal(Literal::Float(f64::NEG_INFINITY)));
+                }
+                _ => {}
+            }
+        }
+        let operand = self.parse_expression_with(operator.precedence())?;
+        Ok(Expression::unary(operator, operand))
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, ParsingError> {
+        // Reserved keywords such as CASE can be followed by a parenthesis too
+        let is_call = match self.lexer.peek() {
+            Some(Ok(Token::Identifier(name))) => {
+                Keyword::from_str(name).map_or(true, |keyword| !keyword.is_reserved())
+            }
+            Some(Ok(Token::QuotedIdentifier(_))) => true,
+            _ => false,
+        } && self.peek_nth_token(1) == Some(Token::OpenParen);
+        let is_subquery = self.at_subquery();
+
+        match self.current_token()? {
+            Token::Number(_) => self.parse_number(),
+            Token::Blob(_) => match self.next_token()? {
+                Token::Blob(digits) => Ok(Expression::Literal(Literal::Blob(
+                    (0..digits.len())
+                        .step_by(2)
+                        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16))
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| ParsingError::InvalidDataType(format!("X'{digits}'")))?,
+                ))),
+                token => Err(ParsingError::UnexpectedToken(token.to_string())),
+            },
+            Token::String(_) => match self.next_token()? {
+                Token::String(string) => {
+                    Ok(Expression::Literal(Literal::String(string.into_owned())))
+                }
+                token => Err(ParsingError::UnexpectedToken(token.to_string())),
+            },
+            Token::OpenParen if is_subquery => Ok(Expression::Subquery(self.parse_subquery()?)),
+            Token::OpenParen => {
+                self.eat()?;
+                let expression = self.parse_expression()?;
+                self.expect_token(Token::CloseParen)?;
+                Ok(Expression::Nested(Box::new(expression)))
+            }
+            token @ (Token::Identifier(_) | Token::QuotedIdentifier(_)) if is_call => {
+                match Keyword::try_from(token) {
+                    Ok(Keyword::Cast) => self.parse_cast(),
+                    _ => self.parse_function(),
+                }
+            }
+            Token::QuotedIdentifier(_) => Ok(Expression::Column(self.parse_column_ref()?)),
+            token @ Token::Identifier(_) => match Keyword::try_from(token) {
+                Ok(Keyword::Null) => {
+                    self.eat()?;
+                    Ok(Expression::Literal(Literal::Null))
+                }
+                Ok(Keyword::True) => {
+                    self.eat()?;
+                    Ok(Expression::Literal(Literal::Boolean(true)))
+                }
+                Ok(Keyword::False) => {
+                    self.eat()?;
+                    Ok(Expression::Literal(Literal::Boolean(false)))
+                }
+                Ok(Keyword::Case) => self.parse_case(),
+                Ok(Keyword::Exists) => {
+                    self.eat()?;
+                    if !self.at_subquery() {
+                        return Err(self.unexpected());
+                    }
+                    Ok(Expression::Exists(self.parse_subquery()?))
+                }
+                _ => Ok(Expression::Column(self.parse_column_ref()?)),
+            },
+            token => Err(ParsingError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    fn parse_subquery(&mut self) -> Result<Box<Select>, ParsingError> {
+        self.expect_token(Token::OpenParen)?;
+        let subquery = Box::new(self.parse_query()?);
+        self.expect_token(Token::CloseParen)?;
+        Ok(subquery)
+    }
+
+    /// Integers are kept as such, unless they do not fit into 64 bits, as SQLite does
+    fn parse_number(&mut self) -> Result<Expression, ParsingError> {
+        let number = match self.next_token()? {
+            Token::Number(number) => number,
+            token => return Err(ParsingError::UnexpectedToken(token.to_string())),
+        };
+        // Hex integers are 64 bits wide, the ones with the top bit set are negative
+        if let Some(digits) = number.strip_prefix("0x").or(number.strip_prefix("0X")) {
+            return u64::from_str_radix(digits, 16)
+                .map(|integer| Expression::Literal(Literal::Integer(integer as i64)))
+                .map_err(|_| {
+                    ParsingError::InvalidDataType(format!("hex literal too big: {number}"))
+                });
+        }
+        if !number.contains(['.', 'e', 'E']) {
+            if let Ok(integer) = number.parse::<i64>() {
+                return Ok(Expression::Literal(Literal::Integer(integer)));
+            }
+        }
+        let float: f64 = number
+            .parse()
+            .map_err(|_| ParsingError::InvalidDataType(format!("Unable parse {number} to f64")))?;
+        Ok(Expression::Literal(Literal::Float(float)))
+    }
+
+    fn parse_function(&mut self) -> Result<Expression, ParsingError> {
+        let name = self.parse_identifier()?;
+        self.expect_token(Token::OpenParen)?;
+
+        let mut distinct = false;
+        let mut arguments = vec![];
+        // Case: count(*)
+        if self.eat_token(Token::Asterisk)? {
+            arguments.push(Expression::Wildcard(None));
+            self.expect_token(Token::CloseParen)?;
+        } else if !self.eat_token(Token::CloseParen)? {
+            // ALL is the default, the same as no quantifier at all
+            distinct = self.eat_keyword(Keyword::Distinct)?;
+            if !distinct {
+                self.eat_keyword(Keyword::All)?;
+            }
+            arguments.push(self.parse_expression()?);
+            while self.eat_token(Token::Comma)? {
+                arguments.push(self.parse_expression()?);
+            }
+            self.expect_token(Token::CloseParen)?;
+        }
+
+        // FILTER is not reserved, without the parenthesis it is an alias
+        let mut filter = None;
+        if self.has_next_token()
+            && self.current_keyword()? == Some(Keyword::Filter)
+            && self.peek_nth_token(1) == Some(Token::OpenParen)
+        {
+            self.eat()?;
+            self.eat()?;
+            self.expect_keyword(Keyword::Where)?;
+            filter = Some(Box::new(self.parse_expression()?));
+            self.expect_token(Token::CloseParen)?;
+        }
+
+        Ok(Expression::Function {
+            name,
+            arguments,
+            distinct,
+            filter,
+        })
+    }
+
+    fn parse_case(&mut self) -> Result<Expression, ParsingError> {
+        self.expect_keyword(Keyword::Case)?;
+        // Case: case x when 1 then ...
+        let operand = match self.current_keyword()? {
+            Some(Keyword::When) => None,
+            _ => Some(Box::new(self.parse_expression()?)),
+        };
+
+        let mut branches = vec![];
+        self.expect_keyword(Keyword::When)?;
+        loop {
+            let condition = self.parse_expression()?;
+            self.expect_keyword(Keyword::Then)?;
+            branches.push((condition, self.parse_expression()?));
+            if !self.eat_keyword(Keyword::When)? {
+                break;
+            }
+        }
+
+        let else_result = match self.eat_keyword(Keyword::Else)? {
+            true => Some(Box::new(self.parse_expression()?)),
+            false => None,
+        };
+        self.expect_keyword(Keyword::End)?;
+        Ok(Expression::Case {
+            operand,
+            branches,
+            else_result,
+        })
+    }
+
+    fn parse_cast(&mut self) -> Result<Expression, ParsingError> {
+        self.expect_keyword(Keyword::Cast)?;
+        self.expect_token(Token::OpenParen)?;
+        let expression = Box::new(self.parse_expression()?);
+        self.expect_keyword(Keyword::As)?;
+        let data_type = self.parse_type_name()?;
+        self.expect_token(Token::CloseParen)?;
+        Ok(Expression::Cast {
+            expression,
+            data_type,
+        })
+    }
+
+    /// Parses `name... [(number [, number])]`, the numbers may have a sign
+    fn parse_type_name(&mut self) -> Result<TypeName, ParsingError> {
+        let mut words = vec![self.parse_identifier()?];
+        while let Some(Token::Identifier(_) | Token::QuotedIdentifier(_)) =
+            self.lexer.peek().and_then(|token| token.as_ref().ok())
+        {
+            match self.current_keyword()? {
+                // GENERATED is not reserved, but starts a column constraint
+                Some(keyword) if keyword.is_reserved() || keyword == Keyword::Generated => break,
+                _ => words.push(self.parse_identifier()?),
+            }
+        }
+
+        let mut arguments = vec![];
+        if self.eat_token(Token::OpenParen)? {
+            loop {
+                let sign = match self.current_token()? {
+                    Token::Minus => "-",
+                    Token::Plus => "+",
+                    _ => "",
+                };
+                if !sign.is_empty() {
+                    self.eat()?;
+                }
+                match self.next_token()? {
+                    Token::Number(number) => arguments.push(format!("{sign}{number}")),
+                    token => return Err(ParsingError::UnexpectedToken(token.to_string())),
+                }
+                if arguments.len() == 2 || !self.eat_token(Token::Comma)? {
+                    break;
+                }
+            }
+            self.expect_token(Token::CloseParen)?;
+        }
+
+        Ok(TypeName {
+            name: words.join(" "),
+            arguments,
+        })
+    }
+
+    fn parse_column_ref(&mut self) -> Result<ColumnRef, ParsingError> {
+        let mut names = vec![self.parse_identifier()?];
+        while names.len() < 3 && self.eat_token(Token::Period)? {
+            names.push(self.parse_identifier()?);
+        }
+
+        let name = names.pop().unwrap_or_default();
+        let table = names.pop();
+        let schema = names.pop();
+        Ok(ColumnRef {
+            schema,
+            table,
+            name,
+        })
+    }
+
+    fn current_binary_operator(&mut self) -> Result<Option<BinaryOperator>, ParsingError> {
+        if !self.has_next_token() {
+            return Ok(None);
+        }
+        let operator = match self.current_token()? {
+            Token::DoublePipe => BinaryOperator::Concat,
+            Token::Asterisk => BinaryOperator::Multiply,
+            Token::Slash => BinaryOperator::Divide,
+            Token::Percent => BinaryOperator::Modulo,
+            Token::Plus => BinaryOperator::Add,
+            Token::Minus => BinaryOperator::Subtract,
+            Token::Ampersand => BinaryOperator::BitAnd,
+            Token::Pipe => BinaryOperator::BitOr,
+            Token::ShiftLeft => BinaryOperator::ShiftLeft,
+            Token::ShiftRight => BinaryOperator::ShiftRight,
+            Token::LessThan => BinaryOperator::LessThan,
+            Token::LessOrEqual => BinaryOperator::LessOrEqual,
+            Token::GreaterThan => BinaryOperator::GreaterThan,
+            Token::GreaterOrEqual => BinaryOperator::GreaterOrEqual,
+            Token::Equals | Token::DoubleEquals => BinaryOperator::Equals,
+            Token::NotEquals => BinaryOperator::NotEquals,
+            token => match Keyword::try_from(token) {
+                Ok(Keyword::And) => BinaryOperator::And,
+                Ok(Keyword::Or) => BinaryOperator::Or,
+                _ => return Ok(None),
+            },
+        };
+        Ok(Some(operator))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(expression: &str) -> Result<Expression, ParsingError> {
+        let mut parser = Parser::new(expression);
+        let expression = parser.parse_expression()?;
+        match parser.has_next_token() {
+            true => Err(ParsingError::UnexpectedToken(
+                parser.current_token()?.to_string(),
+            )),
+            false => Ok(expression),
+        }
+    }
+
+    fn integer(value: i64) -> Expression {
+        Expression::Literal(Literal::Integer(value))
+    }
+
+    fn column(name: &str) -> Expression {
+        Expression::Column(ColumnRef::new(name))
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(parse("1"), Ok(integer(1)));
+        assert_eq!(parse("00"), Ok(integer(0)));
+        assert_eq!(parse("1.5"), Ok(Expression::Literal(Literal::Float(1.5))));
+        assert_eq!(
+            parse("2e3"),
+            Ok(Expression::Literal(Literal::Float(2000.0)))
+        );
+        assert_eq!(
+            parse("9223372036854775808"),
+            Ok(Expression::Literal(Literal::Float(9223372036854775808.0)))
+        );
+        assert_eq!(parse(".5"), Ok(Expression::Literal(Literal::Float(0.5))));
+        // Only the negated magnitude of the smallest INTEGER is one
+        assert_eq!(parse("-9223372036854775808"), Ok(integer(i64::MIN)));
+        assert_eq!(
+            parse("-9223372036854775809"),
+            Ok(Expression::unary(
+                UnaryOperator::Minus,
+                Expression::Literal(Literal::Float(9223372036854775809.0))
+            ))
+        );
+        assert_eq!(
+            parse("-9e999"),
+            Ok(Expression::Literal(Literal::Float(f64::NEG_INFINITY)))
+        );
+        assert_eq!(
+            parse("9e999"),
+            Ok(Expression::Literal(Literal::Float(f64::INFINITY)))
+        );
+        assert_eq!(parse("0x1F"), Ok(integer(31)));
+        assert_eq!(parse("0xffffffffffffffff"), Ok(integer(-1)));
+        assert_eq!(
+            parse("0x10000000000000000"),
+            Err(ParsingError::InvalidDataType(
+                "hex literal too big: 0x10000000000000000".into()
+            ))
+        );
+        assert_eq!(
+            parse("x'00fF'"),
+            Ok(Expression::Literal(Literal::Blob(vec![0, 255])))
+        );
+        assert_eq!(parse("a -- note"), Ok(column("a")));
+        assert_eq!(parse("/* c */ 1"), Ok(integer(1)));
+        assert_eq!(
+            parse("'abc'"),
+            Ok(Expression::Literal(Literal::String("abc".into())))
+        );
+        assert_eq!(parse("NULL"), Ok(Expression::Literal(Literal::Null)));
+        assert_eq!(
+            parse("true"),
+            Ok(Expression::Literal(Literal::Boolean(true)))
+        );
+        assert_eq!(
+            parse("FALSE"),
+            Ok(Expression::Literal(Literal::Boolean(false)))
+        );
+    }
+
+    #[test]
+    fn test_column_references() {
+        assert_eq!(parse("id"), Ok(column("id")));
+        assert_eq!(parse("\"some id\""), Ok(column("some id")));
+        assert_eq!(parse("key"), Ok(column("key")));
+        assert_eq!(
+            parse("movies.id"),
+            Ok(Expression::Column(ColumnRef::qualified("movies", "id")))
+        );
+        assert_eq!(
+            parse("main.movies.id"),
+            Ok(Expression::Column(ColumnRef {
+                schema: Some("main".into()),
+                table: Some("movies".into()),
+                name: "id".into(),
+            }))
+        );
+        assert_eq!(
+            parse("a.b.c.d"),
+            Err(ParsingError::UnexpectedToken(".".into()))
+        );
+        assert_eq!(
+            parse("select"),
+            Err(ParsingError::UnexpectedKeyword("select".into()))
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        // 1 + (2 * 3)
+        assert_eq!(
+            parse("1 + 2 * 3"),
+            Ok(Expression::binary(
+                integer(1),
+                BinaryOperator::Add,
+                Expression::binary(integer(2), BinaryOperator::Multiply, integer(3))
+            ))
+        );
+        // (a = 1) OR ((b = 2) AND (c = 3))
+        assert_eq!(
+            parse("a = 1 OR b = 2 AND c = 3"),
+            Ok(Expression::binary(
+                Expression::binary(column("a"), BinaryOperator::Equals, integer(1)),
+                BinaryOperator::Or,
+                Expression::binary(
+                    Expression::binary(column("b"), BinaryOperator::Equals, integer(2)),
+                    BinaryOperator::And,
+                    Expression::binary(column("c"), BinaryOperator::Equals, integer(3)),
+                )
+            ))
+        );
+        // ((a || b) * 2) < (c & (d + 1))
+        assert_eq!(
+            parse("a || b * 2 < c & d + 1"),
+            Ok(Expression::binary(
+                Expression::binary(
+                    Expression::binary(column("a"), BinaryOperator::Concat, column("b")),
+                    BinaryOperator::Multiply,
+                    integer(2)
+                ),
+                BinaryOperator::LessThan,
+                Expression::binary(
+                    column("c"),
+                    BinaryOperator::BitAnd,
+                    Expression::binary(column("d"), BinaryOperator::Add, integer(1))
+                )
+            ))
+        );
+        // (a < b) = (c >= d)
+        assert_eq!(
+            parse("a < b == c >= d"),
+            Ok(Expression::binary(
+                Expression::binary(column("a"), BinaryOperator::LessThan, column("b")),
+                BinaryOperator::Equals,
+                Expression::binary(column("c"), BinaryOperator::GreaterOrEqual, column("d"))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_left_associativity() {
+        // (1 - 2) - 3
+        assert_eq!(
+            parse("1 - 2 - 3"),
+            Ok(Expression::binary(
+                Expression::binary(integer(1), BinaryOperator::Subtract, integer(2)),
+                BinaryOperator::Subtract,
+                integer(3)
+            ))
+        );
+        // (8 / 4) % 3
+        assert_eq!(
+            parse("8 / 4 % 3"),
+            Ok(Expression::binary(
+                Expression::binary(integer(8), BinaryOperator::Divide, integer(4)),
+                BinaryOperator::Modulo,
+                integer(3)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_unary_operators() {
+        // (-1) * (~2)
+        assert_eq!(
+            parse("-1 * ~2"),
+            Ok(Expression::binary(
+                Expression::unary(UnaryOperator::Minus, integer(1)),
+                BinaryOperator::Multiply,
+                Expression::unary(UnaryOperator::BitNot, integer(2))
+            ))
+        );
+        // (NOT (a = 1)) AND b
+        assert_eq!(
+            parse("NOT a = 1 AND b"),
+            Ok(Expression::binary(
+                Expression::unary(
+                    UnaryOperator::Not,
+                    Expression::binary(column("a"), BinaryOperator::Equals, integer(1))
+                ),
+                BinaryOperator::And,
+                column("b")
+            ))
+        );
+        assert_eq!(
+            parse("- - +1"),
+            Ok(Expression::unary(
+                UnaryOperator::Minus,
+                Expression::unary(
+                    UnaryOperator::Minus,
+                    Expression::unary(UnaryOperator::Plus, integer(1))
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn test_nested() {
+        // (1 + 2) * 3
+        assert_eq!(
+            parse("(1 + 2) * 3"),
+            Ok(Expression::binary(
+                Expression::Nested(Box::new(Expression::binary(
+                    integer(1),
+                    BinaryOperator::Add,
+                    integer(2)
+                ))),
+                BinaryOperator::Multiply,
+                integer(3)
+            ))
+        );
+        assert_eq!(parse("(1"), Err(ParsingError::UnexpectedEOF));
+        assert_eq!(parse("()"), Err(ParsingError::UnexpectedToken(")".into())));
+        assert_eq!(parse("1 +"), Err(ParsingError::UnexpectedEOF));
+        assert_eq!(parse("1 2"), Err(ParsingError::UnexpectedToken("2".into())));
+    }
+
+    #[test]
+    fn test_collate() {
+        // ((-a) COLLATE nocase) = (b COLLATE "x y"), prefix operators bind tighter
+        assert_eq!(
+            parse(r#"-a COLLATE nocase = b collate "x y""#),
+            Ok(Expression::binary(
+                Expression::Collate {
+                    expression: Box::new(Expression::unary(UnaryOperator::Minus, column("a"))),
+                    collation: "nocase".into(),
+                },
+                BinaryOperator::Equals,
+                Expression::Collate {
+                    expression: Box::new(column("b")),
+                    collation: "x y".into(),
+                }
+            ))
+        );
+        // a || (b COLLATE x), NOT (a COLLATE x)
+        assert_eq!(
+            parse("a || b COLLATE x"),
+            Ok(Expression::binary(
+                column("a"),
+                BinaryOperator::Concat,
+                Expression::Collate {
+                    expression: Box::new(column("b")),
+                    collation: "x".into(),
+                }
+            ))
+        );
+        assert_eq!(
+            parse("NOT a COLLATE x"),
+            Ok(Expression::unary(
+                UnaryOperator::Not,
+                Expression::Collate {
+                    expression: Box::new(column("a")),
+                    collation: "x".into(),
+                }
+            ))
+        );
+        assert_eq!(parse("a COLLATE"), Err(ParsingError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_function_calls() {
+        assert_eq!(
+            parse("random()"),
+            Ok(Expression::function("random", vec![]))
+        );
+        // MAX(rating) > (8 + coalesce(a, "b c"(1)))
+        assert_eq!(
+            parse(r#"MAX(rating) > 8 + coalesce(a, "b c"(1))"#),
+            Ok(Expression::binary(
+                Expression::function("MAX", vec![column("rating")]),
+                BinaryOperator::GreaterThan,
+                Expression::binary(
+                    integer(8),
+                    BinaryOperator::Add,
+                    Expression::function(
+                        "coalesce",
+                        vec![column("a"), Expression::function("b c", vec![integer(1)]),]
+                    )
+                )
+            ))
+        );
+        assert_eq!(parse("max(1,"), Err(ParsingError::UnexpectedEOF));
+        assert_eq!(
+            parse("max(1 2)"),
+            Err(ParsingError::UnexpectedToken("2".into()))
+        );
+        assert_eq!(
+            parse("max(1,)"),
+            Err(ParsingError::UnexpectedToken(")".into()))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_calls() {
+        assert_eq!(
+            parse("COUNT(*)"),
+            Ok(Expression::function(
+                "COUNT",
+                vec![Expression::Wildcard(None)]
+            ))
+        );
+        assert_eq!(
+            parse("count(DISTINCT studio_id, genre_id)"),
+            Ok(Expression::Function {
+                name: "count".into(),
+                arguments: vec![column("studio_id"), column("genre_id")],
+                distinct: true,
+                filter: None,
+            })
+        );
+        assert_eq!(
+            parse("sum(ALL rating)"),
+            Ok(Expression::function("sum", vec![column("rating")]))
+        );
+        // (count(*) FILTER (WHERE ultrahd)) + 1
+        assert_eq!(
+            parse("count(*) filter (where ultrahd) + 1"),
+            Ok(Expression::binary(
+                Expression::Function {
+                    name: "count".into(),
+                    arguments: vec![Expression::Wildcard(None)],
+                    distinct: false,
+                    filter: Some(Box::new(column("ultrahd"))),
+                },
+                BinaryOperator::Add,
+                integer(1)
+            ))
+        );
+        assert_eq!(
+            parse("count(*) filter"),
+            Err(ParsingError::UnexpectedToken("filter".into()))
+        );
+        for (expression, error) in [
+            ("count(* )", None),
+            (
+                "count(*, id)",
+                Some(ParsingError::UnexpectedToken(",".into())),
+            ),
+            (
+                "count(DISTINCT *)",
+                Some(ParsingError::UnexpectedToken("*".into())),
+            ),
+            (
+                "count(DISTINCT)",
+                Some(ParsingError::UnexpectedToken(")".into())),
+            ),
+            (
+                "count(id) FILTER (id > 1)",
+                Some(ParsingError::UnexpectedToken("id".into())),
+            ),
+            (
+                "count(id) FILTER (WHERE id > 1",
+                Some(ParsingError::UnexpectedEOF),
+            ),
+        ] {
+            assert_eq!(parse(expression).err(), error, "{expression}");
+        }
+    }
+
+    fn string(value: &str) -> Expression {
+        Expression::Literal(Literal::String(value.into()))
+    }
+
+    #[test]
+    fn test_like() {
+        assert_eq!(
+            parse("'abc' LIKE 'x%'"),
+            Ok(Expression::Like {
+                expression: Box::new(string("abc")),
+                negated: false,
+                operator: PatternOperator::Like,
+                pattern: Box::new(string("x%")),
+                escape: None,
+            })
+        );
+        // (title NOT GLOB ('*' || x) ESCAPE '!') AND ok
+        assert_eq!(
+            parse("title NOT GLOB '*' || x ESCAPE '!' AND ok"),
+            Ok(Expression::binary(
+                Expression::Like {
+                    expression: Box::new(column("title")),
+                    negated: true,
+                    operator: PatternOperator::Glob,
+                    pattern: Box::new(Expression::binary(
+                        string("*"),
+                        BinaryOperator::Concat,
+                        column("x")
+                    )),
+                    escape: Some(Box::new(string("!"))),
+                },
+                BinaryOperator::And,
+                column("ok")
+            ))
+        );
+        assert_eq!(
+            parse("a regexp b").map(|expression| expression.to_string()),
+            Ok("a REGEXP b".into())
+        );
+        assert_eq!(parse("a LIKE"), Err(ParsingError::UnexpectedEOF));
+        assert_eq!(
+            parse("a NOT"),
+            Err(ParsingError::UnexpectedToken("NOT".into()))
+        );
+    }
+
+    #[test]
+    fn test_between() {
+        // (x BETWEEN (1 + 1) AND 3) AND (y = 1)
+        assert_eq!(
+            parse("x BETWEEN 1 + 1 AND 3 AND y = 1"),
+            Ok(Expression::binary(
+                Expression::Between {
+                    expression: Box::new(column("x")),
+                    negated: false,
+                    low: Box::new(Expression::binary(
+                        integer(1),
+                        BinaryOperator::Add,
+                        integer(1)
+                    )),
+                    high: Box::new(integer(3)),
+                },
+                BinaryOperator::And,
+                Expression::binary(column("y"), BinaryOperator::Equals, integer(1))
+            ))
+        );
+        // (x NOT BETWEEN 1 AND 2) = FALSE
+        assert_eq!(
+            parse("x NOT BETWEEN 1 AND 2 = FALSE"),
+            Ok(Expression::binary(
+                Expression::Between {
+                    expression: Box::new(column("x")),
+                    negated: true,
+                    low: Box::new(integer(1)),
+                    high: Box::new(integer(2)),
+                },
+                BinaryOperator::Equals,
+                Expression::Literal(Literal::Boolean(false))
+            ))
+        );
+        assert_eq!(
+            parse("x BETWEEN 1 OR 2"),
+            Err(ParsingError::UnexpectedKeyword("or".into()))
+        );
+    }
+
+    #[test]
+    fn test_in_list() {
+        assert_eq!(
+            parse("id NOT IN (1, 2 + 3)"),
+            Ok(Expression::InList {
+                expression: Box::new(column("id")),
+                negated: true,
+                list: vec![
+                    integer(1),
+                    Expression::binary(integer(2), BinaryOperator::Add, integer(3))
+                ],
+            })
+        );
+        assert_eq!(
+            parse("id IN ()"),
+            Ok(Expression::InList {
+                expression: Box::new(column("id")),
+                negated: false,
+                list: vec![],
+            })
+        );
+        assert_eq!(
+            parse("id IN 1"),
+            Err(ParsingError::UnexpectedToken("1".into()))
+        );
+        assert_eq!(parse("id IN (1,"), Err(ParsingError::UnexpectedEOF));
+    }
+
+    #[test]
+    fn test_is() {
+        let is_null = |negated| Expression::IsNull {
+            expression: Box::new(column("a")),
+            negated,
+        };
+        assert_eq!(parse("a IS NULL"), Ok(is_null(false)));
+        assert_eq!(parse("a ISNULL"), Ok(is_null(false)));
+        assert_eq!(parse("a IS NOT NULL"), Ok(is_null(true)));
+        assert_eq!(parse("a NOTNULL"), Ok(is_null(true)));
+        assert_eq!(parse("a NOT NULL"), Ok(is_null(true)));
+        assert_eq!(
+            parse("a IS NOT b"),
+            Ok(Expression::binary(
+                column("a"),
+                BinaryOperator::IsNot,
+                column("b")
+            ))
+        );
+        assert_eq!(
+            parse("a IS (NOT b)"),
+            Ok(Expression::binary(
+                column("a"),
+                BinaryOperator::Is,
+                Expression::Nested(Box::new(Expression::unary(UnaryOperator::Not, column("b"))))
+            ))
+        );
+        assert_eq!(
+            parse("a IS NOT DISTINCT FROM b + 1"),
+            Ok(Expression::IsDistinctFrom {
+                left: Box::new(column("a")),
+                negated: true,
+                right: Box::new(Expression::binary(
+                    column("b"),
+                    BinaryOperator::Add,
+                    integer(1)
+                )),
+            })
+        );
+        // NOT (a IS NULL)
+        assert_eq!(
+            parse("NOT a IS NULL"),
+            Ok(Expression::unary(UnaryOperator::Not, is_null(false)))
+        );
+        assert_eq!(
+            parse("a IS DISTINCT b"),
+            Err(ParsingError::UnexpectedToken("b".into()))
+        );
+    }
+
+    #[test]
+    fn test_case() {
+        assert_eq!(
+            parse("CASE WHEN a > 1 THEN 'big' WHEN a IS NULL THEN NULL ELSE 'small' END"),
+            Ok(Expression::Case {
+                operand: None,
+                branches: vec![
+                    (
+                        Expression::binary(column("a"), BinaryOperator::GreaterThan, integer(1)),
+                        string("big")
+                    ),
+                    (
+                        Expression::IsNull {
+                            expression: Box::new(column("a")),
+                            negated: false,
+                        },
+                        Expression::Literal(Literal::Null)
+                    ),
+                ],
+                else_result: Some(Box::new(string("small"))),
+            })
+        );
+        assert_eq!(
+            parse("case genre_id when 1 then 'drama' end"),
+            Ok(Expression::Case {
+                operand: Some(Box::new(column("genre_id"))),
+                branches: vec![(integer(1), string("drama"))],
+                else_result: None,
+            })
+        );
+        // END is not reserved, here it is taken for the operand
+        assert_eq!(parse("CASE END"), Err(ParsingError::UnexpectedEOF));
+        assert_eq!(
+            parse("CASE WHEN 1 THEN 2"),
+            Err(ParsingError::UnexpectedEOF)
+        );
+        assert_eq!(
+            parse("CASE a ELSE 1 END"),
+            Err(ParsingError::UnexpectedKeyword("else".into()))
+        );
+    }
+
+    #[test]
+    fn test_cast() {
+        let cast = |data_type: &str, arguments: &[&str]| {
+            Ok(Expression::Cast {
+                expression: Box::new(column("x")),
+                data_type: TypeName {
+                    name: data_type.into(),
+                    arguments: arguments
+                        .iter()
+                        .map(|argument| argument.to_string())
+                        .collect(),
+                },
+            })
+        };
+        assert_eq!(parse("CAST(x AS INTEGER)"), cast("INTEGER", &[]));
+        assert_eq!(parse("cast(x as varchar(255))"), cast("varchar", &["255"]));
+        assert_eq!(
+            parse("CAST(x AS unsigned big int)"),
+            cast("unsigned big int", &[])
+        );
+        assert_eq!(
+            parse("CAST(x AS DECIMAL(-10, +2.5))"),
+            cast("DECIMAL", &["-10", "+2.5"])
+        );
+        // Without the parenthesis CAST is a name
+        assert_eq!(parse("cast"), Ok(column("cast")));
+        assert_eq!(
+            parse("CAST(x)"),
+            Err(ParsingError::UnexpectedToken(")".into()))
+        );
+        assert_eq!(
+            parse("CAST(x AS)"),
+            Err(ParsingError::UnexpectedToken(")".into()))
+        );
+        assert_eq!(
+            parse("CAST(x AS DECIMAL(1, 2, 3))"),
+            Err(ParsingError::UnexpectedToken(",".into()))
+        );
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let depth = MAX_EXPRESSION_DEPTH - 1;
+        let nested = format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&nested).is_ok());
+
+        let nested = format!("{}1{}", "(".repeat(depth + 1), ")".repeat(depth + 1));
+        assert_eq!(
+            parse(&nested),
+            Err(ParsingError::TooDeep(MAX_EXPRESSION_DEPTH))
+        );
+
+        let negated = format!("{}1", "- ".repeat(100_000));
+        assert_eq!(
+            parse(&negated),
+            Err(ParsingError::TooDeep(MAX_EXPRESSION_DEPTH))
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use crate::ast::{Ast, ConflictResolution, Expression, Insert, InsertSource, Upsert, UpsertAction};
+use crate::parser::create::CreateQueryParser;
+use crate::parser::expression::ExpressionParser;
+use crate::parser::select::SelectQueryParser;
+use crate::parser::update::UpdateQueryParser;
+use crate::parser::Parser;
+use crate::token::{Keyword, Token};
+use common::errors::ParsingError;
+
+pub trait InsertQueryParser<'a> {
+    fn parse_insert(&mut self) -> Result<Ast, ParsingError>;
+
+    fn parse_insert_source(&mut self) -> Result<InsertSource, ParsingError>;
+
+    /// Parses the rows after `VALUES`, checking they all have the same length
+    fn parse_values(&mut self) -> Result<Vec<Vec<Expression>>, ParsingError>;
+
+    /// Parses the next `ON CONFLICT` clause, if there is one
+    fn parse_upsert(&mut self) -> Result<Option<Upsert>, ParsingError>;
+}
+
+impl<'a> InsertQueryParser<'a> for Parser<'a> {
+    fn parse_insert(&mut self) -> Result<Ast, ParsingError> {
+        let conflict = if self.eat_keyword(Keyword::Replace)? {
+            Some(ConflictResolution::Replace)
+        } else {
+            self.expect_keyword(Keyword::Insert)?;
+            match self.eat_keyword(Keyword::Or)? {
+                true => Some(self.parse_conflict_resolution()?),
+                false => None,
+            }
+        };
+        self.expect_keyword(Keyword::Into)?;
+        let (schema, table_name) = self.parse_qualified_name()?;
+        let columns = match *self.current_token()? == Token::OpenParen {
+            true => self.parse_identifier_list()?,
+            false => vec![],
+        };
+        let source = self.parse_insert_source()?;
+
+        // SQLite has no upsert clause after DEFAULT VALUES
+        let mut upsert = vec![];
+        if source != InsertSource::DefaultValues {
+            while let Some(clause) = self.parse_upsert()? {
+                upsert.push(clause);
+            }
+        }
+
+        Ok(Ast::Insert(Insert {
+            conflict,
+            schema,
+            table_name,
+            columns,
+            source,
+            upsert,
+            returning: self.parse_returning()?,
+        }))
+    }
+
+    fn parse_insert_source(&mut self) -> Result<InsertSource, ParsingError> {
+        if self.eat_keyword(Keyword::Default)? {
+            self.expect_keyword(Keyword::Values)?;
+            Ok(InsertSource::DefaultValues)
+        } else if self.eat_keyword(Keyword::Values)? {
+            Ok(InsertSource::Values(self.parse_values()?))
+        } else {
+            Ok(InsertSource::Query(Box::new(self.parse_query()?)))
+        }
+    }
+
+    fn parse_values(&mut self) -> Result<Vec<Vec<Expression>>, ParsingError> {
+        let mut rows: Vec<Vec<Expression>> = vec![];
+        loop {
+            self.expect_token(Token::OpenParen)?;
+            let mut row = vec![self.parse_expression()?];
+            while self.eat_token(Token::Comma)? {
+                row.push(self.parse_expression()?);
+            }
+            self.expect_token(Token::CloseParen)?;
+
+            if rows.first().is_some_and(|first| first.len() != row.len()) {
+                return Err(ParsingError::ValuesTermCount);
+            }
+            rows.push(row);
+            if !self.eat_token(Token::Comma)? {
+                return Ok(rows);
+            }
+        }
+    }
+
+    fn parse_upsert(&mut self) -> Result<Option<Upsert>, ParsingError> {
+        if !self.eat_keyword(Keyword::On)? {
+            return Ok(None);
+        }
+        self.expect_keyword(Keyword::Conflict)?;
+
+        let (target, target_where) = match *self.current_token()? == Token::OpenParen {
+            true => (self.parse_indexed_columns()?, self.parse_where_clause()?),
+            false => (vec![], None),
+        };
+        self.expect_keyword(Keyword::Do)?;
+        let action = if self.eat_keyword(Keyword::Nothing)? {
+            UpsertAction::Nothing
+        } else {
+            self.expect_keyword(Keyword::Update)?;
+            UpsertAction::Update {
+                set_clause: self.parse_set_clause()?,
+                where_clause: self.parse_where_clause()?,
+            }
+        };
+
+        Ok(Some(Upsert {
+            target,
+            target_where,
+            action,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{
+        Assignment, BinaryOperator, ColumnLiteral, ColumnRef, IndexedColumn, Literal,
+    };
+
+    fn parse_query(query: &str) -> Result<Insert, ParsingError> {
+        match Parser::new(query).parse()? {
+            Ast::Insert(insert) => Ok(insert),
+            _ => Err(ParsingError::UnexpectedToken("Expected insert AST".into())),
+        }
+    }
+
+    fn integer(value: i64) -> Expression {
+        Expression::Literal(Literal::Integer(value))
+    }
+
+    #[test]
+    fn test_insert_values() {
+        assert_eq!(
+            parse_query("INSERT INTO movies (id, title) VALUES (1, 'Alien'), (2, NULL)"),
+            Ok(Insert {
+                conflict: None,
+                schema: None,
+                table_name: "movies".into(),
+                columns: vec!["id".into(), "title".into()],
+                source: InsertSource::Values(vec![
+                    vec![
+                        integer(1),
+                        Expression::Literal(Literal::String("Alien".into()))
+                    ],
+                    vec![integer(2), Expression::Literal(Literal::Null)],
+                ]),
+                upsert: vec![],
+                returning: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_insert_conflict_resolution() {
+        for (query, conflict) in [
+            (
+                "INSERT OR REPLACE INTO t VALUES (1)",
+                ConflictResolution::Replace,
+            ),
+            (
+                "insert or ignore into t values (1)",
+                ConflictResolution::Ignore,
+            ),
+            (
+                "INSERT OR ABORT INTO t VALUES (1)",
+                ConflictResolution::Abort,
+            ),
+            ("INSERT OR FAIL INTO t VALUES (1)", ConflictResolution::Fail),
+            (
+                "INSERT OR ROLLBACK INTO t VALUES (1)",
+                ConflictResolution::Rollback,
+            ),
+            ("REPLACE INTO t VALUES (1)", ConflictResolution::Replace),
+        ] {
+            assert_eq!(
+                parse_query(query).unwrap().conflict,
+                Some(conflict),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_insert_select_and_default_values() {
+        let insert = parse_query("INSERT INTO main.copy SELECT 1").unwrap();
+        assert_eq!(insert.schema, Some("main".into()));
+        match insert.source {
+            InsertSource::Query(query) => assert_eq!(
+                query.columns,
+                vec![ColumnLiteral::from_literal(Literal::Integer(1))]
+            ),
+            source => panic!("Expected a query, got {source:?}"),
+        }
+        let insert = parse_query("INSERT INTO t DEFAULT VALUES").unwrap();
+        assert_eq!(insert.source, InsertSource::DefaultValues);
+        assert!(insert.columns.is_empty());
+    }
+
+    #[test]
+    fn test_insert_upsert() {
+        let insert = parse_query(
+            "INSERT INTO t (id, hits) VALUES (1, 1)
+                ON CONFLICT (id) WHERE id > 0 DO UPDATE SET hits = hits + 1 WHERE hits < 10
+                ON CONFLICT DO NOTHING",
+        )
+        .unwrap();
+        assert_eq!(
+            insert.upsert,
+            vec![
+                Upsert {
+                    target: vec![IndexedColumn {
+                        expression: Expression::Column(ColumnRef::new("id")),
+                        ordering: None,
+                    }],
+                    target_where: Some(Expression::binary(
+                        Expression::Column(ColumnRef::new("id")),
+                        BinaryOperator::GreaterThan,
+                        integer(0),
+                    )),
+                    action: UpsertAction::Update {
+                        set_clause: vec![Assignment {
+                            columns: vec!["hits".into()],
+                            values: vec![Expression::binary(
+                                Expression::Column(ColumnRef::new("hits")),
+                                BinaryOperator::Add,
+                                integer(1),
+                            )],
+                        }],
+                        where_clause: Some(Expression::binary(
+                            Expression::Column(ColumnRef::new("hits")),
+                            BinaryOperator::LessThan,
+                            integer(10),
+                        )),
+                    },
+                },
+                Upsert {
+                    target: vec![],
+                    target_where: None,
+                    action: UpsertAction::Nothing,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_malformed() {
+        for (query, error) in [
+            (
+                "INSERT t VALUES (1)",
+                ParsingError::UnexpectedToken("t".into()),
+            ),
+            ("INSERT INTO t", ParsingError::UnexpectedEOF),
+            ("INSERT INTO t VALUES", ParsingError::UnexpectedEOF),
+            (
+                "INSERT INTO t VALUES ()",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            (
+                "INSERT INTO t VALUES (1), (2, 3)",
+                ParsingError::ValuesTermCount,
+            ),
+            (
+                "INSERT INTO t () VALUES (1)",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            (
+                "INSERT OR NOTHING INTO t VALUES (1)",
+                ParsingError::UnexpectedKeyword("nothing".into()),
+            ),
+            (
+                "INSERT INTO t DEFAULT VALUES ON CONFLICT DO NOTHING",
+                ParsingError::UnexpectedKeyword("on".into()),
+            ),
+            (
+                "INSERT INTO t VALUES (1) ON CONFLICT DO",
+                ParsingError::UnexpectedEOF,
+            ),
+        ] {
+            assert_eq!(
+                Parser::new(query).parse_all(),
+                Err(ParsingError::InStatement(1, Box::new(error))),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_insert_round_trip() {
+        for query in [
+            "INSERT INTO movies (id, title) VALUES (1, 'Alien'), (2, 'Heat')",
+            "INSERT OR REPLACE INTO main.movies SELECT * FROM old WHERE id > 3",
+            "INSERT INTO movies DEFAULT VALUES RETURNING id, rowid AS r",
+            "INSERT INTO t VALUES (1, 2) ON CONFLICT (a COLLATE NOCASE, b DESC) WHERE b \
+             DO UPDATE SET a = excluded.a WHERE a != 1 ON CONFLICT DO NOTHING",
+        ] {
+            let rendered = parse_query(query).unwrap().to_string();
+            assert_eq!(rendered, query);
+        }
+    }
+}
@@ -1,97 +1,500 @@
+mod alter;
+mod attach;
+mod create;
+mod delete;
+mod drop;
+mod explain;
+mod expression;
+mod insert;
 mod select;
+mod transaction;
+mod update;
 
 use crate::ast::Ast;
 use crate::lexer::Lexer;
+use crate::parser::alter::AlterQueryParser;
+use crate::parser::attach::AttachQueryParser;
+use crate::parser::create::CreateQueryParser;
+use crate::parser::delete::DeleteQueryParser;
+use crate::parser::drop::DropQueryParser;
+use crate::parser::explain::ExplainQueryParser;
+use crate::parser::insert::InsertQueryParser;
 use crate::parser::select::SelectQueryParser;
+use crate::parser::transaction::TransactionQueryParser;
+use crate::parser::update::UpdateQueryParser;
 use crate::token::{Keyword, Token};
 use common::errors::ParsingError;
-use std::convert::TryFrom;
-use std::convert::TryInto;
 use std::iter::Peekable;
+use std::str::FromStr;
 
 pub struct Parser<'a> {
-    lexer1: Peekable<Lexer<'a>>,
+    lexer: Peekable<Lexer<'a>>,
+    /// The current expression nesting, see [`expression::MAX_EXPRESSION_DEPTH`]
+    depth: usize,
+    /// The current subquery nesting, see [`select::MAX_QUERY_DEPTH`]
+    query_depth: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(query: &'a str) -> Self {
         Parser {
-            lexer1: Lexer::new(query).peekable(),
+            lexer: Lexer::new(query).peekable(),
+            depth: 0,
+            query_depth: 0,
         }
     }
 
+    /// Parses a single complete statement, optionally ended by `;`. Anything else after it is
+    /// an error, scripts are parsed with [`Parser::statements`]
     pub fn parse(&mut self) -> Result<Ast, ParsingError> {
-        let keyword: Keyword = self.current()?.expect("Expected a keyword");
+        let ast = self.parse_statement()?;
+        while self.eat_token(Token::SemiColon)? {}
+        if self.has_next_token() {
+            return Err(self.unexpected());
+        }
+        Ok(ast)
+    }
+
+    /// Parses the statement starting at the current token. Anything after it is left in
+    /// place, for the caller to check
+    fn parse_statement_body(&mut self) -> Result<Ast, ParsingError> {
+        let keyword = match self.current_keyword()? {
+            Some(keyword) => keyword,
+            None => {
+                return Err(ParsingError::UnexpectedToken(
+                    self.current_token()?.to_string(),
+                ))
+            }
+        };
 
         // Derived from `SQL As Understood By SQLite` docs
         match keyword {
-            Keyword::Alter => unimplemented!(),
-            Keyword::Analyse => unimplemented!(),
-            Keyword::Attach => unimplemented!(),
-            Keyword::Begin => unimplemented!(),
-            Keyword::Commit => unimplemented!(),
-            Keyword::Create => unimplemented!(),
-            Keyword::Delete => unimplemented!(),
-            Keyword::Detach => unimplemented!(),
-            Keyword::Drop => unimplemented!(),
-            Keyword::Insert => unimplemented!(),
-            Keyword::Reindex => unimplemented!(),
-            Keyword::Release => unimplemented!(),
-            Keyword::Rollback => unimplemented!(),
-            Keyword::Savepoint => unimplemented!(),
             Keyword::Select | Keyword::With => self.parse_select(),
-            Keyword::Update => unimplemented!(),
-            Keyword::Vacuum => unimplemented!(),
-            Keyword::Explain => unimplemented!(),
+            Keyword::Create => self.parse_create(),
+            Keyword::Delete => self.parse_delete(),
+            Keyword::Drop => self.parse_drop(),
+            Keyword::Insert | Keyword::Replace => self.parse_insert(),
+            Keyword::Update => self.parse_update(),
+            Keyword::Alter => self.parse_alter(),
+            Keyword::Begin
+            | Keyword::Commit
+            | Keyword::End
+            | Keyword::Rollback
+            | Keyword::Savepoint
+            | Keyword::Release => self.parse_transaction(),
+            Keyword::Explain => self.parse_explain(),
+            Keyword::Attach | Keyword::Detach => self.parse_attach(),
+            Keyword::Analyze | Keyword::Reindex | Keyword::Vacuum => {
+                Err(ParsingError::Unsupported(keyword.to_string()))
+            }
 
-            _ => Err(ParsingError::UnexpectedToken(keyword.to_string())),
+            _ => Err(ParsingError::UnexpectedKeyword(keyword.to_string())),
         }
     }
 
-    fn current<T: TryFrom<Token>>(&mut self) -> Result<Option<T>, ParsingError> {
-        let token = self.current_token()?;
-        let result: Result<T, T::Error> = token.try_into();
-        match result {
-            Ok(t) => Ok(Some(t)),
-            Err(_) => Ok(None),
+    /// Parses every statement of a script, see [`Parser::statements`]
+    pub fn parse_all(&mut self) -> Result<Vec<Ast>, ParsingError> {
+        self.statements().collect()
+    }
+
+    /// Returns an iterator over the `;` separated statements of a script.
+    ///
+    /// Empty statements are skipped. Every statement must end with a semicolon or the end
+    /// of the input. Errors are reported as [`ParsingError::InStatement`] carrying the
+    /// 1-based number of the failed statement, and end the iteration.
+    pub fn statements(&mut self) -> Statements<'_, 'a> {
+        Statements {
+            parser: self,
+            index: 0,
+            failed: false,
         }
     }
 
-    fn current_token(&mut self) -> Result<Token, ParsingError> {
-        match self.lexer1.peek() {
-            Some(Ok(token)) => Ok(token.clone()),
-            _ => Err(ParsingError::UnexpectedEOF),
+    /// Parses a statement ending with a `;` or the end of the input
+    fn parse_statement(&mut self) -> Result<Ast, ParsingError> {
+        let ast = self.parse_statement_body()?;
+        if self.has_next_token() && !self.eat_token(Token::SemiColon)? {
+            return Err(self.unexpected());
         }
+        Ok(ast)
+    }
+
+    fn current_keyword(&mut self) -> Result<Option<Keyword>, ParsingError> {
+        Ok(Keyword::try_from(self.current_token()?).ok())
+    }
+
+    fn current_token(&mut self) -> Result<&Token<'a>, ParsingError> {
+        match self.lexer.peek() {
+            Some(Ok(token)) => Ok(token),
+            Some(Err(err)) => Err(err.clone()),
+            None => Err(ParsingError::UnexpectedEOF),
+        }
+    }
+
+    /// Looks `n` tokens past the current one, without consuming anything
+    fn peek_nth_token(&self, n: usize) -> Option<Token<'a>> {
+        self.lexer.clone().nth(n).and_then(Result::ok)
+    }
+
+    /// Returns true at a `(` opening a subquery
+    fn at_subquery(&self) -> bool {
+        if self.peek_nth_token(0) != Some(Token::OpenParen) {
+            return false;
+        }
+        match self
+            .peek_nth_token(1)
+            .map(|token| Keyword::try_from(&token))
+        {
+            Some(Ok(Keyword::Select)) => true,
+            // WITH is not reserved, a name has to follow to make it a clause
+            Some(Ok(Keyword::With)) => matches!(
+                self.peek_nth_token(2),
+                Some(Token::Identifier(_) | Token::QuotedIdentifier(_))
+            ),
+            _ => false,
+        }
+    }
+
+    /// Runs a recursive part of the grammar, failing once the nesting gets too deep
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParsingError>,
+    ) -> Result<T, ParsingError> {
+        if self.depth >= expression::MAX_EXPRESSION_DEPTH {
+            return Err(ParsingError::TooDeep(expression::MAX_EXPRESSION_DEPTH));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
     }
 
     fn has_next_token(&mut self) -> bool {
-        self.lexer1.peek().is_some()
+        self.lexer.peek().is_some()
     }
 
     fn eat(&mut self) -> Result<(), ParsingError> {
-        match self.lexer1.next() {
-            Some(_) => Ok(()),
+        self.next_token().map(|_| ())
+    }
+
+    /// Takes the current token out of the lexer, moving it instead of cloning
+    fn next_token(&mut self) -> Result<Token<'a>, ParsingError> {
+        match self.lexer.next() {
+            Some(token) => token,
             None => Err(ParsingError::UnexpectedEOF),
         }
     }
 
+    /// Eats the current token if it is the given one. Nothing matches at the end of input
     fn eat_token(&mut self, token: Token) -> Result<bool, ParsingError> {
-        let current_token = self.current_token()?;
-        if current_token == token {
-            self.lexer1.next();
+        if !self.has_next_token() {
+            return Ok(false);
+        }
+        if *self.current_token()? == token {
+            self.lexer.next();
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
+    /// Eats the given token, failing if the current token is another one
+    fn expect_token(&mut self, token: Token) -> Result<(), ParsingError> {
+        if self.eat_token(token)? {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// Eats the given keyword, failing if the current token is something else
+    fn expect_keyword(&mut self, keyword: Keyword) -> Result<(), ParsingError> {
+        if self.eat_keyword(keyword)? {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// Builds the error describing the current token as unexpected
+    fn unexpected(&mut self) -> ParsingError {
+        match self.current_token() {
+            Ok(token) => match Keyword::try_from(token) {
+                Ok(keyword) => ParsingError::UnexpectedKeyword(keyword.to_string()),
+                Err(_) => ParsingError::UnexpectedToken(token.to_string()),
+            },
+            Err(err) => err,
+        }
+    }
+
     fn eat_keyword(&mut self, keyword: Keyword) -> Result<bool, ParsingError> {
-        if let Some(current_keyword) = self.current()? {
-            if keyword == current_keyword {
-                self.lexer1.next();
-                return Ok(true);
-            }
+        if !self.has_next_token() {
+            return Ok(false);
+        }
+        if self.current_keyword()? == Some(keyword) {
+            self.lexer.next();
+            return Ok(true);
         }
         Ok(false)
     }
+
+    /// Eats the current token as a name. Quoted names are always accepted, bare ones only if
+    /// they are not reserved keywords, so non-reserved keywords (e.g. `key`) can name things.
+    fn parse_identifier(&mut self) -> Result<String, ParsingError> {
+        match self.current_token()? {
+            Token::Identifier(name) => match Keyword::from_str(name) {
+                Ok(keyword) if keyword.is_reserved() => {
+                    Err(ParsingError::UnexpectedKeyword(keyword.to_string()))
+                }
+                _ => {
+                    let name = name.to_string();
+                    self.eat()?;
+                    Ok(name)
+                }
+            },
+            Token::String(_) | Token::QuotedIdentifier(_) => match self.next_token()? {
+                Token::String(name) | Token::QuotedIdentifier(name) => Ok(name.into_owned()),
+                token => Err(ParsingError::UnexpectedToken(token.to_string())),
+            },
+            token => Err(ParsingError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    /// Returns true if the current token is the given bare word, for the few words of the
+    /// grammar that are not keywords (e.g. `ROWID`)
+    fn at_word(&mut self, word: &str) -> bool {
+        matches!(self.lexer.peek(), Some(Ok(Token::Identifier(name))) if name.eq_ignore_ascii_case(word))
+    }
+
+    /// Eats the current token if it is the given bare word, see [`Parser::at_word`]
+    fn eat_word(&mut self, word: &str) -> Result<bool, ParsingError> {
+        if self.at_word(word) {
+            self.eat()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Parses a parenthesized, comma separated list of names, e.g. `(id, name)`
+    fn parse_identifier_list(&mut self) -> Result<Vec<String>, ParsingError> {
+        self.expect_token(Token::OpenParen)?;
+        let mut names = vec![self.parse_identifier()?];
+        while self.eat_token(Token::Comma)? {
+            names.push(self.parse_identifier()?);
+        }
+        self.expect_token(Token::CloseParen)?;
+        Ok(names)
+    }
+}
+
+/// An iterator over the statements of a script, created by [`Parser::statements`]
+pub struct Statements<'p, 'a> {
+    parser: &'p mut Parser<'a>,
+    index: usize,
+    failed: bool,
+}
+
+impl Iterator for Statements<'_, '_> {
+    type Item = Result<Ast, ParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        self.index += 1;
+
+        let result = loop {
+            match self.parser.eat_token(Token::SemiColon) {
+                Ok(true) => continue,
+                Ok(false) if !self.parser.has_next_token() => return None,
+                Ok(false) => break self.parser.parse_statement(),
+                Err(err) => break Err(err),
+            }
+        };
+
+        self.failed = result.is_err();
+        Some(result.map_err(|err| ParsingError::InStatement(self.index, Box::new(err))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Random;
+
+    fn parse_script(script: &str) -> Result<Vec<String>, ParsingError> {
+        let statements = Parser::new(script).parse_all()?;
+        Ok(statements.iter().map(Ast::to_string).collect())
+    }
+
+    #[test]
+    fn test_parse_all_statements() {
+        assert_eq!(
+            parse_script("SELECT 1; SELECT id FROM movies;SELECT 'a;b'"),
+            Ok(vec![
+                "SELECT 1".to_string(),
+                "SELECT id FROM movies".to_string(),
+                "SELECT 'a;b'".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_all_empty_statements() {
+        assert_eq!(parse_script(""), Ok(vec![]));
+        assert_eq!(parse_script(" ;; \n\t; "), Ok(vec![]));
+        assert_eq!(
+            parse_script(";;SELECT 1;;\n;SELECT 2;  \n  "),
+            Ok(vec!["SELECT 1".to_string(), "SELECT 2".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_all_reports_failed_statement() {
+        assert_eq!(
+            parse_script("SELECT 1; SELECT 2,; SELECT 3"),
+            Err(ParsingError::InStatement(
+                2,
+                Box::new(ParsingError::UnexpectedToken(";".into()))
+            ))
+        );
+        assert_eq!(
+            parse_script(";SELECT 1; SELECT id FROM movies 2"),
+            Err(ParsingError::InStatement(
+                2,
+                Box::new(ParsingError::UnexpectedToken("2".into()))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_statements_stop_after_error() {
+        let mut parser = Parser::new("SELECT 1 AS; SELECT 2");
+        let mut statements = parser.statements();
+        assert!(matches!(
+            statements.next(),
+            Some(Err(ParsingError::InStatement(1, _)))
+        ));
+        assert!(statements.next().is_none());
+    }
+
+    #[test]
+    fn test_parse_single_statement() {
+        for query in ["SELECT 1", "SELECT 1;", "SELECT 1 ;; "] {
+            assert_eq!(
+                Parser::new(query).parse().map(|ast| ast.to_string()),
+                Ok("SELECT 1".to_string()),
+                "{query}"
+            );
+        }
+        for (query, error) in [
+            ("SELECT 1 2", ParsingError::UnexpectedToken("2".into())),
+            (
+                "SELECT 1; SELECT 2",
+                ParsingError::UnexpectedKeyword("select".into()),
+            ),
+            (
+                "SELECT 1 FROM t WHERE TRUE, TRUE",
+                ParsingError::UnexpectedToken(",".into()),
+            ),
+        ] {
+            assert_eq!(Parser::new(query).parse(), Err(error), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_malformed_queries_are_errors() {
+        for (query, error) in [
+            ("SELECT (", ParsingError::UnexpectedEOF),
+            ("SELECT (1", ParsingError::UnexpectedEOF),
+            ("SELECT 1 + * 2", ParsingError::UnexpectedToken("*".into())),
+            ("SELECT * FROM movies WHERE", ParsingError::UnexpectedEOF),
+            ("1 + 2", ParsingError::UnexpectedToken("1".into())),
+            ("movies", ParsingError::UnexpectedToken("movies".into())),
+            (
+                "FROM movies",
+                ParsingError::UnexpectedKeyword("from".into()),
+            ),
+            ("WITH t AS (SELECT 1)", ParsingError::UnexpectedEOF),
+            ("VACUUM", ParsingError::Unsupported("vacuum".into())),
+            ("'abc", ParsingError::UnexpectedEOF),
+            (
+                "@",
+                ParsingError::UnexpectedToken("Unexpected token @".into()),
+            ),
+        ] {
+            assert_eq!(Parser::new(query).parse(), Err(error), "{query}");
+        }
+    }
+
+    /// Fragments the random token streams are built from
+    fn fragments() -> Vec<String> {
+        let symbols = [
+            "*", ".", "=", "==", "!=", "<>", ">", ">=", "<", "<=", "+", "-", "/", "%", "!", "?",
+            "(", ")", ",", ";", "|", "||", "&", "~", "<<", ">>", "@", "#", "[", "]",
+        ];
+        let literals = [
+            "id",
+            "movies",
+            "m",
+            "1",
+            "3.14",
+            "2e10",
+            "'abc'",
+            "'it''s'",
+            "\"some id\"",
+            "`x`",
+            "[y]",
+            "'",
+            "\"",
+            "😀",
+            "_x",
+        ];
+        Keyword::ALL
+            .iter()
+            .map(|keyword| keyword.to_string().to_uppercase())
+            .chain(symbols.iter().chain(literals.iter()).map(|s| s.to_string()))
+            .collect()
+    }
+
+    fn random_token_stream(random: &mut Random, fragments: &[String]) -> String {
+        let mut query = String::new();
+        if random.chance(70) {
+            let prefix = random.pick(&["SELECT ", "SELECT DISTINCT ", "WITH ", "EXPLAIN "]);
+            query.push_str(prefix);
+        }
+        for _ in 0..random.below(16) {
+            query.push_str(random.pick(fragments).as_str());
+            if random.chance(80) {
+                query.push(' ');
+            }
+        }
+        query
+    }
+
+    fn random_string(random: &mut Random) -> String {
+        const CHARS: &[char] = &[
+            'a', 'Z', '0', '9', '_', ' ', '\t', '\n', '\'', '"', '.', ',', ';', '(', ')', '*', '-',
+            '+', '=', '<', '>', '!', '|', '😀', 'é', '\0',
+        ];
+        (0..random.below(32)).map(|_| *random.pick(CHARS)).collect()
+    }
+
+    #[test]
+    fn test_parser_never_panics() {
+        let fragments = fragments();
+        let mut random = Random::new(0xfa11);
+        for _ in 0..20_000 {
+            let query = if random.chance(50) {
+                random_token_stream(&mut random, &fragments)
+            } else {
+                random_string(&mut random)
+            };
+            let result = std::panic::catch_unwind(|| {
+                let _ = Parser::new(&query).parse();
+                let _ = Parser::new(&query).parse_all();
+            });
+            assert!(result.is_ok(), "Parser panicked on `{query}`");
+        }
+    }
 }
@@ -1,50 +1,198 @@
-use crate::ast::{Ast, ColumnLiteral, Expression, Literal, Ordering, Select};
+use crate::ast::{
+    Ast, ColumnLiteral, CommonTableExpression, CompoundOperator, Expression, JoinConstraint,
+    JoinKind, JoinOperator, Limit, NullsOrdering, Ordering, OrderingTerm, Select, TableExpression,
+    With,
+};
+use crate::parser::expression::ExpressionParser;
 use crate::parser::Parser;
 use crate::token::{Keyword, Token};
 use common::errors::ParsingError;
+use std::str::FromStr;
+
+/// The deepest subquery nesting accepted. A query takes many more stack frames to parse than
+/// an expression, so it has a much lower limit of its own
+pub const MAX_QUERY_DEPTH: usize = 20;
 
 pub trait SelectQueryParser<'a> {
     fn parse_select(&mut self) -> Result<Ast, ParsingError>;
 
+    /// Parses a SELECT, on its own or as a subquery
+    fn parse_query(&mut self) -> Result<Select, ParsingError>;
+
+    fn parse_query_body(&mut self) -> Result<Select, ParsingError>;
+
+    fn parse_with(&mut self) -> Result<Option<With>, ParsingError>;
+
+    fn parse_common_table_expression(&mut self) -> Result<CommonTableExpression, ParsingError>;
+
+    /// Parses a SELECT without the compound operators, ORDER BY and LIMIT following it
+    fn parse_select_core(&mut self) -> Result<Select, ParsingError>;
+
+    fn parse_compound_operator(&mut self) -> Result<Option<CompoundOperator>, ParsingError>;
+
     fn parse_distinct(&mut self) -> Result<bool, ParsingError>;
 
     fn parse_columns(&mut self) -> Result<Vec<ColumnLiteral>, ParsingError>;
 
-    fn parse_from(&mut self) -> Result<String, ParsingError>;
+    fn parse_column(&mut self) -> Result<ColumnLiteral, ParsingError>;
 
-    fn parse_where_clause(&mut self) -> Result<Option<Expression>, ParsingError>;
+    fn parse_alias(&mut self) -> Result<Option<String>, ParsingError>;
 
-    fn parse_group_by_clause(&mut self) -> Result<Option<Vec<String>>, ParsingError>;
+    fn parse_from(&mut self) -> Result<Option<TableExpression>, ParsingError>;
+
+    fn parse_table_expression(&mut self) -> Result<TableExpression, ParsingError>;
+
+    fn parse_table_or_subexpression(&mut self) -> Result<TableExpression, ParsingError>;
+
+    fn parse_join_operator(&mut self) -> Result<Option<JoinOperator>, ParsingError>;
+
+    fn parse_join_constraint(&mut self) -> Result<Option<JoinConstraint>, ParsingError>;
+
+    fn parse_group_by_clause(&mut self) -> Result<Option<Vec<Expression>>, ParsingError>;
 
     fn parse_having_clause(&mut self) -> Result<Option<Expression>, ParsingError>;
 
-    fn parse_order_by_clause(&mut self) -> Result<Option<Vec<(String, Ordering)>>, ParsingError>;
+    fn parse_order_by_clause(&mut self) -> Result<Option<Vec<OrderingTerm>>, ParsingError>;
+
+    fn parse_ordering_term(&mut self) -> Result<OrderingTerm, ParsingError>;
 
-    fn parse_limit(&mut self) -> Result<Option<usize>, ParsingError>;
+    fn parse_limit(&mut self) -> Result<Option<Limit>, ParsingError>;
 }
 
 impl<'a> SelectQueryParser<'a> for Parser<'a> {
     fn parse_select(&mut self) -> Result<Ast, ParsingError> {
-        let select_keyword_eaten = self.eat_keyword(Keyword::Select)?;
-        assert!(select_keyword_eaten);
+        Ok(Ast::Select(self.parse_query()?))
+    }
+
+    fn parse_query(&mut self) -> Result<Select, ParsingError> {
+        if self.query_depth >= MAX_QUERY_DEPTH {
+            return Err(ParsingError::TooDeep(MAX_QUERY_DEPTH));
+        }
+        self.query_depth += 1;
+        let result = self.parse_query_body();
+        self.query_depth -= 1;
+        result
+    }
+
+    fn parse_query_body(&mut self) -> Result<Select, ParsingError> {
+        let with = self.parse_with()?;
+        let mut select = self.parse_select_core()?;
+        select.with = with;
+        while let Some(operator) = self.parse_compound_operator()? {
+            let core = self.parse_select_core()?;
+            // The count is only known up front without wildcards
+            let has_wildcard = |select: &Select| {
+                select
+                    .columns
+                    .iter()
+                    .any(|column| matches!(column.expression, Expression::Wildcard(_)))
+            };
+            if !has_wildcard(&select)
+                && !has_wildcard(&core)
+                && select.columns.len() != core.columns.len()
+            {
+                return Err(ParsingError::CompoundColumnCount(operator.to_string()));
+            }
+            select.compound.push((operator, core));
+        }
+        select.order_by = self.parse_order_by_clause()?;
+        select.limit = self.parse_limit()?;
+        Ok(select)
+    }
+
+    fn parse_with(&mut self) -> Result<Option<With>, ParsingError> {
+        if !self.eat_keyword(Keyword::With)? {
+            return Ok(None);
+        }
+        let recursive = self.eat_keyword(Keyword::Recursive)?;
+
+        let mut tables = vec![self.parse_common_table_expression()?];
+        while self.eat_token(Token::Comma)? {
+            let table = self.parse_common_table_expression()?;
+            if tables
+                .iter()
+                .any(|other| other.name.eq_ignore_ascii_case(&table.name))
+            {
+                return Err(ParsingError::DuplicateTable(table.name.to_lowercase()));
+            }
+            tables.push(table);
+        }
+        Ok(Some(With { recursive, tables }))
+    }
 
-        Ok(Ast::Select(Select {
+    fn parse_common_table_expression(&mut self) -> Result<CommonTableExpression, ParsingError> {
+        let name = self.parse_identifier()?;
+        let columns = match self.current_token()? {
+            Token::OpenParen => self.parse_identifier_list()?,
+            _ => vec![],
+        };
+        self.expect_keyword(Keyword::As)?;
+        let materialized = if self.eat_keyword(Keyword::Not)? {
+            self.expect_keyword(Keyword::Materialized)?;
+            Some(false)
+        } else if self.eat_keyword(Keyword::Materialized)? {
+            Some(true)
+        } else {
+            None
+        };
+        Ok(CommonTableExpression {
+            name,
+            columns,
+            materialized,
+            query: self.parse_subquery()?,
+        })
+    }
+
+    fn parse_select_core(&mut self) -> Result<Select, ParsingError> {
+        if !self.eat_keyword(Keyword::Select)? {
+            return Err(ParsingError::UnexpectedToken(
+                self.current_token()?.to_string(),
+            ));
+        }
+
+        Ok(Select {
+            with: None,
             distinct: self.parse_distinct()?,
             columns: self.parse_columns()?,
             from: self.parse_from()?,
             where_clause: self.parse_where_clause()?,
             group_by: self.parse_group_by_clause()?,
             having: self.parse_having_clause()?,
-            order_by: self.parse_order_by_clause()?,
-            limit: self.parse_limit()?,
-        }))
+            compound: vec![],
+            order_by: None,
+            limit: None,
+        })
+    }
+
+    fn parse_compound_operator(&mut self) -> Result<Option<CompoundOperator>, ParsingError> {
+        if !self.has_next_token() {
+            return Ok(None);
+        }
+        let operator = match self.current_keyword()? {
+            Some(Keyword::Union) => {
+                self.eat()?;
+                match self.eat_keyword(Keyword::All)? {
+                    true => CompoundOperator::UnionAll,
+                    false => CompoundOperator::Union,
+                }
+            }
+            Some(Keyword::Intersect) => {
+                self.eat()?;
+                CompoundOperator::Intersect
+            }
+            Some(Keyword::Except) => {
+                self.eat()?;
+                CompoundOperator::Except
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(operator))
     }
 
     fn parse_distinct(&mut self) -> Result<bool, ParsingError> {
-        match self.current()? {
+        match self.current_keyword()? {
             Some(Keyword::Distinct) => {
-                self.eat_keyword(Keyword::Distinct)
-                    .expect("TODO: Internal error");
+                self.eat()?;
                 Ok(true)
             }
             _ => Ok(false),
@@ -52,203 +200,256 @@ impl<'a> SelectQueryParser<'a> for Parser<'a> {
     }
 
     fn parse_columns(&mut self) -> Result<Vec<ColumnLiteral>, ParsingError> {
-        type NextElementExpectedFlag = bool;
-        type ParsedColumn = (Option<ColumnLiteral>, NextElementExpectedFlag);
-
-        fn parse_asterisk(parser: &mut Parser) -> Result<ParsedColumn, ParsingError> {
-            parser.eat().expect("TODO: Internal error");
-            Ok((Some(ColumnLiteral::from_literal("*".into())), false))
+        let mut columns = vec![self.parse_column()?];
+        while self.eat_token(Token::Comma)? {
+            columns.push(self.parse_column()?);
         }
+        Ok(columns)
+    }
 
-        fn parse_column_expr(
-            parser: &mut Parser,
-            literal: Literal,
-            current_val: String,
-        ) -> Result<ParsedColumn, ParsingError> {
-            parser.eat().expect("TODO: Internal error");
-
-            let mut column = ColumnLiteral::from_literal(literal);
-
-            if !parser.has_next_token() {
-                return Ok((Some(column), false));
-            }
-            let token = parser.current_token()?;
+    fn parse_column(&mut self) -> Result<ColumnLiteral, ParsingError> {
+        // Case: select *
+        if self.eat_token(Token::Asterisk)? {
+            return Ok(ColumnLiteral::from_expression(Expression::Wildcard(None)));
+        }
+        // Case: select table_name.*
+        if let (Some(Token::Period), Some(Token::Asterisk)) =
+            (self.peek_nth_token(1), self.peek_nth_token(2))
+        {
+            let table = self.parse_identifier()?;
+            self.eat()?;
+            self.eat()?;
+            return Ok(ColumnLiteral::from_expression(Expression::Wildcard(Some(
+                table,
+            ))));
+        }
 
-            if token.clone().try_into() == Ok(Keyword::From) {
-                Ok((Some(column), false))
-            }
-            // Case: select name as something
-            else if token.clone().try_into() == Ok(Keyword::As) {
-                parser.eat().expect("TODO: Internal error");
-                match parser.current_token()? {
-                    Token::Identifier(alias) => {
-                        parser.eat().expect("TODO: Internal error");
-                        let comma_eaten = parser.eat_token(Token::Comma)?;
-                        column.alias = Some(alias);
-                        return Ok((Some(column), comma_eaten));
-                    }
-                    Token::String(alias) => {
-                        parser.eat().expect("TODO: Internal error");
-                        let comma_eaten = parser.eat_token(Token::Comma)?;
-                        column.alias = Some(alias);
-                        return Ok((Some(column), comma_eaten));
-                    }
-                    _ => todo!(),
-                }
-            }
-            // Case: select table_name,
-            else if token == Token::Comma {
-                parser.eat().expect("TODO: Internal error");
-                Ok((Some(column), true))
-            } else if token == Token::Period {
-                parser.eat().expect("TODO: Internal error");
-                let mut names = current_val.clone();
-
-                loop {
-                    let n_token = parser.current_token()?;
-                    match n_token {
-                        // Case: select table_name.*
-                        Token::Asterisk => {
-                            names = format!("{names}.*");
-                            parser.eat().expect("TODO: Internal error");
-                            break;
-                        }
-                        // Case: select table_name.column_name
-                        Token::Identifier(column_name)
-                        | Token::String(column_name)
-                        | Token::Number(column_name) => {
-                            names = format!("{names}.{column_name}");
-                            parser.eat().expect("TODO: Internal error");
-                        }
-                        _ => break,
-                    }
-
-                    let period_eaten = parser.eat_token(Token::Period)?;
-                    if !period_eaten {
-                        break;
-                    }
-                }
-                let as_keyword_eaten = parser.eat_keyword(Keyword::As)?;
-                let mut alias = None;
-                if as_keyword_eaten {
-                    if let Token::Identifier(alias_name) = parser.current_token()? {
-                        alias = Some(alias_name);
-                        parser.eat().expect("TODO: Internal error");
-                    }
-                }
+        Ok(ColumnLiteral {
+            expression: self.parse_expression()?,
+            alias: self.parse_alias()?,
+        })
+    }
 
-                let comma_eaten = parser.eat_token(Token::Comma)?;
-                let column = ColumnLiteral {
-                    expression: Expression::Literal(Literal::String(names)),
-                    alias,
-                };
-                Ok((Some(column), comma_eaten))
-            } else {
-                Err(ParsingError::UnexpectedToken(format!("{token}")))
+    fn parse_alias(&mut self) -> Result<Option<String>, ParsingError> {
+        // Case: select name as something
+        if self.eat_keyword(Keyword::As)? {
+            return Ok(Some(self.parse_identifier()?));
+        }
+        // Case: select name something
+        let implicit = match self.lexer.peek() {
+            Some(Ok(Token::Identifier(name))) => {
+                Keyword::from_str(name).map_or(true, |keyword| !keyword.is_reserved())
             }
+            Some(Ok(Token::QuotedIdentifier(_) | Token::String(_))) => true,
+            _ => false,
+        };
+        match implicit {
+            true => Ok(Some(self.parse_identifier()?)),
+            false => Ok(None),
         }
+    }
 
-        fn parse_column(parser: &mut Parser) -> Result<ParsedColumn, ParsingError> {
-            if !parser.has_next_token() {
-                return Ok((None, false));
+    fn parse_from(&mut self) -> Result<Option<TableExpression>, ParsingError> {
+        // The case where single select query was given (without FROM keyword)
+        if !self.eat_keyword(Keyword::From)? {
+            return Ok(None);
+        }
+        let from = self.parse_table_expression()?;
+
+        // A table can be joined with itself under its own name, e.g. `t NATURAL JOIN t`, but
+        // an alias names a single one
+        let mut aliases: Vec<String> = vec![];
+        for alias in from.aliases() {
+            let alias = alias.to_lowercase();
+            if aliases.contains(&alias) {
+                return Err(ParsingError::DuplicateTable(alias));
             }
-            let current_token = parser.current_token()?;
+            aliases.push(alias);
+        }
+        Ok(Some(from))
+    }
 
-            // Stop parsing columns when the FROM keyword is current token
-            if current_token.clone().try_into() == Ok(Keyword::From) {
-                return Ok((None, false));
-            }
+    fn parse_table_expression(&mut self) -> Result<TableExpression, ParsingError> {
+        let mut left = self.parse_table_or_subexpression()?;
+        while let Some(operator) = self.parse_join_operator()? {
+            let right = self.parse_table_or_subexpression()?;
+            let constraint = match operator.natural || operator.kind == JoinKind::Comma {
+                true => None,
+                false => self.parse_join_constraint()?,
+            };
+            left = TableExpression::Join {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+                constraint,
+            };
+        }
+        Ok(left)
+    }
 
-            match current_token {
-                // Case: select *
-                Token::Asterisk => parse_asterisk(parser),
-                // Case: select expr
-                Token::Identifier(name) => {
-                    parse_column_expr(parser, Literal::String(name.clone()), name)
-                }
+    fn parse_table_or_subexpression(&mut self) -> Result<TableExpression, ParsingError> {
+        if self.at_subquery() {
+            return Ok(TableExpression::Subquery {
+                query: self.nested(|parser| parser.parse_subquery())?,
+                alias: self.parse_alias()?,
+            });
+        }
+        if self.eat_token(Token::OpenParen)? {
+            let expression = self.nested(|parser| parser.parse_table_expression())?;
+            self.expect_token(Token::CloseParen)?;
+            return Ok(expression);
+        }
 
-                // Case: select 1
-                Token::Number(number) => {
-                    let value: f64 = number.parse().map_err(|_| {
-                        ParsingError::InvalidDataType(format!("Unable parse {number} to f64"))
-                    })?;
-                    let literal = Literal::Number(value);
-                    parse_column_expr(parser, literal, number)
-                }
+        let mut schema = None;
+        let mut name = self.parse_identifier()?;
+        if self.eat_token(Token::Period)? {
+            schema = Some(name);
+            name = self.parse_identifier()?;
+        }
+        Ok(TableExpression::Table {
+            schema,
+            name,
+            alias: self.parse_alias()?,
+        })
+    }
 
-                // Case: select 'name'
-                Token::String(string) => {
-                    parse_column_expr(parser, Literal::String(string.clone()), string)
-                }
-                _ => todo!(),
-            }
+    fn parse_join_operator(&mut self) -> Result<Option<JoinOperator>, ParsingError> {
+        if self.eat_token(Token::Comma)? {
+            return Ok(Some(JoinOperator {
+                natural: false,
+                kind: JoinKind::Comma,
+            }));
+        }
+        if !self.has_next_token() {
+            return Ok(None);
         }
 
-        let mut columns = vec![];
-        let mut next_column_wanted = true;
-        loop {
-            let (column_optional, next_exression_expected) = parse_column(self)?;
-            if column_optional.is_none() && next_column_wanted {
-                return Err(ParsingError::UnexpectedEOF);
+        let natural = self.eat_keyword(Keyword::Natural)?;
+        let kind = match self.current_keyword()? {
+            Some(Keyword::Join) => JoinKind::Inner,
+            Some(Keyword::Inner) => {
+                self.eat()?;
+                JoinKind::Inner
             }
-
-            next_column_wanted = next_exression_expected;
-
-            if let Some(column) = column_optional {
-                columns.push(column);
-            } else {
-                break;
+            Some(Keyword::Cross) if !natural => {
+                self.eat()?;
+                JoinKind::Cross
+            }
+            Some(keyword @ (Keyword::Left | Keyword::Right | Keyword::Full)) => {
+                self.eat()?;
+                self.eat_keyword(Keyword::Outer)?;
+                match keyword {
+                    Keyword::Left => JoinKind::Left,
+                    Keyword::Right => JoinKind::Right,
+                    _ => JoinKind::Full,
+                }
             }
+            _ if natural => return Err(self.unexpected()),
+            _ => return Ok(None),
+        };
+        self.expect_keyword(Keyword::Join)?;
+        Ok(Some(JoinOperator { natural, kind }))
+    }
+
+    fn parse_join_constraint(&mut self) -> Result<Option<JoinConstraint>, ParsingError> {
+        if self.eat_keyword(Keyword::On)? {
+            return Ok(Some(JoinConstraint::On(self.parse_expression()?)));
         }
-        if next_column_wanted {
-            return Err(ParsingError::UnexpectedEOF);
+        if !self.eat_keyword(Keyword::Using)? {
+            return Ok(None);
         }
-        Ok(columns)
+        Ok(Some(JoinConstraint::Using(self.parse_identifier_list()?)))
     }
 
-    fn parse_from(&mut self) -> Result<String, ParsingError> {
-        let current_token = self.current_token();
-        match current_token {
-            Ok(token) if token.clone().try_into() == Ok(Keyword::From) => {
-                self.eat().expect("TODO: Internal error");
+    fn parse_group_by_clause(&mut self) -> Result<Option<Vec<Expression>>, ParsingError> {
+        if !self.eat_keyword(Keyword::Group)? {
+            return Ok(None);
+        }
+        self.expect_keyword(Keyword::By)?;
 
-                let token = self.current_token()?;
-                match token {
-                    Token::Identifier(table_name) => Ok(table_name),
-                    Token::String(table_name) => Ok(table_name),
-                    _ => Err(ParsingError::UnexpectedToken(token.to_string())),
-                }
-            }
-            // The case where single select query was given (without FROM keyword)
-            Err(ParsingError::UnexpectedEOF) => Ok("".into()),
-            _ => Err(ParsingError::UnexpectedEOF),
+        let mut expressions = vec![self.parse_expression()?];
+        while self.eat_token(Token::Comma)? {
+            expressions.push(self.parse_expression()?);
         }
+        Ok(Some(expressions))
     }
 
-    fn parse_where_clause(&mut self) -> Result<Option<Expression>, ParsingError> {
-        Ok(None)
+    fn parse_having_clause(&mut self) -> Result<Option<Expression>, ParsingError> {
+        match self.eat_keyword(Keyword::Having)? {
+            true => Ok(Some(self.parse_expression()?)),
+            false => Ok(None),
+        }
     }
 
-    fn parse_group_by_clause(&mut self) -> Result<Option<Vec<String>>, ParsingError> {
-        Ok(None)
-    }
+    fn parse_order_by_clause(&mut self) -> Result<Option<Vec<OrderingTerm>>, ParsingError> {
+        if !self.eat_keyword(Keyword::Order)? {
+            return Ok(None);
+        }
+        self.expect_keyword(Keyword::By)?;
 
-    fn parse_having_clause(&mut self) -> Result<Option<Expression>, ParsingError> {
-        Ok(None)
+        let mut terms = vec![self.parse_ordering_term()?];
+        while self.eat_token(Token::Comma)? {
+            terms.push(self.parse_ordering_term()?);
+        }
+        Ok(Some(terms))
     }
 
-    fn parse_order_by_clause(&mut self) -> Result<Option<Vec<(String, Ordering)>>, ParsingError> {
-        Ok(None)
+    fn parse_ordering_term(&mut self) -> Result<OrderingTerm, ParsingError> {
+        let expression = self.parse_expression()?;
+        let ordering = if self.eat_keyword(Keyword::Desc)? {
+            Ordering::Descending
+        } else {
+            self.eat_keyword(Keyword::Asc)?;
+            Ordering::Ascending
+        };
+        let nulls = match self.eat_keyword(Keyword::Nulls)? {
+            true if self.eat_keyword(Keyword::First)? => Some(NullsOrdering::First),
+            true => {
+                self.expect_keyword(Keyword::Last)?;
+                Some(NullsOrdering::Last)
+            }
+            false => None,
+        };
+
+        Ok(OrderingTerm {
+            expression,
+            ordering,
+            nulls,
+        })
     }
 
-    fn parse_limit(&mut self) -> Result<Option<usize>, ParsingError> {
-        Ok(None)
+    fn parse_limit(&mut self) -> Result<Option<Limit>, ParsingError> {
+        if !self.eat_keyword(Keyword::Limit)? {
+            return Ok(None);
+        }
+
+        let count = self.parse_expression()?;
+        let limit = if self.eat_keyword(Keyword::Offset)? {
+            Limit {
+                count,
+                offset: Some(self.parse_expression()?),
+            }
+        } else if self.eat_token(Token::Comma)? {
+            // Case: limit offset, count
+            Limit {
+                count: self.parse_expression()?,
+                offset: Some(count),
+            }
+        } else {
+            Limit {
+                count,
+                offset: None,
+            }
+        };
+        Ok(Some(limit))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::{BinaryOperator, ColumnRef, Literal, PatternOperator, UnaryOperator};
+    use crate::parser::expression::MAX_EXPRESSION_DEPTH;
 
     fn parse_query(query: &str) -> Result<Select, ParsingError> {
         match Parser::new(query).parse()? {
@@ -265,11 +466,17 @@ mod tests {
 
         assert_eq!(
             select_stmt.columns,
-            vec![ColumnLiteral::from_expression(Expression::Literal(
-                Literal::String("*".to_string())
-            ))]
+            vec![ColumnLiteral::from_expression(Expression::Wildcard(None))]
         );
-        assert_eq!(select_stmt.from, "users".to_string());
+        assert_eq!(select_stmt.from, Some(TableExpression::table("users")));
+    }
+
+    /// Parses the query as a whole statement, so anything left after it is an error
+    fn parse_statement(query: &str) -> Result<Select, ParsingError> {
+        match Parser::new(query).parse_all()?.pop() {
+            Some(Ast::Select(select)) => Ok(select),
+            _ => Err(ParsingError::UnexpectedToken("Expected select AST".into())),
+        }
     }
 
     #[test]
@@ -299,7 +506,7 @@ mod tests {
         assert_eq!(select_stmt.columns.len(), 1);
         assert_eq!(
             select_stmt.columns[0],
-            ColumnLiteral::from_literal(Literal::Number(1.0))
+            ColumnLiteral::from_literal(Literal::Integer(1))
         );
 
         let select_stmt = parse_query("SELECT 'abs'").expect("Expected valid select statement");
@@ -331,8 +538,8 @@ mod tests {
             parse_query("SELECT col1 FROM users").expect("Expected valid select statement");
         assert_eq!(
             select_stmt.columns,
-            vec![ColumnLiteral::from_expression(Expression::Literal(
-                Literal::String("col1".to_string())
+            vec![ColumnLiteral::from_expression(Expression::Column(
+                ColumnRef::new("col1")
             ))]
         );
     }
@@ -344,15 +551,9 @@ mod tests {
         assert_eq!(
             select_stmt.columns,
             vec![
-                ColumnLiteral::from_expression(Expression::Literal(Literal::String(
-                    "col1".to_string()
-                ))),
-                ColumnLiteral::from_expression(Expression::Literal(Literal::String(
-                    "col2".to_string()
-                ))),
-                ColumnLiteral::from_expression(Expression::Literal(Literal::String(
-                    "col3".to_string()
-                ))),
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::new("col1"))),
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::new("col2"))),
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::new("col3"))),
             ]
         );
     }
@@ -363,8 +564,8 @@ mod tests {
             parse_query("SELECT users.col1 FROM users").expect("Expected valid select statement");
         assert_eq!(
             select_stmt.columns,
-            vec![ColumnLiteral::from_expression(Expression::Literal(
-                Literal::String("users.col1".to_string())
+            vec![ColumnLiteral::from_expression(Expression::Column(
+                ColumnRef::qualified("users", "col1")
             )),]
         );
     }
@@ -375,9 +576,9 @@ mod tests {
             parse_query("SELECT users.* FROM users").expect("Expected valid select statement");
         assert_eq!(
             select_stmt.columns,
-            vec![ColumnLiteral::from_expression(Expression::Literal(
-                Literal::String("users.*".to_string())
-            )),]
+            vec![ColumnLiteral::from_expression(Expression::Wildcard(Some(
+                "users".into()
+            ))),]
         );
     }
 
@@ -388,11 +589,11 @@ mod tests {
         assert_eq!(
             select_stmt.columns,
             vec![
-                ColumnLiteral::from_expression(Expression::Literal(Literal::String(
-                    "users.col1".to_string()
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::qualified(
+                    "users", "col1"
                 ))),
-                ColumnLiteral::from_expression(Expression::Literal(Literal::String(
-                    "users.col2".to_string()
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::qualified(
+                    "users", "col2"
                 ))),
             ]
         );
@@ -405,11 +606,11 @@ mod tests {
         assert_eq!(
             select_stmt.columns,
             vec![
-                ColumnLiteral::from_expression(Expression::Literal(Literal::String(
-                    "users.id".to_string()
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::qualified(
+                    "users", "id"
                 ))),
-                ColumnLiteral::from_expression(Expression::Literal(Literal::String(
-                    "orders.order_id".to_string()
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::qualified(
+                    "orders", "order_id"
                 ))),
             ]
         );
@@ -421,8 +622,12 @@ mod tests {
             .expect("Expected valid select statement");
         assert_eq!(
             select_stmt.columns,
-            vec![ColumnLiteral::from_expression(Expression::Literal(
-                Literal::String("users.id.value".to_string())
+            vec![ColumnLiteral::from_expression(Expression::Column(
+                ColumnRef {
+                    schema: Some("users".into()),
+                    table: Some("id".into()),
+                    name: "value".into(),
+                }
             )),]
         );
     }
@@ -434,11 +639,13 @@ mod tests {
         assert_eq!(
             select_stmt.columns,
             vec![
-                ColumnLiteral::from_expression(Expression::Literal(Literal::String(
-                    "users.id.value".to_string()
-                ))),
-                ColumnLiteral::from_expression(Expression::Literal(Literal::String(
-                    "orders.order_id".to_string()
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef {
+                    schema: Some("users".into()),
+                    table: Some("id".into()),
+                    name: "value".into(),
+                })),
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::qualified(
+                    "orders", "order_id"
                 ))),
             ]
         );
@@ -451,7 +658,7 @@ mod tests {
         assert_eq!(
             select_stmt.columns,
             vec![ColumnLiteral {
-                expression: Expression::Literal(Literal::String("col1".to_string())),
+                expression: Expression::Column(ColumnRef::new("col1")),
                 alias: Some("alias".to_string()),
             }]
         );
@@ -465,11 +672,11 @@ mod tests {
             select_stmt.columns,
             vec![
                 ColumnLiteral {
-                    expression: Expression::Literal(Literal::Number(1.0)),
+                    expression: Expression::Literal(Literal::Integer(1)),
                     alias: Some("one".to_string()),
                 },
                 ColumnLiteral {
-                    expression: Expression::Literal(Literal::Number(2.0)),
+                    expression: Expression::Literal(Literal::Integer(2)),
                     alias: Some("two".to_string()),
                 }
             ]
@@ -483,7 +690,11 @@ mod tests {
         assert_eq!(
             select_stmt.columns,
             vec![ColumnLiteral {
-                expression: Expression::Literal(Literal::String("col1.value1.body".to_string())),
+                expression: Expression::Column(ColumnRef {
+                    schema: Some("col1".into()),
+                    table: Some("value1".into()),
+                    name: "body".into(),
+                }),
                 alias: Some("column_name".to_string()),
             }]
         );
@@ -497,87 +708,1194 @@ mod tests {
         assert_eq!(
             select_stmt.columns,
             vec![
-                ColumnLiteral::from_literal(Literal::String("col1".into())),
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::new("col1"))),
                 ColumnLiteral {
-                    expression: Expression::Literal(Literal::String("col2".to_string())),
+                    expression: Expression::Column(ColumnRef::new("col2")),
                     alias: Some("name_2".to_string()),
                 },
                 ColumnLiteral {
-                    expression: Expression::Literal(Literal::Number(3.0)),
+                    expression: Expression::Literal(Literal::Integer(3)),
                     alias: Some("name_3".to_string()),
                 },
                 ColumnLiteral {
-                    expression: Expression::Literal(Literal::String("id".to_string())),
+                    expression: Expression::Column(ColumnRef::new("id")),
                     alias: Some("some id".to_string()),
                 },
             ]
         );
     }
 
+    #[test]
+    fn test_select_implicit_aliases() {
+        let select_stmt =
+            parse_query(r#"SELECT 1, 2 b, 3 AS c, 4 AS "👋", id "some id" FROM movies"#)
+                .expect("Expected valid select statement");
+        let aliases: Vec<_> = select_stmt
+            .columns
+            .iter()
+            .map(|column| column.alias.as_deref())
+            .collect();
+        assert_eq!(
+            aliases,
+            vec![None, Some("b"), Some("c"), Some("👋"), Some("some id")]
+        );
+    }
+
+    #[test]
+    fn test_select_wildcard_with_alias() {
+        assert_eq!(
+            parse_statement("SELECT * AS everything FROM movies").err(),
+            Some(ParsingError::InStatement(
+                1,
+                Box::new(ParsingError::UnexpectedKeyword("as".into()))
+            ))
+        );
+        assert_eq!(
+            parse_statement("SELECT movies.* AS everything FROM movies").err(),
+            Some(ParsingError::InStatement(
+                1,
+                Box::new(ParsingError::UnexpectedKeyword("as".into()))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_select_expressions() {
+        let select_stmt = parse_query("SELECT 1 + 2 * 3, 2020 - released AS age FROM movies")
+            .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.columns,
+            vec![
+                ColumnLiteral::from_expression(Expression::binary(
+                    Expression::Literal(Literal::Integer(1)),
+                    BinaryOperator::Add,
+                    Expression::binary(
+                        Expression::Literal(Literal::Integer(2)),
+                        BinaryOperator::Multiply,
+                        Expression::Literal(Literal::Integer(3)),
+                    ),
+                )),
+                ColumnLiteral {
+                    expression: Expression::binary(
+                        Expression::Literal(Literal::Integer(2020)),
+                        BinaryOperator::Subtract,
+                        Expression::Column(ColumnRef::new("released")),
+                    ),
+                    alias: Some("age".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_predicates() {
+        let select_stmt = parse_statement("SELECT 1 + 2 * 3, 'abc' LIKE 'x%' AS nope")
+            .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.columns[1],
+            ColumnLiteral {
+                expression: Expression::Like {
+                    expression: Box::new(Expression::Literal(Literal::String("abc".into()))),
+                    negated: false,
+                    operator: PatternOperator::Like,
+                    pattern: Box::new(Expression::Literal(Literal::String("x%".into()))),
+                    escape: None,
+                },
+                alias: Some("nope".into()),
+            }
+        );
+
+        let select_stmt = parse_statement(
+            "SELECT CASE WHEN rating IS NULL THEN 0 ELSE CAST(rating AS INTEGER) END FROM movies \
+            WHERE genre_id IN (1, 2) AND released NOT BETWEEN 1990 AND 2000",
+        )
+        .expect("Expected valid select statement");
+        assert!(matches!(
+            select_stmt.columns[0].expression,
+            Expression::Case { .. }
+        ));
+        assert!(matches!(
+            select_stmt.where_clause,
+            Some(Expression::Binary {
+                operator: BinaryOperator::And,
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn test_select_column_with_alias_bare() {
         let select_stmt = parse_query("SELECT col1 AS");
         assert!(select_stmt.is_err());
         assert_eq!(select_stmt.err(), Some(ParsingError::UnexpectedEOF));
+        assert_eq!(
+            parse_query("SELECT col1 AS table.body.value FROM users").err(),
+            Some(ParsingError::UnexpectedKeyword("table".into()))
+        );
+    }
+
+    #[test]
+    fn test_select_non_reserved_keywords_as_names() {
+        let select_stmt = parse_query("SELECT key, action AS temp FROM replace")
+            .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.columns,
+            vec![
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::new("key"))),
+                ColumnLiteral {
+                    expression: Expression::Column(ColumnRef::new("action")),
+                    alias: Some("temp".to_string()),
+                },
+            ]
+        );
+        assert_eq!(select_stmt.from, Some(TableExpression::table("replace")));
+    }
+
+    #[test]
+    fn test_select_reserved_keywords_as_names() {
+        assert_eq!(
+            parse_query("SELECT where FROM movies").err(),
+            Some(ParsingError::UnexpectedKeyword("where".into()))
+        );
+        assert_eq!(
+            parse_query("SELECT id AS select FROM movies").err(),
+            Some(ParsingError::UnexpectedKeyword("select".into()))
+        );
+        assert_eq!(
+            parse_query("SELECT id FROM table").err(),
+            Some(ParsingError::UnexpectedKeyword("table".into()))
+        );
+    }
+
+    #[test]
+    fn test_select_where_literals() {
+        for (query, literal) in [
+            ("SELECT * FROM movies WHERE TRUE", Literal::Boolean(true)),
+            ("SELECT * FROM movies WHERE FALSE", Literal::Boolean(false)),
+            ("SELECT * FROM movies WHERE NULL", Literal::Null),
+            ("SELECT * FROM movies WHERE 2.5", Literal::Float(2.5)),
+            ("SELECT * FROM movies WHERE 7", Literal::Integer(7)),
+            (
+                "SELECT * FROM movies WHERE 'abc'",
+                Literal::String("abc".into()),
+            ),
+        ] {
+            let select_stmt = parse_statement(query).expect("Expected valid select statement");
+            assert_eq!(select_stmt.where_clause, Some(Expression::Literal(literal)));
+        }
+    }
+
+    #[test]
+    fn test_select_where_expression() {
+        let select_stmt =
+            parse_statement("SELECT * FROM movies WHERE released >= 2000 AND ultrahd")
+                .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.where_clause,
+            Some(Expression::binary(
+                Expression::binary(
+                    Expression::Column(ColumnRef::new("released")),
+                    BinaryOperator::GreaterOrEqual,
+                    Expression::Literal(Literal::Integer(2000)),
+                ),
+                BinaryOperator::And,
+                Expression::Column(ColumnRef::new("ultrahd")),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_select_where_nested() {
+        let select_stmt =
+            parse_query("SELECT * FROM movies WHERE (id = 2 OR NOT id = 3) AND genre_id = 1")
+                .expect("Expected valid select statement");
+        let id_equals = |value| {
+            Expression::binary(
+                Expression::Column(ColumnRef::new("id")),
+                BinaryOperator::Equals,
+                Expression::Literal(Literal::Integer(value)),
+            )
+        };
+        assert_eq!(
+            select_stmt.where_clause,
+            Some(Expression::binary(
+                Expression::Nested(Box::new(Expression::binary(
+                    id_equals(2),
+                    BinaryOperator::Or,
+                    Expression::unary(UnaryOperator::Not, id_equals(3)),
+                ))),
+                BinaryOperator::And,
+                Expression::binary(
+                    Expression::Column(ColumnRef::new("genre_id")),
+                    BinaryOperator::Equals,
+                    Expression::Literal(Literal::Integer(1)),
+                ),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_select_where_qualified() {
+        let select_stmt =
+            parse_query("SELECT m.id AS movie_id FROM movies WHERE movie_id >= 3 AND m.id = 1")
+                .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.where_clause,
+            Some(Expression::binary(
+                Expression::binary(
+                    Expression::Column(ColumnRef::new("movie_id")),
+                    BinaryOperator::GreaterOrEqual,
+                    Expression::Literal(Literal::Integer(3)),
+                ),
+                BinaryOperator::And,
+                Expression::binary(
+                    Expression::Column(ColumnRef::qualified("m", "id")),
+                    BinaryOperator::Equals,
+                    Expression::Literal(Literal::Integer(1)),
+                ),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_select_where_without_from() {
+        let select_stmt =
+            parse_statement("SELECT 1 WHERE 2 > 1").expect("Expected valid select statement");
+        assert_eq!(select_stmt.from, None);
+        assert!(select_stmt.where_clause.is_some());
+    }
+
+    #[test]
+    fn test_select_where_malformed() {
+        // where_bare
+        assert_eq!(
+            parse_statement("SELECT * FROM movies WHERE").err(),
+            Some(ParsingError::InStatement(
+                1,
+                Box::new(ParsingError::UnexpectedEOF)
+            ))
+        );
+        // where_multi
+        assert_eq!(
+            parse_statement("SELECT * FROM movies WHERE TRUE, TRUE").err(),
+            Some(ParsingError::InStatement(
+                1,
+                Box::new(ParsingError::UnexpectedToken(",".into()))
+            ))
+        );
+        assert_eq!(
+            parse_query("SELECT * FROM movies WHERE TRUE, TRUE").err(),
+            Some(ParsingError::UnexpectedToken(",".into()))
+        );
+        assert_eq!(
+            parse_statement("SELECT * FROM movies WHERE WHERE").err(),
+            Some(ParsingError::InStatement(
+                1,
+                Box::new(ParsingError::UnexpectedKeyword("where".into()))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_select_group_by() {
+        let select_stmt =
+            parse_statement("SELECT released % 10, COUNT(id) FROM movies GROUP BY released % 10")
+                .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.group_by,
+            Some(vec![Expression::binary(
+                Expression::Column(ColumnRef::new("released")),
+                BinaryOperator::Modulo,
+                Expression::Literal(Literal::Integer(10)),
+            )])
+        );
+        assert_eq!(select_stmt.having, None);
+
+        let select_stmt = parse_statement(
+            "SELECT * FROM movies WHERE id > 1 GROUP BY studio_id, movies.genre_id",
+        )
+        .expect("Expected valid select statement");
+        assert!(select_stmt.where_clause.is_some());
+        assert_eq!(
+            select_stmt.group_by,
+            Some(vec![
+                Expression::Column(ColumnRef::new("studio_id")),
+                Expression::Column(ColumnRef::qualified("movies", "genre_id")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_select_having() {
+        let select_stmt = parse_statement(
+            "SELECT studio_id, MAX(rating) FROM movies GROUP BY studio_id HAVING MAX(rating) > 8",
+        )
+        .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.having,
+            Some(Expression::binary(
+                Expression::function("MAX", vec![Expression::Column(ColumnRef::new("rating"))]),
+                BinaryOperator::GreaterThan,
+                Expression::Literal(Literal::Integer(8)),
+            ))
+        );
+
+        // SQLite accepts HAVING without GROUP BY, treating the whole result as one group
+        let select_stmt = parse_statement("SELECT COUNT(id) FROM movies HAVING COUNT(id) > 1")
+            .expect("Expected valid select statement");
+        assert_eq!(select_stmt.group_by, None);
+        assert!(select_stmt.having.is_some());
+    }
+
+    #[test]
+    fn test_select_group_by_malformed() {
+        for (query, error) in [
+            ("SELECT * FROM movies GROUP", ParsingError::UnexpectedEOF),
+            (
+                "SELECT * FROM movies GROUP id",
+                ParsingError::UnexpectedToken("id".into()),
+            ),
+            ("SELECT * FROM movies GROUP BY", ParsingError::UnexpectedEOF),
+            (
+                "SELECT * FROM movies GROUP BY id,",
+                ParsingError::UnexpectedEOF,
+            ),
+            (
+                "SELECT * FROM movies GROUP BY id HAVING",
+                ParsingError::UnexpectedEOF,
+            ),
+            (
+                "SELECT * FROM movies HAVING id > 1 GROUP BY id",
+                ParsingError::UnexpectedKeyword("group".into()),
+            ),
+        ] {
+            assert_eq!(
+                parse_statement(query).err(),
+                Some(ParsingError::InStatement(1, Box::new(error))),
+                "{query}"
+            );
+        }
+    }
+
+    fn term(expression: Expression, ordering: Ordering) -> OrderingTerm {
+        OrderingTerm {
+            expression,
+            ordering,
+            nulls: None,
+        }
+    }
+
+    #[test]
+    fn test_select_order_by() {
+        for (query, ordering) in [
+            (
+                "SELECT * FROM movies ORDER BY released",
+                Ordering::Ascending,
+            ),
+            (
+                "SELECT * FROM movies ORDER BY released ASC",
+                Ordering::Ascending,
+            ),
+            (
+                "SELECT * FROM movies ORDER BY released asc",
+                Ordering::Ascending,
+            ),
+            (
+                "SELECT * FROM movies ORDER BY released DESC",
+                Ordering::Descending,
+            ),
+            (
+                "SELECT * FROM movies ORDER BY released desc",
+                Ordering::Descending,
+            ),
+        ] {
+            let select_stmt = parse_statement(query).expect("Expected valid select statement");
+            assert_eq!(
+                select_stmt.order_by,
+                Some(vec![term(
+                    Expression::Column(ColumnRef::new("released")),
+                    ordering
+                )]),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_order_by_terms() {
+        let select_stmt = parse_statement(
+            "SELECT id, title FROM movies WHERE id > 1 \
+            ORDER BY released % 4 ASC, 2 DESC NULLS FIRST, title COLLATE nocase NULLS LAST",
+        )
+        .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.order_by,
+            Some(vec![
+                term(
+                    Expression::binary(
+                        Expression::Column(ColumnRef::new("released")),
+                        BinaryOperator::Modulo,
+                        Expression::Literal(Literal::Integer(4)),
+                    ),
+                    Ordering::Ascending
+                ),
+                OrderingTerm {
+                    expression: Expression::Literal(Literal::Integer(2)),
+                    ordering: Ordering::Descending,
+                    nulls: Some(NullsOrdering::First),
+                },
+                OrderingTerm {
+                    expression: Expression::Collate {
+                        expression: Box::new(Expression::Column(ColumnRef::new("title"))),
+                        collation: "nocase".into(),
+                    },
+                    ordering: Ordering::Ascending,
+                    nulls: Some(NullsOrdering::Last),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_select_limit() {
+        let integer = |value| Expression::Literal(Literal::Integer(value));
+        for (query, limit) in [
+            (
+                "SELECT * FROM movies LIMIT 10",
+                Limit {
+                    count: integer(10),
+                    offset: None,
+                },
+            ),
+            (
+                "SELECT * FROM movies ORDER BY id LIMIT 10 OFFSET 2 * 5",
+                Limit {
+                    count: integer(10),
+                    offset: Some(Expression::binary(
+                        integer(2),
+                        BinaryOperator::Multiply,
+                        integer(5),
+                    )),
+                },
+            ),
+            (
+                "SELECT * FROM movies LIMIT 20, 10",
+                Limit {
+                    count: integer(10),
+                    offset: Some(integer(20)),
+                },
+            ),
+        ] {
+            let select_stmt = parse_statement(query).expect("Expected valid select statement");
+            assert_eq!(select_stmt.limit, Some(limit), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_select_order_by_malformed() {
+        for (query, error) in [
+            // order_unknown_dir
+            (
+                "SELECT * FROM movies ORDER BY id X",
+                ParsingError::UnexpectedToken("X".into()),
+            ),
+            // order_trailing_comma
+            (
+                "SELECT * FROM movies ORDER BY id,",
+                ParsingError::UnexpectedEOF,
+            ),
+            (
+                "SELECT * FROM movies ORDER id",
+                ParsingError::UnexpectedToken("id".into()),
+            ),
+            (
+                "SELECT * FROM movies ORDER BY id NULLS",
+                ParsingError::UnexpectedEOF,
+            ),
+            (
+                "SELECT * FROM movies ORDER BY id DESC ASC",
+                ParsingError::UnexpectedKeyword("asc".into()),
+            ),
+            (
+                "SELECT * FROM movies LIMIT 1 ORDER BY id",
+                ParsingError::UnexpectedKeyword("order".into()),
+            ),
+            ("SELECT * FROM movies LIMIT", ParsingError::UnexpectedEOF),
+            (
+                "SELECT * FROM movies LIMIT 1 OFFSET",
+                ParsingError::UnexpectedEOF,
+            ),
+            (
+                "SELECT * FROM movies LIMIT 1, 2 OFFSET 3",
+                ParsingError::UnexpectedKeyword("offset".into()),
+            ),
+        ] {
+            assert_eq!(parse_query(query).err(), Some(error.clone()), "{query}");
+            assert_eq!(
+                parse_statement(query).err(),
+                Some(ParsingError::InStatement(1, Box::new(error))),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_aggregates() {
+        let select_stmt = parse_statement(
+            "SELECT studio_id, MAX(rating) FROM movies GROUP BY studio_id ORDER BY MAX(rating)",
+        )
+        .expect("Expected valid select statement");
+        let max_rating =
+            Expression::function("MAX", vec![Expression::Column(ColumnRef::new("rating"))]);
+        assert_eq!(select_stmt.columns[1].expression, max_rating);
+        assert_eq!(
+            select_stmt.order_by,
+            Some(vec![term(max_rating, Ordering::Ascending)])
+        );
+
+        let select_stmt = parse_statement(
+            "SELECT COUNT(*) AS total, count(DISTINCT genre_id) filter (WHERE ultrahd) FROM movies",
+        )
+        .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.columns,
+            vec![
+                ColumnLiteral {
+                    expression: Expression::function("COUNT", vec![Expression::Wildcard(None)]),
+                    alias: Some("total".into()),
+                },
+                ColumnLiteral::from_expression(Expression::Function {
+                    name: "count".into(),
+                    arguments: vec![Expression::Column(ColumnRef::new("genre_id"))],
+                    distinct: true,
+                    filter: Some(Box::new(Expression::Column(ColumnRef::new("ultrahd")))),
+                }),
+            ]
+        );
+
+        // Without the parenthesis FILTER is just an alias
+        let select_stmt = parse_statement("SELECT COUNT(*) filter FROM movies")
+            .expect("Expected valid select statement");
+        assert_eq!(select_stmt.columns[0].alias, Some("filter".into()));
+    }
+
+    #[test]
+    fn test_select_from_multiple_tables() {
+        let select_stmt = parse_statement("SELECT * FROM movies, genres, countries")
+            .expect("Expected valid select statement");
+        let movies_genres = TableExpression::join(
+            TableExpression::table("movies"),
+            JoinKind::Comma,
+            TableExpression::table("genres"),
+            None,
+        );
+        assert_eq!(
+            select_stmt.from,
+            Some(TableExpression::join(
+                movies_genres,
+                JoinKind::Comma,
+                TableExpression::table("countries"),
+                None,
+            ))
+        );
+
+        // Unknown tables are only detected by the engine
+        let select_stmt =
+            parse_statement("SELECT * FROM main.unknown").expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.from,
+            Some(TableExpression::Table {
+                schema: Some("main".into()),
+                name: "unknown".into(),
+                alias: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_select_from_aliased_tables() {
+        let select_stmt = parse_statement("SELECT m.id, g.id FROM movies AS m, genres g")
+            .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.columns,
+            vec![
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::qualified("m", "id"))),
+                ColumnLiteral::from_expression(Expression::Column(ColumnRef::qualified("g", "id"))),
+            ]
+        );
+        assert_eq!(
+            select_stmt.from,
+            Some(TableExpression::join(
+                TableExpression::aliased("movies", "m"),
+                JoinKind::Comma,
+                TableExpression::aliased("genres", "g"),
+                None,
+            ))
+        );
+    }
+
+    #[test]
+    fn test_select_from_joins() {
+        let on = Expression::binary(
+            Expression::Column(ColumnRef::qualified("m", "genre_id")),
+            BinaryOperator::Equals,
+            Expression::Column(ColumnRef::qualified("g", "id")),
+        );
+        let cases = [
+            ("JOIN", JoinKind::Inner),
+            ("INNER JOIN", JoinKind::Inner),
+            ("LEFT JOIN", JoinKind::Left),
+            ("left outer join", JoinKind::Left),
+            ("RIGHT OUTER JOIN", JoinKind::Right),
+            ("FULL JOIN", JoinKind::Full),
+            ("CROSS JOIN", JoinKind::Cross),
+        ];
+        for (operator, kind) in cases {
+            let query = format!("SELECT * FROM movies m {operator} genres g ON m.genre_id = g.id");
+            let select_stmt = parse_statement(&query).expect("Expected valid select statement");
+            assert_eq!(
+                select_stmt.from,
+                Some(TableExpression::join(
+                    TableExpression::aliased("movies", "m"),
+                    kind,
+                    TableExpression::aliased("genres", "g"),
+                    Some(JoinConstraint::On(on.clone())),
+                )),
+                "{query}"
+            );
+        }
+
+        let select_stmt =
+            parse_statement("SELECT * FROM movies JOIN genres USING (id, name) WHERE TRUE")
+                .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.from,
+            Some(TableExpression::join(
+                TableExpression::table("movies"),
+                JoinKind::Inner,
+                TableExpression::table("genres"),
+                Some(JoinConstraint::Using(vec!["id".into(), "name".into()])),
+            ))
+        );
+        assert!(select_stmt.where_clause.is_some());
+
+        let select_stmt = parse_statement("SELECT * FROM movies NATURAL LEFT JOIN genres")
+            .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.from,
+            Some(TableExpression::Join {
+                left: Box::new(TableExpression::table("movies")),
+                operator: JoinOperator {
+                    natural: true,
+                    kind: JoinKind::Left,
+                },
+                right: Box::new(TableExpression::table("genres")),
+                constraint: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_select_from_nested_joins() {
+        // Joins are left-associative unless parenthesized
+        let select_stmt = parse_statement("SELECT * FROM a JOIN b ON TRUE, c")
+            .expect("Expected valid select statement");
+        let a_b = TableExpression::join(
+            TableExpression::table("a"),
+            JoinKind::Inner,
+            TableExpression::table("b"),
+            Some(JoinConstraint::On(Expression::Literal(Literal::Boolean(
+                true,
+            )))),
+        );
+        assert_eq!(
+            select_stmt.from,
+            Some(TableExpression::join(
+                a_b,
+                JoinKind::Comma,
+                TableExpression::table("c"),
+                None
+            ))
+        );
+
+        let select_stmt = parse_statement("SELECT * FROM a LEFT JOIN (b, c) USING (id)")
+            .expect("Expected valid select statement");
+        let b_c = TableExpression::join(
+            TableExpression::table("b"),
+            JoinKind::Comma,
+            TableExpression::table("c"),
+            None,
+        );
+        assert_eq!(
+            select_stmt.from,
+            Some(TableExpression::join(
+                TableExpression::table("a"),
+                JoinKind::Left,
+                b_c,
+                Some(JoinConstraint::Using(vec!["id".into()])),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_select_from_duplicate_tables() {
+        let cases = [
+            ("SELECT * FROM movies a, genres a", "a"),
+            ("SELECT * FROM movies a JOIN genres A ON TRUE", "a"),
+            ("SELECT * FROM movies m, (genres, Movies M)", "m"),
+            ("SELECT * FROM (SELECT 1) t, main.movies AS t", "t"),
+        ];
+        for (query, name) in cases {
+            assert_eq!(
+                parse_statement(query),
+                Err(ParsingError::InStatement(
+                    1,
+                    Box::new(ParsingError::DuplicateTable(name.into()))
+                )),
+                "{query}"
+            );
+        }
+        // Only aliases have to be unique, as in SQLite
+        for query in [
+            "SELECT * FROM movies, movies",
+            "SELECT * FROM movies NATURAL JOIN Movies",
+            "SELECT * FROM movies, genres movies",
+            "SELECT * FROM main.movies, MAIN.Movies",
+            "SELECT * FROM main.movies, archive.movies",
+        ] {
+            assert!(parse_statement(query).is_ok(), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_select_from_malformed() {
+        let cases = [
+            ("SELECT * FROM", ParsingError::UnexpectedEOF),
+            ("SELECT * FROM movies,", ParsingError::UnexpectedEOF),
+            ("SELECT * FROM movies JOIN", ParsingError::UnexpectedEOF),
+            (
+                "SELECT * FROM movies LEFT genres",
+                ParsingError::UnexpectedToken("genres".into()),
+            ),
+            (
+                "SELECT * FROM movies NATURAL genres",
+                ParsingError::UnexpectedToken("genres".into()),
+            ),
+            (
+                "SELECT * FROM movies JOIN genres ON",
+                ParsingError::UnexpectedEOF,
+            ),
+            (
+                "SELECT * FROM movies JOIN genres USING ()",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            ("SELECT * FROM (movies", ParsingError::UnexpectedEOF),
+            ("SELECT * FROM movies AS", ParsingError::UnexpectedEOF),
+        ];
+        for (query, error) in cases {
+            assert_eq!(
+                parse_statement(query),
+                Err(ParsingError::InStatement(1, Box::new(error))),
+                "{query}"
+            );
+        }
+    }
+
+    fn select_one() -> Box<Select> {
+        Box::new(Select {
+            with: None,
+            columns: vec![ColumnLiteral::from_expression(Expression::Literal(
+                Literal::Integer(1),
+            ))],
+            from: None,
+            where_clause: None,
+            group_by: None,
+            having: None,
+            compound: vec![],
+            order_by: None,
+            limit: None,
+            distinct: false,
+        })
+    }
+
+    #[test]
+    fn test_select_scalar_subqueries() {
+        let select_stmt = parse_statement("SELECT (SELECT 1), (SELECT 1) + 1, ((SELECT 1))")
+            .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.columns,
+            vec![
+                ColumnLiteral::from_expression(Expression::Subquery(select_one())),
+                ColumnLiteral::from_expression(Expression::binary(
+                    Expression::Subquery(select_one()),
+                    BinaryOperator::Add,
+                    Expression::Literal(Literal::Integer(1)),
+                )),
+                ColumnLiteral::from_expression(Expression::Nested(Box::new(Expression::Subquery(
+                    select_one()
+                )))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_exists_and_in_subqueries() {
+        let select_stmt = parse_statement(
+            "SELECT * FROM movies WHERE EXISTS (SELECT 1) AND NOT EXISTS (SELECT 1) \
+             AND id IN (SELECT 1) AND id NOT IN (SELECT 1)",
+        )
+        .expect("Expected valid select statement");
+        let id = || Box::new(Expression::Column(ColumnRef::new("id")));
+        let expected = [
+            Expression::Exists(select_one()),
+            Expression::unary(UnaryOperator::Not, Expression::Exists(select_one())),
+            Expression::InSubquery {
+                expression: id(),
+                negated: false,
+                subquery: select_one(),
+            },
+            Expression::InSubquery {
+                expression: id(),
+                negated: true,
+                subquery: select_one(),
+            },
+        ]
+        .into_iter()
+        .reduce(|left, right| Expression::binary(left, BinaryOperator::And, right));
+        assert_eq!(select_stmt.where_clause, expected);
+    }
+
+    #[test]
+    fn test_select_correlated_subquery() {
+        let select_stmt = parse_statement(
+            "SELECT title FROM movies m WHERE rating > \
+             (SELECT AVG(rating) FROM movies WHERE genre_id = m.genre_id)",
+        )
+        .expect("Expected valid select statement");
+        let Some(Expression::Binary { right, .. }) = select_stmt.where_clause else {
+            panic!("Expected a comparison");
+        };
+        let Expression::Subquery(subquery) = *right else {
+            panic!("Expected a subquery");
+        };
+        assert_eq!(subquery.from, Some(TableExpression::table("movies")));
+        assert_eq!(
+            subquery.where_clause,
+            Some(Expression::binary(
+                Expression::Column(ColumnRef::new("genre_id")),
+                BinaryOperator::Equals,
+                Expression::Column(ColumnRef::qualified("m", "genre_id")),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_select_from_subqueries() {
+        let select_stmt = parse_statement("SELECT t.x FROM (SELECT 1) AS t, (SELECT 1) u")
+            .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.from,
+            Some(TableExpression::join(
+                TableExpression::Subquery {
+                    query: select_one(),
+                    alias: Some("t".into()),
+                },
+                JoinKind::Comma,
+                TableExpression::Subquery {
+                    query: select_one(),
+                    alias: Some("u".into()),
+                },
+                None,
+            ))
+        );
+
+        // The alias is optional, as in SQLite
+        let select_stmt = parse_statement("SELECT * FROM ((SELECT 1)) JOIN movies USING (id)")
+            .expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.from,
+            Some(TableExpression::join(
+                TableExpression::Subquery {
+                    query: select_one(),
+                    alias: None,
+                },
+                JoinKind::Inner,
+                TableExpression::table("movies"),
+                Some(JoinConstraint::Using(vec!["id".into()])),
+            ))
+        );
+
+        assert!(parse_statement("SELECT * FROM (SELECT 1) movies, movies").is_ok());
     }
 
-    // field_ambiguous: "SELECT id FROM movies, genres",
-    // field_unknown: "SELECT unknown FROM movies",
-    // alias: SELECT col1 AS table.body.value FROM users
-
-    // field_unknown_aliased: "SELECT movies.id FROM movies AS m",
-    // field_unknown_qualified: "SELECT movies.unknown FROM movies",
-    // field_unknown_table: "SELECT unknown.id FROM movies",
-    // field_aliased: "SELECT m.id, g.id FROM movies AS m, genres g",
-
-    // expr_dynamic: "SELECT 2020 - year AS age FROM movies",
-    // expr_static: "SELECT 1 + 2 * 3, 'abc' LIKE 'x%' AS nope",
-    // expr_mixed: "SELECT 1 + 2 * 3, 2020 - released AS age FROM movies",
-
-    // from_bare: "SELECT * FROM",
-    // from_multiple: "SELECT * FROM movies, genres, countries",
-    // from_unknown: "SELECT * FROM unknown",
-    // from_alias_duplicate: "SELECT * FROM movies a, genres a",
-    // from_alias_duplicate_join: "SELECT * FROM movies a JOIN genres a ON TRUE",
-    // from_duplicate: "SELECT * FROM movies, movies",
-
-    // where_bare: "SELECT * FROM movies WHERE",
-    // where_true: "SELECT * FROM movies WHERE TRUE",
-    // where_false: "SELECT * FROM movies WHERE FALSE",
-    // where_null: "SELECT * FROM movies WHERE NULL",
-    // where_expr: "SELECT * FROM movies WHERE released >= 2000 AND ultrahd",
-    // where_float: "SELECT * FROM movies WHERE 3.14",
-    // where_integer: "SELECT * FROM movies WHERE 7",
-    // where_string: "SELECT * FROM movies WHERE 'abc'",
-    // where_multi: "SELECT * FROM movies WHERE TRUE, TRUE",
-    // where_pk: "SELECT * FROM movies WHERE id = 3",
-    // where_pk_or: "SELECT * FROM movies WHERE id = 3 OR id = 5 OR id = 7",
-    // where_pk_or_partial: "SELECT * FROM movies WHERE (id = 2 OR id = 3 OR id = 4 OR id = 5) AND genre_id = 1",
-    // where_index: "SELECT * FROM movies WHERE genre_id = 2 ORDER BY id",
-    // where_index_or: "SELECT * FROM movies WHERE genre_id = 2 OR genre_id = 3 OR genre_id = 4 OR genre_id = 5 ORDER BY id",
-    // where_index_or_partial: "SELECT * FROM movies WHERE (genre_id = 2 OR genre_id = 3) AND studio_id = 2 ORDER BY id",
-    // where_field_unknown: "SELECT * FROM movies WHERE unknown",
-    // where_field_qualified: "SELECT movies.id, genres.id FROM movies, genres WHERE movies.id >= 3 AND genres.id = 1",
-    // where_field_ambiguous: "SELECT movies.id, genres.id FROM movies, genres WHERE id >= 3",
-    // where_field_aliased_select: "SELECT m.id AS movie_id, g.id AS genre_id FROM movies m, genres g WHERE movie_id >= 3 AND genre_id = 1",
-    // where_field_aliased_table: "SELECT m.id, g.id FROM movies m, genres g WHERE m.id >= 3 AND g.id = 1",
-    // where_join_inner: "SELECT * FROM movies, genres WHERE movies.genre_id = genres.id",
-
-    // order: "SELECT * FROM movies ORDER BY released",
-    // order_asc: "SELECT * FROM movies ORDER BY released ASC",
-    // order_asc_lowercase: "SELECT * FROM movies ORDER BY released asc",
-    // order_desc: "SELECT * FROM movies ORDER BY released DESC",
-    // order_desc_lowercase: "SELECT * FROM movies ORDER BY released desc",
-    // order_expr: "SELECT id, title, released, released % 4 AS ord FROM movies ORDER BY released % 4 ASC",
-    // order_multi: "SELECT * FROM movies ORDER BY ultrahd ASC, id DESC",
-    // order_noselect: "SELECT id, title FROM movies ORDER BY released",
-    // order_unknown_dir: "SELECT * FROM movies ORDER BY id X",
-    // order_field_unknown: "SELECT * FROM movies ORDER BY unknown",
-    // order_field_qualified: "SELECT movies.id, title, name FROM movies, genres WHERE movies.genre_id = genres.id ORDER BY genres.name, movies.title",
-    // order_field_aliased: "SELECT movies.id, title, genres.name AS genre FROM movies, genres WHERE movies.genre_id = genres.id ORDER BY genre, title",
-    // order_field_ambiguous: "SELECT * FROM movies, genres WHERE movies.genre_id = genres.id ORDER BY id",
-    // order_trailing_comma: "SELECT * FROM movies ORDER BY id,",
-    // order_aggregate: "SELECT studio_id, MAX(rating) FROM movies GROUP BY studio_id ORDER BY MAX(rating)",
-    // order_aggregate_noselect: "SELECT studio_id, MAX(rating) FROM movies GROUP BY studio_id ORDER BY MIN(rating)",
-    // order_group_by_noselect: "SELECT MAX(rating) FROM movies GROUP BY studio_id ORDER BY studio_id",
+    #[test]
+    fn test_select_subqueries_malformed() {
+        let cases = [
+            ("SELECT EXISTS 1", ParsingError::UnexpectedToken("1".into())),
+            (
+                "SELECT EXISTS (1)",
+                ParsingError::UnexpectedToken("(".into()),
+            ),
+            ("SELECT (SELECT 1", ParsingError::UnexpectedEOF),
+            ("SELECT (SELECT)", ParsingError::UnexpectedToken(")".into())),
+            (
+                "SELECT 1 IN (SELECT 1, )",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            ("SELECT * FROM (SELECT 1) AS", ParsingError::UnexpectedEOF),
+        ];
+        for (query, error) in cases {
+            assert_eq!(
+                parse_statement(query),
+                Err(ParsingError::InStatement(1, Box::new(error))),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_subquery_nesting_limit() {
+        let nested = |depth: usize| {
+            let query = format!("SELECT {}1{}", "(SELECT ".repeat(depth), ")".repeat(depth));
+            Parser::new(&query).parse().map(|_| ())
+        };
+        assert!(nested(MAX_QUERY_DEPTH - 1).is_ok());
+        assert_eq!(
+            nested(MAX_QUERY_DEPTH),
+            Err(ParsingError::TooDeep(MAX_QUERY_DEPTH))
+        );
+
+        let from = |depth: usize| {
+            let query = format!(
+                "SELECT * FROM {}t{}",
+                "(SELECT * FROM ".repeat(depth),
+                ")".repeat(depth)
+            );
+            Parser::new(&query).parse().map(|_| ())
+        };
+        assert!(from(MAX_QUERY_DEPTH - 1).is_ok());
+        assert_eq!(
+            from(MAX_QUERY_DEPTH),
+            Err(ParsingError::TooDeep(MAX_QUERY_DEPTH))
+        );
+
+        let parenthesized = |depth: usize| {
+            let query = format!("SELECT * FROM {}t{}", "(".repeat(depth), ")".repeat(depth));
+            Parser::new(&query).parse().map(|_| ())
+        };
+        assert!(parenthesized(MAX_EXPRESSION_DEPTH - 1).is_ok());
+        assert_eq!(
+            parenthesized(MAX_EXPRESSION_DEPTH + 1),
+            Err(ParsingError::TooDeep(MAX_EXPRESSION_DEPTH))
+        );
+
+        // Both limits at once still fit on the stack
+        let depth = MAX_QUERY_DEPTH - 1;
+        let parentheses = MAX_EXPRESSION_DEPTH - depth - 1;
+        let query = format!(
+            "SELECT {}{}1{}{}",
+            "(SELECT ".repeat(depth),
+            "(".repeat(parentheses),
+            ")".repeat(parentheses),
+            ")".repeat(depth)
+        );
+        assert!(Parser::new(&query).parse().is_ok());
+    }
+
+    #[test]
+    fn test_select_compound() {
+        let select_stmt = parse_statement(
+            "SELECT id FROM movies UNION SELECT id FROM genres UNION ALL SELECT 1 \
+             INTERSECT SELECT 1 EXCEPT SELECT 1 ORDER BY id DESC LIMIT 3",
+        )
+        .expect("Expected valid select statement");
+        assert_eq!(select_stmt.from, Some(TableExpression::table("movies")));
+        let operators: Vec<CompoundOperator> = select_stmt
+            .compound
+            .iter()
+            .map(|(operator, _)| *operator)
+            .collect();
+        assert_eq!(
+            operators,
+            vec![
+                CompoundOperator::Union,
+                CompoundOperator::UnionAll,
+                CompoundOperator::Intersect,
+                CompoundOperator::Except,
+            ]
+        );
+        assert_eq!(
+            select_stmt.compound[0].1.from,
+            Some(TableExpression::table("genres"))
+        );
+        assert_eq!(select_stmt.compound[1].1, *select_one());
+        // ORDER BY and LIMIT belong to the whole compound
+        assert_eq!(
+            select_stmt.order_by,
+            Some(vec![term(
+                Expression::Column(ColumnRef::new("id")),
+                Ordering::Descending
+            )])
+        );
+        assert!(select_stmt.limit.is_some());
+        assert!(select_stmt
+            .compound
+            .iter()
+            .all(|(_, core)| core.order_by.is_none() && core.limit.is_none()));
+    }
+
+    #[test]
+    fn test_select_compound_in_subquery() {
+        let select_stmt =
+            parse_statement("SELECT 1 WHERE 1 IN (SELECT 1 UNION SELECT 1) UNION SELECT 1")
+                .expect("Expected valid select statement");
+        assert_eq!(select_stmt.compound.len(), 1);
+        let Some(Expression::InSubquery { subquery, .. }) = select_stmt.where_clause else {
+            panic!("Expected an IN subquery");
+        };
+        assert_eq!(
+            subquery.compound,
+            vec![(CompoundOperator::Union, *select_one())]
+        );
+    }
+
+    #[test]
+    fn test_select_compound_column_count() {
+        assert_eq!(
+            parse_statement("SELECT 1, 2 UNION SELECT 1"),
+            Err(ParsingError::InStatement(
+                1,
+                Box::new(ParsingError::CompoundColumnCount("UNION".into()))
+            ))
+        );
+        assert_eq!(
+            parse_statement("SELECT 1 UNION ALL SELECT 1 EXCEPT SELECT 1, 2"),
+            Err(ParsingError::InStatement(
+                1,
+                Box::new(ParsingError::CompoundColumnCount("EXCEPT".into()))
+            ))
+        );
+        // A wildcard's column count is only known to the engine
+        assert!(parse_statement("SELECT * FROM movies UNION SELECT 1, 2").is_ok());
+        assert!(parse_statement("SELECT 1, 2 INTERSECT SELECT genres.* FROM genres").is_ok());
+    }
+
+    #[test]
+    fn test_select_compound_malformed() {
+        let cases = [
+            ("SELECT 1 UNION", ParsingError::UnexpectedEOF),
+            ("SELECT 1 UNION ALL", ParsingError::UnexpectedEOF),
+            (
+                "SELECT 1 UNION 1",
+                ParsingError::UnexpectedToken("1".into()),
+            ),
+            (
+                "SELECT 1 ORDER BY 1 UNION SELECT 1",
+                ParsingError::UnexpectedKeyword("union".into()),
+            ),
+            (
+                "SELECT 1 LIMIT 1 UNION SELECT 1",
+                ParsingError::UnexpectedKeyword("union".into()),
+            ),
+        ];
+        for (query, error) in cases {
+            assert_eq!(
+                parse_statement(query),
+                Err(ParsingError::InStatement(1, Box::new(error))),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_with() {
+        let select_stmt = parse_statement(
+            "WITH RECURSIVE chain(id, manager) AS (SELECT 1, NULL UNION ALL SELECT 2, 1), \
+             top AS MATERIALIZED (SELECT 1), rest AS NOT MATERIALIZED (SELECT 1) \
+             SELECT * FROM chain, top, rest",
+        )
+        .expect("Expected valid select statement");
+        let with = select_stmt.with.expect("Expected a WITH clause");
+        assert!(with.recursive);
+        let names: Vec<(&str, Option<bool>)> = with
+            .tables
+            .iter()
+            .map(|table| (table.name.as_str(), table.materialized))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("chain", None), ("top", Some(true)), ("rest", Some(false))]
+        );
+        assert_eq!(with.tables[0].columns, vec!["id", "manager"]);
+        assert_eq!(with.tables[0].query.compound.len(), 1);
+        assert_eq!(with.tables[1].query, select_one());
+        assert!(with.tables[1].columns.is_empty());
+
+        let select_stmt = parse_statement("with t as (select 1) select 1")
+            .expect("Expected valid select statement");
+        assert!(!select_stmt.with.expect("Expected a WITH clause").recursive);
+    }
+
+    #[test]
+    fn test_select_with_in_subqueries() {
+        let select_stmt = parse_statement(
+            "SELECT (WITH t AS (SELECT 1) SELECT 1) FROM (WITH u AS (SELECT 1) SELECT 1)",
+        )
+        .expect("Expected valid select statement");
+        let Expression::Subquery(subquery) = &select_stmt.columns[0].expression else {
+            panic!("Expected a subquery");
+        };
+        assert!(subquery.with.is_some());
+        let Some(TableExpression::Subquery { query, .. }) = &select_stmt.from else {
+            panic!("Expected a subquery");
+        };
+        assert!(query.with.is_some());
+
+        // WITH is not reserved, it can still name a column
+        let select_stmt =
+            parse_statement("SELECT (with) FROM movies").expect("Expected valid select statement");
+        assert_eq!(
+            select_stmt.columns[0].expression,
+            Expression::Nested(Box::new(Expression::Column(ColumnRef::new("with"))))
+        );
+    }
+
+    #[test]
+    fn test_select_with_malformed() {
+        let cases = [
+            ("WITH", ParsingError::UnexpectedEOF),
+            (
+                "WITH t SELECT 1",
+                ParsingError::UnexpectedKeyword("select".into()),
+            ),
+            (
+                "WITH t AS SELECT 1",
+                ParsingError::UnexpectedKeyword("select".into()),
+            ),
+            (
+                "WITH t() AS (SELECT 1) SELECT 1",
+                ParsingError::UnexpectedToken(")".into()),
+            ),
+            (
+                "WITH t AS NOT (SELECT 1) SELECT 1",
+                ParsingError::UnexpectedToken("(".into()),
+            ),
+            ("WITH t AS (SELECT 1),", ParsingError::UnexpectedEOF),
+            (
+                "WITH t AS (SELECT 1), T AS (SELECT 2) SELECT 1",
+                ParsingError::DuplicateTable("t".into()),
+            ),
+            (
+                "WITH t AS (SELECT 1) WITH u AS (SELECT 1) SELECT 1",
+                ParsingError::UnexpectedToken("WITH".into()),
+            ),
+        ];
+        for (query, error) in cases {
+            assert_eq!(
+                parse_statement(query),
+                Err(ParsingError::InStatement(1, Box::new(error))),
+                "{query}"
+            );
+        }
+    }
 }
@@ -0,0 +1,142 @@
+use crate::ast::{Ast, TransactionMode};
+use crate::parser::Parser;
+use crate::token::Keyword;
+use common::errors::ParsingError;
+
+pub trait TransactionQueryParser<'a> {
+    fn parse_transaction(&mut self) -> Result<Ast, ParsingError>;
+}
+
+impl<'a> TransactionQueryParser<'a> for Parser<'a> {
+    fn parse_transaction(&mut self) -> Result<Ast, ParsingError> {
+        let keyword = self.current_keyword()?;
+        self.eat()?;
+        match keyword {
+            Some(Keyword::Begin) => {
+                let mode = if self.eat_keyword(Keyword::Deferred)? {
+                    Some(TransactionMode::Deferred)
+                } else if self.eat_keyword(Keyword::Immediate)? {
+                    Some(TransactionMode::Immediate)
+                } else if self.eat_keyword(Keyword::Exclusive)? {
+                    Some(TransactionMode::Exclusive)
+                } else {
+                    None
+                };
+                self.eat_keyword(Keyword::Transaction)?;
+                Ok(Ast::Begin(mode))
+            }
+            Some(Keyword::Commit | Keyword::End) => {
+                self.eat_keyword(Keyword::Transaction)?;
+                Ok(Ast::Commit)
+            }
+            Some(Keyword::Rollback) => {
+                self.eat_keyword(Keyword::Transaction)?;
+                if !self.eat_keyword(Keyword::To)? {
+                    return Ok(Ast::Rollback(None));
+                }
+                self.eat_keyword(Keyword::Savepoint)?;
+                Ok(Ast::Rollback(Some(self.parse_identifier()?)))
+            }
+            Some(Keyword::Savepoint) => Ok(Ast::Savepoint(self.parse_identifier()?)),
+            Some(Keyword::Release) => {
+                self.eat_keyword(Keyword::Savepoint)?;
+                Ok(Ast::Release(self.parse_identifier()?))
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_query(query: &str) -> Result<Ast, ParsingError> {
+        Parser::new(query).parse()
+    }
+
+    #[test]
+    fn test_begin_commit() {
+        for (query, ast) in [
+            ("BEGIN", Ast::Begin(None)),
+            ("begin transaction", Ast::Begin(None)),
+            (
+                "BEGIN DEFERRED",
+                Ast::Begin(Some(TransactionMode::Deferred)),
+            ),
+            (
+                "BEGIN IMMEDIATE TRANSACTION",
+                Ast::Begin(Some(TransactionMode::Immediate)),
+            ),
+            (
+                "begin exclusive",
+                Ast::Begin(Some(TransactionMode::Exclusive)),
+            ),
+            ("COMMIT", Ast::Commit),
+            ("COMMIT TRANSACTION", Ast::Commit),
+            ("end", Ast::Commit),
+            ("END TRANSACTION", Ast::Commit),
+        ] {
+            assert_eq!(parse_query(query), Ok(ast), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_savepoints() {
+        for (query, ast) in [
+            ("ROLLBACK", Ast::Rollback(None)),
+            ("rollback transaction", Ast::Rollback(None)),
+            ("ROLLBACK TO sp", Ast::Rollback(Some("sp".into()))),
+            (
+                "ROLLBACK TRANSACTION TO SAVEPOINT \"my point\"",
+                Ast::Rollback(Some("my point".into())),
+            ),
+            ("SAVEPOINT sp", Ast::Savepoint("sp".into())),
+            ("savepoint \"end\"", Ast::Savepoint("end".into())),
+            ("RELEASE sp", Ast::Release("sp".into())),
+            ("RELEASE SAVEPOINT sp", Ast::Release("sp".into())),
+        ] {
+            assert_eq!(parse_query(query), Ok(ast), "{query}");
+        }
+    }
+
+    #[test]
+    fn test_transaction_round_trip() {
+        for query in [
+            "BEGIN",
+            "BEGIN IMMEDIATE",
+            "COMMIT",
+            "ROLLBACK",
+            "ROLLBACK TO SAVEPOINT \"select\"",
+            "SAVEPOINT sp",
+            "RELEASE SAVEPOINT sp",
+        ] {
+            assert_eq!(parse_query(query).unwrap().to_string(), query);
+        }
+    }
+
+    #[test]
+    fn test_transaction_malformed() {
+        for (query, error) in [
+            ("BEGIN WORK", ParsingError::UnexpectedToken("WORK".into())),
+            (
+                "BEGIN TRANSACTION IMMEDIATE",
+                ParsingError::UnexpectedKeyword("immediate".into()),
+            ),
+            ("COMMIT TO sp", ParsingError::UnexpectedKeyword("to".into())),
+            ("ROLLBACK TO", ParsingError::UnexpectedEOF),
+            ("ROLLBACK TO SAVEPOINT", ParsingError::UnexpectedEOF),
+            ("SAVEPOINT", ParsingError::UnexpectedEOF),
+            (
+                "RELEASE sp TO",
+                ParsingError::UnexpectedKeyword("to".into()),
+            ),
+        ] {
+            assert_eq!(
+                Parser::new(query).parse_all(),
+                Err(ParsingError::InStatement(1, Box::new(error.clone()))),
+                "{query}"
+            );
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use crate::ast::{Assignment, Ast, ColumnLiteral, Expression, Update};
+use crate::parser::create::CreateQueryParser;
+use crate::parser::expression::ExpressionParser;
+use crate::parser::select::SelectQueryParser;
+use crate::parser::Parser;
+use crate::token::{Keyword, Token};
+use common::errors::ParsingError;
+
+pub trait UpdateQueryParser<'a> {
+    fn parse_update(&mut self) -> Result<Ast, ParsingError>;
+
+    fn parse_set_clause(&mut self) -> Result<Vec<Assignment>, ParsingError>;
+
+    fn parse_assignment(&mut self) -> Result<Assignment, ParsingError>;
+
+    /// Parses the `RETURNING` clause of an INSERT, UPDATE or DELETE, if there is one
+    fn parse_returning(&mut self) -> Result<Vec<ColumnLiteral>, ParsingError>;
+}
+
+impl<'a> UpdateQueryParser<'a> for Parser<'a> {
+    fn parse_update(&mut self) -> Result<Ast, ParsingError> {
+        self.expect_keyword(Keyword::Update)?;
+        let conflict = match self.eat_keyword(Keyword::Or)? {
+            true => Some(self.parse_conflict_resolution()?),
+            false => None,
+        };
+        let (schema, table_name) = self.parse_qualified_name()?;
+
+        Ok(Ast::Update(Update {
+            conflict,
+            schema,
+            table_name,
+            set_clause: self.parse_set_clause()?,
+            from: self.parse_from()?,
+            where_clause: self.parse_where_clause()?,
+            returning: self.parse_returning()?,
+            order_by: self.parse_order_by_clause()?,
+            limit: self.parse_limit()?,
+        }))
+    }
+
+    fn parse_set_clause(&mut self) -> Result<Vec<Assignment>, ParsingError> {
+        self.expect_keyword(Keyword::Set)?;
+
+        let mut assignments = vec![self.parse_assignment()?];
+        while self.eat_token(Token::Comma)? {
+            assignments.push(self.parse_assignment()?);
+        }
+        Ok(assignments)
+    }
+
+    fn parse_assignment(&mut self) -> Result<Assignment, ParsingError> {
+        let columns = match *self.current_token()? == Token::OpenParen {
+            true => self.parse_identifier_list()?,
+            false => vec![self.parse_identifier()?],
+        };
+        self.expect_token(Token::Equals)?;
+        if columns.len() == 1 {
+            let values = vec![self.parse_expression()?];
+            return Ok(Assignment { columns, values });
+        }
+
+        // A row of columns takes a row of values, or a subquery
+        let values = if *self.current_token()? == Token::OpenParen && !self.at_subquery() {
+            self.eat()?;
+            let mut values = vec![self.parse_expression()?];
+            while self.eat_token(Token::Comma)? {
+                values.push(self.parse_expression()?);
+            }
+            self.expect_token(Token::CloseParen)?;
+            values
+        } else {
+            vec![self.parse_expression()?]
+        };
+        match values.as_slice() {
+            [Expression::Subquery(_)] => {}
+            _ if values.len() != columns.len() => {
+                return Err(ParsingError::AssignmentCount(columns.len(), values.len()))
+            }
+            _ => {}
+        }
+        Ok(Assignment { columns, values })
+    }
+
+    fn parse_returning(&mut self) -> Result<Vec<ColumnLiteral>, ParsingError> {
+        match self.eat_keyword(Keyword::Returning)? {
+            true => self.parse_columns(),
+            false => Ok(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{
+        BinaryOperator, ColumnRef, ConflictResolution, Limit, Literal, Ordering, OrderingTerm,
+        TableExpression, UnaryOperator,
+    };
+
+    fn parse_query(query: &str) -> Result<Update, ParsingError> {
+        match Parser::new(query).parse()? {
+            Ast::Update(update) => Ok(update),
+            _ => Err(ParsingError::UnexpectedToken("Expected update AST".into())),
+        }
+    }
+
+    fn assignment(column: &str, value: Expression) -> Assignment {
+        Assignment {
+            columns: vec![column.into()],
+            values: vec![value],
+        }
+    }
+
+    fn integer(value: i64) -> Expression {
+        Expression::Literal(Literal::Integer(value))
+    }
+
+    #[test]
+    fn test_update_all() {
+        assert_eq!(
+            parse_query("UPDATE movies SET ultrahd = TRUE, rating = rating + 1"),
+            Ok(Update {
+                conflict: None,
+                schema: None,
+                table_name: "movies".into(),
+                set_clause: vec![
+                    assignment("ultrahd", Expression::Literal(Literal::Boolean(true))),
+                    assignment(
+                        "rating",
+                        Expression::binary(
+                            Expression::Column(ColumnRef::new("rating")),
+                            BinaryOperator::Add,
+                            Expression::Literal(Literal::Integer(1)),
+                        )
+                    ),
+                ],
+                from: None,
+                where_clause: None,
+                returning: vec![],
+                order_by: None,
+                limit: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_where() {
+        let update = parse_query("update movies set title = 'x' where id = 3 or not ultrahd")
+            .expect("Expected valid update statement");
+        assert_eq!(
+            update.where_clause,
+            Some(Expression::binary(
+                Expression::binary(
+                    Expression::Column(ColumnRef::new("id")),
+                    BinaryOperator::Equals,
+                    Expression::Literal(Literal::Integer(3)),
+                ),
+                BinaryOperator::Or,
+                Expression::unary(
+                    UnaryOperator::Not,
+                    Expression::Column(ColumnRef::new("ultrahd")),
+                ),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_update_malformed() {
+        assert_eq!(
+            parse_query("UPDATE movies"),
+            Err(ParsingError::UnexpectedEOF)
+        );
+        assert_eq!(
+            parse_query("UPDATE movies SET title"),
+            Err(ParsingError::UnexpectedEOF)
+        );
+        assert_eq!(
+            parse_query("UPDATE movies SET title = 'x',"),
+            Err(ParsingError::UnexpectedEOF)
+        );
+        assert_eq!(
+            parse_query("UPDATE movies SET WHERE id = 1"),
+            Err(ParsingError::UnexpectedKeyword("where".into()))
+        );
+    }
+
+    #[test]
+    fn test_update_row_values() {
+        let update = parse_query(
+            "UPDATE OR IGNORE main.movies SET (title, year) = ('Heat', 1995), \
+             (a, b) = (SELECT 1, 2), (c) = (3)",
+        )
+        .unwrap();
+        assert_eq!(update.conflict, Some(ConflictResolution::Ignore));
+        assert_eq!(update.schema, Some("main".into()));
+        assert_eq!(
+            update.set_clause[0],
+            Assignment {
+                columns: vec!["title".into(), "year".into()],
+                values: vec![
+                    Expression::Literal(Literal::String("Heat".into())),
+                    integer(1995)
+                ],
+            }
+        );
+        assert!(matches!(
+            update.set_clause[1].values.as_slice(),
+            [Expression::Subquery(_)]
+        ));
+        assert_eq!(
+            update.set_clause[2],
+            assignment("c", Expression::Nested(Box::new(integer(3))))
+        );
+    }
+
+    #[test]
+    fn test_update_from_returning_limit() {
+        let update = parse_query(
+            "UPDATE movies SET rating = r.value FROM ratings AS r WHERE r.id = movies.id \
+             RETURNING id, rating AS new_rating ORDER BY id DESC LIMIT 5",
+        )
+        .unwrap();
+        assert_eq!(
+            update.from,
+            Some(TableExpression::Table {
+                schema: None,
+                name: "ratings".into(),
+                alias: Some("r".into()),
+            })
+        );
+        assert_eq!(update.returning.len(), 2);
+        assert_eq!(update.returning[1].alias, Some("new_rating".into()));
+        assert_eq!(
+            update.order_by,
+            Some(vec![OrderingTerm {
+                expression: Expression::Column(ColumnRef::new("id")),
+                ordering: Ordering::Descending,
+                nulls: None,
+            }])
+        );
+        assert_eq!(
+            update.limit,
+            Some(Limit {
+                count: integer(5),
+                offset: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_round_trip() {
+        for query in [
+            "UPDATE OR REPLACE main.movies SET (a, b) = (1, 2), c = 3 RETURNING *",
+            "UPDATE movies SET (a, b) = (SELECT x, y FROM other) WHERE id = 1",
+            "UPDATE movies SET a = b FROM other WHERE other.id = movies.id ORDER BY a LIMIT 1",
+        ] {
+            assert_eq!(parse_query(query).unwrap().to_string(), query);
+        }
+    }
+
+    #[test]
+    fn test_update_assignment_count() {
+        assert_eq!(
+            parse_query("UPDATE movies SET (a, b) = (1, 2, 3)"),
+            Err(ParsingError::AssignmentCount(2, 3))
+        );
+        assert_eq!(
+            parse_query("UPDATE movies SET (a, b) = 1"),
+            Err(ParsingError::AssignmentCount(2, 1))
+        );
+        assert_eq!(
+            parse_query("UPDATE movies SET () = 1"),
+            Err(ParsingError::UnexpectedToken(")".into()))
+        );
+    }
+}
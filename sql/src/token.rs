@@ -1,13 +1,18 @@
-use std::{fmt::Display, str::FromStr};
+use std::{borrow::Cow, fmt::Display, str::FromStr};
 
+/// A lexical token, borrowing its text from the scanned input
 #[derive(Debug, Clone, PartialEq)]
-pub enum Token {
-    /// A number literal
-    Number(String),
-    /// A string literal
-    String(String),
+pub enum Token<'a> {
+    /// A number literal, decimal or `0x` hexadecimal
+    Number(&'a str),
+    /// A blob literal, the hex digits between the quotes of `x'...'`
+    Blob(&'a str),
+    /// A string literal. Only owned if the literal contained escaped (doubled) quotes
+    String(Cow<'a, str>),
     /// A textual identifier
-    Identifier(String),
+    Identifier(&'a str),
+    /// An identifier quoted with "double quotes", `backticks` or [brackets]
+    QuotedIdentifier(Cow<'a, str>),
     /// The period symbol .
     Period,
     /// The equals symbol =
@@ -50,17 +55,32 @@ pub enum Token {
     Pipe,
     /// A double pipe ||
     DoublePipe,
+    /// An ampersand &
+    Ampersand,
+    /// A tilde ~
+    Tilde,
+    /// The left shift symbol <<
+    ShiftLeft,
+    /// The right shift symbol >>
+    ShiftRight,
 }
 
-impl Display for Token {
+impl Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Token::Number(n) => f.write_str(n),
+            Token::Blob(b) => write!(f, "X'{b}'"),
             Token::String(s) => f.write_str(s),
             Token::Identifier(i) => f.write_str(i),
+            Token::QuotedIdentifier(i) => f.write_str(i),
+            Token::Period => f.write_str("."),
             Token::Equals => f.write_str("="),
+            Token::DoubleEquals => f.write_str("=="),
+            Token::NotEquals => f.write_str("!="),
             Token::GreaterThan => f.write_str(">"),
+            Token::GreaterOrEqual => f.write_str(">="),
             Token::LessThan => f.write_str("<"),
+            Token::LessOrEqual => f.write_str("<="),
             Token::Plus => f.write_str("+"),
             Token::Minus => f.write_str("-"),
             Token::Asterisk => f.write_str("*"),
@@ -72,139 +92,235 @@ impl Display for Token {
             Token::CloseParen => f.write_str(")"),
             Token::Comma => f.write_str(","),
             Token::SemiColon => f.write_str(";"),
-            _ => unreachable!(),
+            Token::Pipe => f.write_str("|"),
+            Token::DoublePipe => f.write_str("||"),
+            Token::Ampersand => f.write_str("&"),
+            Token::Tilde => f.write_str("~"),
+            Token::ShiftLeft => f.write_str("<<"),
+            Token::ShiftRight => f.write_str(">>"),
         }
     }
 }
 
-/// Reserved SQL Keywords
-#[derive(Debug, PartialEq)]
-pub enum Keyword {
-    Alter,
-    Analyse,
-    And,
-    As,
-    Asc,
-    Attach,
-    Begin,
-    Boolean,
-    By,
-    Char,
-    Commit,
-    Create,
-    Cross,
-    Delete,
-    Desc,
-    Detach,
-    Distinct,
-    Double,
-    Drop,
-    Exists,
-    Explain,
-    False,
-    File,
-    From,
-    Group,
-    Having,
-    If,
-    Index,
-    Infinity,
-    Inner,
-    Insert,
-    Into,
-    Is,
-    Join,
-    Left,
-    Limit,
-    Not,
-    Null,
-    On,
-    Or,
-    Order,
-    Outer,
-    Primary,
-    Reindex,
-    Release,
-    Right,
-    Rollback,
-    Savepoint,
-    Select,
-    Table,
-    Text,
-    Transaction,
-    True,
-    Unique,
-    Update,
-    Vacuum,
-    Values,
-    Where,
-    With,
-}
+/// Declares the keyword table from a single list of `Variant => "text", kind;` entries.
+///
+/// Every entry produces a `Keyword` variant, its textual form and its reservation kind.
+/// A `nonreserved` keyword falls back to a plain identifier wherever the grammar expects a
+/// name, the same way SQLite's `%fallback ID` tokens do (e.g. a column named `key`).
+macro_rules! keywords {
+    ($($variant:ident => $text:literal, $kind:ident;)*) => {
+        /// SQL Keywords, as understood by SQLite
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Keyword {
+            $($variant,)*
+        }
+
+        impl Keyword {
+            /// The full keyword table, sorted alphabetically
+            pub const ALL: &'static [Keyword] = &[$(Keyword::$variant,)*];
+
+            /// The length of the longest keyword
+            const MAX_LEN: usize = {
+                let lengths = [$($text.len(),)*];
+                let mut max = 0;
+                let mut i = 0;
+                while i < lengths.len() {
+                    if lengths[i] > max {
+                        max = lengths[i];
+                    }
+                    i += 1;
+                }
+                max
+            };
 
-impl From<&Keyword> for &str {
-    fn from(value: &Keyword) -> Self {
-        match value {
-            Keyword::Alter => "alter",
-            Keyword::Analyse => "analyse",
-            Keyword::And => "and",
-            Keyword::As => "as",
-            Keyword::Asc => "asc",
-            Keyword::Attach => "attach",
-            Keyword::Begin => "begin",
-            Keyword::Boolean => "boolean",
-            Keyword::By => "by",
-            Keyword::Char => "char",
-            Keyword::Commit => "commit",
-            Keyword::Create => "create",
-            Keyword::Cross => "cross",
-            Keyword::Delete => "delete",
-            Keyword::Desc => "desc",
-            Keyword::Detach => "detach",
-            Keyword::Distinct => "distinct",
-            Keyword::Double => "double",
-            Keyword::Drop => "drop",
-            Keyword::Exists => "exists",
-            Keyword::Explain => "explain",
-            Keyword::False => "false",
-            Keyword::File => "file",
-            Keyword::From => "from",
-            Keyword::Group => "group",
-            Keyword::Having => "having",
-            Keyword::If => "if",
-            Keyword::Index => "index",
-            Keyword::Infinity => "infinity",
-            Keyword::Inner => "inner",
-            Keyword::Insert => "insert",
-            Keyword::Into => "into",
-            Keyword::Is => "is",
-            Keyword::Join => "join",
-            Keyword::Left => "left",
-            Keyword::Limit => "limit",
-            Keyword::Not => "not",
-            Keyword::Null => "null",
-            Keyword::On => "on",
-            Keyword::Or => "or",
-            Keyword::Order => "order",
-            Keyword::Outer => "outer",
-            Keyword::Primary => "primary",
-            Keyword::Reindex => "reindex",
-            Keyword::Release => "release",
-            Keyword::Right => "right",
-            Keyword::Rollback => "rollback",
-            Keyword::Savepoint => "savepoint",
-            Keyword::Select => "select",
-            Keyword::Table => "table",
-            Keyword::Text => "text",
-            Keyword::Transaction => "transaction",
-            Keyword::True => "true",
-            Keyword::Unique => "unique",
-            Keyword::Update => "update",
-            Keyword::Vacuum => "vacuum",
-            Keyword::Values => "values",
-            Keyword::Where => "where",
-            Keyword::With => "with",
+            /// Returns true if the keyword can never be used as a plain identifier
+            pub fn is_reserved(&self) -> bool {
+                match self {
+                    $(Keyword::$variant => keywords!(@reserved $kind),)*
+                }
+            }
         }
-    }
+
+        impl From<&Keyword> for &str {
+            fn from(value: &Keyword) -> Self {
+                match value {
+                    $(Keyword::$variant => $text,)*
+                }
+            }
+        }
+
+        impl FromStr for Keyword {
+            type Err = ();
+            /// Case-insensitive lookup, lowercasing into a stack buffer instead of allocating
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                let mut buffer = [0u8; Keyword::MAX_LEN];
+                let lowercase = buffer.get_mut(..value.len()).ok_or(())?;
+                lowercase.copy_from_slice(value.as_bytes());
+                lowercase.make_ascii_lowercase();
+
+                match std::str::from_utf8(lowercase) {
+                    $(Ok($text) => Ok(Keyword::$variant),)*
+                    _ => Err(()),
+                }
+            }
+        }
+    };
+    (@reserved reserved) => { true };
+    (@reserved nonreserved) => { false };
+}
+
+// Derived from the `SQL As Understood By SQLite` keyword list. TRUE and FALSE are not SQLite
+// keywords, but are kept here as non-reserved ones, so they still can be used as names.
+keywords! {
+    Abort => "abort", nonreserved;
+    Action => "action", nonreserved;
+    Add => "add", reserved;
+    After => "after", nonreserved;
+    All => "all", reserved;
+    Alter => "alter", reserved;
+    Always => "always", nonreserved;
+    Analyze => "analyze", nonreserved;
+    And => "and", reserved;
+    As => "as", reserved;
+    Asc => "asc", nonreserved;
+    Attach => "attach", nonreserved;
+    Autoincrement => "autoincrement", reserved;
+    Before => "before", nonreserved;
+    Begin => "begin", nonreserved;
+    Between => "between", reserved;
+    By => "by", nonreserved;
+    Cascade => "cascade", nonreserved;
+    Case => "case", reserved;
+    Cast => "cast", nonreserved;
+    Check => "check", reserved;
+    Collate => "collate", reserved;
+    Column => "column", nonreserved;
+    Commit => "commit", reserved;
+    Conflict => "conflict", nonreserved;
+    Constraint => "constraint", reserved;
+    Create => "create", reserved;
+    Cross => "cross", reserved;
+    Current => "current", nonreserved;
+    CurrentDate => "current_date", nonreserved;
+    CurrentTime => "current_time", nonreserved;
+    CurrentTimestamp => "current_timestamp", nonreserved;
+    Database => "database", nonreserved;
+    Default => "default", reserved;
+    Deferrable => "deferrable", reserved;
+    Deferred => "deferred", nonreserved;
+    Delete => "delete", reserved;
+    Desc => "desc", nonreserved;
+    Detach => "detach", nonreserved;
+    Distinct => "distinct", reserved;
+    Do => "do", nonreserved;
+    Drop => "drop", reserved;
+    Each => "each", nonreserved;
+    Else => "else", reserved;
+    End => "end", nonreserved;
+    Escape => "escape", reserved;
+    Except => "except", reserved;
+    Exclude => "exclude", nonreserved;
+    Exclusive => "exclusive", nonreserved;
+    Exists => "exists", reserved;
+    Explain => "explain", nonreserved;
+    Fail => "fail", nonreserved;
+    False => "false", nonreserved;
+    Filter => "filter", nonreserved;
+    First => "first", nonreserved;
+    Following => "following", nonreserved;
+    For => "for", nonreserved;
+    Foreign => "foreign", reserved;
+    From => "from", reserved;
+    Full => "full", reserved;
+    Generated => "generated", nonreserved;
+    Glob => "glob", nonreserved;
+    Group => "group", reserved;
+    Groups => "groups", nonreserved;
+    Having => "having", reserved;
+    If => "if", nonreserved;
+    Ignore => "ignore", nonreserved;
+    Immediate => "immediate", nonreserved;
+    In => "in", reserved;
+    Index => "index", reserved;
+    Indexed => "indexed", reserved;
+    Initially => "initially", nonreserved;
+    Inner => "inner", reserved;
+    Insert => "insert", reserved;
+    Instead => "instead", nonreserved;
+    Intersect => "intersect", reserved;
+    Into => "into", reserved;
+    Is => "is", reserved;
+    Isnull => "isnull", reserved;
+    Join => "join", reserved;
+    Key => "key", nonreserved;
+    Last => "last", nonreserved;
+    Left => "left", reserved;
+    Like => "like", nonreserved;
+    Limit => "limit", reserved;
+    Match => "match", nonreserved;
+    Materialized => "materialized", nonreserved;
+    Natural => "natural", reserved;
+    No => "no", nonreserved;
+    Not => "not", reserved;
+    Nothing => "nothing", reserved;
+    Notnull => "notnull", reserved;
+    Null => "null", reserved;
+    Nulls => "nulls", nonreserved;
+    Of => "of", nonreserved;
+    Offset => "offset", nonreserved;
+    On => "on", reserved;
+    Or => "or", reserved;
+    Order => "order", reserved;
+    Others => "others", nonreserved;
+    Outer => "outer", reserved;
+    Over => "over", nonreserved;
+    Partition => "partition", nonreserved;
+    Plan => "plan", nonreserved;
+    Pragma => "pragma", nonreserved;
+    Preceding => "preceding", nonreserved;
+    Primary => "primary", reserved;
+    Query => "query", nonreserved;
+    Raise => "raise", nonreserved;
+    Range => "range", nonreserved;
+    Recursive => "recursive", nonreserved;
+    References => "references", reserved;
+    Regexp => "regexp", nonreserved;
+    Reindex => "reindex", nonreserved;
+    Release => "release", nonreserved;
+    Rename => "rename", nonreserved;
+    Replace => "replace", nonreserved;
+    Restrict => "restrict", nonreserved;
+    Returning => "returning", reserved;
+    Right => "right", reserved;
+    Rollback => "rollback", reserved;
+    Row => "row", nonreserved;
+    Rows => "rows", nonreserved;
+    Savepoint => "savepoint", nonreserved;
+    Select => "select", reserved;
+    Set => "set", reserved;
+    Table => "table", reserved;
+    Temp => "temp", nonreserved;
+    Temporary => "temporary", nonreserved;
+    Then => "then", reserved;
+    Ties => "ties", nonreserved;
+    To => "to", reserved;
+    Transaction => "transaction", reserved;
+    Trigger => "trigger", nonreserved;
+    True => "true", nonreserved;
+    Unbounded => "unbounded", nonreserved;
+    Union => "union", reserved;
+    Unique => "unique", reserved;
+    Update => "update", reserved;
+    Using => "using", reserved;
+    Vacuum => "vacuum", nonreserved;
+    Values => "values", reserved;
+    View => "view", nonreserved;
+    Virtual => "virtual", nonreserved;
+    When => "when", reserved;
+    Where => "where", reserved;
+    Window => "window", nonreserved;
+    With => "with", nonreserved;
+    Without => "without", nonreserved;
 }
 
 impl Display for Keyword {
@@ -213,73 +329,73 @@ impl Display for Keyword {
     }
 }
 
-impl FromStr for Keyword {
-    type Err = ();
-    fn from_str(value: &str) -> Result<Self, Self::Err> {
-        match value.to_lowercase().as_str() {
-            "and" => Ok(Keyword::And),
-            "as" => Ok(Keyword::As),
-            "asc" => Ok(Keyword::Asc),
-            "begin" => Ok(Keyword::Begin),
-            "boolean" => Ok(Keyword::Boolean),
-            "by" => Ok(Keyword::By),
-            "char" => Ok(Keyword::Char),
-            "commit" => Ok(Keyword::Commit),
-            "create" => Ok(Keyword::Create),
-            "cross" => Ok(Keyword::Cross),
-            "delete" => Ok(Keyword::Delete),
-            "desc" => Ok(Keyword::Desc),
-            "distinct" => Ok(Keyword::Distinct),
-            "double" => Ok(Keyword::Double),
-            "drop" => Ok(Keyword::Drop),
-            "exists" => Ok(Keyword::Exists),
-            "explain" => Ok(Keyword::Explain),
-            "false" => Ok(Keyword::False),
-            "from" => Ok(Keyword::From),
-            "file" => Ok(Keyword::File),
-            "group" => Ok(Keyword::Group),
-            "having" => Ok(Keyword::Having),
-            "if" => Ok(Keyword::If),
-            "index" => Ok(Keyword::Index),
-            "infinity" => Ok(Keyword::Infinity),
-            "inner" => Ok(Keyword::Inner),
-            "insert" => Ok(Keyword::Insert),
-            "into" => Ok(Keyword::Into),
-            "is" => Ok(Keyword::Is),
-            "join" => Ok(Keyword::Join),
-            "left" => Ok(Keyword::Left),
-            "limit" => Ok(Keyword::Limit),
-            "not" => Ok(Keyword::Not),
-            "null" => Ok(Keyword::Null),
-            "on" => Ok(Keyword::On),
-            "or" => Ok(Keyword::Or),
-            "order" => Ok(Keyword::Order),
-            "outer" => Ok(Keyword::Outer),
-            "primary" => Ok(Keyword::Primary),
-            "right" => Ok(Keyword::Right),
-            "rollback" => Ok(Keyword::Rollback),
-            "select" => Ok(Keyword::Select),
-            "table" => Ok(Keyword::Table),
-            "text" => Ok(Keyword::Text),
-            "transaction" => Ok(Keyword::Transaction),
-            "true" => Ok(Keyword::True),
-            "unique" => Ok(Keyword::Unique),
-            "update" => Ok(Keyword::Update),
-            "values" => Ok(Keyword::Values),
-            "where" => Ok(Keyword::Where),
+impl TryFrom<&Token<'_>> for Keyword {
+    type Error = ();
+
+    /// Only bare identifiers can be keywords, quoted text never is
+    fn try_from(token: &Token<'_>) -> Result<Self, Self::Error> {
+        match token {
+            Token::Identifier(ident) => Keyword::from_str(ident),
             _ => Err(()),
         }
     }
 }
 
-impl TryFrom<Token> for Keyword {
-    type Error = ();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn try_from(token: Token) -> Result<Self, Self::Error> {
-        match token {
-            Token::Identifier(ident) => Keyword::from_str(&ident.to_lowercase()),
-            Token::String(string) => Keyword::from_str(&string.to_lowercase()),
-            _ => Err(()),
+    #[test]
+    fn keyword_table_round_trip() {
+        for keyword in Keyword::ALL {
+            let text: &str = keyword.into();
+            assert_eq!(Keyword::from_str(text), Ok(*keyword));
+            assert_eq!(Keyword::from_str(&text.to_uppercase()), Ok(*keyword));
+        }
+    }
+
+    #[test]
+    fn keyword_table_covers_statements() {
+        for (text, keyword) in [
+            ("ALTER", Keyword::Alter),
+            ("analyze", Keyword::Analyze),
+            ("Attach", Keyword::Attach),
+            ("detach", Keyword::Detach),
+            ("EXPLAIN", Keyword::Explain),
+            ("reindex", Keyword::Reindex),
+            ("release", Keyword::Release),
+            ("SAVEPOINT", Keyword::Savepoint),
+            ("vacuum", Keyword::Vacuum),
+            ("with", Keyword::With),
+        ] {
+            assert_eq!(Keyword::from_str(text), Ok(keyword));
         }
+        assert_eq!(Keyword::ALL.len(), 149);
+    }
+
+    #[test]
+    fn keyword_reservation() {
+        assert!(Keyword::Select.is_reserved());
+        assert!(Keyword::From.is_reserved());
+        assert!(!Keyword::Key.is_reserved());
+        assert!(!Keyword::Action.is_reserved());
+        assert!(!Keyword::Temp.is_reserved());
+        assert_eq!(Keyword::from_str("movies"), Err(()));
+    }
+
+    #[test]
+    fn keyword_lookup_edge_cases() {
+        assert_eq!(
+            Keyword::from_str("Current_Timestamp"),
+            Ok(Keyword::CurrentTimestamp)
+        );
+        assert_eq!(Keyword::from_str("current_timestamps"), Err(()));
+        assert_eq!(Keyword::from_str(""), Err(()));
+        assert_eq!(Keyword::from_str("sélect"), Err(()));
+        assert_eq!(
+            Keyword::try_from(&Token::Identifier("FROM")),
+            Ok(Keyword::From)
+        );
+        assert_eq!(Keyword::try_from(&Token::String("FROM".into())), Err(()));
     }
 }